@@ -0,0 +1,80 @@
+use core::time::Duration;
+
+/// A stub trait for the hooks that could adjust the delay before every retry.
+///
+/// All `FnMut(&E, Option<Duration>) -> Option<Duration>` implement it, so `adjust` and
+/// `backoff_for` share the same slot.
+#[doc(hidden)]
+pub trait Adjust<E> {
+    fn adjust(&mut self, err: &E, dur: Option<Duration>) -> Option<Duration>;
+
+    /// Whether to retry immediately without sleeping for given error.
+    fn no_sleep(&mut self, _err: &E) -> bool {
+        false
+    }
+}
+
+impl<E, F: FnMut(&E, Option<Duration>) -> Option<Duration>> Adjust<E> for F {
+    fn adjust(&mut self, err: &E, dur: Option<Duration>) -> Option<Duration> {
+        self(err, dur)
+    }
+}
+
+/// Adjust that picks the delay by the error, generated by `backoff_for`.
+#[doc(hidden)]
+#[derive(Clone)]
+pub struct BackoffFor<F>(pub(crate) F);
+
+impl<E, F: FnMut(&E) -> Option<Duration>> Adjust<E> for BackoffFor<F> {
+    fn adjust(&mut self, err: &E, dur: Option<Duration>) -> Option<Duration> {
+        // The backoff still decides whether to retry, only the delay is replaced.
+        let dur = dur?;
+        Some((self.0)(err).unwrap_or(dur))
+    }
+}
+
+/// Adjust that picks the backoff by the key of the error, generated by `backoff_by_key`.
+#[cfg(feature = "std")]
+#[doc(hidden)]
+#[derive(Clone)]
+pub struct BackoffByKey<K, B, F> {
+    pub(crate) classify: F,
+    pub(crate) backoffs: std::collections::HashMap<K, B>,
+}
+
+#[cfg(feature = "std")]
+impl<E, K, B, F> Adjust<E> for BackoffByKey<K, B, F>
+where
+    K: Eq + core::hash::Hash,
+    B: crate::Backoff,
+    F: FnMut(&E) -> K,
+{
+    fn adjust(&mut self, err: &E, dur: Option<Duration>) -> Option<Duration> {
+        // The backoff of the retry still limits the retries, like `backoff_for`.
+        let dur = dur?;
+        // Errors without a keyed backoff use the backoff of the retry.
+        match self.backoffs.get_mut(&(self.classify)(err)) {
+            Some(backoff) => backoff.next(),
+            None => Some(dur),
+        }
+    }
+}
+
+/// Adjust that skips sleeping for some errors on top of another adjust, generated by
+/// `no_sleep_when`.
+#[doc(hidden)]
+#[derive(Clone)]
+pub struct NoSleepWhen<A, F> {
+    pub(crate) inner: A,
+    pub(crate) no_sleep: F,
+}
+
+impl<E, A: Adjust<E>, F: FnMut(&E) -> bool> Adjust<E> for NoSleepWhen<A, F> {
+    fn adjust(&mut self, err: &E, dur: Option<Duration>) -> Option<Duration> {
+        self.inner.adjust(err, dur)
+    }
+
+    fn no_sleep(&mut self, err: &E) -> bool {
+        self.inner.no_sleep(err) || (self.no_sleep)(err)
+    }
+}
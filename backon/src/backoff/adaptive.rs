@@ -0,0 +1,232 @@
+use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::Ordering;
+use core::time::Duration;
+use std::sync::Arc;
+
+use crate::backoff::BackoffBuilder;
+use crate::backoff::BackoffConfigError;
+
+/// AdaptiveBuilder builds backoffs that remember the failures of the previous retries, and heal
+/// after successes.
+///
+/// It's meant for a long-lived client: every delay yielded by the backoffs built from it moves
+/// the shared position one step further along the policy, and every success recorded moves it
+/// one step back. A new retry starts from the delay at the current position instead of the
+/// first one, so a client that keeps failing keeps long delays, and a client that recovers
+/// heals back to short delays.
+///
+/// The number of retries of every retry is still limited by the policy, only the delays are
+/// shifted. Past the end of the policy, the last delay is repeated and the position stays, so
+/// the position never goes past the length of the policy.
+///
+/// The successes are recorded by [`Retry::heal`](crate::Retry::heal), or manually by
+/// [`AdaptiveBuilder::record_success`]. The clones of the builder share the same position.
+/// There is no success hook on [`Backoff`](crate::Backoff) itself: every iterator of
+/// durations is a backoff, so the builtin backoffs couldn't react to it anyway, and the
+/// position is shared by the builder instead.
+///
+/// # Examples
+///
+/// ```no_run
+/// use anyhow::Result;
+/// use backon::AdaptiveBuilder;
+/// use backon::ExponentialBuilder;
+/// use backon::Retryable;
+///
+/// async fn fetch() -> Result<String> {
+///     Ok(reqwest::get("https://www.rust-lang.org")
+///         .await?
+///         .text()
+///         .await?)
+/// }
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() -> Result<()> {
+///     let backoff = AdaptiveBuilder::new(ExponentialBuilder::default());
+///
+///     for _ in 0..10 {
+///         let content = fetch.retry(&backoff).heal(&backoff).await?;
+///         println!("fetch succeeded: {}", content);
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct AdaptiveBuilder<B> {
+    builder: B,
+    position: Arc<AtomicUsize>,
+}
+
+impl<B> AdaptiveBuilder<B> {
+    /// Create a new adaptive builder following the delays of given builder.
+    pub fn new(builder: B) -> Self {
+        AdaptiveBuilder {
+            builder,
+            position: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Record a success, moving the position one step back towards the first delay.
+    pub fn record_success(&self) {
+        heal(&self.position);
+    }
+
+    /// Return the current position, the number of delays the next retry starts after.
+    pub fn position(&self) -> usize {
+        self.position.load(Ordering::Relaxed)
+    }
+
+    /// Return the shared position to be healed by a retry.
+    pub(crate) fn position_handle(&self) -> Arc<AtomicUsize> {
+        self.position.clone()
+    }
+}
+
+impl<B: BackoffBuilder + Clone> BackoffBuilder for &AdaptiveBuilder<B> {
+    type Backoff = AdaptiveBackoff<B::Backoff>;
+
+    fn build(self) -> Self::Backoff {
+        self.try_build().unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    fn try_build(self) -> Result<Self::Backoff, BackoffConfigError> {
+        let mut delays = self.builder.clone().try_build()?;
+        // Skip the delays of the failures recorded so far.
+        let mut skipped = 0;
+        let mut last = None;
+        for _ in 0..self.position() {
+            match delays.next() {
+                Some(delay) => {
+                    skipped += 1;
+                    last = Some(delay);
+                }
+                None => break,
+            }
+        }
+
+        Ok(AdaptiveBackoff {
+            delays,
+            index: skipped,
+            repeats: skipped,
+            last,
+            position: self.position.clone(),
+        })
+    }
+}
+
+impl<B: BackoffBuilder + Clone> BackoffBuilder for AdaptiveBuilder<B> {
+    type Backoff = AdaptiveBackoff<B::Backoff>;
+
+    fn build(self) -> Self::Backoff {
+        (&self).build()
+    }
+
+    fn try_build(self) -> Result<Self::Backoff, BackoffConfigError> {
+        (&self).try_build()
+    }
+}
+
+/// Move the shared position one step back.
+pub(crate) fn heal(position: &AtomicUsize) {
+    let _ = position.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1));
+}
+
+/// AdaptiveBackoff is the backoff built by [`AdaptiveBuilder`].
+#[derive(Debug)]
+pub struct AdaptiveBackoff<B> {
+    delays: B,
+    /// The position in the policy of the next delay.
+    index: usize,
+    /// The number of delays skipped from the start of the policy, repeated once it runs out.
+    repeats: usize,
+    last: Option<Duration>,
+    position: Arc<AtomicUsize>,
+}
+
+impl<B: Iterator<Item = Duration>> Iterator for AdaptiveBackoff<B> {
+    type Item = Duration;
+
+    fn next(&mut self) -> Option<Duration> {
+        match self.delays.next() {
+            Some(delay) => {
+                self.index += 1;
+                self.last = Some(delay);
+                // The position never goes past the end of the policy, even if retries run
+                // concurrently.
+                self.position.fetch_max(self.index, Ordering::Relaxed);
+                Some(delay)
+            }
+            // Repeat the last delay past the end of the policy, so the number of retries is
+            // still limited by the policy.
+            None => {
+                self.repeats = self.repeats.checked_sub(1)?;
+                self.last
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (lower, upper) = self.delays.size_hint();
+        (
+            lower.saturating_add(self.repeats),
+            upper.and_then(|n| n.checked_add(self.repeats)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test as test;
+
+    use super::*;
+    use crate::Backoff;
+    use crate::ExponentialBuilder;
+
+    #[test]
+    fn test_adaptive_backoff() {
+        let adaptive = AdaptiveBuilder::new(ExponentialBuilder::default().with_max_times(3));
+
+        // The failures move the position forward.
+        assert!((&adaptive).build().eq([1, 2, 4].map(Duration::from_secs)));
+        assert_eq!(adaptive.position(), 3);
+
+        // The next retry starts from the position, past the end of the policy it repeats the
+        // last delay, without moving the position further.
+        assert!((&adaptive).build().eq([4, 4, 4].map(Duration::from_secs)));
+        assert_eq!(adaptive.position(), 3);
+
+        // The successes heal the position.
+        adaptive.record_success();
+        adaptive.record_success();
+        assert!((&adaptive).build().eq([2, 4, 4].map(Duration::from_secs)));
+        assert_eq!(adaptive.position(), 3);
+        for _ in 0..10 {
+            adaptive.record_success();
+        }
+        assert_eq!(adaptive.position(), 0);
+    }
+
+    #[test]
+    fn test_adaptive_backoff_concurrent() {
+        let adaptive = AdaptiveBuilder::new(ExponentialBuilder::default().with_max_times(3));
+
+        // Concurrent retries don't move the position past the end of the policy.
+        let (mut a, mut b) = ((&adaptive).build(), (&adaptive).build());
+        assert_eq!(a.remaining(), Some(3));
+        for _ in 0..3 {
+            assert!(a.next().is_some());
+            assert!(b.next().is_some());
+        }
+        assert_eq!(adaptive.position(), 3);
+
+        let mut c = (&adaptive).build();
+        assert_eq!(c.remaining(), Some(3));
+        assert!(c.by_ref().eq([4, 4, 4].map(Duration::from_secs)));
+        assert_eq!(c.remaining(), Some(0));
+        assert_eq!(adaptive.position(), 3);
+    }
+}
@@ -1,21 +1,178 @@
+use core::fmt;
 use core::time::Duration;
 
 /// Backoff is an [`Iterator`] that returns [`Duration`].
 ///
 /// - `Some(Duration)` indicates the caller should `sleep(Duration)` and retry the request.
 /// - `None` indicates the limits have been reached, and the caller should return the current error instead.
-pub trait Backoff: Iterator<Item = Duration> + Send + Sync + Unpin {}
+pub trait Backoff: Iterator<Item = Duration> + Send + Sync + Unpin {
+    /// Return the number of delays left in this backoff, or `None` if it's unknown or unbounded.
+    ///
+    /// It's backed by the upper bound of [`Iterator::size_hint`]. All builtin backoffs
+    /// return `Some` if `max_times` is set.
+    fn remaining(&self) -> Option<usize> {
+        self.size_hint().1
+    }
+}
 impl<T> Backoff for T where T: Iterator<Item = Duration> + Send + Sync + Unpin {}
 
 /// BackoffBuilder is utilized to construct a new backoff.
+///
+/// Every [`Backoff`] is a builder that returns itself. A reference to a builtin backoff is a
+/// builder as well, which clones it, so a backoff could be built once and reused across
+/// retries. Every retry gets its own clone starting from the state of the backoff when it's
+/// cloned, so pass a backoff that has not been iterated yet to get the full sequence every time.
+///
+/// The random number generator of the jitter is reseeded for every retry, so the retries
+/// reusing a backoff don't sleep in sync. If the seed is given explicitly, like by
+/// [`ExponentialBuilder::with_jitter_seed`](crate::ExponentialBuilder::with_jitter_seed), the
+/// generator is cloned as is, and all retries share the same jitter sequence.
+///
+/// A mutable reference to a backoff is a backoff as well. Pass `&mut backoff` to keep the
+/// ownership, and inspect how far the backoff has advanced once the retry has resolved, for
+/// example by [`Backoff::remaining`]. The backoff is borrowed by the retry until then.
+///
+/// # Examples
+///
+/// ```no_run
+/// use anyhow::Result;
+/// use backon::BackoffBuilder;
+/// use backon::ExponentialBuilder;
+/// use backon::Retryable;
+///
+/// async fn fetch() -> Result<String> {
+///     Ok(reqwest::get("https://www.rust-lang.org")
+///         .await?
+///         .text()
+///         .await?)
+/// }
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() -> Result<()> {
+///     let backoff = ExponentialBuilder::default().build();
+///
+///     for _ in 0..3 {
+///         let content = fetch.retry(&backoff).await?;
+///         println!("fetch succeeded: {}", content);
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+///
+/// Inspect the backoff after retrying:
+///
+/// ```no_run
+/// use anyhow::Result;
+/// use backon::Backoff;
+/// use backon::BackoffBuilder;
+/// use backon::ExponentialBuilder;
+/// use backon::Retryable;
+///
+/// async fn fetch() -> Result<String> {
+///     Ok(reqwest::get("https://www.rust-lang.org")
+///         .await?
+///         .text()
+///         .await?)
+/// }
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() -> Result<()> {
+///     let mut backoff = ExponentialBuilder::default().with_max_times(5).build();
+///
+///     let result = fetch.retry(&mut backoff).await;
+///     println!("fetch finished with {:?} retries left", backoff.remaining());
+///     println!("fetch result: {:?}", result);
+///
+///     Ok(())
+/// }
+/// ```
 pub trait BackoffBuilder: Send + Sync + Unpin {
     /// The associated backoff returned by this builder.
     type Backoff: Backoff;
 
     /// Construct a new backoff using the builder.
+    ///
+    /// Builders with an invalid configuration panic here, use [`BackoffBuilder::try_build`]
+    /// to handle it gracefully.
     fn build(self) -> Self::Backoff;
+
+    /// Construct a new backoff using the builder, or return an error if the configuration
+    /// of the builder is invalid.
+    ///
+    /// This is useful for rejecting retry configs read at runtime, for example from a file.
+    /// The default implementation never fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use backon::BackoffBuilder;
+    /// use backon::BackoffConfigError;
+    /// use backon::ExponentialBuilder;
+    ///
+    /// let err = ExponentialBuilder::default()
+    ///     .with_jitter_ratio(1.5)
+    ///     .try_build()
+    ///     .unwrap_err();
+    /// assert_eq!(err, BackoffConfigError::InvalidJitterRatio(1.5));
+    /// ```
+    fn try_build(self) -> Result<Self::Backoff, BackoffConfigError>
+    where
+        Self: Sized,
+    {
+        Ok(self.build())
+    }
+}
+
+/// BackoffConfigError is returned by [`BackoffBuilder::try_build`] if the configuration of
+/// a builder is invalid.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub enum BackoffConfigError {
+    /// The jitter ratio is not within `[0.0, 1.0]`.
+    InvalidJitterRatio(f32),
+    /// The factor of the exponential backoff is not greater than `0.0` or not a number.
+    InvalidFactor(f32),
+    /// The minimum delay is larger than the maximum delay.
+    MinDelayExceedsMaxDelay {
+        /// The minimum delay.
+        min_delay: Duration,
+        /// The maximum delay.
+        max_delay: Duration,
+    },
+    /// No policy with a positive weight is given to the weighted backoff.
+    NoWeightedPolicy,
+}
+
+impl fmt::Display for BackoffConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BackoffConfigError::InvalidJitterRatio(ratio) => {
+                write!(
+                    f,
+                    "invalid jitter ratio {ratio} that is not within [0.0, 1.0]"
+                )
+            }
+            BackoffConfigError::InvalidFactor(factor) => {
+                write!(f, "invalid factor {factor}: must be greater than 0")
+            }
+            BackoffConfigError::MinDelayExceedsMaxDelay {
+                min_delay,
+                max_delay,
+            } => write!(
+                f,
+                "invalid min delay {min_delay:?}: larger than max delay {max_delay:?}"
+            ),
+            BackoffConfigError::NoWeightedPolicy => {
+                write!(f, "no policy with a positive weight to choose from")
+            }
+        }
+    }
 }
 
+#[cfg(feature = "std")]
+impl std::error::Error for BackoffConfigError {}
+
 impl<B: Backoff> BackoffBuilder for B {
     type Backoff = B;
 
@@ -33,6 +190,37 @@ mod tests {
         let _ = b.build();
     }
 
+    #[test]
+    fn test_backoff_remaining() {
+        let mut it = ConstantBuilder::default().with_max_times(2).build();
+        assert_eq!(it.remaining(), Some(2));
+        it.next();
+        assert_eq!(it.remaining(), Some(1));
+        it.next();
+        assert_eq!(it.remaining(), Some(0));
+        it.next();
+        assert_eq!(it.remaining(), Some(0));
+
+        let mut it = ExponentialBuilder::default().with_max_times(1).build();
+        assert_eq!(it.remaining(), Some(1));
+        it.next();
+        assert_eq!(it.remaining(), Some(0));
+
+        let mut it = FibonacciBuilder::default().with_max_times(1).build();
+        assert_eq!(it.remaining(), Some(1));
+        it.next();
+        assert_eq!(it.remaining(), Some(0));
+
+        assert_eq!(
+            ConstantBuilder::default()
+                .without_max_times()
+                .build()
+                .remaining(),
+            None
+        );
+        assert_eq!(core::iter::repeat(Duration::ZERO).remaining(), None);
+    }
+
     #[test]
     fn test_backoff_builder() {
         test_fn_builder([Duration::from_secs(1)].into_iter());
@@ -45,4 +233,67 @@ mod tests {
             test_fn_builder(&ExponentialBuilder::default());
         }
     }
+
+    #[test]
+    fn test_with_rng_seed() {
+        fn assert_seeded(b: impl BackoffBuilder + Clone) {
+            assert!(b.clone().build().eq(b.build()));
+        }
+
+        assert_seeded(
+            ConstantBuilder::default()
+                .with_jitter()
+                .with_rng_seed(Some(7)),
+        );
+        assert_seeded(
+            ExponentialBuilder::default()
+                .with_jitter()
+                .with_rng_seed(Some(7)),
+        );
+        assert_seeded(
+            FibonacciBuilder::default()
+                .with_jitter()
+                .with_rng_seed(Some(7)),
+        );
+        assert_seeded(crate::EqualJitterBuilder::default().with_rng_seed(Some(7)));
+    }
+
+    #[test]
+    fn test_built_backoff_reused() {
+        let backoff = ExponentialBuilder::default()
+            .with_jitter()
+            .with_jitter_seed(7)
+            .with_max_times(3)
+            .build();
+
+        assert_eq!((&backoff).build().count(), 3);
+        assert!((&backoff).build().eq((&backoff).build()));
+
+        // The backoff itself is untouched.
+        assert_eq!(backoff.remaining(), Some(3));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_built_backoff_reused_jitter() {
+        let backoff = ExponentialBuilder::default()
+            .with_jitter()
+            .with_max_times(10)
+            .build();
+
+        // Every retry draws its own jitter without an explicit seed.
+        assert!(!(&backoff).build().eq((&backoff).build()));
+
+        let backoff = ConstantBuilder::default()
+            .with_jitter()
+            .with_max_times(10)
+            .build();
+        assert!(!(&backoff).build().eq((&backoff).build()));
+
+        let backoff = FibonacciBuilder::default()
+            .with_jitter()
+            .with_max_times(10)
+            .build();
+        assert!(!(&backoff).build().eq((&backoff).build()));
+    }
 }
@@ -0,0 +1,111 @@
+use core::time::Duration;
+use std::sync::mpsc::Receiver;
+use std::sync::mpsc::TryRecvError;
+use std::sync::Mutex;
+
+/// ChannelBackoff is a [`Backoff`](crate::Backoff) that reads its delays from a channel.
+///
+/// It allows the delays to be pushed at runtime from elsewhere, like a control plane that
+/// adapts the backoff to the load of a service.
+///
+/// [`Backoff`](crate::Backoff) is a synchronous iterator, so the channel is never waited on.
+/// Every delay is taken with [`Receiver::try_recv`]:
+///
+/// - If a delay is pending, it's returned and remembered.
+/// - If no delay is pending, the last delay received is returned again. The backoff stops if no
+///   delay has been received yet.
+/// - If the channel is closed and all pending delays have been taken, the backoff stops.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::sync::mpsc;
+/// use std::time::Duration;
+///
+/// use anyhow::Result;
+/// use backon::ChannelBackoff;
+/// use backon::Retryable;
+///
+/// async fn fetch() -> Result<String> {
+///     Ok(reqwest::get("https://www.rust-lang.org")
+///         .await?
+///         .text()
+///         .await?)
+/// }
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() -> Result<()> {
+///     let (tx, rx) = mpsc::channel();
+///     tx.send(Duration::from_millis(100))?;
+///
+///     // The delay could be updated by `tx` while retrying, dropping it stops the retry.
+///     let content = fetch.retry(ChannelBackoff::new(rx)).await?;
+///     println!("fetch succeeded: {}", content);
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug)]
+pub struct ChannelBackoff {
+    rx: Mutex<Receiver<Duration>>,
+    last: Option<Duration>,
+}
+
+impl ChannelBackoff {
+    /// Create a new backoff that reads the delays from given receiver.
+    pub fn new(rx: Receiver<Duration>) -> Self {
+        ChannelBackoff {
+            rx: Mutex::new(rx),
+            last: None,
+        }
+    }
+}
+
+impl Iterator for ChannelBackoff {
+    type Item = Duration;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rx = self.rx.get_mut().unwrap_or_else(|err| err.into_inner());
+        match rx.try_recv() {
+            Ok(delay) => {
+                self.last = Some(delay);
+                Some(delay)
+            }
+            Err(TryRecvError::Empty) => self.last,
+            Err(TryRecvError::Disconnected) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test as test;
+
+    use super::*;
+
+    #[test]
+    fn test_channel_backoff() {
+        let (tx, rx) = mpsc::channel();
+        let mut backoff = ChannelBackoff::new(rx);
+
+        // Nothing has been received yet.
+        assert_eq!(None, backoff.next());
+
+        tx.send(Duration::from_secs(1)).unwrap();
+        assert_eq!(Some(Duration::from_secs(1)), backoff.next());
+        // The last delay is reused until a new one is pushed.
+        assert_eq!(Some(Duration::from_secs(1)), backoff.next());
+
+        tx.send(Duration::from_secs(2)).unwrap();
+        tx.send(Duration::from_secs(3)).unwrap();
+        drop(tx);
+
+        // The pending delays are taken before the backoff stops.
+        assert_eq!(Some(Duration::from_secs(2)), backoff.next());
+        assert_eq!(Some(Duration::from_secs(3)), backoff.next());
+        assert_eq!(None, backoff.next());
+    }
+}
@@ -1,6 +1,16 @@
+use core::fmt;
+use core::hash::Hash;
 use core::time::Duration;
 
+use crate::backoff::new_rng;
+use crate::backoff::seed_from_key;
 use crate::backoff::BackoffBuilder;
+use crate::backoff::BackoffConfigError;
+use crate::backoff::Jitter;
+#[cfg(feature = "rand-core")]
+use crate::backoff::RngCoreJitter;
+#[cfg(feature = "rand-core")]
+use crate::backoff::WithRng;
 
 /// ConstantBuilder is used to create a [`ConstantBackoff`], providing a steady delay with a fixed number of retries.
 ///
@@ -9,6 +19,8 @@ use crate::backoff::BackoffBuilder;
 /// - delay: 1s
 /// - max_times: 3
 ///
+/// The default is bounded on purpose, call `without_max_times` to opt in to unlimited retries.
+///
 /// # Examples
 ///
 /// ```no_run
@@ -35,30 +47,44 @@ use crate::backoff::BackoffBuilder;
 pub struct ConstantBuilder {
     delay: Duration,
     max_times: Option<usize>,
-    jitter: bool,
+    jitter: Jitter,
+    max_jitter: Option<Duration>,
     seed: Option<u64>,
 }
 
 impl Default for ConstantBuilder {
     fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ConstantBuilder {
+    /// Create a new builder with the default configuration, the same as
+    /// [`ConstantBuilder::default`].
+    ///
+    /// The builder and its setters are `const`, so a policy could be declared in a `const` or a
+    /// `static` without lazy initialization.
+    pub const fn new() -> Self {
         Self {
             delay: Duration::from_secs(1),
             max_times: Some(3),
-            jitter: false,
+            jitter: Jitter::None,
+            max_jitter: None,
             seed: None,
         }
     }
-}
 
-impl ConstantBuilder {
     /// Set the delay for the backoff.
-    pub fn with_delay(mut self, delay: Duration) -> Self {
+    pub const fn with_delay(mut self, delay: Duration) -> Self {
         self.delay = delay;
         self
     }
 
     /// Set the maximum number of attempts to be made.
-    pub fn with_max_times(mut self, max_times: usize) -> Self {
+    ///
+    /// `max_times` is the number of retries, so the operation is attempted at most `max_times + 1`
+    /// times. `with_max_times(0)` makes exactly one attempt without any retry.
+    pub const fn with_max_times(mut self, max_times: usize) -> Self {
         self.max_times = Some(max_times);
         self
     }
@@ -66,23 +92,69 @@ impl ConstantBuilder {
     /// Enable jitter for the backoff.
     ///
     /// Jitter is a random value added to the delay to prevent a thundering herd problem.
-    pub fn with_jitter(mut self) -> Self {
-        self.jitter = true;
+    pub const fn with_jitter(mut self) -> Self {
+        self.jitter = Jitter::Full;
+        self
+    }
+
+    /// Enable jitter bounded by a ratio of the delay for the backoff.
+    ///
+    /// When jitter ratio is enabled, [`ConstantBackoff`] will scale the delay by a random
+    /// factor within `[1 - ratio, 1 + ratio]`.
+    ///
+    /// This replaces the jitter set by [`ConstantBuilder::with_jitter`].
+    ///
+    /// # Panics
+    ///
+    /// The backoff will panic while building if the ratio is not within `[0.0, 1.0]`, use
+    /// [`BackoffBuilder::try_build`] to get an error instead.
+    pub const fn with_jitter_ratio(mut self, ratio: f32) -> Self {
+        self.jitter = Jitter::Ratio(ratio);
         self
     }
 
-    /// Set the seed value for the jitter random number generator. If no seed is given, a random seed is used in std and default seed is used in no_std.
-    pub fn with_jitter_seed(mut self, seed: u64) -> Self {
-        self.seed = Some(seed);
+    /// Cap the jitter to at most `max_jitter` away from the delay, in either direction.
+    ///
+    /// See [`ExponentialBuilder::with_max_jitter`](crate::ExponentialBuilder::with_max_jitter)
+    /// for details.
+    pub const fn with_max_jitter(mut self, max_jitter: Duration) -> Self {
+        self.max_jitter = Some(max_jitter);
         self
     }
 
+    /// Set the seed of the jitter random number generator.
+    ///
+    /// `Some(seed)` makes the jitter deterministic, which is useful in tests. `None` seeds the
+    /// generator from the system entropy in std, and uses a default seed in no_std. The
+    /// generator is created by [`BackoffBuilder::build`], so all backoffs built from a builder
+    /// with a seed give the same delays.
+    pub const fn with_rng_seed(mut self, seed: Option<u64>) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Set the seed value for the jitter random number generator, it's a shorthand of
+    /// `with_rng_seed(Some(seed))`.
+    pub const fn with_jitter_seed(self, seed: u64) -> Self {
+        self.with_rng_seed(Some(seed))
+    }
+
+    /// Seed the jitter random number generator from given key, like a hostname or a shard id.
+    ///
+    /// See [`ExponentialBuilder::with_jitter_key`](crate::ExponentialBuilder::with_jitter_key)
+    /// for details.
+    pub fn with_jitter_key(self, key: impl Hash) -> Self {
+        self.with_rng_seed(Some(seed_from_key(key)))
+    }
+
     /// Set no max times for the backoff.
     ///
     /// The backoff will not stop by itself.
     ///
     /// _The backoff could stop reaching `usize::MAX` attempts but this is **unrealistic**._
-    pub fn without_max_times(mut self) -> Self {
+    ///
+    /// Unlimited retries must be asked for explicitly, the default is bounded to 3 retries.
+    pub const fn without_max_times(mut self) -> Self {
         self.max_times = None;
         self
     }
@@ -92,24 +164,22 @@ impl BackoffBuilder for ConstantBuilder {
     type Backoff = ConstantBackoff;
 
     fn build(self) -> Self::Backoff {
-        ConstantBackoff {
+        self.try_build().unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    fn try_build(self) -> Result<Self::Backoff, BackoffConfigError> {
+        self.jitter.validate()?;
+
+        Ok(ConstantBackoff {
             delay: self.delay,
             max_times: self.max_times,
 
             attempts: 0,
             jitter: self.jitter,
-            rng: if let Some(seed) = self.seed {
-                fastrand::Rng::with_seed(seed)
-            } else {
-                #[cfg(feature = "std")]
-                let rng = fastrand::Rng::new();
-
-                #[cfg(not(feature = "std"))]
-                let rng = fastrand::Rng::with_seed(super::RANDOM_SEED);
-
-                rng
-            },
-        }
+            max_jitter: self.max_jitter,
+            rng: new_rng(self.seed),
+            seeded: self.seed.is_some(),
+        })
     }
 }
 
@@ -119,44 +189,130 @@ impl BackoffBuilder for &ConstantBuilder {
     fn build(self) -> Self::Backoff {
         (*self).build()
     }
+
+    fn try_build(self) -> Result<Self::Backoff, BackoffConfigError> {
+        (*self).try_build()
+    }
 }
 
 /// ConstantBackoff offers a consistent delay with a limited number of retries.
 ///
 /// This backoff strategy is constructed by [`ConstantBuilder`].
 #[doc(hidden)]
-#[derive(Debug)]
+#[derive(Clone)]
 pub struct ConstantBackoff {
     delay: Duration,
     max_times: Option<usize>,
 
     attempts: usize,
-    jitter: bool,
+    jitter: Jitter,
+    max_jitter: Option<Duration>,
     rng: fastrand::Rng,
+    /// Whether the seed of the jitter is given explicitly.
+    seeded: bool,
 }
 
-impl Iterator for ConstantBackoff {
-    type Item = Duration;
+/// Show the policy and the attempts made, the random number generator is omitted.
+impl fmt::Debug for ConstantBackoff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConstantBackoff")
+            .field("jitter", &self.jitter)
+            .field("max_jitter", &self.max_jitter)
+            .field("delay", &self.delay)
+            .field("max_times", &self.max_times)
+            .field("attempts", &self.attempts)
+            .finish_non_exhaustive()
+    }
+}
 
-    fn next(&mut self) -> Option<Self::Item> {
-        let mut delay = || match self.jitter {
-            true => self.delay + self.delay.mul_f32(self.rng.f32()),
-            false => self.delay,
-        };
+impl ConstantBackoff {
+    /// Return the next delay without consuming it, or `None` if the jitter is set.
+    ///
+    /// See [`ExponentialBackoff::peek`](crate::ExponentialBackoff::peek) for details.
+    pub fn peek(&self) -> Option<Duration> {
+        if self.jitter != Jitter::None {
+            return None;
+        }
+        self.clone().next()
+    }
+
+    /// Return the next delay, drawing the jitter from given random number generator instead of
+    /// the one seeded by the builder.
+    ///
+    /// See [`ExponentialBackoff::next_with_rng`](crate::ExponentialBackoff::next_with_rng) for
+    /// details.
+    #[cfg(feature = "rand-core")]
+    pub fn next_with_rng<R: rand_core::RngCore + ?Sized>(
+        &mut self,
+        rng: &mut R,
+    ) -> Option<Duration> {
+        let delay = self.next_delay()?;
+        Some(
+            self.jitter
+                .apply(delay, self.delay, self.max_jitter, &mut RngCoreJitter(rng)),
+        )
+    }
+
+    /// Draw the jitter of every delay from given random number generator instead of the one
+    /// seeded by the builder.
+    ///
+    /// See [`WithRng`] for details.
+    #[cfg(feature = "rand-core")]
+    pub fn with_rng<R: rand_core::RngCore>(self, rng: R) -> WithRng<Self, R> {
+        WithRng::new(self, rng)
+    }
+
+    /// Advance to the next delay before applying the jitter.
+    fn next_delay(&mut self) -> Option<Duration> {
         match self.max_times {
-            None => Some(delay()),
+            None => Some(self.delay),
             Some(max_times) => {
                 if self.attempts >= max_times {
                     None
                 } else {
                     self.attempts += 1;
-                    Some(delay())
+                    Some(self.delay)
                 }
             }
         }
     }
 }
 
+impl BackoffBuilder for &ConstantBackoff {
+    type Backoff = ConstantBackoff;
+
+    fn build(self) -> Self::Backoff {
+        let mut backoff = self.clone();
+        // Every retry draws its own jitter, unless the seed is given explicitly.
+        if !backoff.seeded {
+            backoff.rng = new_rng(None);
+        }
+        backoff
+    }
+}
+
+impl Iterator for ConstantBackoff {
+    type Item = Duration;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let delay = self.next_delay()?;
+        Some(
+            self.jitter
+                .apply(delay, self.delay, self.max_jitter, &mut self.rng),
+        )
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.max_times {
+            None => (usize::MAX, None),
+            Some(max_times) => {
+                let remaining = max_times.saturating_sub(self.attempts);
+                (remaining, Some(remaining))
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use core::time::Duration;
@@ -164,7 +320,9 @@ mod tests {
     #[cfg(target_arch = "wasm32")]
     use wasm_bindgen_test::wasm_bindgen_test as test;
 
+    use crate::Backoff;
     use crate::BackoffBuilder;
+    use crate::BackoffConfigError;
     use crate::ConstantBuilder;
 
     #[test]
@@ -206,6 +364,39 @@ mod tests {
         assert!(dur > Duration::from_secs(1));
     }
 
+    #[test]
+    fn test_constant_with_jitter_ratio() {
+        let mut it = ConstantBuilder::default()
+            .with_jitter_ratio(0.5)
+            .with_max_times(100)
+            .build();
+
+        for _ in 0..100 {
+            let dur = it.next().unwrap();
+            assert!(dur >= Duration::from_millis(500), "current: {dur:?}");
+            assert!(dur <= Duration::from_millis(1500), "current: {dur:?}");
+        }
+        assert_eq!(None, it.next());
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid jitter ratio")]
+    fn test_constant_with_invalid_jitter_ratio() {
+        let _ = ConstantBuilder::default().with_jitter_ratio(-0.1).build();
+    }
+
+    #[test]
+    fn test_constant_try_build() {
+        assert!(ConstantBuilder::default().try_build().is_ok());
+        assert_eq!(
+            ConstantBuilder::default()
+                .with_jitter_ratio(-0.1)
+                .try_build()
+                .unwrap_err(),
+            BackoffConfigError::InvalidJitterRatio(-0.1)
+        );
+    }
+
     #[test]
     fn test_constant_without_max_times() {
         let mut it = ConstantBuilder::default().without_max_times().build();
@@ -214,4 +405,59 @@ mod tests {
             assert_eq!(Some(Duration::from_secs(1)), it.next());
         }
     }
+
+    #[test]
+    fn test_constant_max_times_zero() {
+        let mut it = ConstantBuilder::default()
+            .with_jitter()
+            .with_max_times(0)
+            .build();
+
+        assert_eq!(it.remaining(), Some(0));
+        assert_eq!(None, it.next());
+    }
+
+    #[test]
+    fn test_constant_default_max_times_is_bounded() {
+        assert_eq!(ConstantBuilder::default().build().remaining(), Some(3));
+        assert_eq!(
+            ConstantBuilder::default()
+                .without_max_times()
+                .build()
+                .remaining(),
+            None
+        );
+    }
+
+    #[cfg(feature = "rand-core")]
+    #[test]
+    fn test_constant_next_with_rng() {
+        use rand::rngs::SmallRng;
+        use rand::SeedableRng;
+
+        let builder = ConstantBuilder::default().with_jitter().with_max_times(5);
+        let mut a = builder.build();
+        let mut b = builder.build();
+        let mut rng_a = SmallRng::seed_from_u64(42);
+        let mut rng_b = SmallRng::seed_from_u64(42);
+
+        while let Some(v) = a.next_with_rng(&mut rng_a) {
+            assert_eq!(Some(v), b.next_with_rng(&mut rng_b));
+            assert!(v >= Duration::from_secs(1), "current: {v:?}");
+            assert!(v <= Duration::from_secs(2), "current: {v:?}");
+        }
+        assert_eq!(None, b.next_with_rng(&mut rng_b));
+    }
+
+    #[test]
+    fn test_constant_peek() {
+        let mut backoff = ConstantBuilder::default().with_max_times(1).build();
+        assert_eq!(backoff.peek(), Some(Duration::from_secs(1)));
+        assert_eq!(backoff.next(), Some(Duration::from_secs(1)));
+        assert_eq!(backoff.peek(), None);
+        assert_eq!(backoff.next(), None);
+
+        let backoff = ConstantBuilder::default().with_jitter().build();
+        assert_eq!(backoff.peek(), None);
+    }
 }
@@ -0,0 +1,253 @@
+use core::hash::Hash;
+use core::time::Duration;
+
+use crate::backoff::seed_from_key;
+use crate::backoff::BackoffBuilder;
+use crate::backoff::BackoffConfigError;
+use crate::ExponentialBackoff;
+use crate::ExponentialBuilder;
+
+/// EqualJitterBuilder is used to construct an [`ExponentialBackoff`] with the "equal jitter"
+/// popularized by AWS.
+///
+/// Every delay is `base / 2 + random(0, base / 2)`, where `base` is the exponential delay. It's
+/// the same as [`ExponentialBuilder::with_equal_jitter`], provided as a named type for teams
+/// that standardize on this algorithm.
+///
+/// # Default
+///
+/// - factor: 2
+/// - min_delay: 1s
+/// - max_delay: 60s
+/// - max_times: 3
+///
+/// # Examples
+///
+/// ```no_run
+/// use anyhow::Result;
+/// use backon::EqualJitterBuilder;
+/// use backon::Retryable;
+///
+/// async fn fetch() -> Result<String> {
+///     Ok(reqwest::get("https://www.rust-lang.org")
+///         .await?
+///         .text()
+///         .await?)
+/// }
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() -> Result<()> {
+///     let content = fetch.retry(EqualJitterBuilder::default()).await?;
+///     println!("fetch succeeded: {}", content);
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct EqualJitterBuilder {
+    inner: ExponentialBuilder,
+}
+
+impl Default for EqualJitterBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EqualJitterBuilder {
+    /// Create a new builder with the default configuration, the same as
+    /// [`EqualJitterBuilder::default`].
+    ///
+    /// The builder and its setters are `const`, so a policy could be declared in a `const` or a
+    /// `static` without lazy initialization.
+    pub const fn new() -> Self {
+        Self {
+            inner: ExponentialBuilder::new().with_equal_jitter(),
+        }
+    }
+
+    /// Set the seed of the jitter random number generator.
+    ///
+    /// `Some(seed)` makes the jitter deterministic, which is useful in tests. `None` seeds the
+    /// generator from the system entropy in std, and uses a default seed in no_std.
+    pub const fn with_rng_seed(mut self, seed: Option<u64>) -> Self {
+        self.inner = self.inner.with_rng_seed(seed);
+        self
+    }
+
+    /// Set the seed value for the jitter random number generator, it's a shorthand of
+    /// `with_rng_seed(Some(seed))`.
+    pub const fn with_jitter_seed(self, seed: u64) -> Self {
+        self.with_rng_seed(Some(seed))
+    }
+
+    /// Seed the jitter random number generator from given key, like a hostname or a shard id.
+    ///
+    /// See [`ExponentialBuilder::with_jitter_key`](crate::ExponentialBuilder::with_jitter_key)
+    /// for details.
+    pub fn with_jitter_key(self, key: impl Hash) -> Self {
+        self.with_rng_seed(Some(seed_from_key(key)))
+    }
+
+    /// Cap the jitter to at most `max_jitter` away from the delay, in either direction.
+    ///
+    /// See [`ExponentialBuilder::with_max_jitter`] for details.
+    pub const fn with_max_jitter(mut self, max_jitter: Duration) -> Self {
+        self.inner = self.inner.with_max_jitter(max_jitter);
+        self
+    }
+
+    /// Set the factor for the backoff.
+    ///
+    /// # Panics
+    ///
+    /// The backoff will panic while building if the factor is not greater than `0.0`, use
+    /// [`BackoffBuilder::try_build`] to get an error instead.
+    pub const fn with_factor(mut self, factor: f32) -> Self {
+        self.inner = self.inner.with_factor(factor);
+        self
+    }
+
+    /// Set the minimum delay for the backoff.
+    ///
+    /// The first delay falls within `[min_delay / 2, min_delay)`.
+    pub const fn with_min_delay(mut self, min_delay: Duration) -> Self {
+        self.inner = self.inner.with_min_delay(min_delay);
+        self
+    }
+
+    /// Set the maximum delay for the backoff.
+    ///
+    /// The delay will not increase if the current delay is greater than the maximum delay.
+    pub const fn with_max_delay(mut self, max_delay: Duration) -> Self {
+        self.inner = self.inner.with_max_delay(max_delay);
+        self
+    }
+
+    /// Set no maximum delay for the backoff.
+    ///
+    /// The delay will keep increasing.
+    ///
+    /// _The delay will saturate at `Duration::MAX` which is an **unrealistic** delay._
+    pub const fn without_max_delay(mut self) -> Self {
+        self.inner = self.inner.without_max_delay();
+        self
+    }
+
+    /// Set the maximum number of attempts for the current backoff.
+    ///
+    /// `max_times` is the number of retries, so the operation is attempted at most `max_times + 1`
+    /// times. `with_max_times(0)` makes exactly one attempt without any retry.
+    pub const fn with_max_times(mut self, max_times: usize) -> Self {
+        self.inner = self.inner.with_max_times(max_times);
+        self
+    }
+
+    /// Start the backoff as if given number of attempts have already been made.
+    ///
+    /// See [`ExponentialBuilder::with_starting_attempt`] for details.
+    pub const fn with_starting_attempt(mut self, attempt: usize) -> Self {
+        self.inner = self.inner.with_starting_attempt(attempt);
+        self
+    }
+
+    /// Set the maximum number of delays in the plateau phase.
+    ///
+    /// See [`ExponentialBuilder::with_plateau_times`] for details.
+    pub const fn with_plateau_times(mut self, plateau_times: usize) -> Self {
+        self.inner = self.inner.with_plateau_times(plateau_times);
+        self
+    }
+
+    /// Set no maximum number of attempts for the current backoff.
+    ///
+    /// The backoff will not stop by itself.
+    ///
+    /// Unlimited retries must be asked for explicitly, the default is bounded to 3 retries.
+    pub const fn without_max_times(mut self) -> Self {
+        self.inner = self.inner.without_max_times();
+        self
+    }
+}
+
+impl BackoffBuilder for EqualJitterBuilder {
+    type Backoff = ExponentialBackoff;
+
+    fn build(self) -> Self::Backoff {
+        self.inner.build()
+    }
+
+    fn try_build(self) -> Result<Self::Backoff, BackoffConfigError> {
+        self.inner.try_build()
+    }
+}
+
+impl BackoffBuilder for &EqualJitterBuilder {
+    type Backoff = ExponentialBackoff;
+
+    fn build(self) -> Self::Backoff {
+        (*self).build()
+    }
+
+    fn try_build(self) -> Result<Self::Backoff, BackoffConfigError> {
+        (*self).try_build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate alloc;
+
+    use alloc::vec::Vec;
+    use core::time::Duration;
+
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test as test;
+
+    use crate::BackoffBuilder;
+    use crate::EqualJitterBuilder;
+    use crate::ExponentialBuilder;
+
+    #[test]
+    fn test_equal_jitter_default() {
+        let mut it = EqualJitterBuilder::default().build();
+
+        for base in [1, 2, 4] {
+            let v = it.next().expect("value must valid");
+            assert!(v >= Duration::from_secs(base) / 2, "current: {v:?}");
+            assert!(v < Duration::from_secs(base), "current: {v:?}");
+        }
+        assert_eq!(None, it.next());
+    }
+
+    #[test]
+    fn test_equal_jitter_same_as_exponential() {
+        for seed in 0..10 {
+            let expected: Vec<_> = ExponentialBuilder::default()
+                .with_equal_jitter()
+                .with_jitter_seed(seed)
+                .with_min_delay(Duration::from_millis(100))
+                .with_max_delay(Duration::from_secs(1))
+                .with_max_times(8)
+                .build()
+                .collect();
+            let actual: Vec<_> = EqualJitterBuilder::default()
+                .with_jitter_seed(seed)
+                .with_min_delay(Duration::from_millis(100))
+                .with_max_delay(Duration::from_secs(1))
+                .with_max_times(8)
+                .build()
+                .collect();
+
+            assert_eq!(expected, actual, "seed: {seed}");
+        }
+    }
+
+    #[test]
+    fn test_equal_jitter_try_build() {
+        assert!(EqualJitterBuilder::default()
+            .with_factor(0.0)
+            .try_build()
+            .is_err());
+    }
+}
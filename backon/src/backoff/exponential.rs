@@ -1,6 +1,16 @@
+use core::fmt;
+use core::hash::Hash;
 use core::time::Duration;
 
+use crate::backoff::new_rng;
+use crate::backoff::seed_from_key;
 use crate::backoff::BackoffBuilder;
+use crate::backoff::BackoffConfigError;
+use crate::backoff::Jitter;
+#[cfg(feature = "rand-core")]
+use crate::backoff::RngCoreJitter;
+#[cfg(feature = "rand-core")]
+use crate::backoff::WithRng;
 
 /// ExponentialBuilder is used to construct an [`ExponentialBackoff`] that offers delays with exponential retries.
 ///
@@ -12,6 +22,8 @@ use crate::backoff::BackoffBuilder;
 /// - max_delay: 60s
 /// - max_times: 3
 ///
+/// The default is bounded on purpose, call `without_max_times` to opt in to unlimited retries.
+///
 /// # Examples
 ///
 /// ```no_run
@@ -36,65 +48,281 @@ use crate::backoff::BackoffBuilder;
 /// ```
 #[derive(Debug, Clone, Copy)]
 pub struct ExponentialBuilder {
-    jitter: bool,
+    jitter: Jitter,
+    max_jitter: Option<Duration>,
     factor: f32,
     min_delay: Duration,
     max_delay: Option<Duration>,
     max_times: Option<usize>,
     seed: Option<u64>,
+    starting_attempt: usize,
+    plateau_times: Option<usize>,
 }
 
 impl Default for ExponentialBuilder {
     fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ExponentialBuilder {
+    /// Create a new builder with the default configuration, the same as
+    /// [`ExponentialBuilder::default`].
+    ///
+    /// The builder and its setters are `const`, so a policy could be declared in a `const` or a
+    /// `static` without lazy initialization.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use core::time::Duration;
+    ///
+    /// use backon::ExponentialBuilder;
+    ///
+    /// const POLICY: ExponentialBuilder = ExponentialBuilder::new()
+    ///     .with_jitter()
+    ///     .with_min_delay(Duration::from_millis(100))
+    ///     .with_max_times(5);
+    /// ```
+    pub const fn new() -> Self {
         Self {
-            jitter: false,
+            jitter: Jitter::None,
+            max_jitter: None,
             factor: 2.0,
             min_delay: Duration::from_secs(1),
             max_delay: Some(Duration::from_secs(60)),
             max_times: Some(3),
             seed: None,
+            starting_attempt: 0,
+            plateau_times: None,
         }
     }
-}
 
-impl ExponentialBuilder {
+    /// Create a builder preset for calling HTTP services.
+    ///
+    /// It retries quickly for transient errors and gives up within a few seconds:
+    ///
+    /// - jitter: true
+    /// - factor: 2
+    /// - min_delay: 100ms
+    /// - max_delay: 30s
+    /// - max_times: 5
+    ///
+    /// It's a starting point, tweak it with other methods like any builder.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use anyhow::Result;
+    /// use backon::ExponentialBuilder;
+    /// use backon::Retryable;
+    ///
+    /// async fn fetch() -> Result<String> {
+    ///     Ok(reqwest::get("https://www.rust-lang.org")
+    ///         .await?
+    ///         .text()
+    ///         .await?)
+    /// }
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<()> {
+    ///     let content = fetch
+    ///         .retry(ExponentialBuilder::for_http().with_max_times(3))
+    ///         .await?;
+    ///     println!("fetch succeeded: {}", content);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub const fn for_http() -> Self {
+        Self::new()
+            .with_jitter()
+            .with_min_delay(Duration::from_millis(100))
+            .with_max_delay(Duration::from_secs(30))
+            .with_max_times(5)
+    }
+
+    /// Create a builder preset for background jobs.
+    ///
+    /// Nobody is waiting for a background job, so it retries longer to ride out outages:
+    ///
+    /// - jitter: true
+    /// - factor: 2
+    /// - min_delay: 1s
+    /// - max_delay: 5min
+    /// - max_times: 10
+    ///
+    /// It's a starting point, tweak it with other methods like any builder.
+    pub const fn for_background_job() -> Self {
+        Self::new()
+            .with_jitter()
+            .with_min_delay(Duration::from_secs(1))
+            .with_max_delay(Duration::from_secs(5 * 60))
+            .with_max_times(10)
+    }
+
     /// Enable jitter for the backoff.
     ///
     /// When jitter is enabled, [`ExponentialBackoff`] will add a random jitter within `(0, min_delay)`
-    /// to the current delay.
-    pub fn with_jitter(mut self) -> Self {
-        self.jitter = true;
+    /// to the current delay. The jittered delay never exceeds `max_delay`.
+    pub const fn with_jitter(mut self) -> Self {
+        self.jitter = Jitter::Full;
+        self
+    }
+
+    /// Enable jitter bounded by a ratio of the current delay for the backoff.
+    ///
+    /// When jitter ratio is enabled, [`ExponentialBackoff`] will scale the current delay by a random
+    /// factor within `[1 - ratio, 1 + ratio]`, and the result is clamped within `[min_delay, max_delay]`.
+    ///
+    /// This replaces the jitter set by [`ExponentialBuilder::with_jitter`].
+    ///
+    /// # Panics
+    ///
+    /// The backoff will panic while building if the ratio is not within `[0.0, 1.0]`, use
+    /// [`BackoffBuilder::try_build`] to get an error instead.
+    pub const fn with_jitter_ratio(mut self, ratio: f32) -> Self {
+        self.jitter = Jitter::Ratio(ratio);
+        self
+    }
+
+    /// Enable equal jitter for the backoff.
+    ///
+    /// When equal jitter is enabled, [`ExponentialBackoff`] will keep half of the current delay
+    /// and add a random jitter within `[0, delay / 2)`, so the delay falls within
+    /// `[delay / 2, delay)`. The result is clamped within `[min_delay / 2, max_delay]`.
+    ///
+    /// This replaces the jitter set by [`ExponentialBuilder::with_jitter`]. See also
+    /// [`EqualJitterBuilder`](crate::EqualJitterBuilder) which provides the same backoff as a
+    /// named type.
+    pub const fn with_equal_jitter(mut self) -> Self {
+        self.jitter = Jitter::Equal;
         self
     }
 
-    /// Set the seed value for the jitter random number generator. If no seed is given, a random seed is used in std and default seed is used in no_std.
-    pub fn with_jitter_seed(mut self, seed: u64) -> Self {
-        self.seed = Some(seed);
+    /// Cap the jitter to at most `max_jitter` away from the delay, in either direction.
+    ///
+    /// The jitter is proportional to the delay, so it could add or remove many seconds from a
+    /// large delay. With the cap, the smaller of the jitter drawn and `max_jitter` is applied,
+    /// which bounds the worst-case timing regardless of the delay. The cap has no effect
+    /// without jitter.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use core::time::Duration;
+    ///
+    /// use backon::BackoffBuilder;
+    /// use backon::ExponentialBuilder;
+    ///
+    /// let backoff = ExponentialBuilder::default()
+    ///     .with_min_delay(Duration::from_secs(10))
+    ///     .with_max_delay(Duration::from_secs(60))
+    ///     .with_jitter_ratio(0.5)
+    ///     .with_max_jitter(Duration::from_millis(500))
+    ///     .build();
+    ///
+    /// for (delay, expected) in backoff.zip([10, 20, 40]) {
+    ///     let expected = Duration::from_secs(expected);
+    ///     assert!(delay >= expected - Duration::from_millis(500));
+    ///     assert!(delay <= expected + Duration::from_millis(500));
+    /// }
+    /// ```
+    pub const fn with_max_jitter(mut self, max_jitter: Duration) -> Self {
+        self.max_jitter = Some(max_jitter);
         self
     }
 
+    /// Set the seed of the jitter random number generator.
+    ///
+    /// `Some(seed)` makes the jitter deterministic, which is useful in tests. `None` seeds the
+    /// generator from the system entropy in std, and uses a default seed in no_std. The
+    /// generator is created by [`BackoffBuilder::build`], so all backoffs built from a builder
+    /// with a seed give the same delays.
+    pub const fn with_rng_seed(mut self, seed: Option<u64>) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Set the seed value for the jitter random number generator, it's a shorthand of
+    /// `with_rng_seed(Some(seed))`.
+    pub const fn with_jitter_seed(self, seed: u64) -> Self {
+        self.with_rng_seed(Some(seed))
+    }
+
+    /// Seed the jitter random number generator from given key, like a hostname or a shard id.
+    ///
+    /// The backoffs built with the same key give the same delays, while different keys give
+    /// different delays. That makes the jitter of every node reproducible, yet spreads the
+    /// retries of the nodes in a cluster so they don't retry in lockstep. The key is hashed
+    /// into the seed set by [`ExponentialBuilder::with_rng_seed`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use backon::BackoffBuilder;
+    /// use backon::ExponentialBuilder;
+    ///
+    /// let builder = ExponentialBuilder::default().with_jitter();
+    /// let a = builder.with_jitter_key("node-a").build();
+    ///
+    /// assert!(a.eq(builder.with_jitter_key("node-a").build()));
+    /// ```
+    pub fn with_jitter_key(self, key: impl Hash) -> Self {
+        self.with_rng_seed(Some(seed_from_key(key)))
+    }
+
     /// Set the factor for the backoff.
     ///
+    /// A factor greater than `1.0` makes the delay grow from `min_delay` to `max_delay`, and `1.0`
+    /// keeps it at `min_delay`.
+    ///
+    /// A factor within `(0.0, 1.0)` makes a decaying schedule that starts cautious and speeds up:
+    /// the delay starts at `max_delay` and shrinks down to `min_delay`, where it stays. Without
+    /// a maximum delay, the delay stays at `min_delay`.
+    ///
     /// # Panics
     ///
-    /// This function will panic if the input factor is less than `1.0`.
-    pub fn with_factor(mut self, factor: f32) -> Self {
-        debug_assert!(factor >= 1.0, "invalid factor that lower than 1");
-
+    /// The backoff will panic while building if the factor is not greater than `0.0`, use
+    /// [`BackoffBuilder::try_build`] to get an error instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use core::time::Duration;
+    ///
+    /// use backon::BackoffBuilder;
+    /// use backon::ExponentialBuilder;
+    ///
+    /// let mut backoff = ExponentialBuilder::default()
+    ///     .with_factor(0.5)
+    ///     .with_min_delay(Duration::from_secs(1))
+    ///     .with_max_delay(Duration::from_secs(4))
+    ///     .with_max_times(4)
+    ///     .build();
+    ///
+    /// assert_eq!(backoff.next(), Some(Duration::from_secs(4)));
+    /// assert_eq!(backoff.next(), Some(Duration::from_secs(2)));
+    /// assert_eq!(backoff.next(), Some(Duration::from_secs(1)));
+    /// assert_eq!(backoff.next(), Some(Duration::from_secs(1)));
+    /// ```
+    pub const fn with_factor(mut self, factor: f32) -> Self {
         self.factor = factor;
         self
     }
 
     /// Set the minimum delay for the backoff.
-    pub fn with_min_delay(mut self, min_delay: Duration) -> Self {
+    pub const fn with_min_delay(mut self, min_delay: Duration) -> Self {
         self.min_delay = min_delay;
         self
     }
 
     /// Set the maximum delay for the backoff.
     ///
-    /// The delay will not increase if the current delay exceeds the maximum delay.
-    pub fn with_max_delay(mut self, max_delay: Duration) -> Self {
+    /// The delay will not increase if the current delay exceeds the maximum delay. Once the delay
+    /// reaches the maximum delay, the backoff enters the plateau phase and keeps returning the
+    /// maximum delay until `max_times` or `plateau_times` is reached.
+    pub const fn with_max_delay(mut self, max_delay: Duration) -> Self {
         self.max_delay = Some(max_delay);
         self
     }
@@ -104,7 +332,7 @@ impl ExponentialBuilder {
     /// The delay will keep increasing.
     ///
     /// _The delay will saturate at `Duration::MAX` which is an **unrealistic** delay._
-    pub fn without_max_delay(mut self) -> Self {
+    pub const fn without_max_delay(mut self) -> Self {
         self.max_delay = None;
         self
     }
@@ -112,17 +340,86 @@ impl ExponentialBuilder {
     /// Set the maximum number of attempts for the current backoff.
     ///
     /// The backoff will stop if the maximum number of attempts is reached.
-    pub fn with_max_times(mut self, max_times: usize) -> Self {
+    ///
+    /// `max_times` is the number of retries, so the operation is attempted at most `max_times + 1`
+    /// times. `with_max_times(0)` makes exactly one attempt without any retry.
+    pub const fn with_max_times(mut self, max_times: usize) -> Self {
         self.max_times = Some(max_times);
         self
     }
 
+    /// Start the backoff as if given number of attempts have already been made.
+    ///
+    /// The backoff skips the first `attempt` delays when it's built, so the next delay is the one
+    /// that would follow them. It's useful to resume the backoff at the position restored from a
+    /// previous run.
+    ///
+    /// The skipped attempts count toward `max_times`, the backoff is empty if `attempt` is not
+    /// less than `max_times`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use core::time::Duration;
+    ///
+    /// use backon::BackoffBuilder;
+    /// use backon::ExponentialBuilder;
+    ///
+    /// // 1s and 2s have been slept in the previous run.
+    /// let mut backoff = ExponentialBuilder::default()
+    ///     .with_starting_attempt(2)
+    ///     .build();
+    ///
+    /// assert_eq!(backoff.next(), Some(Duration::from_secs(4)));
+    /// assert_eq!(backoff.next(), None);
+    /// ```
+    pub const fn with_starting_attempt(mut self, attempt: usize) -> Self {
+        self.starting_attempt = attempt;
+        self
+    }
+
+    /// Set the maximum number of delays in the plateau phase.
+    ///
+    /// The plateau phase starts with the first delay that reaches the maximum delay, or
+    /// `Duration::MAX` if there is no maximum delay. The backoff stops after `plateau_times`
+    /// delays at the plateau, which bounds the constant tail separately from the growth phase.
+    /// `max_times` still applies to the whole backoff.
+    ///
+    /// If not specified, the plateau lasts until `max_times` is reached.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use core::time::Duration;
+    ///
+    /// use backon::BackoffBuilder;
+    /// use backon::ExponentialBuilder;
+    ///
+    /// let delays: Vec<_> = ExponentialBuilder::default()
+    ///     .with_max_delay(Duration::from_secs(4))
+    ///     .with_plateau_times(2)
+    ///     .without_max_times()
+    ///     .build()
+    ///     .collect();
+    ///
+    /// assert_eq!(
+    ///     delays,
+    ///     [1, 2, 4, 4].map(Duration::from_secs),
+    /// );
+    /// ```
+    pub const fn with_plateau_times(mut self, plateau_times: usize) -> Self {
+        self.plateau_times = Some(plateau_times);
+        self
+    }
+
     /// Set no maximum number of attempts for the current backoff.
     ///
     /// The backoff will not stop by itself.
     ///
     /// _The backoff could stop reaching `usize::MAX` attempts but this is **unrealistic**._
-    pub fn without_max_times(mut self) -> Self {
+    ///
+    /// Unlimited retries must be asked for explicitly, the default is bounded to 3 retries.
+    pub const fn without_max_times(mut self) -> Self {
         self.max_times = None;
         self
     }
@@ -132,27 +429,40 @@ impl BackoffBuilder for ExponentialBuilder {
     type Backoff = ExponentialBackoff;
 
     fn build(self) -> Self::Backoff {
-        ExponentialBackoff {
-            jitter: self.jitter,
-            rng: if let Some(seed) = self.seed {
-                fastrand::Rng::with_seed(seed)
-            } else {
-                #[cfg(feature = "std")]
-                let rng = fastrand::Rng::new();
+        self.try_build().unwrap_or_else(|err| panic!("{err}"))
+    }
 
-                #[cfg(not(feature = "std"))]
-                let rng = fastrand::Rng::with_seed(super::RANDOM_SEED);
+    fn try_build(self) -> Result<Self::Backoff, BackoffConfigError> {
+        self.jitter.validate()?;
+        if self.factor.is_nan() || self.factor <= 0.0 {
+            return Err(BackoffConfigError::InvalidFactor(self.factor));
+        }
+        if let Some(max_delay) = self.max_delay {
+            if self.min_delay > max_delay {
+                return Err(BackoffConfigError::MinDelayExceedsMaxDelay {
+                    min_delay: self.min_delay,
+                    max_delay,
+                });
+            }
+        }
 
-                rng
-            },
+        let mut backoff = ExponentialBackoff {
+            jitter: self.jitter,
+            max_jitter: self.max_jitter,
+            rng: new_rng(self.seed),
+            seeded: self.seed.is_some(),
             factor: self.factor,
             min_delay: self.min_delay,
             max_delay: self.max_delay,
             max_times: self.max_times,
+            plateau_times: self.plateau_times,
 
             current_delay: None,
             attempts: 0,
-        }
+            plateau_attempts: 0,
+        };
+        backoff.skip_attempts(self.starting_attempt);
+        Ok(backoff)
     }
 }
 
@@ -162,40 +472,149 @@ impl BackoffBuilder for &ExponentialBuilder {
     fn build(self) -> Self::Backoff {
         (*self).build()
     }
+
+    fn try_build(self) -> Result<Self::Backoff, BackoffConfigError> {
+        (*self).try_build()
+    }
 }
 
 /// ExponentialBackoff provides a delay with exponential retries.
 ///
 /// This backoff strategy is constructed by [`ExponentialBuilder`].
 #[doc(hidden)]
-#[derive(Debug)]
+#[derive(Clone)]
 pub struct ExponentialBackoff {
-    jitter: bool,
+    jitter: Jitter,
+    max_jitter: Option<Duration>,
     rng: fastrand::Rng,
+    /// Whether the seed of the jitter is given explicitly.
+    seeded: bool,
     factor: f32,
     min_delay: Duration,
     max_delay: Option<Duration>,
     max_times: Option<usize>,
+    plateau_times: Option<usize>,
 
     current_delay: Option<Duration>,
     attempts: usize,
+    plateau_attempts: usize,
 }
 
-impl Iterator for ExponentialBackoff {
-    type Item = Duration;
+/// Show the policy and the attempts made, the random number generator is omitted.
+impl fmt::Debug for ExponentialBackoff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExponentialBackoff")
+            .field("jitter", &self.jitter)
+            .field("max_jitter", &self.max_jitter)
+            .field("factor", &self.factor)
+            .field("min_delay", &self.min_delay)
+            .field("max_delay", &self.max_delay)
+            .field("max_times", &self.max_times)
+            .field("plateau_times", &self.plateau_times)
+            .field("attempts", &self.attempts)
+            .finish_non_exhaustive()
+    }
+}
 
-    fn next(&mut self) -> Option<Self::Item> {
+impl ExponentialBackoff {
+    /// Return the next delay without consuming it, or `None` if the jitter is set.
+    ///
+    /// This allows showing the upcoming delay, for example in the logs, without advancing the
+    /// backoff. A jittered delay is only decided when it's drawn, so it can't be peeked.
+    ///
+    /// It's an inherent method of the builtin exponential, constant and fibonacci backoffs.
+    /// [`Backoff`](crate::Backoff) doesn't provide it, since most backoffs can't tell their
+    /// next delay in advance.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    ///
+    /// use backon::BackoffBuilder;
+    /// use backon::ExponentialBuilder;
+    ///
+    /// let mut backoff = ExponentialBuilder::default().build();
+    /// assert_eq!(backoff.peek(), Some(Duration::from_secs(1)));
+    /// assert_eq!(backoff.next(), Some(Duration::from_secs(1)));
+    /// assert_eq!(backoff.peek(), Some(Duration::from_secs(2)));
+    ///
+    /// // The jittered delays can't be peeked.
+    /// let backoff = ExponentialBuilder::default().with_jitter().build();
+    /// assert_eq!(backoff.peek(), None);
+    /// ```
+    pub fn peek(&self) -> Option<Duration> {
+        if self.jitter != Jitter::None {
+            return None;
+        }
+        self.clone().next()
+    }
+
+    /// Return the next delay, drawing the jitter from given random number generator instead of
+    /// the one seeded by the builder.
+    ///
+    /// This allows embedding applications to control the randomness instead of relying on the
+    /// seed of the builder, for example to reproduce a run.
+    ///
+    /// It's an inherent method of the builtin exponential, constant and fibonacci backoffs.
+    /// To draw every delay of a retry, or of any code generic over [`Backoff`](crate::Backoff),
+    /// from the generator, wrap the backoff with [`ExponentialBackoff::with_rng`] instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use backon::BackoffBuilder;
+    /// use backon::ExponentialBuilder;
+    /// use rand::rngs::SmallRng;
+    /// use rand::SeedableRng;
+    ///
+    /// let builder = ExponentialBuilder::default().with_jitter();
+    ///
+    /// // The generators seeded with the same value give the same delays.
+    /// let mut a = builder.build();
+    /// let mut b = builder.build();
+    /// let mut rng_a = SmallRng::seed_from_u64(42);
+    /// let mut rng_b = SmallRng::seed_from_u64(42);
+    /// for _ in 0..3 {
+    ///     assert_eq!(a.next_with_rng(&mut rng_a), b.next_with_rng(&mut rng_b));
+    /// }
+    /// ```
+    #[cfg(feature = "rand-core")]
+    pub fn next_with_rng<R: rand_core::RngCore + ?Sized>(
+        &mut self,
+        rng: &mut R,
+    ) -> Option<Duration> {
+        let delay = self.next_delay()?;
+        let delay = self.jitter.apply(
+            delay,
+            self.min_delay,
+            self.max_jitter,
+            &mut RngCoreJitter(rng),
+        );
+        Some(self.bound(delay))
+    }
+
+    /// Draw the jitter of every delay from given random number generator instead of the one
+    /// seeded by the builder.
+    ///
+    /// See [`WithRng`] for details.
+    #[cfg(feature = "rand-core")]
+    pub fn with_rng<R: rand_core::RngCore>(self, rng: R) -> WithRng<Self, R> {
+        WithRng::new(self, rng)
+    }
+
+    /// Advance to the next delay before applying the jitter.
+    fn next_delay(&mut self) -> Option<Duration> {
         if self.attempts >= self.max_times.unwrap_or(usize::MAX) {
             return None;
         }
-        self.attempts += 1;
 
-        let mut tmp_cur = match self.current_delay {
-            None => {
-                // If current_delay is None, it's must be the first time to retry.
-                self.current_delay = Some(self.min_delay);
-                self.min_delay
-            }
+        let next = match self.current_delay {
+            // If current_delay is None, it's must be the first time to retry.
+            None if self.decaying() => self.max_delay.unwrap_or(self.min_delay),
+            None => self.min_delay,
+            // A decaying delay shrinks down to the min delay.
+            Some(cur) if self.decaying() => saturating_mul(cur, self.factor).max(self.min_delay),
             Some(mut cur) => {
                 // If current delay larger than max delay, we should stop increment anymore.
                 if let Some(max_delay) = self.max_delay {
@@ -208,15 +627,114 @@ impl Iterator for ExponentialBackoff {
                 } else {
                     cur = saturating_mul(cur, self.factor);
                 }
-                self.current_delay = Some(cur);
                 cur
             }
         };
-        // If jitter is enabled, add random jitter based on min delay.
-        if self.jitter {
-            tmp_cur = tmp_cur.saturating_add(self.min_delay.mul_f32(self.rng.f32()));
+
+        // The delays that reach the cap are in the plateau phase.
+        if self.is_plateau(next) {
+            if self.plateau_attempts >= self.plateau_times.unwrap_or(usize::MAX) {
+                return None;
+            }
+            self.plateau_attempts += 1;
+        }
+        self.attempts += 1;
+        self.current_delay = Some(next);
+        Some(next)
+    }
+
+    /// Skip the delays until given number of attempts have been made.
+    fn skip_attempts(&mut self, attempts: usize) {
+        while self.attempts < attempts {
+            if self.next_delay().is_none() {
+                return;
+            }
+            // The delay won't change anymore once it reaches the cap, jump to the end.
+            if self.in_plateau() {
+                let skipped = attempts.min(self.max_times.unwrap_or(usize::MAX)) - self.attempts;
+                let plateau_left = self.plateau_times.unwrap_or(usize::MAX) - self.plateau_attempts;
+                let skipped = skipped.min(plateau_left);
+                self.attempts += skipped;
+                self.plateau_attempts += skipped;
+                return;
+            }
+        }
+    }
+
+    /// Check if the delay shrinks instead of growing.
+    fn decaying(&self) -> bool {
+        self.factor < 1.0
+    }
+
+    /// Check if given delay is in the plateau phase, where it won't change anymore.
+    ///
+    /// The plateau is at the max delay for a growing delay, and at the min delay for a decaying
+    /// one.
+    fn is_plateau(&self, delay: Duration) -> bool {
+        if self.decaying() {
+            delay <= self.min_delay
+        } else {
+            delay >= self.max_delay.unwrap_or(Duration::MAX)
+        }
+    }
+
+    /// Check if the backoff has entered the plateau phase.
+    fn in_plateau(&self) -> bool {
+        self.current_delay
+            .is_some_and(|delay| self.is_plateau(delay))
+    }
+
+    /// Keep the jittered delay within the bounds.
+    fn bound(&self, mut delay: Duration) -> Duration {
+        delay = delay.max(self.jitter.lower_bound(self.min_delay));
+        if let Some(max_delay) = self.max_delay {
+            delay = delay.min(max_delay);
+        }
+        delay
+    }
+}
+
+impl BackoffBuilder for &ExponentialBackoff {
+    type Backoff = ExponentialBackoff;
+
+    fn build(self) -> Self::Backoff {
+        let mut backoff = self.clone();
+        // Every retry draws its own jitter, unless the seed is given explicitly.
+        if !backoff.seeded {
+            backoff.rng = new_rng(None);
+        }
+        backoff
+    }
+}
+
+impl Iterator for ExponentialBackoff {
+    type Item = Duration;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let delay = self.next_delay()?;
+        // Apply the jitter first, and then keep the final delay within the bounds.
+        let delay = self
+            .jitter
+            .apply(delay, self.min_delay, self.max_jitter, &mut self.rng);
+        Some(self.bound(delay))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self
+            .max_times
+            .map(|max_times| max_times.saturating_sub(self.attempts));
+        let Some(plateau_times) = self.plateau_times else {
+            return (remaining.unwrap_or(usize::MAX), remaining);
+        };
+
+        // The growth phase may never end, only the plateau phase is known to be bounded.
+        let plateau_left = plateau_times.saturating_sub(self.plateau_attempts);
+        let lower = remaining.map_or(plateau_left, |r| r.min(plateau_left));
+        if self.in_plateau() {
+            (lower, Some(lower))
+        } else {
+            (lower, remaining)
         }
-        Some(tmp_cur)
     }
 }
 
@@ -227,12 +745,18 @@ pub(crate) fn saturating_mul(d: Duration, rhs: f32) -> Duration {
 
 #[cfg(test)]
 mod tests {
+    extern crate alloc;
+
+    use alloc::format;
     use core::time::Duration;
 
     #[cfg(target_arch = "wasm32")]
     use wasm_bindgen_test::wasm_bindgen_test as test;
 
+    use crate::backoff::Jitter;
+    use crate::Backoff;
     use crate::BackoffBuilder;
+    use crate::BackoffConfigError;
     use crate::ExponentialBuilder;
 
     #[test]
@@ -245,6 +769,23 @@ mod tests {
         assert_eq!(None, exp.next());
     }
 
+    #[test]
+    fn test_exponential_presets() {
+        let mut exp = ExponentialBuilder::for_http().build();
+        for base in [100, 200, 400, 800, 1600] {
+            let base = Duration::from_millis(base);
+            let v = exp.next().expect("value must valid");
+            assert!(v >= base && v < base * 2, "current: {v:?}");
+        }
+        assert_eq!(None, exp.next());
+
+        let mut exp = ExponentialBuilder::for_background_job().build();
+        let v = exp.nth(9).expect("value must valid");
+        // The last delay is capped by the max delay.
+        assert_eq!(v, Duration::from_secs(5 * 60));
+        assert_eq!(None, exp.next());
+    }
+
     #[test]
     fn test_exponential_factor() {
         let mut exp = ExponentialBuilder::default().with_factor(1.5).build();
@@ -255,6 +796,16 @@ mod tests {
         assert_eq!(None, exp.next());
     }
 
+    #[test]
+    fn test_exponential_rng_seed() {
+        let builder = ExponentialBuilder::default().with_jitter_seed(7);
+        assert_eq!(builder.seed, Some(7));
+
+        // `None` goes back to the system entropy.
+        let builder = builder.with_rng_seed(None);
+        assert_eq!(builder.seed, None);
+    }
+
     #[test]
     fn test_exponential_jitter() {
         let mut exp = ExponentialBuilder::default().with_jitter().build();
@@ -274,6 +825,211 @@ mod tests {
         assert_eq!(None, exp.next());
     }
 
+    #[test]
+    fn test_exponential_const() {
+        const POLICY: ExponentialBuilder = ExponentialBuilder::new()
+            .with_factor(3.0)
+            .with_min_delay(Duration::from_secs(1))
+            .with_max_delay(Duration::from_secs(20))
+            .with_max_times(4);
+
+        let delays = [1, 3, 9, 20].map(Duration::from_secs);
+        assert!(POLICY.build().eq(delays));
+        assert!(ExponentialBuilder::new()
+            .build()
+            .eq(ExponentialBuilder::default().build()));
+    }
+
+    #[test]
+    fn test_exponential_max_jitter() {
+        let max_jitter = Duration::from_millis(50);
+
+        for seed in 0..100 {
+            let exp = ExponentialBuilder::default()
+                .with_min_delay(Duration::from_millis(100))
+                .with_max_times(6)
+                .with_jitter_ratio(0.1)
+                .with_max_jitter(max_jitter)
+                .with_jitter_seed(seed)
+                .build();
+
+            // 100ms, 200ms, ..., 3200ms: the ratio bounds the jitter of the small delays, and
+            // the cap bounds the large ones. Allow 1ms for the rounding of the delays.
+            for (i, v) in exp.enumerate() {
+                let delay = Duration::from_millis(100 << i);
+                let bound = (delay / 10).min(max_jitter) + Duration::from_millis(1);
+                assert!(v >= delay - bound, "seed: {seed}, current: {v:?}");
+                assert!(v <= delay + bound, "seed: {seed}, current: {v:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_exponential_jitter_ratio() {
+        let mut exp = ExponentialBuilder::default()
+            .with_jitter_ratio(0.1)
+            .with_max_delay(Duration::from_secs(4))
+            .with_max_times(4)
+            .build();
+
+        // The first delay can't be lower than min_delay.
+        let v = exp.next().expect("value must valid");
+        assert!(v >= Duration::from_secs(1), "current: {v:?}");
+        assert!(v <= Duration::from_millis(1100), "current: {v:?}");
+
+        let v = exp.next().expect("value must valid");
+        assert!(v >= Duration::from_millis(1800), "current: {v:?}");
+        assert!(v <= Duration::from_millis(2200), "current: {v:?}");
+
+        let v = exp.next().expect("value must valid");
+        assert!(v >= Duration::from_millis(3600), "current: {v:?}");
+        assert!(v <= Duration::from_secs(4), "current: {v:?}");
+
+        // The delay can't exceed max_delay.
+        let v = exp.next().expect("value must valid");
+        assert!(v >= Duration::from_millis(3600), "current: {v:?}");
+        assert!(v <= Duration::from_secs(4), "current: {v:?}");
+
+        assert_eq!(None, exp.next());
+    }
+
+    #[test]
+    fn test_exponential_jitter_within_bounds() {
+        let min_delay = Duration::from_millis(100);
+        let max_delay = Duration::from_millis(500);
+
+        for jitter in [
+            Jitter::None,
+            Jitter::Full,
+            Jitter::Ratio(0.5),
+            Jitter::Ratio(1.0),
+            Jitter::Equal,
+        ] {
+            for seed in 0..100 {
+                let exp = ExponentialBuilder {
+                    jitter,
+                    max_jitter: None,
+                    seed: Some(seed),
+                    starting_attempt: 0,
+                    plateau_times: None,
+                    factor: 2.0,
+                    min_delay,
+                    max_delay: Some(max_delay),
+                    max_times: Some(10),
+                }
+                .build();
+
+                for v in exp {
+                    assert!(
+                        v >= jitter.lower_bound(min_delay),
+                        "jitter: {jitter:?}, seed: {seed}, current: {v:?}"
+                    );
+                    assert!(
+                        v <= max_delay,
+                        "jitter: {jitter:?}, seed: {seed}, current: {v:?}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid jitter ratio")]
+    fn test_exponential_invalid_jitter_ratio() {
+        let _ = ExponentialBuilder::default().with_jitter_ratio(1.5).build();
+    }
+
+    #[test]
+    fn test_exponential_try_build() {
+        assert!(ExponentialBuilder::default().try_build().is_ok());
+        assert_eq!(
+            ExponentialBuilder::default()
+                .with_jitter_ratio(1.5)
+                .try_build()
+                .unwrap_err(),
+            BackoffConfigError::InvalidJitterRatio(1.5)
+        );
+        assert_eq!(
+            ExponentialBuilder::default()
+                .with_factor(0.0)
+                .try_build()
+                .unwrap_err(),
+            BackoffConfigError::InvalidFactor(0.0)
+        );
+        assert_eq!(
+            ExponentialBuilder::default()
+                .with_factor(-1.0)
+                .try_build()
+                .unwrap_err(),
+            BackoffConfigError::InvalidFactor(-1.0)
+        );
+        assert!(ExponentialBuilder::default()
+            .with_factor(f32::NAN)
+            .try_build()
+            .is_err());
+        assert_eq!(
+            ExponentialBuilder::default()
+                .with_min_delay(Duration::from_secs(10))
+                .with_max_delay(Duration::from_secs(5))
+                .try_build()
+                .unwrap_err(),
+            BackoffConfigError::MinDelayExceedsMaxDelay {
+                min_delay: Duration::from_secs(10),
+                max_delay: Duration::from_secs(5),
+            }
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid factor")]
+    fn test_exponential_invalid_factor() {
+        let _ = ExponentialBuilder::default().with_factor(0.0).build();
+    }
+
+    #[test]
+    fn test_exponential_decaying_factor() {
+        let mut exp = ExponentialBuilder::default()
+            .with_factor(0.5)
+            .with_min_delay(Duration::from_millis(300))
+            .with_max_delay(Duration::from_secs(2))
+            .with_max_times(5)
+            .build();
+
+        // The delay starts at max_delay and never goes below min_delay.
+        assert_eq!(Some(Duration::from_secs(2)), exp.next());
+        assert_eq!(Some(Duration::from_secs(1)), exp.next());
+        assert_eq!(Some(Duration::from_millis(500)), exp.next());
+        assert_eq!(Some(Duration::from_millis(300)), exp.next());
+        assert_eq!(Some(Duration::from_millis(300)), exp.next());
+        assert_eq!(None, exp.next());
+
+        // Without max_delay, there is nothing to decay from.
+        let mut exp = ExponentialBuilder::default()
+            .with_factor(0.5)
+            .without_max_delay()
+            .with_max_times(2)
+            .build();
+        assert_eq!(Some(Duration::from_secs(1)), exp.next());
+        assert_eq!(Some(Duration::from_secs(1)), exp.next());
+        assert_eq!(None, exp.next());
+    }
+
+    #[test]
+    fn test_exponential_decaying_plateau_times() {
+        let mut exp = ExponentialBuilder::default()
+            .with_factor(0.5)
+            .with_max_delay(Duration::from_secs(4))
+            .with_plateau_times(1)
+            .without_max_times()
+            .build();
+
+        // The plateau of a decaying delay is at min_delay.
+        assert_eq!(Some(Duration::from_secs(4)), exp.next());
+        assert_eq!(Some(Duration::from_secs(2)), exp.next());
+        assert_eq!(Some(Duration::from_secs(1)), exp.next());
+        assert_eq!(None, exp.next());
+    }
+
     #[test]
     fn test_exponential_min_delay() {
         let mut exp = ExponentialBuilder::default()
@@ -332,12 +1088,15 @@ mod tests {
     #[test]
     fn test_exponential_max_delay_without_default_1() {
         let mut exp = ExponentialBuilder {
-            jitter: false,
+            jitter: Jitter::None,
+            max_jitter: None,
             seed: Some(0x2fdb0020ffc7722b),
             factor: 10_000_000_000_f32,
             min_delay: Duration::from_secs(1),
             max_delay: None,
             max_times: None,
+            starting_attempt: 0,
+            plateau_times: None,
         }
         .build();
 
@@ -350,12 +1109,15 @@ mod tests {
     #[test]
     fn test_exponential_max_delay_without_default_2() {
         let mut exp = ExponentialBuilder {
-            jitter: true,
+            jitter: Jitter::Full,
+            max_jitter: None,
             seed: Some(0x2fdb0020ffc7722b),
             factor: 10_000_000_000_f32,
             min_delay: Duration::from_secs(10_000_000_000),
             max_delay: None,
             max_times: Some(2),
+            starting_attempt: 0,
+            plateau_times: None,
         }
         .build();
         let v = exp.next().expect("value must valid");
@@ -368,12 +1130,15 @@ mod tests {
     #[test]
     fn test_exponential_max_delay_without_default_3() {
         let mut exp = ExponentialBuilder {
-            jitter: false,
+            jitter: Jitter::None,
+            max_jitter: None,
             seed: Some(0x2fdb0020ffc7722b),
             factor: 10_000_000_000_f32,
             min_delay: Duration::from_secs(10_000_000_000),
             max_delay: Some(Duration::from_secs(60_000_000_000)),
             max_times: Some(3),
+            starting_attempt: 0,
+            plateau_times: None,
         }
         .build();
         assert_eq!(Some(Duration::from_secs(10_000_000_000)), exp.next());
@@ -389,4 +1154,145 @@ mod tests {
         assert_eq!(Some(Duration::from_secs(1)), exp.next());
         assert_eq!(None, exp.next());
     }
+
+    #[test]
+    fn test_exponential_max_times_zero() {
+        let mut it = ExponentialBuilder::default()
+            .with_jitter()
+            .with_max_times(0)
+            .build();
+
+        assert_eq!(it.remaining(), Some(0));
+        assert_eq!(None, it.next());
+    }
+
+    #[test]
+    fn test_exponential_default_max_times_is_bounded() {
+        assert_eq!(ExponentialBuilder::default().build().remaining(), Some(3));
+        assert_eq!(
+            ExponentialBuilder::default()
+                .without_max_times()
+                .build()
+                .remaining(),
+            None
+        );
+    }
+
+    #[cfg(feature = "rand-core")]
+    #[test]
+    fn test_exponential_next_with_rng() {
+        use rand::rngs::SmallRng;
+        use rand::SeedableRng;
+
+        let builder = ExponentialBuilder::default()
+            .with_equal_jitter()
+            .with_max_times(5);
+        let mut a = builder.build();
+        let mut b = builder.build();
+        let mut rng_a = SmallRng::seed_from_u64(42);
+        let mut rng_b = SmallRng::seed_from_u64(42);
+
+        while let Some(v) = a.next_with_rng(&mut rng_a) {
+            assert_eq!(Some(v), b.next_with_rng(&mut rng_b));
+            assert!(v >= Duration::from_millis(500), "current: {v:?}");
+            assert!(v <= Duration::from_secs(60), "current: {v:?}");
+        }
+        assert_eq!(None, b.next_with_rng(&mut rng_b));
+    }
+
+    #[test]
+    fn test_exponential_starting_attempt() {
+        let mut exp = ExponentialBuilder::default()
+            .with_starting_attempt(1)
+            .build();
+
+        assert_eq!(exp.remaining(), Some(2));
+        assert_eq!(Some(Duration::from_secs(2)), exp.next());
+        assert_eq!(Some(Duration::from_secs(4)), exp.next());
+        assert_eq!(None, exp.next());
+
+        // The skipped attempts count toward max_times.
+        let mut exp = ExponentialBuilder::default()
+            .with_starting_attempt(3)
+            .build();
+        assert_eq!(None, exp.next());
+    }
+
+    #[test]
+    fn test_exponential_starting_attempt_capped() {
+        let mut exp = ExponentialBuilder::default()
+            .with_max_delay(Duration::from_secs(8))
+            .without_max_times()
+            .with_starting_attempt(1_000_000_000)
+            .build();
+
+        assert_eq!(Some(Duration::from_secs(8)), exp.next());
+        assert_eq!(Some(Duration::from_secs(8)), exp.next());
+    }
+
+    #[test]
+    fn test_exponential_plateau_times() {
+        let mut exp = ExponentialBuilder::default()
+            .with_max_delay(Duration::from_secs(4))
+            .with_plateau_times(2)
+            .without_max_times()
+            .build();
+
+        // The growth phase doesn't count toward plateau_times.
+        assert_eq!(exp.remaining(), None);
+        assert_eq!(Some(Duration::from_secs(1)), exp.next());
+        assert_eq!(Some(Duration::from_secs(2)), exp.next());
+        assert_eq!(Some(Duration::from_secs(4)), exp.next());
+        assert_eq!(exp.remaining(), Some(1));
+        assert_eq!(Some(Duration::from_secs(4)), exp.next());
+        assert_eq!(exp.remaining(), Some(0));
+        assert_eq!(None, exp.next());
+
+        // max_times still applies to the whole backoff.
+        let exp = ExponentialBuilder::default()
+            .with_max_delay(Duration::from_secs(4))
+            .with_plateau_times(5)
+            .with_max_times(4)
+            .build();
+        assert_eq!(exp.count(), 4);
+
+        // The plateau could be empty.
+        let mut exp = ExponentialBuilder::default()
+            .with_max_delay(Duration::from_secs(4))
+            .with_plateau_times(0)
+            .without_max_times()
+            .build();
+        assert_eq!(Some(Duration::from_secs(1)), exp.next());
+        assert_eq!(Some(Duration::from_secs(2)), exp.next());
+        assert_eq!(None, exp.next());
+    }
+
+    #[test]
+    fn test_exponential_plateau_times_with_starting_attempt() {
+        let mut exp = ExponentialBuilder::default()
+            .with_max_delay(Duration::from_secs(4))
+            .with_plateau_times(3)
+            .without_max_times()
+            .with_starting_attempt(4)
+            .build();
+
+        // 1s, 2s, 4s and 4s have been slept, one delay is left in the plateau.
+        assert_eq!(Some(Duration::from_secs(4)), exp.next());
+        assert_eq!(None, exp.next());
+    }
+
+    #[test]
+    fn test_exponential_debug() {
+        let mut exp = ExponentialBuilder::default()
+            .with_min_delay(Duration::from_millis(100))
+            .with_max_times(5)
+            .build();
+        exp.next();
+
+        let debug = format!("{exp:?}");
+        assert!(debug.contains("min_delay: 100ms"), "{debug}");
+        assert!(debug.contains("max_times: Some(5)"), "{debug}");
+        assert!(debug.contains("attempts: 1"), "{debug}");
+        assert!(!debug.contains("rng"), "{debug}");
+    }
 }
@@ -0,0 +1,647 @@
+use core::fmt;
+use core::iter;
+use core::time::Duration;
+
+use crate::backoff::BackoffBuilder;
+use crate::backoff::BackoffConfigError;
+
+/// BackoffBuilderExt provides combinators for all [`BackoffBuilder`]s.
+///
+/// Since every [`Backoff`](crate::Backoff) is also a [`BackoffBuilder`], the combinators
+/// can be used on custom backoffs as well. Some of them share names with [`Iterator`]
+/// methods, so use the fully qualified syntax like `BackoffBuilderExt::take(backoff, 3)`
+/// if both traits apply.
+///
+/// # Examples
+///
+/// ```no_run
+/// use anyhow::Result;
+/// use backon::BackoffBuilderExt;
+/// use backon::ConstantBuilder;
+/// use backon::Retryable;
+///
+/// async fn fetch() -> Result<String> {
+///     Ok(reqwest::get("https://www.rust-lang.org")
+///         .await?
+///         .text()
+///         .await?)
+/// }
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() -> Result<()> {
+///     let content = fetch
+///         .retry(ConstantBuilder::default().without_max_times().take(5))
+///         .await?;
+///     println!("fetch succeeded: {}", content);
+///
+///     Ok(())
+/// }
+/// ```
+pub trait BackoffBuilderExt: BackoffBuilder + Sized {
+    /// Limit the backoff to yield at most `n` delays.
+    ///
+    /// The backoff stops after `n` delays even if the underlying backoff never stops by itself,
+    /// and stops earlier if the underlying backoff stops first.
+    fn take(self, n: usize) -> Take<Self> {
+        Take { builder: self, n }
+    }
+
+    /// Transform every delay yielded by the backoff.
+    ///
+    /// The transform is applied to the final delays, that is after jitter and clamping
+    /// have been applied by the underlying backoff.
+    fn map_delay<F>(self, f: F) -> MapDelay<Self, F>
+    where
+        F: FnMut(Duration) -> Duration + Send + Sync + Unpin,
+    {
+        MapDelay { builder: self, f }
+    }
+
+    /// Call a function with every delay yielded by the backoff, and pass the delay through.
+    ///
+    /// It mirrors [`Iterator::inspect`], and is handy to see the delays while debugging a
+    /// backoff. Like [`BackoffBuilderExt::map_delay`], the function sees the final delays, and
+    /// the position in a chain of combinators decides which delays it sees.
+    ///
+    /// ```
+    /// use backon::BackoffBuilder;
+    /// use backon::BackoffBuilderExt;
+    /// use backon::ExponentialBuilder;
+    ///
+    /// let backoff = ExponentialBuilder::default()
+    ///     .with_jitter()
+    ///     .inspect(|d| println!("next delay: {:?}", d))
+    ///     .build();
+    /// ```
+    fn inspect<F>(self, f: F) -> Inspect<Self, F>
+    where
+        F: FnMut(&Duration) + Send + Sync + Unpin,
+    {
+        Inspect { builder: self, f }
+    }
+
+    /// Continue with another backoff after this backoff stops.
+    ///
+    /// For example, `ConstantBuilder::default().with_delay(Duration::from_millis(50)).with_max_times(5).chain(ExponentialBuilder::default())`
+    /// retries 5 times quickly before switching to exponential delays.
+    ///
+    /// Both backoffs are built together when the chain is built, so every retry starts
+    /// over from the beginning of the first backoff.
+    fn chain<B: BackoffBuilder>(self, other: B) -> Chain<Self, B> {
+        Chain {
+            first: self,
+            second: other,
+        }
+    }
+
+    /// Combine the delays of this backoff and another backoff step by step with `f`.
+    ///
+    /// Every step pulls a delay from both backoffs and yields `f(this, other)`, and the backoff
+    /// stops as soon as either of them stops. Unlike [`BackoffBuilderExt::chain`] which runs
+    /// the backoffs one after another, this overlays them, see [`BackoffBuilderExt::zip_max`]
+    /// and [`BackoffBuilderExt::zip_min`] for the common cases.
+    ///
+    /// ```
+    /// use std::time::Duration;
+    ///
+    /// use backon::BackoffBuilder;
+    /// use backon::BackoffBuilderExt;
+    /// use backon::ConstantBuilder;
+    /// use backon::ExponentialBuilder;
+    ///
+    /// let backoff = ExponentialBuilder::default()
+    ///     .with_max_times(4)
+    ///     .zip_with(ConstantBuilder::default().without_max_times(), |a, b| a + b)
+    ///     .build();
+    /// let delays = [2, 3, 5, 9].map(Duration::from_secs);
+    /// assert!(backoff.eq(delays));
+    /// ```
+    fn zip_with<B, F>(self, other: B, f: F) -> ZipWith<Self, B, F>
+    where
+        B: BackoffBuilder,
+        F: FnMut(Duration, Duration) -> Duration + Send + Sync + Unpin,
+    {
+        ZipWith {
+            first: self,
+            second: other,
+            f,
+        }
+    }
+
+    /// Take the longer delay of this backoff and another backoff at every step.
+    ///
+    /// It's a shorthand of [`BackoffBuilderExt::zip_with`] with [`Ord::max`], for example to
+    /// never retry sooner than either of two policies allows.
+    ///
+    /// ```
+    /// use std::time::Duration;
+    ///
+    /// use backon::BackoffBuilder;
+    /// use backon::BackoffBuilderExt;
+    /// use backon::ConstantBuilder;
+    /// use backon::ExponentialBuilder;
+    ///
+    /// let backoff = ExponentialBuilder::default()
+    ///     .zip_max(ConstantBuilder::default().with_delay(Duration::from_secs(3)))
+    ///     .build();
+    /// let delays = [3, 3, 4].map(Duration::from_secs);
+    /// assert!(backoff.eq(delays));
+    /// ```
+    fn zip_max<B: BackoffBuilder>(
+        self,
+        other: B,
+    ) -> ZipWith<Self, B, fn(Duration, Duration) -> Duration> {
+        self.zip_with(other, Ord::max)
+    }
+
+    /// Take the shorter delay of this backoff and another backoff at every step.
+    ///
+    /// It's a shorthand of [`BackoffBuilderExt::zip_with`] with [`Ord::min`].
+    fn zip_min<B: BackoffBuilder>(
+        self,
+        other: B,
+    ) -> ZipWith<Self, B, fn(Duration, Duration) -> Duration> {
+        self.zip_with(other, Ord::min)
+    }
+
+    /// Cap the sum of all the delays yielded by the backoff at `budget`.
+    ///
+    /// Once the next delay would take the sum over the budget, it's truncated to what's left of
+    /// the budget, and the backoff stops after it. So the total time slept by a retry never
+    /// exceeds the budget, however long the attempts themselves take, unlike a deadline on the
+    /// wall clock.
+    ///
+    /// ```
+    /// use std::time::Duration;
+    ///
+    /// use backon::BackoffBuilder;
+    /// use backon::BackoffBuilderExt;
+    /// use backon::ExponentialBuilder;
+    ///
+    /// let backoff = ExponentialBuilder::default()
+    ///     .without_max_times()
+    ///     .with_total_delay_budget(Duration::from_secs(10))
+    ///     .build();
+    /// let delays = [1, 2, 4, 3].map(Duration::from_secs);
+    /// assert!(backoff.eq(delays));
+    /// ```
+    fn with_total_delay_budget(self, budget: Duration) -> TotalDelayBudget<Self> {
+        TotalDelayBudget {
+            builder: self,
+            budget,
+        }
+    }
+
+    /// Preview up to `max` delays that the backoff would yield, without running any retry.
+    ///
+    /// The builder is cloned before building, so it can still be used afterward.
+    ///
+    /// ```
+    /// use backon::BackoffBuilderExt;
+    /// use backon::ExponentialBuilder;
+    ///
+    /// let builder = ExponentialBuilder::default().with_max_times(5);
+    /// println!("delays: {:?}", builder.preview(10));
+    /// ```
+    #[cfg(feature = "std")]
+    fn preview(&self, max: usize) -> std::vec::Vec<Duration>
+    where
+        Self: Clone,
+    {
+        Iterator::take(self.clone().build(), max).collect()
+    }
+}
+
+impl<B: BackoffBuilder> BackoffBuilderExt for B {}
+
+/// Take is a [`BackoffBuilder`] that limits the number of delays, created by [`BackoffBuilderExt::take`].
+#[derive(Debug, Clone, Copy)]
+pub struct Take<B> {
+    builder: B,
+    n: usize,
+}
+
+impl<B: BackoffBuilder> BackoffBuilder for Take<B> {
+    type Backoff = iter::Take<B::Backoff>;
+
+    fn build(self) -> Self::Backoff {
+        Iterator::take(self.builder.build(), self.n)
+    }
+
+    fn try_build(self) -> Result<Self::Backoff, BackoffConfigError> {
+        Ok(Iterator::take(self.builder.try_build()?, self.n))
+    }
+}
+
+/// MapDelay is a [`BackoffBuilder`] that transforms every delay, created by [`BackoffBuilderExt::map_delay`].
+#[derive(Clone, Copy)]
+pub struct MapDelay<B, F> {
+    builder: B,
+    f: F,
+}
+
+impl<B: fmt::Debug, F> fmt::Debug for MapDelay<B, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MapDelay")
+            .field("builder", &self.builder)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<B, F> BackoffBuilder for MapDelay<B, F>
+where
+    B: BackoffBuilder,
+    F: FnMut(Duration) -> Duration + Send + Sync + Unpin,
+{
+    type Backoff = iter::Map<B::Backoff, F>;
+
+    fn build(self) -> Self::Backoff {
+        Iterator::map(self.builder.build(), self.f)
+    }
+
+    fn try_build(self) -> Result<Self::Backoff, BackoffConfigError> {
+        Ok(Iterator::map(self.builder.try_build()?, self.f))
+    }
+}
+
+/// Inspect is a [`BackoffBuilder`] that calls a function with every delay, created by [`BackoffBuilderExt::inspect`].
+#[derive(Clone, Copy)]
+pub struct Inspect<B, F> {
+    builder: B,
+    f: F,
+}
+
+impl<B: fmt::Debug, F> fmt::Debug for Inspect<B, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Inspect")
+            .field("builder", &self.builder)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<B, F> BackoffBuilder for Inspect<B, F>
+where
+    B: BackoffBuilder,
+    F: FnMut(&Duration) + Send + Sync + Unpin,
+{
+    type Backoff = iter::Inspect<B::Backoff, F>;
+
+    fn build(self) -> Self::Backoff {
+        Iterator::inspect(self.builder.build(), self.f)
+    }
+
+    fn try_build(self) -> Result<Self::Backoff, BackoffConfigError> {
+        Ok(Iterator::inspect(self.builder.try_build()?, self.f))
+    }
+}
+
+/// Chain is a [`BackoffBuilder`] that runs two backoffs one after another, created by [`BackoffBuilderExt::chain`].
+#[derive(Debug, Clone, Copy)]
+pub struct Chain<A, B> {
+    first: A,
+    second: B,
+}
+
+impl<A: BackoffBuilder, B: BackoffBuilder> BackoffBuilder for Chain<A, B> {
+    type Backoff = iter::Chain<A::Backoff, B::Backoff>;
+
+    fn build(self) -> Self::Backoff {
+        Iterator::chain(self.first.build(), self.second.build())
+    }
+
+    fn try_build(self) -> Result<Self::Backoff, BackoffConfigError> {
+        Ok(Iterator::chain(
+            self.first.try_build()?,
+            self.second.try_build()?,
+        ))
+    }
+}
+
+/// ZipWith is a [`BackoffBuilder`] that combines two backoffs step by step, created by [`BackoffBuilderExt::zip_with`].
+#[derive(Clone, Copy)]
+pub struct ZipWith<A, B, F> {
+    first: A,
+    second: B,
+    f: F,
+}
+
+impl<A: fmt::Debug, B: fmt::Debug, F> fmt::Debug for ZipWith<A, B, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ZipWith")
+            .field("first", &self.first)
+            .field("second", &self.second)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<A, B, F> BackoffBuilder for ZipWith<A, B, F>
+where
+    A: BackoffBuilder,
+    B: BackoffBuilder,
+    F: FnMut(Duration, Duration) -> Duration + Send + Sync + Unpin,
+{
+    type Backoff = ZipWithBackoff<A::Backoff, B::Backoff, F>;
+
+    fn build(self) -> Self::Backoff {
+        ZipWithBackoff {
+            first: self.first.build(),
+            second: self.second.build(),
+            f: self.f,
+        }
+    }
+
+    fn try_build(self) -> Result<Self::Backoff, BackoffConfigError> {
+        Ok(ZipWithBackoff {
+            first: self.first.try_build()?,
+            second: self.second.try_build()?,
+            f: self.f,
+        })
+    }
+}
+
+/// ZipWithBackoff is the backoff built by [`ZipWith`].
+#[derive(Clone)]
+pub struct ZipWithBackoff<A, B, F> {
+    first: A,
+    second: B,
+    f: F,
+}
+
+impl<A: fmt::Debug, B: fmt::Debug, F> fmt::Debug for ZipWithBackoff<A, B, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ZipWithBackoff")
+            .field("first", &self.first)
+            .field("second", &self.second)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<A, B, F> Iterator for ZipWithBackoff<A, B, F>
+where
+    A: Iterator<Item = Duration>,
+    B: Iterator<Item = Duration>,
+    F: FnMut(Duration, Duration) -> Duration,
+{
+    type Item = Duration;
+
+    fn next(&mut self) -> Option<Duration> {
+        let first = self.first.next()?;
+        let second = self.second.next()?;
+        Some((self.f)(first, second))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (first_lower, first_upper) = self.first.size_hint();
+        let (second_lower, second_upper) = self.second.size_hint();
+        let upper = match (first_upper, second_upper) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        (first_lower.min(second_lower), upper)
+    }
+}
+
+/// TotalDelayBudget is a [`BackoffBuilder`] that caps the sum of the delays, created by [`BackoffBuilderExt::with_total_delay_budget`].
+#[derive(Debug, Clone, Copy)]
+pub struct TotalDelayBudget<B> {
+    builder: B,
+    budget: Duration,
+}
+
+impl<B: BackoffBuilder> BackoffBuilder for TotalDelayBudget<B> {
+    type Backoff = TotalDelayBudgetBackoff<B::Backoff>;
+
+    fn build(self) -> Self::Backoff {
+        TotalDelayBudgetBackoff {
+            backoff: self.builder.build(),
+            left: self.budget,
+        }
+    }
+
+    fn try_build(self) -> Result<Self::Backoff, BackoffConfigError> {
+        Ok(TotalDelayBudgetBackoff {
+            backoff: self.builder.try_build()?,
+            left: self.budget,
+        })
+    }
+}
+
+/// TotalDelayBudgetBackoff is the backoff built by [`TotalDelayBudget`].
+#[derive(Debug, Clone)]
+pub struct TotalDelayBudgetBackoff<B> {
+    backoff: B,
+    left: Duration,
+}
+
+impl<B: Iterator<Item = Duration>> Iterator for TotalDelayBudgetBackoff<B> {
+    type Item = Duration;
+
+    fn next(&mut self) -> Option<Duration> {
+        // Don't advance the underlying backoff once the budget is used up.
+        if self.left.is_zero() {
+            return None;
+        }
+        let delay = self.backoff.next()?.min(self.left);
+        self.left -= delay;
+        Some(delay)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.left.is_zero() {
+            return (0, Some(0));
+        }
+        let (lower, upper) = self.backoff.size_hint();
+        (lower.min(1), upper)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test as test;
+
+    use crate::BackoffBuilder;
+    use crate::BackoffBuilderExt;
+    use crate::BackoffConfigError;
+    use crate::ConstantBuilder;
+    use crate::ExponentialBuilder;
+
+    #[test]
+    fn test_take_infinite_backoff() {
+        let mut it = ConstantBuilder::default()
+            .without_max_times()
+            .take(2)
+            .build();
+
+        assert_eq!(Some(Duration::from_secs(1)), it.next());
+        assert_eq!(Some(Duration::from_secs(1)), it.next());
+        assert_eq!(None, it.next());
+    }
+
+    #[test]
+    fn test_take_more_than_backoff() {
+        let mut it = ExponentialBuilder::default()
+            .with_max_times(1)
+            .take(5)
+            .build();
+
+        assert_eq!(Some(Duration::from_secs(1)), it.next());
+        assert_eq!(None, it.next());
+    }
+
+    #[test]
+    fn test_take_custom_backoff() {
+        let mut it = BackoffBuilderExt::take(core::iter::repeat(Duration::from_secs(2)), 1).build();
+
+        assert_eq!(Some(Duration::from_secs(2)), it.next());
+        assert_eq!(None, it.next());
+    }
+
+    #[test]
+    fn test_map_delay() {
+        let mut it = ExponentialBuilder::default()
+            .with_max_delay(Duration::from_secs(2))
+            .map_delay(|d| d + Duration::from_millis(100))
+            .build();
+
+        assert_eq!(Some(Duration::from_millis(1100)), it.next());
+        assert_eq!(Some(Duration::from_millis(2100)), it.next());
+        // The transform is applied after clamping.
+        assert_eq!(Some(Duration::from_millis(2100)), it.next());
+        assert_eq!(None, it.next());
+    }
+
+    #[test]
+    fn test_inspect() {
+        let mut seen = [Duration::ZERO; 3];
+        let mut n = 0;
+        let mut it = ExponentialBuilder::default()
+            .map_delay(|d| d * 2)
+            .inspect(|d| {
+                seen[n] = *d;
+                n += 1;
+            })
+            .take(2)
+            .build();
+
+        // The delays are passed through unchanged.
+        assert_eq!(Some(Duration::from_secs(2)), it.next());
+        assert_eq!(Some(Duration::from_secs(4)), it.next());
+        assert_eq!(None, it.next());
+        drop(it);
+
+        // Only the delays yielded are inspected.
+        assert_eq!(n, 2);
+        assert_eq!(seen[..2], [2, 4].map(Duration::from_secs));
+    }
+
+    #[test]
+    fn test_chain() {
+        let mut it = ConstantBuilder::default()
+            .with_delay(Duration::from_millis(50))
+            .with_max_times(2)
+            .chain(ExponentialBuilder::default().with_max_times(2))
+            .build();
+
+        assert_eq!(Some(Duration::from_millis(50)), it.next());
+        assert_eq!(Some(Duration::from_millis(50)), it.next());
+        assert_eq!(Some(Duration::from_secs(1)), it.next());
+        assert_eq!(Some(Duration::from_secs(2)), it.next());
+        assert_eq!(None, it.next());
+    }
+
+    #[test]
+    fn test_try_build_checks_inner_builders() {
+        let result = ConstantBuilder::default()
+            .take(2)
+            .chain(ExponentialBuilder::default().with_factor(0.0))
+            .try_build();
+
+        assert_eq!(result.unwrap_err(), BackoffConfigError::InvalidFactor(0.0));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_preview() {
+        let builder = ExponentialBuilder::default().with_max_times(4);
+
+        assert_eq!(
+            builder.preview(10),
+            [1, 2, 4, 8].map(Duration::from_secs).to_vec()
+        );
+        assert_eq!(builder.preview(2), [1, 2].map(Duration::from_secs).to_vec());
+
+        // The builder is not consumed by preview.
+        let mut it = builder.build();
+        assert_eq!(Some(Duration::from_secs(1)), it.next());
+    }
+
+    #[test]
+    fn test_map_delay_with_take() {
+        let mut it = ConstantBuilder::default()
+            .without_max_times()
+            .map_delay(|d| d * 2)
+            .take(1)
+            .build();
+
+        assert_eq!(Some(Duration::from_secs(2)), it.next());
+        assert_eq!(None, it.next());
+    }
+
+    #[test]
+    fn test_zip_stops_with_either_backoff() {
+        let mut it = ExponentialBuilder::default()
+            .without_max_times()
+            .zip_min(
+                ConstantBuilder::default()
+                    .with_delay(Duration::from_secs(3))
+                    .with_max_times(4),
+            )
+            .build();
+
+        assert_eq!((4, Some(4)), it.size_hint());
+        let delays = [1, 2, 3, 3].map(Duration::from_secs);
+        assert!(it.by_ref().eq(delays));
+        assert_eq!(None, it.next());
+    }
+
+    #[test]
+    fn test_total_delay_budget() {
+        let budget = Duration::from_secs(10);
+
+        // The delay over the budget is truncated, and the backoff stops after it.
+        let it = ExponentialBuilder::default()
+            .without_max_times()
+            .with_total_delay_budget(budget)
+            .build();
+        assert!(it.eq([1, 2, 4, 3].map(Duration::from_secs)));
+
+        // The sum never exceeds the budget, whatever the jitter draws.
+        for seed in 0..100 {
+            let total: Duration = ExponentialBuilder::default()
+                .with_jitter()
+                .with_rng_seed(Some(seed))
+                .without_max_times()
+                .with_total_delay_budget(budget)
+                .build()
+                .sum();
+            assert_eq!(total, budget);
+        }
+
+        // The backoff stops first if it runs out within the budget.
+        let total: Duration = ConstantBuilder::default()
+            .with_max_times(3)
+            .with_total_delay_budget(budget)
+            .build()
+            .sum();
+        assert_eq!(total, Duration::from_secs(3));
+
+        // No delay fits in an empty budget.
+        let mut it = ConstantBuilder::default()
+            .with_total_delay_budget(Duration::ZERO)
+            .build();
+        assert_eq!(it.next(), None);
+    }
+}
@@ -1,6 +1,16 @@
+use core::fmt;
+use core::hash::Hash;
 use core::time::Duration;
 
+use crate::backoff::new_rng;
+use crate::backoff::seed_from_key;
 use crate::backoff::BackoffBuilder;
+use crate::backoff::BackoffConfigError;
+use crate::backoff::Jitter;
+#[cfg(feature = "rand-core")]
+use crate::backoff::RngCoreJitter;
+#[cfg(feature = "rand-core")]
+use crate::backoff::WithRng;
 
 /// FibonacciBuilder is used to build a [`FibonacciBackoff`] which offers a delay with Fibonacci-based retries.
 ///
@@ -11,6 +21,8 @@ use crate::backoff::BackoffBuilder;
 /// - max_delay: 60s
 /// - max_times: 3
 ///
+/// The default is bounded on purpose, call `without_max_times` to opt in to unlimited retries.
+///
 /// # Examples
 ///
 /// ```no_run
@@ -35,7 +47,8 @@ use crate::backoff::BackoffBuilder;
 /// ```
 #[derive(Debug, Clone, Copy)]
 pub struct FibonacciBuilder {
-    jitter: bool,
+    jitter: Jitter,
+    max_jitter: Option<Duration>,
     seed: Option<u64>,
     min_delay: Duration,
     max_delay: Option<Duration>,
@@ -44,41 +57,111 @@ pub struct FibonacciBuilder {
 
 impl Default for FibonacciBuilder {
     fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FibonacciBuilder {
+    /// Create a new builder with the default configuration, the same as
+    /// [`FibonacciBuilder::default`].
+    ///
+    /// The builder and its setters are `const`, so a policy could be declared in a `const` or a
+    /// `static` without lazy initialization.
+    pub const fn new() -> Self {
         Self {
-            jitter: false,
+            jitter: Jitter::None,
+            max_jitter: None,
             seed: None,
             min_delay: Duration::from_secs(1),
             max_delay: Some(Duration::from_secs(60)),
             max_times: Some(3),
         }
     }
-}
 
-impl FibonacciBuilder {
     /// Set the jitter for the backoff.
     ///
     /// When jitter is enabled, FibonacciBackoff will add a random jitter between `(0, min_delay)` to the delay.
-    pub fn with_jitter(mut self) -> Self {
-        self.jitter = true;
+    ///
+    /// The jitter is added after the delay is capped by `max_delay`, so the delay could exceed
+    /// `max_delay` by up to `min_delay`.
+    pub const fn with_jitter(mut self) -> Self {
+        self.jitter = Jitter::Full;
+        self
+    }
+
+    /// Enable jitter bounded by a ratio of the current delay for the backoff.
+    ///
+    /// When jitter ratio is enabled, FibonacciBackoff will scale the delay by a random
+    /// factor within `[1 - ratio, 1 + ratio]`, and the result is clamped within `[min_delay, max_delay]`.
+    ///
+    /// This replaces the jitter set by [`FibonacciBuilder::with_jitter`].
+    ///
+    /// # Panics
+    ///
+    /// The backoff will panic while building if the ratio is not within `[0.0, 1.0]`, use
+    /// [`BackoffBuilder::try_build`] to get an error instead.
+    pub const fn with_jitter_ratio(mut self, ratio: f32) -> Self {
+        self.jitter = Jitter::Ratio(ratio);
+        self
+    }
+
+    /// Enable equal jitter for the backoff.
+    ///
+    /// When equal jitter is enabled, FibonacciBackoff will keep half of the current delay and add
+    /// a random jitter within `[0, delay / 2)`, so the delay falls within `[delay / 2, delay)`.
+    /// The jitter is applied after the fibonacci term is capped by `max_delay`, and the result is
+    /// clamped within `[min_delay / 2, max_delay]`.
+    ///
+    /// This replaces the jitter set by [`FibonacciBuilder::with_jitter`].
+    pub const fn with_equal_jitter(mut self) -> Self {
+        self.jitter = Jitter::Equal;
+        self
+    }
+
+    /// Cap the jitter to at most `max_jitter` away from the delay, in either direction.
+    ///
+    /// See [`ExponentialBuilder::with_max_jitter`](crate::ExponentialBuilder::with_max_jitter)
+    /// for details.
+    pub const fn with_max_jitter(mut self, max_jitter: Duration) -> Self {
+        self.max_jitter = Some(max_jitter);
         self
     }
 
-    /// Set the seed value for the jitter random number generator. If no seed is given, a random seed is used in std and default seed is used in no_std.
-    pub fn with_jitter_seed(mut self, seed: u64) -> Self {
-        self.seed = Some(seed);
+    /// Set the seed of the jitter random number generator.
+    ///
+    /// `Some(seed)` makes the jitter deterministic, which is useful in tests. `None` seeds the
+    /// generator from the system entropy in std, and uses a default seed in no_std. The
+    /// generator is created by [`BackoffBuilder::build`], so all backoffs built from a builder
+    /// with a seed give the same delays.
+    pub const fn with_rng_seed(mut self, seed: Option<u64>) -> Self {
+        self.seed = seed;
         self
     }
 
+    /// Set the seed value for the jitter random number generator, it's a shorthand of
+    /// `with_rng_seed(Some(seed))`.
+    pub const fn with_jitter_seed(self, seed: u64) -> Self {
+        self.with_rng_seed(Some(seed))
+    }
+
+    /// Seed the jitter random number generator from given key, like a hostname or a shard id.
+    ///
+    /// See [`ExponentialBuilder::with_jitter_key`](crate::ExponentialBuilder::with_jitter_key)
+    /// for details.
+    pub fn with_jitter_key(self, key: impl Hash) -> Self {
+        self.with_rng_seed(Some(seed_from_key(key)))
+    }
+
     /// Set the minimum delay for the backoff.
-    pub fn with_min_delay(mut self, min_delay: Duration) -> Self {
+    pub const fn with_min_delay(mut self, min_delay: Duration) -> Self {
         self.min_delay = min_delay;
         self
     }
 
     /// Set the maximum delay for the current backoff.
     ///
-    /// The delay will not increase if the current delay exceeds the maximum delay.
-    pub fn with_max_delay(mut self, max_delay: Duration) -> Self {
+    /// Every delay is capped by the maximum delay before the jitter is applied.
+    pub const fn with_max_delay(mut self, max_delay: Duration) -> Self {
         self.max_delay = Some(max_delay);
         self
     }
@@ -88,7 +171,7 @@ impl FibonacciBuilder {
     /// The delay will keep increasing.
     ///
     /// _The delay will saturate at `Duration::MAX` which is an **unrealistic** delay._
-    pub fn without_max_delay(mut self) -> Self {
+    pub const fn without_max_delay(mut self) -> Self {
         self.max_delay = None;
         self
     }
@@ -96,7 +179,10 @@ impl FibonacciBuilder {
     /// Set the maximum number of attempts for the current backoff.
     ///
     /// The backoff will stop if the maximum number of attempts is reached.
-    pub fn with_max_times(mut self, max_times: usize) -> Self {
+    ///
+    /// `max_times` is the number of retries, so the operation is attempted at most `max_times + 1`
+    /// times. `with_max_times(0)` makes exactly one attempt without any retry.
+    pub const fn with_max_times(mut self, max_times: usize) -> Self {
         self.max_times = Some(max_times);
         self
     }
@@ -106,7 +192,9 @@ impl FibonacciBuilder {
     /// The backoff will not stop by itself.
     ///
     /// _The backoff could stop reaching `usize::MAX` attempts but this is **unrealistic**._
-    pub fn without_max_times(mut self) -> Self {
+    ///
+    /// Unlimited retries must be asked for explicitly, the default is bounded to 3 retries.
+    pub const fn without_max_times(mut self) -> Self {
         self.max_times = None;
         self
     }
@@ -116,19 +204,25 @@ impl BackoffBuilder for FibonacciBuilder {
     type Backoff = FibonacciBackoff;
 
     fn build(self) -> Self::Backoff {
-        FibonacciBackoff {
-            jitter: self.jitter,
-            rng: if let Some(seed) = self.seed {
-                fastrand::Rng::with_seed(seed)
-            } else {
-                #[cfg(feature = "std")]
-                let rng = fastrand::Rng::new();
+        self.try_build().unwrap_or_else(|err| panic!("{err}"))
+    }
 
-                #[cfg(not(feature = "std"))]
-                let rng = fastrand::Rng::with_seed(super::RANDOM_SEED);
+    fn try_build(self) -> Result<Self::Backoff, BackoffConfigError> {
+        self.jitter.validate()?;
+        if let Some(max_delay) = self.max_delay {
+            if self.min_delay > max_delay {
+                return Err(BackoffConfigError::MinDelayExceedsMaxDelay {
+                    min_delay: self.min_delay,
+                    max_delay,
+                });
+            }
+        }
 
-                rng
-            },
+        Ok(FibonacciBackoff {
+            jitter: self.jitter,
+            max_jitter: self.max_jitter,
+            rng: new_rng(self.seed),
+            seeded: self.seed.is_some(),
             min_delay: self.min_delay,
             max_delay: self.max_delay,
             max_times: self.max_times,
@@ -136,7 +230,7 @@ impl BackoffBuilder for FibonacciBuilder {
             previous_delay: None,
             current_delay: None,
             attempts: 0,
-        }
+        })
     }
 }
 
@@ -146,16 +240,23 @@ impl BackoffBuilder for &FibonacciBuilder {
     fn build(self) -> Self::Backoff {
         (*self).build()
     }
+
+    fn try_build(self) -> Result<Self::Backoff, BackoffConfigError> {
+        (*self).try_build()
+    }
 }
 
 /// FibonacciBackoff offers a delay with Fibonacci-based retries.
 ///
 /// This backoff strategy is constructed by [`FibonacciBuilder`].
 #[doc(hidden)]
-#[derive(Debug)]
+#[derive(Clone)]
 pub struct FibonacciBackoff {
-    jitter: bool,
+    jitter: Jitter,
+    max_jitter: Option<Duration>,
     rng: fastrand::Rng,
+    /// Whether the seed of the jitter is given explicitly.
+    seeded: bool,
     min_delay: Duration,
     max_delay: Option<Duration>,
     max_times: Option<usize>,
@@ -165,27 +266,72 @@ pub struct FibonacciBackoff {
     attempts: usize,
 }
 
-impl Iterator for FibonacciBackoff {
-    type Item = Duration;
+/// Show the policy and the attempts made, the random number generator is omitted.
+impl fmt::Debug for FibonacciBackoff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FibonacciBackoff")
+            .field("jitter", &self.jitter)
+            .field("max_jitter", &self.max_jitter)
+            .field("min_delay", &self.min_delay)
+            .field("max_delay", &self.max_delay)
+            .field("max_times", &self.max_times)
+            .field("attempts", &self.attempts)
+            .finish_non_exhaustive()
+    }
+}
 
-    fn next(&mut self) -> Option<Self::Item> {
+impl FibonacciBackoff {
+    /// Return the next delay without consuming it, or `None` if the jitter is set.
+    ///
+    /// See [`ExponentialBackoff::peek`](crate::ExponentialBackoff::peek) for details.
+    pub fn peek(&self) -> Option<Duration> {
+        if self.jitter != Jitter::None {
+            return None;
+        }
+        self.clone().next()
+    }
+
+    /// Return the next delay, drawing the jitter from given random number generator instead of
+    /// the one seeded by the builder.
+    ///
+    /// See [`ExponentialBackoff::next_with_rng`](crate::ExponentialBackoff::next_with_rng) for
+    /// details.
+    #[cfg(feature = "rand-core")]
+    pub fn next_with_rng<R: rand_core::RngCore + ?Sized>(
+        &mut self,
+        rng: &mut R,
+    ) -> Option<Duration> {
+        let delay = self.next_delay()?;
+        let delay = self.jitter.apply(
+            delay,
+            self.min_delay,
+            self.max_jitter,
+            &mut RngCoreJitter(rng),
+        );
+        Some(self.bound(delay))
+    }
+
+    /// Draw the jitter of every delay from given random number generator instead of the one
+    /// seeded by the builder.
+    ///
+    /// See [`WithRng`] for details.
+    #[cfg(feature = "rand-core")]
+    pub fn with_rng<R: rand_core::RngCore>(self, rng: R) -> WithRng<Self, R> {
+        WithRng::new(self, rng)
+    }
+
+    /// Advance to the next delay before applying the jitter.
+    fn next_delay(&mut self) -> Option<Duration> {
         if self.attempts >= self.max_times.unwrap_or(usize::MAX) {
             return None;
         }
         self.attempts += 1;
 
-        match self.current_delay {
+        let mut next = match self.current_delay {
             None => {
                 // If current_delay is None, it's must be the first time to retry.
-                let mut next = self.min_delay;
-                self.current_delay = Some(next);
-
-                // If jitter is enabled, add random jitter based on min delay.
-                if self.jitter {
-                    next += self.min_delay.mul_f32(self.rng.f32());
-                }
-
-                Some(next)
+                self.current_delay = Some(self.min_delay);
+                self.min_delay
             }
             Some(cur) => {
                 let mut next = cur;
@@ -193,18 +339,72 @@ impl Iterator for FibonacciBackoff {
                 // If current delay larger than max delay, we should stop increment anymore.
                 if next < self.max_delay.unwrap_or(Duration::MAX) {
                     if let Some(prev) = self.previous_delay {
+                        // Saturate at `Duration::MAX` instead of overflowing.
                         next = next.saturating_add(prev);
                         self.current_delay = Some(next);
                     }
                     self.previous_delay = Some(cur);
                 }
 
-                // If jitter is enabled, add random jitter based on min delay.
-                if self.jitter {
-                    next += self.min_delay.mul_f32(self.rng.f32());
+                next
+            }
+        };
+
+        // Cap the delay before applying jitter.
+        if let Some(max_delay) = self.max_delay {
+            next = next.min(max_delay);
+        }
+
+        Some(next)
+    }
+
+    /// Keep the jittered delay within the bounds.
+    fn bound(&self, mut delay: Duration) -> Duration {
+        match self.jitter {
+            // If jitter is enabled, random jitter based on min delay is added on top.
+            Jitter::None | Jitter::Full => {}
+            // If jitter ratio is enabled, the delay is scaled and kept within the bounds.
+            Jitter::Ratio(_) | Jitter::Equal => {
+                delay = delay.max(self.jitter.lower_bound(self.min_delay));
+                if let Some(max_delay) = self.max_delay {
+                    delay = delay.min(max_delay);
                 }
+            }
+        }
+        delay
+    }
+}
+
+impl BackoffBuilder for &FibonacciBackoff {
+    type Backoff = FibonacciBackoff;
+
+    fn build(self) -> Self::Backoff {
+        let mut backoff = self.clone();
+        // Every retry draws its own jitter, unless the seed is given explicitly.
+        if !backoff.seeded {
+            backoff.rng = new_rng(None);
+        }
+        backoff
+    }
+}
+
+impl Iterator for FibonacciBackoff {
+    type Item = Duration;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let delay = self.next_delay()?;
+        let delay = self
+            .jitter
+            .apply(delay, self.min_delay, self.max_jitter, &mut self.rng);
+        Some(self.bound(delay))
+    }
 
-                Some(next)
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.max_times {
+            None => (usize::MAX, None),
+            Some(max_times) => {
+                let remaining = max_times.saturating_sub(self.attempts);
+                (remaining, Some(remaining))
             }
         }
     }
@@ -217,7 +417,9 @@ mod tests {
     #[cfg(target_arch = "wasm32")]
     use wasm_bindgen_test::wasm_bindgen_test as test;
 
+    use crate::Backoff;
     use crate::BackoffBuilder;
+    use crate::BackoffConfigError;
     use crate::FibonacciBuilder;
 
     #[test]
@@ -249,6 +451,38 @@ mod tests {
         assert_eq!(None, fib.next());
     }
 
+    #[test]
+    fn test_fibonacci_equal_jitter() {
+        let mut fib = FibonacciBuilder::default()
+            .with_equal_jitter()
+            .with_max_delay(Duration::from_secs(4))
+            .with_max_times(6)
+            .build();
+
+        // 1, 1, 2, 3, 4 (capped from 5) and 4 (capped from 8).
+        for expected in [1, 1, 2, 3, 4, 4] {
+            let v = fib.next().expect("value must valid");
+            assert!(v >= Duration::from_secs(expected) / 2, "current: {v:?}");
+            assert!(v < Duration::from_secs(expected), "current: {v:?}");
+        }
+        assert_eq!(None, fib.next());
+    }
+
+    #[test]
+    fn test_fibonacci_jitter_seed() {
+        for builder in [
+            FibonacciBuilder::default().with_jitter(),
+            FibonacciBuilder::default().with_jitter_ratio(0.5),
+            FibonacciBuilder::default().with_equal_jitter(),
+        ] {
+            let builder = builder.with_jitter_seed(42).with_max_times(8);
+
+            // The same seed gives the same sequence.
+            assert!(builder.build().eq(builder.build()));
+            assert!(!builder.build().eq(builder.with_jitter_seed(7).build()));
+        }
+    }
+
     #[test]
     fn test_fibonacci_min_delay() {
         let mut fib = FibonacciBuilder::default()
@@ -275,6 +509,103 @@ mod tests {
         assert_eq!(None, fib.next());
     }
 
+    #[test]
+    fn test_fibonacci_max_delay_caps_every_term() {
+        let mut fib = FibonacciBuilder::default()
+            .with_max_times(6)
+            .with_max_delay(Duration::from_secs(4))
+            .build();
+
+        assert_eq!(Some(Duration::from_secs(1)), fib.next());
+        assert_eq!(Some(Duration::from_secs(1)), fib.next());
+        assert_eq!(Some(Duration::from_secs(2)), fib.next());
+        assert_eq!(Some(Duration::from_secs(3)), fib.next());
+        // The next term is 5s, but capped by max_delay.
+        assert_eq!(Some(Duration::from_secs(4)), fib.next());
+        assert_eq!(Some(Duration::from_secs(4)), fib.next());
+        assert_eq!(None, fib.next());
+    }
+
+    #[test]
+    fn test_fibonacci_without_max_delay() {
+        let mut fib = FibonacciBuilder::default()
+            .with_max_times(6)
+            .without_max_delay()
+            .build();
+
+        assert_eq!(Some(Duration::from_secs(1)), fib.next());
+        assert_eq!(Some(Duration::from_secs(1)), fib.next());
+        assert_eq!(Some(Duration::from_secs(2)), fib.next());
+        assert_eq!(Some(Duration::from_secs(3)), fib.next());
+        assert_eq!(Some(Duration::from_secs(5)), fib.next());
+        assert_eq!(Some(Duration::from_secs(8)), fib.next());
+        assert_eq!(None, fib.next());
+    }
+
+    #[test]
+    fn test_fibonacci_max_delay_with_jitter() {
+        let mut fib = FibonacciBuilder::default()
+            .with_jitter()
+            .with_max_times(6)
+            .with_max_delay(Duration::from_secs(4))
+            .build();
+
+        // The jitter is added on top of the capped delay.
+        for expected in [1, 1, 2, 3, 4, 4] {
+            let v = fib.next().expect("value must valid");
+            assert!(v >= Duration::from_secs(expected), "current: {v:?}");
+            assert!(v < Duration::from_secs(expected + 1), "current: {v:?}");
+        }
+        assert_eq!(None, fib.next());
+    }
+
+    #[test]
+    fn test_fibonacci_max_delay_with_jitter_ratio() {
+        let mut fib = FibonacciBuilder::default()
+            .with_jitter_ratio(0.5)
+            .with_max_times(6)
+            .with_max_delay(Duration::from_secs(4))
+            .build();
+
+        for expected in [1.0, 1.0, 2.0, 3.0, 4.0, 4.0] {
+            let v = fib.next().expect("value must valid");
+            let lower = Duration::from_secs_f32(expected * 0.5).max(Duration::from_secs(1));
+            let upper = Duration::from_secs_f32(expected * 1.5).min(Duration::from_secs(4));
+            assert!(v >= lower, "current: {v:?}");
+            assert!(v <= upper, "current: {v:?}");
+        }
+        assert_eq!(None, fib.next());
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid jitter ratio")]
+    fn test_fibonacci_invalid_jitter_ratio() {
+        let _ = FibonacciBuilder::default().with_jitter_ratio(2.0).build();
+    }
+
+    #[test]
+    fn test_fibonacci_try_build() {
+        assert!(FibonacciBuilder::default().try_build().is_ok());
+        assert_eq!(
+            FibonacciBuilder::default()
+                .with_jitter_ratio(2.0)
+                .try_build()
+                .unwrap_err(),
+            BackoffConfigError::InvalidJitterRatio(2.0)
+        );
+        assert_eq!(
+            FibonacciBuilder::default()
+                .with_min_delay(Duration::from_secs(10))
+                .with_max_delay(Duration::from_secs(5))
+                .try_build()
+                .unwrap_err(),
+            BackoffConfigError::MinDelayExceedsMaxDelay {
+                min_delay: Duration::from_secs(10),
+                max_delay: Duration::from_secs(5),
+            }
+        );
+    }
+
     #[test]
     fn test_fibonacci_no_max_delay() {
         let mut fib = FibonacciBuilder::default()
@@ -296,6 +627,49 @@ mod tests {
         assert_eq!(None, fib.next());
     }
 
+    #[test]
+    fn test_fibonacci_saturates_after_many_attempts() {
+        // fib(94) seconds overflows `u64`, keep going well beyond it.
+        let mut fib = FibonacciBuilder::default()
+            .with_min_delay(Duration::from_secs(1))
+            .without_max_delay()
+            .without_max_times()
+            .build();
+
+        let mut prev = Duration::ZERO;
+        for _ in 0..500 {
+            let v = fib.next().expect("value must valid");
+            assert!(v >= prev, "current: {v:?}, previous: {prev:?}");
+            prev = v;
+        }
+        assert_eq!(Duration::MAX, prev);
+        assert_eq!(Some(Duration::MAX), fib.next());
+
+        let mut fib = FibonacciBuilder::default()
+            .with_min_delay(Duration::from_secs(1))
+            .with_max_delay(Duration::from_secs(60))
+            .without_max_times()
+            .build();
+
+        for _ in 0..500 {
+            let v = fib.next().expect("value must valid");
+            assert!(v <= Duration::from_secs(60), "current: {v:?}");
+        }
+        assert_eq!(Some(Duration::from_secs(60)), fib.next());
+
+        let mut fib = FibonacciBuilder::default()
+            .with_jitter()
+            .with_min_delay(Duration::from_secs(1))
+            .without_max_delay()
+            .without_max_times()
+            .build();
+
+        for _ in 0..500 {
+            fib.next().expect("value must valid");
+        }
+        assert_eq!(Some(Duration::MAX), fib.next());
+    }
+
     #[test]
     fn test_fibonacci_max_times() {
         let mut fib = FibonacciBuilder::default().with_max_times(6).build();
@@ -322,4 +696,63 @@ mod tests {
             assert_eq!(Some(Duration::from_secs(0)), fib.next());
         }
     }
+
+    #[test]
+    fn test_fibonacci_max_times_zero() {
+        let mut it = FibonacciBuilder::default()
+            .with_jitter()
+            .with_max_times(0)
+            .build();
+
+        assert_eq!(it.remaining(), Some(0));
+        assert_eq!(None, it.next());
+    }
+
+    #[test]
+    fn test_fibonacci_default_max_times_is_bounded() {
+        assert_eq!(FibonacciBuilder::default().build().remaining(), Some(3));
+        assert_eq!(
+            FibonacciBuilder::default()
+                .without_max_times()
+                .build()
+                .remaining(),
+            None
+        );
+    }
+
+    #[cfg(feature = "rand-core")]
+    #[test]
+    fn test_fibonacci_next_with_rng() {
+        use rand::rngs::SmallRng;
+        use rand::SeedableRng;
+
+        let builder = FibonacciBuilder::default()
+            .with_jitter_ratio(0.5)
+            .with_max_times(5);
+        let mut a = builder.build();
+        let mut b = builder.build();
+        let mut rng_a = SmallRng::seed_from_u64(42);
+        let mut rng_b = SmallRng::seed_from_u64(42);
+
+        while let Some(v) = a.next_with_rng(&mut rng_a) {
+            assert_eq!(Some(v), b.next_with_rng(&mut rng_b));
+            assert!(v >= Duration::from_secs(1), "current: {v:?}");
+            assert!(v <= Duration::from_secs(60), "current: {v:?}");
+        }
+        assert_eq!(None, b.next_with_rng(&mut rng_b));
+    }
+
+    #[test]
+    fn test_fibonacci_peek() {
+        let mut backoff = FibonacciBuilder::default().with_max_times(3).build();
+        for _ in 0..3 {
+            let peeked = backoff.peek();
+            assert!(peeked.is_some());
+            assert_eq!(peeked, backoff.next());
+        }
+        assert_eq!(backoff.peek(), None);
+
+        let backoff = FibonacciBuilder::default().with_jitter().build();
+        assert_eq!(backoff.peek(), None);
+    }
 }
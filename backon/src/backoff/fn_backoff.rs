@@ -0,0 +1,132 @@
+use core::fmt;
+use core::time::Duration;
+
+/// FnBackoff is a [`Backoff`](crate::Backoff) that asks a function for every delay.
+///
+/// The function is called every time a retry is about to happen, returning the delay before
+/// the next attempt, or `None` to stop retrying. Unlike a precomputed iterator, it can observe
+/// external state at each step, like a rate limiter that tells when the next token is
+/// available.
+///
+/// Every [`Backoff`](crate::Backoff) is a [`BackoffBuilder`](crate::BackoffBuilder), so
+/// `FnBackoff` can be passed to `retry` directly. It's used by a single retry only, wrap it with
+/// [`SharedBackoff`](crate::SharedBackoff) or create one per retry if needed.
+///
+/// # Examples
+///
+/// ```no_run
+/// use core::time::Duration;
+///
+/// use anyhow::Result;
+/// use backon::FnBackoff;
+/// use backon::Retryable;
+///
+/// struct Bucket;
+///
+/// impl Bucket {
+///     /// Return the time until the next token is available.
+///     fn next_token(&self) -> Duration {
+///         Duration::from_millis(100)
+///     }
+/// }
+///
+/// async fn fetch() -> Result<String> {
+///     Ok(reqwest::get("https://www.rust-lang.org")
+///         .await?
+///         .text()
+///         .await?)
+/// }
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() -> Result<()> {
+///     let bucket = Bucket;
+///     let mut retries = 0;
+///
+///     let content = fetch
+///         .retry(FnBackoff::new(move || {
+///             retries += 1;
+///             (retries <= 3).then(|| bucket.next_token())
+///         }))
+///         .await?;
+///     println!("fetch succeeded: {}", content);
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone, Copy)]
+pub struct FnBackoff<F> {
+    f: F,
+}
+
+impl<F: FnMut() -> Option<Duration>> FnBackoff<F> {
+    /// Create a new backoff that calls given function for every delay.
+    pub const fn new(f: F) -> Self {
+        FnBackoff { f }
+    }
+}
+
+impl<F> fmt::Debug for FnBackoff<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FnBackoff").finish_non_exhaustive()
+    }
+}
+
+impl<F: FnMut() -> Option<Duration>> Iterator for FnBackoff<F> {
+    type Item = Duration;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        (self.f)()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate alloc;
+
+    use alloc::sync::Arc;
+    use alloc::vec;
+    use alloc::vec::Vec;
+    use core::future::ready;
+
+    use spin::Mutex;
+
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test as test;
+
+    #[cfg(not(target_arch = "wasm32"))]
+    use tokio::test;
+
+    use super::*;
+    use crate::Retryable;
+
+    #[test]
+    async fn test_fn_backoff() {
+        // The delays are read from the external state at each step.
+        let tokens = Arc::new(Mutex::new(vec![
+            Duration::from_millis(30),
+            Duration::from_millis(10),
+        ]));
+        let delays = Arc::new(Mutex::new(Vec::new()));
+
+        let result = (|| ready(Err::<(), _>("retryable")))
+            .retry(FnBackoff::new({
+                let tokens = tokens.clone();
+                move || tokens.lock().pop()
+            }))
+            .sleep({
+                let delays = delays.clone();
+                move |dur| {
+                    delays.lock().push(dur);
+                    ready(())
+                }
+            })
+            .await;
+
+        assert!(result.is_err());
+        assert_eq!(
+            *delays.lock(),
+            vec![Duration::from_millis(10), Duration::from_millis(30)]
+        );
+        assert!(tokens.lock().is_empty());
+    }
+}
@@ -0,0 +1,332 @@
+use core::hash::Hash;
+use core::hash::Hasher;
+use core::time::Duration;
+
+use crate::backoff::BackoffConfigError;
+
+/// Jitter decides how randomness is applied to the delays of a backoff.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Jitter {
+    /// No jitter, the delay is used as is.
+    None,
+    /// Add a random jitter within `(0, min_delay)` to the delay.
+    Full,
+    /// Scale the delay by a random factor within `[1 - ratio, 1 + ratio]`.
+    Ratio(f32),
+    /// Keep half of the delay and randomize the other half, known as "equal jitter".
+    Equal,
+}
+
+impl Jitter {
+    /// Apply the jitter to given delay.
+    ///
+    /// The jittered delay stays within `max_jitter` of the delay if it's given.
+    pub(crate) fn apply(
+        self,
+        delay: Duration,
+        min_delay: Duration,
+        max_jitter: Option<Duration>,
+        rng: &mut impl JitterRng,
+    ) -> Duration {
+        let jittered = self.draw(delay, min_delay, rng);
+        match max_jitter {
+            Some(max) if jittered > delay => jittered.min(delay.saturating_add(max)),
+            Some(max) => jittered.max(delay.saturating_sub(max)),
+            None => jittered,
+        }
+    }
+
+    /// Draw the jittered delay without any cap.
+    fn draw(self, delay: Duration, min_delay: Duration, rng: &mut impl JitterRng) -> Duration {
+        match self {
+            Jitter::None => delay,
+            Jitter::Full => delay.saturating_add(min_delay.mul_f32(rng.f32())),
+            Jitter::Ratio(ratio) => {
+                super::exponential::saturating_mul(delay, 1.0 - ratio + 2.0 * ratio * rng.f32())
+            }
+            Jitter::Equal => {
+                let half = delay / 2;
+                half.saturating_add(half.mul_f32(rng.f32()))
+            }
+        }
+    }
+
+    /// The lower bound of the jittered delays.
+    ///
+    /// Equal jitter could halve the delay, so it's allowed to go below `min_delay`.
+    pub(crate) fn lower_bound(self, min_delay: Duration) -> Duration {
+        match self {
+            Jitter::Equal => min_delay / 2,
+            _ => min_delay,
+        }
+    }
+
+    /// Check if the jitter is valid.
+    pub(crate) fn validate(self) -> Result<(), BackoffConfigError> {
+        match self {
+            Jitter::Ratio(ratio) if !(0.0..=1.0).contains(&ratio) => {
+                Err(BackoffConfigError::InvalidJitterRatio(ratio))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The source of randomness for the jitter.
+pub(crate) trait JitterRng {
+    /// Return a random number within `[0.0, 1.0)`.
+    fn f32(&mut self) -> f32;
+}
+
+impl JitterRng for fastrand::Rng {
+    fn f32(&mut self) -> f32 {
+        fastrand::Rng::f32(self)
+    }
+}
+
+/// Jitter drawn from a caller-provided [`rand_core::RngCore`].
+#[cfg(feature = "rand-core")]
+pub(crate) struct RngCoreJitter<'a, R: ?Sized>(pub(crate) &'a mut R);
+
+#[cfg(feature = "rand-core")]
+impl<R: rand_core::RngCore + ?Sized> JitterRng for RngCoreJitter<'_, R> {
+    fn f32(&mut self) -> f32 {
+        // Keep the 24 bits that fit in the mantissa of f32.
+        (self.0.next_u32() >> 8) as f32 / (1u32 << 24) as f32
+    }
+}
+
+/// Create the random number generator for the jitter of a backoff.
+///
+/// The seed given to the builder takes precedence, then the seed set by [`set_jitter_seed`].
+pub(crate) fn new_rng(seed: Option<u64>) -> fastrand::Rng {
+    if let Some(seed) = seed {
+        return fastrand::Rng::with_seed(seed);
+    }
+
+    #[cfg(feature = "std")]
+    let rng = global::new_rng().unwrap_or_default();
+
+    #[cfg(not(feature = "std"))]
+    let rng = fastrand::Rng::with_seed(super::RANDOM_SEED);
+
+    rng
+}
+
+/// Derive the seed of the jitter from given key.
+///
+/// The key is hashed with FNV-1a instead of the std hasher, so the seed is stable across
+/// processes and Rust versions.
+pub(crate) fn seed_from_key(key: impl Hash) -> u64 {
+    let mut hasher = KeyHasher(0xcbf2_9ce4_8422_2325);
+    key.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// A FNV-1a hasher.
+struct KeyHasher(u64);
+
+impl Hasher for KeyHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 ^ *byte as u64).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+/// Set a process-wide seed for the jitter of all backoffs built without an explicit seed.
+///
+/// This is intended for tests that need reproducible jitter without calling
+/// `with_jitter_seed` on every builder. Passing `None` restores the default, which seeds
+/// every backoff from a fast nondeterministic source.
+///
+/// # Thread Safety
+///
+/// The seed is shared by all threads, but the sequence of generators derived from it is
+/// tracked per thread: every thread starts over from the seed and then advances on its own,
+/// so backoffs built on one thread are reproducible regardless of what other threads do.
+/// Setting the seed again (even to the same value) restarts the sequence of every thread.
+///
+/// Builders that are given a seed via `with_jitter_seed` are not affected.
+///
+/// # Examples
+///
+/// ```
+/// use backon::set_jitter_seed;
+/// use backon::BackoffBuilder;
+/// use backon::ExponentialBuilder;
+///
+/// set_jitter_seed(Some(42));
+/// let first: Vec<_> = ExponentialBuilder::default().with_jitter().build().collect();
+///
+/// set_jitter_seed(Some(42));
+/// let second: Vec<_> = ExponentialBuilder::default().with_jitter().build().collect();
+///
+/// assert_eq!(first, second);
+/// set_jitter_seed(None);
+/// ```
+#[cfg(feature = "std")]
+pub fn set_jitter_seed(seed: Option<u64>) {
+    global::set_seed(seed)
+}
+
+#[cfg(feature = "std")]
+mod global {
+    use core::cell::RefCell;
+    use core::sync::atomic::AtomicBool;
+    use core::sync::atomic::AtomicU64;
+    use core::sync::atomic::AtomicUsize;
+    use core::sync::atomic::Ordering;
+
+    static ENABLED: AtomicBool = AtomicBool::new(false);
+    static SEED: AtomicU64 = AtomicU64::new(0);
+    /// Bumped every time the seed is set so that threads know to restart their sequence.
+    static GENERATION: AtomicUsize = AtomicUsize::new(0);
+
+    std::thread_local! {
+        static LOCAL: RefCell<Option<(usize, fastrand::Rng)>> = const { RefCell::new(None) };
+    }
+
+    pub(super) fn set_seed(seed: Option<u64>) {
+        match seed {
+            Some(seed) => {
+                SEED.store(seed, Ordering::Relaxed);
+                GENERATION.fetch_add(1, Ordering::Release);
+                ENABLED.store(true, Ordering::Release);
+            }
+            None => ENABLED.store(false, Ordering::Release),
+        }
+    }
+
+    pub(super) fn new_rng() -> Option<fastrand::Rng> {
+        if !ENABLED.load(Ordering::Acquire) {
+            return None;
+        }
+        let generation = GENERATION.load(Ordering::Acquire);
+
+        LOCAL.with(|local| {
+            let mut local = local.borrow_mut();
+            match local.as_mut() {
+                Some((g, rng)) if *g == generation => Some(fastrand::Rng::with_seed(rng.u64(..))),
+                _ => {
+                    let mut rng = fastrand::Rng::with_seed(SEED.load(Ordering::Relaxed));
+                    let seed = rng.u64(..);
+                    *local = Some((generation, rng));
+                    Some(fastrand::Rng::with_seed(seed))
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate alloc;
+
+    use alloc::vec::Vec;
+
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test as test;
+
+    use super::*;
+    use crate::BackoffBuilder;
+    #[cfg(feature = "std")]
+    use crate::ConstantBuilder;
+    use crate::ExponentialBuilder;
+    #[cfg(feature = "std")]
+    use crate::FibonacciBuilder;
+
+    #[cfg(feature = "std")]
+    fn draw() -> (Vec<Duration>, Vec<Duration>, Vec<Duration>) {
+        (
+            ConstantBuilder::default().with_jitter().build().collect(),
+            ExponentialBuilder::default()
+                .with_jitter()
+                .build()
+                .collect(),
+            FibonacciBuilder::default().with_jitter().build().collect(),
+        )
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_set_jitter_seed() {
+        set_jitter_seed(Some(42));
+        let first = draw();
+        let second = draw();
+        set_jitter_seed(Some(42));
+        let third = draw();
+        set_jitter_seed(None);
+
+        assert_eq!(first, third);
+        assert_ne!(first, second);
+
+        // An explicit seed always wins over the global one.
+        set_jitter_seed(Some(7));
+        let seeded: Vec<_> = ExponentialBuilder::default()
+            .with_jitter()
+            .with_jitter_seed(1)
+            .build()
+            .collect();
+        set_jitter_seed(None);
+        let expected: Vec<_> = ExponentialBuilder::default()
+            .with_jitter()
+            .with_jitter_seed(1)
+            .build()
+            .collect();
+        assert_eq!(seeded, expected);
+    }
+
+    #[test]
+    fn test_jitter_key() {
+        let delays = |key: &str| -> Vec<Duration> {
+            ExponentialBuilder::default()
+                .with_jitter()
+                .with_jitter_key(key)
+                .build()
+                .collect()
+        };
+
+        assert_eq!(delays("node-a"), delays("node-a"));
+        assert_ne!(delays("node-a"), delays("node-b"));
+
+        // The seed doesn't depend on the process or the Rust version.
+        assert_eq!(seed_from_key(0u8), 0xaf63_bd4c_8601_b7df);
+    }
+
+    #[test]
+    fn test_jitter_max_jitter() {
+        /// Always draw the same number.
+        struct Fixed(f32);
+
+        impl JitterRng for Fixed {
+            fn f32(&mut self) -> f32 {
+                self.0
+            }
+        }
+
+        let jitter = Jitter::Ratio(0.5);
+        let max = Some(Duration::from_millis(500));
+        let delay = Duration::from_secs(10);
+
+        // The absolute cap is smaller than half of a large delay.
+        let v = jitter.apply(delay, delay, max, &mut Fixed(0.0));
+        assert_eq!(v, Duration::from_millis(9500));
+        let v = jitter.apply(delay, delay, max, &mut Fixed(0.999));
+        assert_eq!(v, Duration::from_millis(10500));
+
+        // Half of a small delay is smaller than the absolute cap.
+        let delay = Duration::from_millis(100);
+        let v = jitter.apply(delay, delay, max, &mut Fixed(0.0));
+        assert!(v <= Duration::from_millis(51), "current: {v:?}");
+        assert!(v >= Duration::from_millis(49), "current: {v:?}");
+
+        // Full jitter is capped the same way.
+        let v = Jitter::Full.apply(delay, Duration::from_secs(10), max, &mut Fixed(0.999));
+        assert_eq!(v, Duration::from_millis(600));
+    }
+}
@@ -1,6 +1,26 @@
 mod api;
 pub use api::*;
 
+mod ext;
+pub use ext::BackoffBuilderExt;
+pub use ext::Chain;
+pub use ext::Inspect;
+pub use ext::MapDelay;
+pub use ext::Take;
+pub use ext::TotalDelayBudget;
+pub use ext::TotalDelayBudgetBackoff;
+pub use ext::ZipWith;
+pub use ext::ZipWithBackoff;
+
+mod jitter;
+pub(crate) use jitter::new_rng;
+pub(crate) use jitter::seed_from_key;
+#[cfg(feature = "std")]
+pub use jitter::set_jitter_seed;
+pub(crate) use jitter::Jitter;
+#[cfg(feature = "rand-core")]
+pub(crate) use jitter::RngCoreJitter;
+
 mod constant;
 pub use constant::ConstantBackoff;
 pub use constant::ConstantBuilder;
@@ -13,6 +33,53 @@ mod exponential;
 pub use exponential::ExponentialBackoff;
 pub use exponential::ExponentialBuilder;
 
+mod equal_jitter;
+pub use equal_jitter::EqualJitterBuilder;
+
+mod fn_backoff;
+pub use fn_backoff::FnBackoff;
+
+mod no_backoff;
+pub use no_backoff::NoBackoff;
+
+#[cfg(feature = "std")]
+mod adaptive;
+#[cfg(feature = "std")]
+pub(crate) use adaptive::heal;
+#[cfg(feature = "std")]
+pub use adaptive::AdaptiveBackoff;
+#[cfg(feature = "std")]
+pub use adaptive::AdaptiveBuilder;
+
+#[cfg(feature = "std")]
+mod channel;
+#[cfg(feature = "std")]
+pub use channel::ChannelBackoff;
+
+#[cfg(feature = "std")]
+mod schedule;
+#[cfg(feature = "std")]
+pub use schedule::ScheduleBackoff;
+
+#[cfg(feature = "std")]
+mod shared;
+#[cfg(feature = "std")]
+pub use shared::SharedBackoff;
+
+#[cfg(feature = "rand-core")]
+mod with_rng;
+#[cfg(all(feature = "rand-core", feature = "std"))]
+pub use with_rng::SharedRng;
+#[cfg(feature = "rand-core")]
+pub use with_rng::WithRng;
+
+#[cfg(feature = "std")]
+mod weighted;
+#[cfg(feature = "std")]
+pub use weighted::WeightedBackoff;
+#[cfg(feature = "std")]
+pub use weighted::WeightedBuilder;
+
 // Random seed value for no_std (the value is "backon" in hex)
 #[cfg(not(feature = "std"))]
 const RANDOM_SEED: u64 = 0x6261636b6f6e;
@@ -0,0 +1,86 @@
+use core::time::Duration;
+
+/// NoBackoff is a [`Backoff`](crate::Backoff) that never retries.
+///
+/// A retry with `NoBackoff` performs exactly one attempt and returns its result. It's the
+/// same as setting `with_max_times(0)` on the builtin builders, prefer that if retries are
+/// disabled at runtime so that both branches share the same type.
+///
+/// # Examples
+///
+/// ```no_run
+/// use anyhow::Result;
+/// use backon::NoBackoff;
+/// use backon::Retryable;
+///
+/// async fn fetch() -> Result<String> {
+///     Ok(reqwest::get("https://www.rust-lang.org")
+///         .await?
+///         .text()
+///         .await?)
+/// }
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() -> Result<()> {
+///     let content = fetch.retry(NoBackoff).await?;
+///     println!("fetch succeeded: {}", content);
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NoBackoff;
+
+impl Iterator for NoBackoff {
+    type Item = Duration;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate alloc;
+
+    use alloc::sync::Arc;
+    use core::future::ready;
+
+    use spin::Mutex;
+
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test as test;
+
+    #[cfg(not(target_arch = "wasm32"))]
+    use tokio::test;
+
+    use super::*;
+    use crate::Backoff;
+    use crate::Retryable;
+
+    #[test]
+    async fn test_no_backoff() {
+        let mut backoff = NoBackoff;
+        assert_eq!(backoff.remaining(), Some(0));
+        assert_eq!(None, backoff.next());
+
+        let attempts = Arc::new(Mutex::new(0));
+        let result = {
+            let attempts = attempts.clone();
+            move || {
+                *attempts.lock() += 1;
+                ready(Err::<(), _>("retryable"))
+            }
+        }
+        .retry(NoBackoff)
+        .sleep(|_| ready(()))
+        .await;
+
+        assert!(result.is_err());
+        assert_eq!(*attempts.lock(), 1);
+    }
+}
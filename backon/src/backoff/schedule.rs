@@ -0,0 +1,107 @@
+use core::time::Duration;
+use std::time::Instant;
+
+use crate::Clock;
+use crate::SystemClock;
+
+/// ScheduleBackoff is a [`Backoff`](crate::Backoff) that retries at the instants of a schedule.
+///
+/// Unlike other backoffs that give relative delays, every delay is the time from now until the
+/// next instant of the schedule, so retries are aligned with fixed points in time like
+/// maintenance windows or the reset of a rate limit. Instants that have already passed when a
+/// delay is asked are skipped, and the backoff stops once the schedule runs out.
+///
+/// The current time is read from a [`Clock`], [`SystemClock`] by default. Set a
+/// [`ManualClock`](crate::ManualClock) by [`ScheduleBackoff::with_clock`] to test it.
+///
+/// # Examples
+///
+/// ```no_run
+/// use core::time::Duration;
+/// use std::time::Instant;
+///
+/// use anyhow::Result;
+/// use backon::Retryable;
+/// use backon::ScheduleBackoff;
+///
+/// async fn fetch() -> Result<String> {
+///     Ok(reqwest::get("https://www.rust-lang.org")
+///         .await?
+///         .text()
+///         .await?)
+/// }
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() -> Result<()> {
+///     // Retry on every 15 minutes boundary from the rate limit reset, 4 times at most.
+///     let reset = Instant::now() + Duration::from_secs(120);
+///     let schedule = (0..4).map(move |i| reset + Duration::from_secs(15 * 60) * i);
+///
+///     let content = fetch.retry(ScheduleBackoff::new(schedule)).await?;
+///     println!("fetch succeeded: {}", content);
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ScheduleBackoff<I, C = SystemClock> {
+    schedule: I,
+    clock: C,
+}
+
+impl<I: Iterator<Item = Instant>> ScheduleBackoff<I> {
+    /// Create a new backoff that retries at the instants of given schedule, in order.
+    pub fn new(schedule: impl IntoIterator<IntoIter = I>) -> Self {
+        ScheduleBackoff {
+            schedule: schedule.into_iter(),
+            clock: SystemClock,
+        }
+    }
+}
+
+impl<I, C> ScheduleBackoff<I, C> {
+    /// Set the clock to read the current time from.
+    pub fn with_clock<CN: Clock>(self, clock: CN) -> ScheduleBackoff<I, CN> {
+        ScheduleBackoff {
+            schedule: self.schedule,
+            clock,
+        }
+    }
+}
+
+impl<I: Iterator<Item = Instant>, C: Clock> Iterator for ScheduleBackoff<I, C> {
+    type Item = Duration;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let now = self.clock.now();
+        self.schedule
+            .find(|instant| *instant > now)
+            .map(|instant| instant - now)
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(target_arch = "wasm32"))]
+mod tests {
+    use super::*;
+    use crate::ManualClock;
+
+    #[test]
+    fn test_schedule_backoff() {
+        let clock = ManualClock::new();
+        let start = clock.now();
+        let schedule = [10, 20, 30, 40].map(|secs| start + Duration::from_secs(secs));
+
+        let mut backoff = ScheduleBackoff::new(schedule).with_clock(clock.clone());
+
+        assert_eq!(Some(Duration::from_secs(10)), backoff.next());
+        // The delay is counted from now instead of the last instant.
+        clock.advance(Duration::from_secs(15));
+        assert_eq!(Some(Duration::from_secs(5)), backoff.next());
+        // The instants that have passed are skipped.
+        clock.advance(Duration::from_secs(20));
+        assert_eq!(Some(Duration::from_secs(5)), backoff.next());
+        clock.advance(Duration::from_secs(5));
+        assert_eq!(None, backoff.next());
+    }
+}
@@ -0,0 +1,147 @@
+use core::time::Duration;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+
+use crate::backoff::BackoffBuilder;
+
+/// SharedBackoff is a [`Backoff`](crate::Backoff) shared by all its clones.
+///
+/// All clones draw delays from one sequence, so a group of concurrent retries talking to the
+/// same service share one budget: once the underlying backoff stops, every retry in the group
+/// gives up on its next failure.
+///
+/// Every delay is taken under a lock, so retries that fail at the same time contend for it.
+/// The lock is only held for a single `next()` call, which is cheap for the builtin backoffs,
+/// but the delays are handed out in the order the retries happen to fail, so the delay seen by
+/// a single retry is not deterministic.
+///
+/// # Examples
+///
+/// ```no_run
+/// use anyhow::Result;
+/// use backon::ExponentialBuilder;
+/// use backon::Retryable;
+/// use backon::SharedBackoff;
+/// use futures::future::join_all;
+///
+/// async fn fetch(url: &str) -> Result<String> {
+///     Ok(reqwest::get(url).await?.text().await?)
+/// }
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() -> Result<()> {
+///     // All requests share at most 5 retries in total.
+///     let backoff = SharedBackoff::new(ExponentialBuilder::default().with_max_times(5));
+///
+///     let urls = ["https://www.rust-lang.org", "https://crates.io"];
+///     let results = join_all(
+///         urls.iter()
+///             .map(|url| (|| fetch(url)).retry(backoff.clone())),
+///     )
+///     .await;
+///     println!("fetch results: {:?}", results);
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug)]
+pub struct SharedBackoff<B> {
+    inner: Arc<Mutex<B>>,
+}
+
+impl<B> SharedBackoff<B> {
+    /// Create a new shared backoff built from given builder.
+    pub fn new<BB: BackoffBuilder<Backoff = B>>(builder: BB) -> Self {
+        SharedBackoff {
+            inner: Arc::new(Mutex::new(builder.build())),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, B> {
+        // The backoff can't be left in an inconsistent state by a panic, ignore the poison.
+        self.inner.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl<B> Clone for SharedBackoff<B> {
+    fn clone(&self) -> Self {
+        SharedBackoff {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<B: Iterator<Item = Duration>> Iterator for SharedBackoff<B> {
+    type Item = Duration;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.lock().next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.lock().size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate alloc;
+
+    use alloc::sync::Arc;
+    use core::future::ready;
+    use core::time::Duration;
+
+    use futures::future::join_all;
+    use spin::Mutex;
+
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test as test;
+
+    #[cfg(not(target_arch = "wasm32"))]
+    use tokio::test;
+
+    use super::*;
+    use crate::Backoff;
+    use crate::ConstantBuilder;
+    use crate::ExponentialBuilder;
+    use crate::Retryable;
+
+    #[test]
+    async fn test_shared_backoff_sequence() {
+        let mut a = SharedBackoff::new(ExponentialBuilder::default().with_max_times(3));
+        let mut b = a.clone();
+
+        assert_eq!(a.remaining(), Some(3));
+        assert_eq!(Some(Duration::from_secs(1)), a.next());
+        assert_eq!(Some(Duration::from_secs(2)), b.next());
+        assert_eq!(Some(Duration::from_secs(4)), a.next());
+        assert_eq!(b.remaining(), Some(0));
+        assert_eq!(None, b.next());
+    }
+
+    #[test]
+    async fn test_shared_backoff_budget() {
+        let attempts = Arc::new(Mutex::new(0));
+        let backoff = SharedBackoff::new(
+            ConstantBuilder::default()
+                .with_delay(Duration::from_millis(1))
+                .with_max_times(4),
+        );
+
+        let results = join_all((0..3).map(|_| {
+            let attempts = attempts.clone();
+            (move || {
+                *attempts.lock() += 1;
+                ready(Err::<(), _>("retryable"))
+            })
+            .retry(backoff.clone())
+            .sleep(|_| ready(()))
+        }))
+        .await;
+
+        assert!(results.iter().all(|r| r.is_err()));
+        // 3 first attempts plus 4 retries in total.
+        assert_eq!(*attempts.lock(), 7);
+    }
+}
@@ -0,0 +1,199 @@
+use core::time::Duration;
+use std::vec::Vec;
+
+use crate::backoff::new_rng;
+use crate::backoff::BackoffBuilder;
+use crate::backoff::BackoffConfigError;
+
+/// WeightedBuilder picks one of several backoff policies at random by weight.
+///
+/// Every backoff built from it is one of the policies, chosen when it's built with a
+/// probability proportional to the weight of the policy. The chosen backoff then behaves as
+/// usual for the whole retry. It allows A/B testing retry strategies in production: spread the
+/// retries over the candidate policies, and compare the outcome of each policy, telling them
+/// apart by [`WeightedBackoff::chosen`].
+///
+/// The choice is drawn from the random number generator seeded by
+/// [`WeightedBuilder::with_rng_seed`], or the seed set by
+/// [`set_jitter_seed`](crate::set_jitter_seed), just like the jitter.
+///
+/// # Examples
+///
+/// ```no_run
+/// use anyhow::Result;
+/// use backon::BackoffBuilder;
+/// use backon::ExponentialBuilder;
+/// use backon::Retryable;
+/// use backon::WeightedBuilder;
+///
+/// async fn fetch() -> Result<String> {
+///     Ok(reqwest::get("https://www.rust-lang.org")
+///         .await?
+///         .text()
+///         .await?)
+/// }
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() -> Result<()> {
+///     // Try the faster policy for 10% of the retries.
+///     let builder = WeightedBuilder::new([
+///         (ExponentialBuilder::default(), 9),
+///         (ExponentialBuilder::default().with_factor(1.5), 1),
+///     ]);
+///
+///     let mut backoff = builder.build();
+///     let result = fetch.retry(&mut backoff).await;
+///     println!("policy {} finished with {:?}", backoff.chosen(), result);
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct WeightedBuilder<B> {
+    choices: Vec<(B, u32)>,
+    seed: Option<u64>,
+}
+
+impl<B: BackoffBuilder> WeightedBuilder<B> {
+    /// Create a new builder choosing among given policies and their weights.
+    ///
+    /// The policies with a weight of `0` are never chosen. Building panics if no policy has a
+    /// positive weight, use [`BackoffBuilder::try_build`] to handle it gracefully.
+    pub fn new(choices: impl IntoIterator<Item = (B, u32)>) -> Self {
+        WeightedBuilder {
+            choices: choices.into_iter().collect(),
+            seed: None,
+        }
+    }
+
+    /// Set the seed of the random number generator that picks the policy.
+    ///
+    /// `Some(seed)` makes the choice deterministic, which is useful in tests.
+    pub fn with_rng_seed(mut self, seed: Option<u64>) -> Self {
+        self.seed = seed;
+        self
+    }
+}
+
+impl<B: BackoffBuilder> BackoffBuilder for WeightedBuilder<B> {
+    type Backoff = WeightedBackoff<B::Backoff>;
+
+    fn build(self) -> Self::Backoff {
+        self.try_build().unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    fn try_build(self) -> Result<Self::Backoff, BackoffConfigError> {
+        let total = self
+            .choices
+            .iter()
+            .map(|(_, weight)| u64::from(*weight))
+            .sum::<u64>();
+        if total == 0 {
+            return Err(BackoffConfigError::NoWeightedPolicy);
+        }
+
+        let mut point = new_rng(self.seed).u64(..total);
+        let chosen = self
+            .choices
+            .iter()
+            .position(|(_, weight)| match point.checked_sub(u64::from(*weight)) {
+                Some(rest) => {
+                    point = rest;
+                    false
+                }
+                None => true,
+            })
+            .expect("the point must fall within the total weight");
+        let (builder, _) = self
+            .choices
+            .into_iter()
+            .nth(chosen)
+            .expect("the chosen policy must exist");
+
+        Ok(WeightedBackoff {
+            inner: builder.try_build()?,
+            chosen,
+        })
+    }
+}
+
+/// WeightedBackoff is the backoff built by [`WeightedBuilder`].
+#[derive(Debug, Clone)]
+pub struct WeightedBackoff<B> {
+    inner: B,
+    chosen: usize,
+}
+
+impl<B> WeightedBackoff<B> {
+    /// The index of the chosen policy, in the order given to [`WeightedBuilder::new`].
+    pub fn chosen(&self) -> usize {
+        self.chosen
+    }
+}
+
+impl<B: Iterator<Item = Duration>> Iterator for WeightedBackoff<B> {
+    type Item = Duration;
+
+    fn next(&mut self) -> Option<Duration> {
+        self.inner.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test as test;
+
+    use super::*;
+    use crate::ConstantBuilder;
+
+    #[test]
+    fn test_weighted_choice() {
+        let builder = WeightedBuilder::new([
+            (
+                ConstantBuilder::default().with_delay(Duration::from_secs(1)),
+                3,
+            ),
+            (
+                ConstantBuilder::default().with_delay(Duration::from_secs(2)),
+                0,
+            ),
+            (
+                ConstantBuilder::default().with_delay(Duration::from_secs(3)),
+                1,
+            ),
+        ]);
+
+        let mut counts = [0; 3];
+        for seed in 0..1000 {
+            let mut backoff = builder.clone().with_rng_seed(Some(seed)).build();
+            let chosen = backoff.chosen();
+            counts[chosen] += 1;
+            // The chosen policy gives the delays.
+            assert_eq!(backoff.next(), Some(Duration::from_secs(chosen as u64 + 1)));
+        }
+        // The policy without weight is never chosen.
+        assert_eq!(counts[1], 0);
+        assert!((650..850).contains(&counts[0]), "counts: {counts:?}");
+
+        // The same seed gives the same choice.
+        let a = builder.clone().with_rng_seed(Some(42)).build();
+        let b = builder.with_rng_seed(Some(42)).build();
+        assert_eq!(a.chosen(), b.chosen());
+    }
+
+    #[test]
+    fn test_weighted_no_policy() {
+        let builder = WeightedBuilder::new([(ConstantBuilder::default(), 0)]);
+        assert_eq!(
+            builder.try_build().unwrap_err(),
+            BackoffConfigError::NoWeightedPolicy
+        );
+    }
+}
@@ -0,0 +1,211 @@
+use core::time::Duration;
+#[cfg(feature = "std")]
+use std::sync::Arc;
+#[cfg(feature = "std")]
+use std::sync::Mutex;
+
+use rand_core::RngCore;
+
+use crate::ConstantBackoff;
+use crate::ExponentialBackoff;
+use crate::FibonacciBackoff;
+
+/// WithRng is a backoff that draws its jitter from a given random number generator.
+///
+/// It's created by the `with_rng` method of the builtin backoffs, like
+/// [`ExponentialBackoff::with_rng`], and replaces the generator seeded by the builder. It allows
+/// a deterministic simulation to drive all the randomness of the retries: pass the generator of
+/// the simulation, a `&mut dyn RngCore`, or a [`SharedRng`] handle to share it among retries.
+///
+/// Every backoff is a builder returning itself, so it could be passed to `retry` directly.
+///
+/// # Examples
+///
+/// ```
+/// use backon::BackoffBuilder;
+/// use backon::ExponentialBuilder;
+/// use backon::Retryable;
+/// use backon::SharedRng;
+/// use rand::rngs::SmallRng;
+/// use rand::SeedableRng;
+///
+/// async fn fetch() -> anyhow::Result<String> {
+///     Err(anyhow::anyhow!("simulated failure"))
+/// }
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() {
+///     // The generator of the simulation, shared by all the retries.
+///     let rng = SharedRng::new(SmallRng::seed_from_u64(42));
+///
+///     let backoff = ExponentialBuilder::default()
+///         .with_jitter()
+///         .build()
+///         .with_rng(rng.clone());
+///     let result = fetch
+///         .retry(backoff)
+///         .sleep(|_| async {})
+///         .await;
+///     assert!(result.is_err());
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct WithRng<B, R> {
+    backoff: B,
+    rng: R,
+}
+
+impl<B, R> WithRng<B, R> {
+    pub(crate) fn new(backoff: B, rng: R) -> Self {
+        WithRng { backoff, rng }
+    }
+}
+
+macro_rules! impl_with_rng {
+    ($($backoff:ty),*) => {
+        $(
+            impl<R: RngCore> Iterator for WithRng<$backoff, R> {
+                type Item = Duration;
+
+                fn next(&mut self) -> Option<Duration> {
+                    self.backoff.next_with_rng(&mut self.rng)
+                }
+
+                fn size_hint(&self) -> (usize, Option<usize>) {
+                    self.backoff.size_hint()
+                }
+            }
+        )*
+    };
+}
+
+impl_with_rng!(ExponentialBackoff, ConstantBackoff, FibonacciBackoff);
+
+/// SharedRng is a handle to a random number generator shared by several backoffs.
+///
+/// Cloning the handle doesn't clone the generator, all the clones draw from the same one. So
+/// the delays of concurrent retries depend on the order they draw in, like in a deterministic
+/// simulation that controls the order.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct SharedRng<R: ?Sized>(Arc<Mutex<R>>);
+
+#[cfg(feature = "std")]
+impl<R> SharedRng<R> {
+    /// Create a new handle to given random number generator.
+    pub fn new(rng: R) -> Self {
+        SharedRng(Arc::new(Mutex::new(rng)))
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: ?Sized> SharedRng<R> {
+    fn lock(&self) -> std::sync::MutexGuard<'_, R> {
+        self.0.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: ?Sized> Clone for SharedRng<R> {
+    fn clone(&self) -> Self {
+        SharedRng(self.0.clone())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: RngCore + ?Sized> RngCore for SharedRng<R> {
+    fn next_u32(&mut self) -> u32 {
+        self.lock().next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.lock().next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.lock().fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+        self.lock().try_fill_bytes(dest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate alloc;
+
+    use alloc::vec::Vec;
+
+    use rand::rngs::SmallRng;
+    use rand::SeedableRng;
+
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test as test;
+
+    use super::*;
+    use crate::BackoffBuilder;
+    use crate::ConstantBuilder;
+    use crate::ExponentialBuilder;
+
+    #[test]
+    fn test_with_dyn_rng() {
+        let builder = ExponentialBuilder::default()
+            .with_jitter()
+            .with_max_times(5);
+        let mut rng = SmallRng::seed_from_u64(42);
+        let mut expected = builder.build();
+        let expected: Vec<_> = core::iter::from_fn(|| expected.next_with_rng(&mut rng)).collect();
+
+        let mut rng = SmallRng::seed_from_u64(42);
+        let rng: &mut (dyn RngCore + Send + Sync) = &mut rng;
+        let delays: Vec<_> = builder.build().with_rng(rng).collect();
+        assert_eq!(delays, expected);
+    }
+
+    #[test]
+    fn test_with_rng_through_backoff() {
+        fn drain<B: crate::Backoff>(backoff: B) -> Vec<Duration> {
+            backoff.collect()
+        }
+
+        let builder = ConstantBuilder::default().with_jitter().with_max_times(5);
+        let mut rng = SmallRng::seed_from_u64(42);
+        let mut expected = builder.build();
+        let expected: Vec<_> = core::iter::from_fn(|| expected.next_with_rng(&mut rng)).collect();
+
+        // Generic code draws from the given generator as well.
+        let delays = drain(builder.build().with_rng(SmallRng::seed_from_u64(42)));
+        assert_eq!(delays, expected);
+        assert_ne!(delays, drain(builder.build()));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_with_shared_rng() {
+        let exponential = ExponentialBuilder::default()
+            .with_jitter()
+            .with_max_times(3);
+        let constant = ConstantBuilder::default().with_jitter().with_max_times(3);
+
+        // The backoffs draw from the same generator in turn.
+        let mut rng = SmallRng::seed_from_u64(42);
+        let (mut a, mut b) = (exponential.build(), constant.build());
+        let mut expected = Vec::new();
+        for _ in 0..3 {
+            expected.push(a.next_with_rng(&mut rng));
+            expected.push(b.next_with_rng(&mut rng));
+        }
+
+        let rng = SharedRng::new(SmallRng::seed_from_u64(42));
+        let mut a = exponential.build().with_rng(rng.clone());
+        let mut b = constant.build().with_rng(rng);
+        let mut delays = Vec::new();
+        for _ in 0..3 {
+            delays.push(a.next());
+            delays.push(b.next());
+        }
+        assert_eq!(delays, expected);
+        assert_eq!(a.next(), None);
+    }
+}
@@ -1,8 +1,13 @@
+use core::fmt;
 use core::time::Duration;
 
 use crate::backoff::BackoffBuilder;
 use crate::blocking_sleep::MaybeBlockingSleeper;
-use crate::{Backoff, BlockingSleeper, DefaultBlockingSleeper};
+use crate::notify::{Notify, OnRetry};
+use crate::progress::Progress;
+#[cfg(feature = "std")]
+use crate::AttemptTimedOut;
+use crate::{Backoff, BlockingSleeper, DefaultBlockingSleeper, RetryContext};
 
 /// BlockingRetryable adds retry support for blocking functions.
 ///
@@ -66,12 +71,32 @@ pub struct BlockingRetry<
     SF: MaybeBlockingSleeper = DefaultBlockingSleeper,
     RF = fn(&E) -> bool,
     NF = fn(&E, Duration),
+    GF = fn(&E, usize),
 > {
     backoff: B,
     retryable: RF,
     notify: NF,
+    on_giveup: GF,
     f: F,
     sleep_fn: SF,
+    #[cfg(feature = "tracing")]
+    tracer: Option<crate::trace::Tracer<E>>,
+    progress: Progress,
+}
+
+/// Show the backoff and the number of attempts made, the hooks are omitted.
+impl<B, T, E, F, SF, RF, NF, GF> fmt::Debug for BlockingRetry<B, T, E, F, SF, RF, NF, GF>
+where
+    B: Backoff + fmt::Debug,
+    F: FnMut() -> Result<T, E>,
+    SF: MaybeBlockingSleeper,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BlockingRetry")
+            .field("backoff", &self.backoff)
+            .field("attempts", &self.progress.attempts)
+            .finish_non_exhaustive()
+    }
 }
 
 impl<B, T, E, F> BlockingRetry<B, T, E, F>
@@ -85,19 +110,24 @@ where
             backoff,
             retryable: |_: &E| true,
             notify: |_: &E, _: Duration| {},
+            on_giveup: |_: &E, _: usize| {},
             sleep_fn: DefaultBlockingSleeper::default(),
+            #[cfg(feature = "tracing")]
+            tracer: None,
+            progress: Progress::default(),
             f,
         }
     }
 }
 
-impl<B, T, E, F, SF, RF, NF> BlockingRetry<B, T, E, F, SF, RF, NF>
+impl<B, T, E, F, SF, RF, NF, GF> BlockingRetry<B, T, E, F, SF, RF, NF, GF>
 where
     B: Backoff,
     F: FnMut() -> Result<T, E>,
     SF: MaybeBlockingSleeper,
     RF: FnMut(&E) -> bool,
-    NF: FnMut(&E, Duration),
+    NF: Notify<E>,
+    GF: FnMut(&E, usize),
 {
     /// Set the sleeper for retrying.
     ///
@@ -126,13 +156,20 @@ where
     ///     Ok(())
     /// }
     /// ```
-    pub fn sleep<SN: BlockingSleeper>(self, sleep_fn: SN) -> BlockingRetry<B, T, E, F, SN, RF, NF> {
+    pub fn sleep<SN: BlockingSleeper>(
+        self,
+        sleep_fn: SN,
+    ) -> BlockingRetry<B, T, E, F, SN, RF, NF, GF> {
         BlockingRetry {
             backoff: self.backoff,
             retryable: self.retryable,
             notify: self.notify,
+            on_giveup: self.on_giveup,
             f: self.f,
             sleep_fn,
+            #[cfg(feature = "tracing")]
+            tracer: self.tracer,
+            progress: self.progress,
         }
     }
 
@@ -164,13 +201,17 @@ where
     pub fn when<RN: FnMut(&E) -> bool>(
         self,
         retryable: RN,
-    ) -> BlockingRetry<B, T, E, F, SF, RN, NF> {
+    ) -> BlockingRetry<B, T, E, F, SF, RN, NF, GF> {
         BlockingRetry {
             backoff: self.backoff,
             retryable,
             notify: self.notify,
+            on_giveup: self.on_giveup,
             f: self.f,
             sleep_fn: self.sleep_fn,
+            #[cfg(feature = "tracing")]
+            tracer: self.tracer,
+            progress: self.progress,
         }
     }
 
@@ -208,43 +249,273 @@ where
     pub fn notify<NN: FnMut(&E, Duration)>(
         self,
         notify: NN,
-    ) -> BlockingRetry<B, T, E, F, SF, RF, NN> {
+    ) -> BlockingRetry<B, T, E, F, SF, RF, NN, GF> {
         BlockingRetry {
             backoff: self.backoff,
             retryable: self.retryable,
             notify,
+            on_giveup: self.on_giveup,
+            f: self.f,
+            sleep_fn: self.sleep_fn,
+            #[cfg(feature = "tracing")]
+            tracer: self.tracer,
+            progress: self.progress,
+        }
+    }
+
+    /// Set to notify for all retry attempts with the whole [`RetryContext`].
+    ///
+    /// It's invoked at the same time as [`BlockingRetry::notify`], but also receives the number of
+    /// the failed attempt and the time elapsed since the first attempt started.
+    ///
+    /// `notify` and `on_retry` share the same slot, setting one will replace the other.
+    pub fn on_retry<FN: FnMut(RetryContext<'_, E>)>(
+        mut self,
+        on_retry: FN,
+    ) -> BlockingRetry<B, T, E, F, SF, RF, OnRetry<FN>, GF> {
+        self.progress.measure_time();
+        BlockingRetry {
+            backoff: self.backoff,
+            retryable: self.retryable,
+            notify: OnRetry(on_retry),
+            on_giveup: self.on_giveup,
+            f: self.f,
+            sleep_fn: self.sleep_fn,
+            #[cfg(feature = "tracing")]
+            tracer: self.tracer,
+            progress: self.progress,
+        }
+    }
+
+    /// Set to notify when the retry gives up.
+    ///
+    /// The input function will be invoked exactly once with the last error and the number of
+    /// attempts made, when the error is not retryable or no more retry will happen. It's not invoked
+    /// if the retry succeeds.
+    ///
+    /// If not specified, this operation does nothing.
+    pub fn on_giveup<GN: FnMut(&E, usize)>(
+        self,
+        on_giveup: GN,
+    ) -> BlockingRetry<B, T, E, F, SF, RF, NF, GN> {
+        BlockingRetry {
+            backoff: self.backoff,
+            retryable: self.retryable,
+            notify: self.notify,
+            on_giveup,
             f: self.f,
             sleep_fn: self.sleep_fn,
+            #[cfg(feature = "tracing")]
+            tracer: self.tracer,
+            progress: self.progress,
         }
     }
+
+    /// Set a timeout for every attempt.
+    ///
+    /// Every attempt is run on a new thread, and an attempt that doesn't finish within `timeout`
+    /// fails with [`AttemptTimedOut`], which is converted into `E` and could be retried like
+    /// any other error.
+    ///
+    /// A thread can't be killed, so the thread of a timed out attempt is detached: it's left
+    /// running in the background until the function returns, and its result is discarded.
+    /// Every timed out attempt leaks its thread and whatever the function holds until then,
+    /// and forever if the function never returns, so the function should give up on its own
+    /// eventually, like with a timeout of its own I/O. The detached threads may outlive the
+    /// retry, that's why the function must be `Fn + Send + Sync + 'static` to be shared by
+    /// them, and `T` and `E` must be `Send + 'static` to be sent back.
+    ///
+    /// If the function panics, the panic is propagated to the caller.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    ///
+    /// use anyhow::Result;
+    /// use backon::BlockingRetryable;
+    /// use backon::ExponentialBuilder;
+    ///
+    /// fn fetch() -> Result<String> {
+    ///     Ok("hello, world!".to_string())
+    /// }
+    ///
+    /// fn main() -> Result<()> {
+    ///     let content = fetch
+    ///         .retry(ExponentialBuilder::default())
+    ///         .attempt_timeout(Duration::from_secs(5))
+    ///         .call()?;
+    ///     println!("fetch succeeded: {}", content);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn attempt_timeout(
+        self,
+        timeout: Duration,
+    ) -> BlockingRetry<B, T, E, impl FnMut() -> Result<T, E>, SF, RF, NF, GF>
+    where
+        F: Fn() -> Result<T, E> + Send + Sync + 'static,
+        T: Send + 'static,
+        E: From<AttemptTimedOut> + Send + 'static,
+    {
+        self.attempt_timeouts(core::iter::repeat(timeout))
+    }
+
+    /// Set a timeout for every attempt that follows a schedule, like a growing timeout.
+    ///
+    /// It's the same as [`BlockingRetry::attempt_timeout`], except that the timeouts are taken
+    /// from a backoff built by `timeouts`: the first attempt gets the first delay as its
+    /// timeout, the second attempt the second one, and so on. It's useful when the backend is
+    /// slow under load, so the attempts get more time while it recovers. Once the schedule
+    /// stops, the last timeout is kept for the remaining attempts, and the attempts run without
+    /// a timeout if it yields nothing at all.
+    ///
+    /// The schedule is independent of the backoff of the retry, which still decides the delays
+    /// between the attempts and how many attempts are made. There is no overall deadline, the
+    /// retry may take up to the sum of all timeouts and delays.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    ///
+    /// use anyhow::Result;
+    /// use backon::BlockingRetryable;
+    /// use backon::ExponentialBuilder;
+    ///
+    /// fn fetch() -> Result<String> {
+    ///     Ok("hello, world!".to_string())
+    /// }
+    ///
+    /// fn main() -> Result<()> {
+    ///     let content = fetch
+    ///         .retry(ExponentialBuilder::default())
+    ///         // 1s, 2s, 4s, 8s for the attempts.
+    ///         .attempt_timeouts(ExponentialBuilder::default().without_max_times())
+    ///         .call()?;
+    ///     println!("fetch succeeded: {}", content);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn attempt_timeouts<TB: BackoffBuilder>(
+        self,
+        timeouts: TB,
+    ) -> BlockingRetry<B, T, E, impl FnMut() -> Result<T, E>, SF, RF, NF, GF>
+    where
+        F: Fn() -> Result<T, E> + Send + Sync + 'static,
+        T: Send + 'static,
+        E: From<AttemptTimedOut> + Send + 'static,
+    {
+        let f = std::sync::Arc::new(self.f);
+        let mut timeouts = timeouts.build();
+        let mut last = None;
+
+        BlockingRetry {
+            backoff: self.backoff,
+            retryable: self.retryable,
+            notify: self.notify,
+            on_giveup: self.on_giveup,
+            f: move || {
+                last = timeouts.next().or(last);
+                let Some(timeout) = last else {
+                    return f();
+                };
+
+                let (tx, rx) = std::sync::mpsc::sync_channel(1);
+                let f = f.clone();
+                let handle = std::thread::spawn(move || {
+                    // The receiver is gone if the attempt timed out, ignore the result.
+                    let _ = tx.send(f());
+                });
+
+                match rx.recv_timeout(timeout) {
+                    Ok(result) => result,
+                    Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {
+                        Err(AttemptTimedOut { timeout }.into())
+                    }
+                    // The sender is dropped without sending, so the function must have panicked.
+                    Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => match handle.join() {
+                        Err(panic) => std::panic::resume_unwind(panic),
+                        Ok(()) => unreachable!("attempt thread exited without a result"),
+                    },
+                }
+            },
+            sleep_fn: self.sleep_fn,
+            #[cfg(feature = "tracing")]
+            tracer: self.tracer,
+            progress: self.progress,
+        }
+    }
+
+    /// Instrument this retry with [`tracing`].
+    ///
+    /// A span is entered while this retry is called, and an event carrying the attempt
+    /// number, the error and the upcoming delay is emitted at given `level` for every failed attempt.
+    ///
+    /// The fields of the events are the same as [`Retry::trace`](crate::Retry::trace).
+    #[cfg(feature = "tracing")]
+    pub fn trace(mut self, level: tracing::Level) -> Self
+    where
+        E: fmt::Display,
+    {
+        self.tracer = Some(crate::trace::Tracer::new(
+            level,
+            self.backoff.remaining().map(|n| n.saturating_add(1)),
+        ));
+        self
+    }
 }
 
-impl<B, T, E, F, SF, RF, NF> BlockingRetry<B, T, E, F, SF, RF, NF>
+impl<B, T, E, F, SF, RF, NF, GF> BlockingRetry<B, T, E, F, SF, RF, NF, GF>
 where
     B: Backoff,
     F: FnMut() -> Result<T, E>,
     SF: BlockingSleeper,
     RF: FnMut(&E) -> bool,
-    NF: FnMut(&E, Duration),
+    NF: Notify<E>,
+    GF: FnMut(&E, usize),
 {
     /// Call the retried function.
     ///
     /// TODO: implement [`FnOnce`] after it stable.
     pub fn call(mut self) -> Result<T, E> {
+        #[cfg(feature = "tracing")]
+        let _span = self.tracer.as_ref().map(|t| t.enter());
+
         loop {
+            self.progress.start_attempt();
             let result = (self.f)();
 
             match result {
                 Ok(v) => return Ok(v),
                 Err(err) => {
-                    if !(self.retryable)(&err) {
-                        return Err(err);
+                    let next = if (self.retryable)(&err) {
+                        self.backoff.next()
+                    } else {
+                        None
+                    };
+                    #[cfg(feature = "tracing")]
+                    if let Some(tracer) = &self.tracer {
+                        tracer.record(self.progress.attempts, &err, next);
                     }
 
-                    match self.backoff.next() {
-                        None => return Err(err),
+                    match next {
+                        None => {
+                            (self.on_giveup)(&err, self.progress.attempts);
+                            return Err(err);
+                        }
                         Some(dur) => {
-                            (self.notify)(&err, dur);
+                            self.notify.notify(RetryContext {
+                                attempt: self.progress.attempts,
+                                error: &err,
+                                next_delay: dur,
+                                elapsed: self.progress.elapsed(),
+                                remaining: self.backoff.remaining(),
+                            });
                             self.sleep_fn.sleep(dur);
                         }
                     }
@@ -359,4 +630,149 @@ mod tests {
         assert_eq!(calls_notify.len(), 3);
         Ok(())
     }
+
+    #[test]
+    fn test_on_retry() -> anyhow::Result<()> {
+        let mut attempts: Vec<(usize, Duration)> = vec![];
+
+        let f = || Err::<(), anyhow::Error>(anyhow::anyhow!("retryable"));
+
+        let backoff = ExponentialBuilder::default().with_min_delay(Duration::from_millis(1));
+        let result = f
+            .retry(backoff)
+            .on_retry(|ctx| attempts.push((ctx.attempt, ctx.elapsed)))
+            .sleep(|_dur| {
+                #[cfg(feature = "std")]
+                std::thread::sleep(_dur);
+            })
+            .call();
+
+        assert!(result.is_err());
+        assert_eq!(
+            attempts.iter().map(|(n, _)| *n).collect::<Vec<_>>(),
+            vec![1, 2, 3]
+        );
+        // The third attempt starts after sleeping 1ms and 2ms.
+        #[cfg(feature = "std")]
+        assert!(attempts[2].1 >= Duration::from_millis(3));
+        Ok(())
+    }
+
+    #[test]
+    fn test_on_giveup() -> anyhow::Result<()> {
+        let mut giveups: Vec<usize> = vec![];
+        let mut calls = 0;
+
+        let f = || {
+            calls += 1;
+            if calls < 3 {
+                Err(anyhow::anyhow!("retryable"))
+            } else {
+                Ok(())
+            }
+        };
+
+        let backoff = ExponentialBuilder::default().with_min_delay(Duration::from_millis(1));
+        let result = f
+            .retry(backoff)
+            .sleep(|_| {})
+            .on_giveup(|_, n| giveups.push(n))
+            .call();
+
+        // The hook is not invoked if the retry succeeds.
+        assert!(result.is_ok());
+        assert!(giveups.is_empty());
+        Ok(())
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_retry_with_attempt_timeout() -> anyhow::Result<()> {
+        use alloc::sync::Arc;
+        use core::sync::atomic::AtomicUsize;
+        use core::sync::atomic::Ordering;
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mut errors = vec![];
+        // The hanging attempts are released only after the retry is done.
+        let (release, hang) = std::sync::mpsc::channel::<()>();
+        let hang = Arc::new(std::sync::Mutex::new(hang));
+
+        let result = {
+            let calls = calls.clone();
+            move || {
+                // The first attempt hangs.
+                if calls.fetch_add(1, Ordering::SeqCst) == 0 {
+                    let _ = hang.lock().unwrap().recv();
+                }
+                Ok::<_, anyhow::Error>("hello")
+            }
+        }
+        .retry(ExponentialBuilder::default())
+        .attempt_timeout(Duration::from_millis(500))
+        .sleep(|_| {})
+        .notify(|e, _| errors.push(e.to_string()))
+        .call();
+        drop(release);
+
+        assert_eq!(result?, "hello");
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert_eq!(errors, vec!["attempt timed out after 500ms"]);
+        Ok(())
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_retry_with_attempt_timeouts() -> anyhow::Result<()> {
+        use alloc::sync::Arc;
+        use core::sync::atomic::AtomicUsize;
+        use core::sync::atomic::Ordering;
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mut errors = vec![];
+        // The hanging attempts are released only after the retry is done.
+        let (release, hang) = std::sync::mpsc::channel::<()>();
+        let hang = Arc::new(std::sync::Mutex::new(hang));
+
+        let result = {
+            let calls = calls.clone();
+            move || {
+                // The first 3 attempts hang.
+                if calls.fetch_add(1, Ordering::SeqCst) < 3 {
+                    let _ = hang.lock().unwrap().recv();
+                }
+                Ok::<_, anyhow::Error>("hello")
+            }
+        }
+        .retry(ExponentialBuilder::default().with_max_times(5))
+        .attempt_timeouts([10, 500].map(Duration::from_millis).into_iter())
+        .sleep(|_| {})
+        .notify(|e, _| errors.push(e.to_string()))
+        .call();
+        drop(release);
+
+        assert_eq!(result?, "hello");
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+        // The last timeout is kept once the schedule stops.
+        assert_eq!(
+            errors,
+            vec![
+                "attempt timed out after 10ms",
+                "attempt timed out after 500ms",
+                "attempt timed out after 500ms",
+            ]
+        );
+        Ok(())
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    #[should_panic(expected = "boom")]
+    fn test_retry_with_attempt_timeout_panic() {
+        let _ = (|| -> anyhow::Result<()> { panic!("boom") })
+            .retry(ExponentialBuilder::default())
+            .attempt_timeout(Duration::from_secs(1))
+            .sleep(|_| {})
+            .call();
+    }
 }
@@ -2,7 +2,9 @@ use core::time::Duration;
 
 use crate::backoff::BackoffBuilder;
 use crate::blocking_sleep::MaybeBlockingSleeper;
-use crate::{Backoff, BlockingSleeper, DefaultBlockingSleeper};
+use crate::notify::{Notify, OnRetry};
+use crate::progress::Progress;
+use crate::{Backoff, BlockingSleeper, DefaultBlockingSleeper, RetryContext};
 
 /// BlockingRetryableWithContext adds retry support for blocking functions.
 pub trait BlockingRetryableWithContext<
@@ -37,12 +39,17 @@ pub struct BlockingRetryWithContext<
     SF: MaybeBlockingSleeper = DefaultBlockingSleeper,
     RF = fn(&E) -> bool,
     NF = fn(&E, Duration),
+    GF = fn(&E, usize),
 > {
     backoff: B,
     retryable: RF,
     notify: NF,
+    on_giveup: GF,
     f: F,
     sleep_fn: SF,
+    #[cfg(feature = "tracing")]
+    tracer: Option<crate::trace::Tracer<E>>,
+    progress: Progress,
     ctx: Option<Ctx>,
 }
 
@@ -57,31 +64,43 @@ where
             backoff,
             retryable: |_: &E| true,
             notify: |_: &E, _: Duration| {},
+            on_giveup: |_: &E, _: usize| {},
             sleep_fn: DefaultBlockingSleeper::default(),
+            #[cfg(feature = "tracing")]
+            tracer: None,
+            progress: Progress::default(),
             f,
             ctx: None,
         }
     }
 }
 
-impl<B, T, E, Ctx, F, SF, RF, NF> BlockingRetryWithContext<B, T, E, Ctx, F, SF, RF, NF>
+impl<B, T, E, Ctx, F, SF, RF, NF, GF> BlockingRetryWithContext<B, T, E, Ctx, F, SF, RF, NF, GF>
 where
     B: Backoff,
     F: FnMut(Ctx) -> (Ctx, Result<T, E>),
     SF: MaybeBlockingSleeper,
     RF: FnMut(&E) -> bool,
-    NF: FnMut(&E, Duration),
+    NF: Notify<E>,
+    GF: FnMut(&E, usize),
 {
     /// Set the context for retrying.
     ///
     /// Context is used to capture ownership manually to prevent lifetime issues.
-    pub fn context(self, context: Ctx) -> BlockingRetryWithContext<B, T, E, Ctx, F, SF, RF, NF> {
+    pub fn context(
+        self,
+        context: Ctx,
+    ) -> BlockingRetryWithContext<B, T, E, Ctx, F, SF, RF, NF, GF> {
         BlockingRetryWithContext {
             backoff: self.backoff,
             retryable: self.retryable,
             notify: self.notify,
+            on_giveup: self.on_giveup,
             f: self.f,
             sleep_fn: self.sleep_fn,
+            #[cfg(feature = "tracing")]
+            tracer: self.tracer,
+            progress: self.progress,
             ctx: Some(context),
         }
     }
@@ -94,13 +113,17 @@ where
     pub fn sleep<SN: BlockingSleeper>(
         self,
         sleep_fn: SN,
-    ) -> BlockingRetryWithContext<B, T, E, Ctx, F, SN, RF, NF> {
+    ) -> BlockingRetryWithContext<B, T, E, Ctx, F, SN, RF, NF, GF> {
         BlockingRetryWithContext {
             backoff: self.backoff,
             retryable: self.retryable,
             notify: self.notify,
+            on_giveup: self.on_giveup,
             f: self.f,
             sleep_fn,
+            #[cfg(feature = "tracing")]
+            tracer: self.tracer,
+            progress: self.progress,
             ctx: self.ctx,
         }
     }
@@ -111,13 +134,17 @@ where
     pub fn when<RN: FnMut(&E) -> bool>(
         self,
         retryable: RN,
-    ) -> BlockingRetryWithContext<B, T, E, Ctx, F, SF, RN, NF> {
+    ) -> BlockingRetryWithContext<B, T, E, Ctx, F, SF, RN, NF, GF> {
         BlockingRetryWithContext {
             backoff: self.backoff,
             retryable,
             notify: self.notify,
+            on_giveup: self.on_giveup,
             f: self.f,
             sleep_fn: self.sleep_fn,
+            #[cfg(feature = "tracing")]
+            tracer: self.tracer,
+            progress: self.progress,
             ctx: self.ctx,
         }
     }
@@ -130,32 +157,109 @@ where
     pub fn notify<NN: FnMut(&E, Duration)>(
         self,
         notify: NN,
-    ) -> BlockingRetryWithContext<B, T, E, Ctx, F, SF, RF, NN> {
+    ) -> BlockingRetryWithContext<B, T, E, Ctx, F, SF, RF, NN, GF> {
         BlockingRetryWithContext {
             backoff: self.backoff,
             retryable: self.retryable,
             notify,
+            on_giveup: self.on_giveup,
+            f: self.f,
+            sleep_fn: self.sleep_fn,
+            #[cfg(feature = "tracing")]
+            tracer: self.tracer,
+            progress: self.progress,
+            ctx: self.ctx,
+        }
+    }
+
+    /// Set to notify for all retry attempts with the whole [`RetryContext`].
+    ///
+    /// It's invoked at the same time as [`BlockingRetryWithContext::notify`], but also receives the number of
+    /// the failed attempt and the time elapsed since the first attempt started.
+    ///
+    /// `notify` and `on_retry` share the same slot, setting one will replace the other.
+    pub fn on_retry<FN: FnMut(RetryContext<'_, E>)>(
+        mut self,
+        on_retry: FN,
+    ) -> BlockingRetryWithContext<B, T, E, Ctx, F, SF, RF, OnRetry<FN>, GF> {
+        self.progress.measure_time();
+        BlockingRetryWithContext {
+            backoff: self.backoff,
+            retryable: self.retryable,
+            notify: OnRetry(on_retry),
+            on_giveup: self.on_giveup,
+            f: self.f,
+            sleep_fn: self.sleep_fn,
+            #[cfg(feature = "tracing")]
+            tracer: self.tracer,
+            progress: self.progress,
+            ctx: self.ctx,
+        }
+    }
+
+    /// Set to notify when the retry gives up.
+    ///
+    /// The input function will be invoked exactly once with the last error and the number of
+    /// attempts made, when the error is not retryable or no more retry will happen. It's not invoked
+    /// if the retry succeeds.
+    ///
+    /// If not specified, this operation does nothing.
+    pub fn on_giveup<GN: FnMut(&E, usize)>(
+        self,
+        on_giveup: GN,
+    ) -> BlockingRetryWithContext<B, T, E, Ctx, F, SF, RF, NF, GN> {
+        BlockingRetryWithContext {
+            backoff: self.backoff,
+            retryable: self.retryable,
+            notify: self.notify,
+            on_giveup,
             f: self.f,
             sleep_fn: self.sleep_fn,
+            #[cfg(feature = "tracing")]
+            tracer: self.tracer,
+            progress: self.progress,
             ctx: self.ctx,
         }
     }
+
+    /// Instrument this retry with [`tracing`].
+    ///
+    /// A span is entered while this retry is called, and an event carrying the attempt
+    /// number, the error and the upcoming delay is emitted at given `level` for every failed attempt.
+    ///
+    /// The fields of the events are the same as [`Retry::trace`](crate::Retry::trace).
+    #[cfg(feature = "tracing")]
+    pub fn trace(mut self, level: tracing::Level) -> Self
+    where
+        E: core::fmt::Display,
+    {
+        self.tracer = Some(crate::trace::Tracer::new(
+            level,
+            self.backoff.remaining().map(|n| n.saturating_add(1)),
+        ));
+        self
+    }
 }
 
-impl<B, T, E, Ctx, F, SF, RF, NF> BlockingRetryWithContext<B, T, E, Ctx, F, SF, RF, NF>
+impl<B, T, E, Ctx, F, SF, RF, NF, GF> BlockingRetryWithContext<B, T, E, Ctx, F, SF, RF, NF, GF>
 where
     B: Backoff,
     F: FnMut(Ctx) -> (Ctx, Result<T, E>),
     SF: BlockingSleeper,
     RF: FnMut(&E) -> bool,
-    NF: FnMut(&E, Duration),
+    NF: Notify<E>,
+    GF: FnMut(&E, usize),
 {
     /// Call the retried function.
     ///
     /// TODO: implement [`FnOnce`] after it stable.
     pub fn call(mut self) -> (Ctx, Result<T, E>) {
+        #[cfg(feature = "tracing")]
+        let _span = self.tracer.as_ref().map(|t| t.enter());
+
         let mut ctx = self.ctx.take().expect("context must be valid");
         loop {
+            self.progress.start_attempt();
             let (xctx, result) = (self.f)(ctx);
             // return ctx ownership back
             ctx = xctx;
@@ -163,14 +267,29 @@ where
             match result {
                 Ok(v) => return (ctx, Ok(v)),
                 Err(err) => {
-                    if !(self.retryable)(&err) {
-                        return (ctx, Err(err));
+                    let next = if (self.retryable)(&err) {
+                        self.backoff.next()
+                    } else {
+                        None
+                    };
+                    #[cfg(feature = "tracing")]
+                    if let Some(tracer) = &self.tracer {
+                        tracer.record(self.progress.attempts, &err, next);
                     }
 
-                    match self.backoff.next() {
-                        None => return (ctx, Err(err)),
+                    match next {
+                        None => {
+                            (self.on_giveup)(&err, self.progress.attempts);
+                            return (ctx, Err(err));
+                        }
                         Some(dur) => {
-                            (self.notify)(&err, dur);
+                            self.notify.notify(RetryContext {
+                                attempt: self.progress.attempts,
+                                error: &err,
+                                next_delay: dur,
+                                elapsed: self.progress.elapsed(),
+                                remaining: self.backoff.remaining(),
+                            });
                             self.sleep_fn.sleep(dur);
                         }
                     }
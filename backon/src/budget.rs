@@ -0,0 +1,131 @@
+use core::sync::atomic::AtomicU64;
+use core::sync::atomic::Ordering;
+
+/// The tokens are stored as fixed-point numbers in units of `1 / SCALE` token.
+const SCALE: f64 = 1000.0;
+
+/// RetryBudget caps the retries as a ratio of the requests that succeed, to prevent retry
+/// storms.
+///
+/// It's a token bucket shared by all retries that call the same service, set by
+/// [`Retry::budget`](crate::Retry::budget). Every retry withdraws one token before backing off,
+/// and gives up right away if no token is left. Every success deposits `ratio` tokens, up to
+/// `max_tokens`. With a ratio of `0.1`, at most one retry is made for every ten successes
+/// once the initial tokens are used up, so a service that is down doesn't get flooded with
+/// retries.
+///
+/// The bucket starts full, so a burst of `max_tokens` retries is allowed before any request
+/// succeeds.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::sync::Arc;
+///
+/// use anyhow::Result;
+/// use backon::ExponentialBuilder;
+/// use backon::RetryBudget;
+/// use backon::Retryable;
+///
+/// async fn fetch() -> Result<String> {
+///     Ok(reqwest::get("https://www.rust-lang.org")
+///         .await?
+///         .text()
+///         .await?)
+/// }
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() -> Result<()> {
+///     // Shared by all calls to the same service.
+///     let budget = Arc::new(RetryBudget::new(10, 0.1));
+///
+///     let content = fetch
+///         .retry(ExponentialBuilder::default())
+///         .budget(budget.clone())
+///         .await?;
+///     println!("fetch succeeded: {}", content);
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug)]
+pub struct RetryBudget {
+    tokens: AtomicU64,
+    max_tokens: u64,
+    deposit: u64,
+}
+
+impl RetryBudget {
+    /// Create a new full budget holding at most `max_tokens` tokens, refilled by `ratio` token
+    /// for every success.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the ratio is negative or not a number.
+    pub fn new(max_tokens: u32, ratio: f32) -> Self {
+        assert!(
+            ratio >= 0.0,
+            "invalid budget ratio that is negative or not a number"
+        );
+
+        let max_tokens = max_tokens as u64 * SCALE as u64;
+        RetryBudget {
+            tokens: AtomicU64::new(max_tokens),
+            max_tokens,
+            deposit: (ratio as f64 * SCALE) as u64,
+        }
+    }
+
+    /// Return the number of tokens left.
+    pub fn tokens(&self) -> f64 {
+        self.tokens.load(Ordering::Relaxed) as f64 / SCALE
+    }
+
+    /// Record a success, which refills the budget.
+    pub fn deposit(&self) {
+        let _ = self
+            .tokens
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |tokens| {
+                Some(tokens.saturating_add(self.deposit).min(self.max_tokens))
+            });
+    }
+
+    /// Withdraw a token for a retry, return `false` if no token is left.
+    pub fn withdraw(&self) -> bool {
+        self.tokens
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |tokens| {
+                tokens.checked_sub(SCALE as u64)
+            })
+            .is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test as test;
+
+    use super::*;
+
+    #[test]
+    fn test_retry_budget() {
+        let budget = RetryBudget::new(2, 0.5);
+        assert_eq!(budget.tokens(), 2.0);
+
+        assert!(budget.withdraw());
+        assert!(budget.withdraw());
+        assert!(!budget.withdraw());
+
+        // Two successes make a retry.
+        budget.deposit();
+        assert!(!budget.withdraw());
+        budget.deposit();
+        assert!(budget.withdraw());
+
+        // The budget never exceeds the max tokens.
+        for _ in 0..10 {
+            budget.deposit();
+        }
+        assert_eq!(budget.tokens(), 2.0);
+    }
+}
@@ -0,0 +1,431 @@
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::ready;
+use core::task::Context;
+use core::task::Poll;
+use core::time::Duration;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+use std::time::Instant;
+
+use crate::backoff::BackoffBuilder;
+
+/// CircuitBreaker stops calling an operation after a burst of failures, and probes it
+/// periodically until it recovers.
+///
+/// The breaker starts [`Closed`](CircuitState::Closed) and lets every call through. After
+/// `failure_threshold` consecutive failures it becomes [`Open`](CircuitState::Open), and calls
+/// fail with [`CircuitOpen`] immediately without invoking the operation. When the delay given
+/// by the backoff elapses, the breaker becomes [`HalfOpen`](CircuitState::HalfOpen) and lets
+/// exactly one probe call through: the breaker closes if the probe succeeds, and opens again
+/// for the next delay of the backoff if it fails. The backoff is rebuilt every time the breaker
+/// closes, and the last delay is reused if it stops.
+///
+/// Clones of the breaker share the same state, so a breaker can guard all calls to a service.
+///
+/// # Examples
+///
+/// ```no_run
+/// use core::time::Duration;
+///
+/// use anyhow::Result;
+/// use backon::CircuitBreaker;
+/// use backon::CircuitOpen;
+/// use backon::ExponentialBuilder;
+/// use backon::Retryable;
+///
+/// async fn fetch() -> Result<String> {
+///     Ok(reqwest::get("https://www.rust-lang.org")
+///         .await?
+///         .text()
+///         .await?)
+/// }
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() -> Result<()> {
+///     let breaker = CircuitBreaker::new(
+///         ExponentialBuilder::default()
+///             .with_min_delay(Duration::from_secs(5))
+///             .without_max_times(),
+///     )
+///     .with_failure_threshold(3);
+///
+///     let content = breaker
+///         .wrap(fetch)
+///         .retry(ExponentialBuilder::default())
+///         // Don't retry while the circuit is open.
+///         .when(|e| !e.is::<CircuitOpen>())
+///         .await?;
+///     println!("fetch succeeded: {}", content);
+///
+///     Ok(())
+/// }
+/// ```
+pub struct CircuitBreaker<B: BackoffBuilder> {
+    inner: Arc<Mutex<Inner<B>>>,
+}
+
+struct Inner<B: BackoffBuilder> {
+    builder: B,
+    backoff: B::Backoff,
+    failure_threshold: usize,
+
+    state: State,
+    /// The delay used to open the breaker last time.
+    last_delay: Duration,
+}
+
+enum State {
+    Closed { failures: usize },
+    Open { since: Instant, delay: Duration },
+    HalfOpen,
+}
+
+/// The state of a [`CircuitBreaker`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    /// All calls are let through.
+    Closed,
+    /// All calls fail with [`CircuitOpen`] until the delay elapses.
+    Open,
+    /// A probe call has been let through, other calls fail with [`CircuitOpen`] until it finishes.
+    HalfOpen,
+}
+
+impl<B: BackoffBuilder + Clone> CircuitBreaker<B> {
+    /// Create a new circuit breaker, the delays of the backoff are used as the time to stay open
+    /// before probing.
+    pub fn new(builder: B) -> Self {
+        CircuitBreaker {
+            inner: Arc::new(Mutex::new(Inner {
+                backoff: builder.clone().build(),
+                builder,
+                failure_threshold: 5,
+
+                state: State::Closed { failures: 0 },
+                last_delay: Duration::ZERO,
+            })),
+        }
+    }
+
+    /// Set the number of consecutive failures that opens the breaker.
+    ///
+    /// The default is `5`, and `0` is treated as `1`.
+    pub fn with_failure_threshold(self, failure_threshold: usize) -> Self {
+        self.lock().failure_threshold = failure_threshold.max(1);
+        self
+    }
+
+    /// The current state of the breaker.
+    pub fn state(&self) -> CircuitState {
+        match self.lock().state {
+            State::Closed { .. } => CircuitState::Closed,
+            State::Open { .. } => CircuitState::Open,
+            State::HalfOpen => CircuitState::HalfOpen,
+        }
+    }
+
+    /// Guard the given function with this breaker.
+    ///
+    /// The returned function fails with [`CircuitOpen`] converted into `E` without calling `f`
+    /// while the breaker is open, and records the result of `f` otherwise.
+    pub fn wrap<T, E, Fut, FutureFn>(
+        &self,
+        mut f: FutureFn,
+    ) -> impl FnMut() -> CircuitFuture<Fut, B>
+    where
+        E: From<CircuitOpen>,
+        Fut: Future<Output = Result<T, E>>,
+        FutureFn: FnMut() -> Fut,
+    {
+        let breaker = self.clone();
+        move || match breaker.acquire() {
+            Some(probe) => CircuitFuture {
+                state: CircuitFutureState::Running(f()),
+                breaker: Some(breaker.clone()),
+                probe,
+            },
+            None => CircuitFuture {
+                state: CircuitFutureState::Open,
+                breaker: None,
+                probe: false,
+            },
+        }
+    }
+
+    /// Check if a call can be let through, returns `Some(true)` if it's a probe.
+    fn acquire(&self) -> Option<bool> {
+        let mut inner = self.lock();
+        match inner.state {
+            State::Closed { .. } => Some(false),
+            State::Open { since, delay } if since.elapsed() >= delay => {
+                inner.state = State::HalfOpen;
+                Some(true)
+            }
+            State::Open { .. } | State::HalfOpen => None,
+        }
+    }
+
+    /// Record the result of a call.
+    fn record(&self, success: bool, probe: bool) {
+        let mut inner = self.lock();
+        match (&mut inner.state, success) {
+            (_, true) if probe => {
+                inner.backoff = inner.builder.clone().build();
+                inner.state = State::Closed { failures: 0 };
+            }
+            (State::Closed { failures }, true) => *failures = 0,
+            (_, false) if probe => inner.open(),
+            (State::Closed { failures }, false) => {
+                *failures += 1;
+                if *failures >= inner.failure_threshold {
+                    inner.open();
+                }
+            }
+            // Calls let through before the breaker opened don't affect it anymore.
+            _ => {}
+        }
+    }
+}
+
+impl<B: BackoffBuilder> CircuitBreaker<B> {
+    fn lock(&self) -> MutexGuard<'_, Inner<B>> {
+        // The state is always updated in a single step, ignore the poison.
+        self.inner.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Give up a probe that has been dropped before finishing, so that another call can probe.
+    fn release(&self) {
+        let mut inner = self.lock();
+        if let State::HalfOpen = inner.state {
+            inner.state = State::Open {
+                since: Instant::now(),
+                delay: Duration::ZERO,
+            };
+        }
+    }
+}
+
+impl<B: BackoffBuilder> Inner<B> {
+    fn open(&mut self) {
+        if let Some(delay) = self.backoff.next() {
+            self.last_delay = delay;
+        }
+        self.state = State::Open {
+            since: Instant::now(),
+            delay: self.last_delay,
+        };
+    }
+}
+
+impl<B: BackoffBuilder> Clone for CircuitBreaker<B> {
+    fn clone(&self) -> Self {
+        CircuitBreaker {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<B: BackoffBuilder> fmt::Debug for CircuitBreaker<B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CircuitBreaker").finish_non_exhaustive()
+    }
+}
+
+/// CircuitOpen is the error of calls rejected by an open [`CircuitBreaker`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CircuitOpen;
+
+impl fmt::Display for CircuitOpen {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "circuit breaker is open")
+    }
+}
+
+impl std::error::Error for CircuitOpen {}
+
+/// Future generated by the function returned by [`CircuitBreaker::wrap`].
+pub struct CircuitFuture<Fut, B: BackoffBuilder> {
+    state: CircuitFutureState<Fut>,
+    /// The breaker to record the result, `None` if the call has been rejected or finished.
+    breaker: Option<CircuitBreaker<B>>,
+    probe: bool,
+}
+
+enum CircuitFutureState<Fut> {
+    Open,
+    Running(Fut),
+}
+
+impl<T, E, Fut, B> Future for CircuitFuture<Fut, B>
+where
+    E: From<CircuitOpen>,
+    Fut: Future<Output = Result<T, E>>,
+    B: BackoffBuilder + Clone,
+{
+    type Output = Result<T, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // Safety: This is safe because we don't move the `CircuitFuture` struct itself,
+        // only its internal state.
+        //
+        // We do the exactly same thing like `pin_project` but without depending on it directly.
+        let this = unsafe { self.get_unchecked_mut() };
+
+        match &mut this.state {
+            CircuitFutureState::Open => Poll::Ready(Err(CircuitOpen.into())),
+            CircuitFutureState::Running(fut) => {
+                // Safety: This is safe because we don't move the `CircuitFuture` struct and this fut,
+                // only its internal state.
+                //
+                // We do the exactly same thing like `pin_project` but without depending on it directly.
+                let fut = unsafe { Pin::new_unchecked(fut) };
+
+                let result = ready!(fut.poll(cx));
+                if let Some(breaker) = this.breaker.take() {
+                    breaker.record(result.is_ok(), this.probe);
+                }
+                Poll::Ready(result)
+            }
+        }
+    }
+}
+
+impl<Fut, B: BackoffBuilder> Drop for CircuitFuture<Fut, B> {
+    fn drop(&mut self) {
+        if let Some(breaker) = self.breaker.take() {
+            if self.probe {
+                breaker.release();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::future::ready;
+    use core::time::Duration;
+    use std::sync::Arc;
+    use std::sync::Mutex;
+
+    use tokio::test;
+
+    use super::*;
+    use crate::ConstantBuilder;
+
+    #[derive(Debug, PartialEq)]
+    enum TestError {
+        Failed,
+        Open,
+    }
+
+    impl From<CircuitOpen> for TestError {
+        fn from(_: CircuitOpen) -> Self {
+            TestError::Open
+        }
+    }
+
+    /// A function that fails while `failing` is set, and counts its calls.
+    fn flaky(
+        calls: &Arc<Mutex<usize>>,
+        failing: &Arc<Mutex<bool>>,
+    ) -> impl FnMut() -> core::future::Ready<Result<(), TestError>> {
+        let calls = calls.clone();
+        let failing = failing.clone();
+        move || {
+            *calls.lock().unwrap() += 1;
+            if *failing.lock().unwrap() {
+                ready(Err(TestError::Failed))
+            } else {
+                ready(Ok(()))
+            }
+        }
+    }
+
+    #[test]
+    async fn test_circuit_breaker_opens() {
+        let calls = Arc::new(Mutex::new(0));
+        let failing = Arc::new(Mutex::new(true));
+        let breaker =
+            CircuitBreaker::new(ConstantBuilder::default().with_delay(Duration::from_secs(60)))
+                .with_failure_threshold(2);
+        let mut f = breaker.wrap(flaky(&calls, &failing));
+
+        assert_eq!(f().await, Err(TestError::Failed));
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        assert_eq!(f().await, Err(TestError::Failed));
+        assert_eq!(breaker.state(), CircuitState::Open);
+
+        // The function is not called while the breaker is open.
+        assert_eq!(f().await, Err(TestError::Open));
+        assert_eq!(*calls.lock().unwrap(), 2);
+    }
+
+    #[test]
+    async fn test_circuit_breaker_probes() {
+        let calls = Arc::new(Mutex::new(0));
+        let failing = Arc::new(Mutex::new(true));
+        let breaker = CircuitBreaker::new(ConstantBuilder::default().with_delay(Duration::ZERO))
+            .with_failure_threshold(1);
+        let mut f = breaker.wrap(flaky(&calls, &failing));
+
+        assert_eq!(f().await, Err(TestError::Failed));
+        assert_eq!(breaker.state(), CircuitState::Open);
+
+        // Only one probe is let through at a time.
+        let probe = f();
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+        assert_eq!(f().await, Err(TestError::Open));
+
+        // A failed probe opens the breaker again.
+        assert_eq!(probe.await, Err(TestError::Failed));
+        assert_eq!(breaker.state(), CircuitState::Open);
+
+        // A successful probe closes the breaker.
+        *failing.lock().unwrap() = false;
+        assert_eq!(f().await, Ok(()));
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        assert_eq!(*calls.lock().unwrap(), 3);
+    }
+
+    #[test]
+    async fn test_circuit_breaker_dropped_probe() {
+        let calls = Arc::new(Mutex::new(0));
+        let failing = Arc::new(Mutex::new(true));
+        let breaker = CircuitBreaker::new(ConstantBuilder::default().with_delay(Duration::ZERO))
+            .with_failure_threshold(1);
+        let mut f = breaker.wrap(flaky(&calls, &failing));
+
+        assert_eq!(f().await, Err(TestError::Failed));
+
+        // Dropping a probe lets another call probe.
+        drop(f());
+        assert_eq!(breaker.state(), CircuitState::Open);
+        *failing.lock().unwrap() = false;
+        assert_eq!(f().await, Ok(()));
+        assert_eq!(breaker.state(), CircuitState::Closed);
+    }
+
+    #[test]
+    async fn test_circuit_breaker_with_retry() {
+        use crate::Retryable;
+
+        let calls = Arc::new(Mutex::new(0));
+        let failing = Arc::new(Mutex::new(true));
+        let breaker =
+            CircuitBreaker::new(ConstantBuilder::default().with_delay(Duration::from_secs(60)))
+                .with_failure_threshold(2);
+
+        let result = breaker
+            .wrap(flaky(&calls, &failing))
+            .retry(ConstantBuilder::default().with_max_times(5))
+            .sleep(|_| ready(()))
+            .when(|e| *e != TestError::Open)
+            .await;
+
+        assert_eq!(result, Err(TestError::Open));
+        assert_eq!(*calls.lock().unwrap(), 2);
+    }
+}
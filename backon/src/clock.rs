@@ -0,0 +1,101 @@
+use core::time::Duration;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Clock is used to measure the time elapsed during retrying.
+///
+/// The default [`SystemClock`] reads [`Instant::now`], which is not available on
+/// `wasm32-unknown-unknown` and can't be controlled in tests. Implement this trait to provide
+/// another source of time, like [`ManualClock`] for deterministic tests.
+pub trait Clock {
+    /// Return the current instant.
+    fn now(&self) -> Instant;
+}
+
+/// The default clock that reads [`Instant::now`].
+///
+/// Don't use it on `wasm32-unknown-unknown`, where [`Instant::now`] panics.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A clock that only moves forward when it's told to.
+///
+/// All clones share the same time, so a clone can be handed to the retry while the test, or a
+/// custom sleeper, advances the time.
+///
+/// # Examples
+///
+/// ```
+/// use core::time::Duration;
+///
+/// use backon::Clock;
+/// use backon::ManualClock;
+///
+/// let clock = ManualClock::new();
+/// let start = clock.now();
+///
+/// clock.advance(Duration::from_secs(5));
+/// assert_eq!(clock.now() - start, Duration::from_secs(5));
+/// ```
+#[derive(Debug, Clone)]
+pub struct ManualClock {
+    now: Arc<Mutex<Instant>>,
+}
+
+impl ManualClock {
+    /// Create a new manual clock starting from [`Instant::now`].
+    pub fn new() -> Self {
+        Self::starting_at(Instant::now())
+    }
+
+    /// Create a new manual clock starting from given instant.
+    pub fn starting_at(start: Instant) -> Self {
+        ManualClock {
+            now: Arc::new(Mutex::new(start)),
+        }
+    }
+
+    /// Move the clock forward by given duration.
+    pub fn advance(&self, dur: Duration) {
+        let mut now = self.now.lock().unwrap_or_else(|err| err.into_inner());
+        *now += dur;
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        *self.now.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(target_arch = "wasm32"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manual_clock() {
+        let clock = ManualClock::new();
+        let other = clock.clone();
+        let start = clock.now();
+
+        assert_eq!(clock.now(), start);
+
+        other.advance(Duration::from_millis(10));
+        other.advance(Duration::from_millis(20));
+        assert_eq!(clock.now() - start, Duration::from_millis(30));
+    }
+}
@@ -0,0 +1,314 @@
+use core::time::Duration;
+
+/// RetryDecision is returned by the hook set by [`Retry::decide`][crate::Retry::decide].
+#[derive(Debug)]
+pub enum RetryDecision<E> {
+    /// Retry the operation.
+    ///
+    /// The error is handed back so it can be passed to the notify hooks, and returned if the
+    /// backoff is exhausted. It may be replaced by another error of the same type.
+    Retry {
+        /// The error of the failed attempt.
+        error: E,
+        /// The delay before the next attempt, or `None` to use the delay given by the backoff.
+        after: Option<Duration>,
+    },
+    /// Stop retrying and return the error.
+    Stop(E),
+}
+
+/// ErrorClass is the class of an error, returned by the hook set by
+/// [`Retry::classify`][crate::Retry::classify].
+///
+/// Classifying the errors once centralizes the error policy: the class decides whether to
+/// retry, and could pick the backoff by [`Retry::backoff_by_key`][crate::Retry::backoff_by_key]
+/// as well.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorClass {
+    /// The error is temporary and the operation may succeed if retried, like a timeout or a
+    /// dropped connection.
+    Transient,
+    /// The error will happen again no matter how many times the operation is retried, like an
+    /// invalid request.
+    Permanent,
+    /// The operation is rejected by a rate limit, and may succeed if retried later.
+    RateLimited,
+}
+
+impl ErrorClass {
+    /// Return whether the errors of this class should be retried.
+    ///
+    /// [`ErrorClass::Transient`] and [`ErrorClass::RateLimited`] are retried, and
+    /// [`ErrorClass::Permanent`] is not.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            ErrorClass::Transient | ErrorClass::RateLimited => true,
+            ErrorClass::Permanent => false,
+        }
+    }
+}
+
+/// ErrorMetadata is implemented by the errors that carry structured metadata.
+///
+/// Whether to retry a gRPC or HTTP call often depends on the metadata returned with the error,
+/// like the status code or the trailers, rather than on the message of the error. Implement it
+/// for such errors to decide by the metadata with [`Retry::when_meta`][crate::Retry::when_meta].
+pub trait ErrorMetadata {
+    /// The type of the metadata, like a status code.
+    type Meta;
+
+    /// Return the metadata carried by this error.
+    fn metadata(&self) -> &Self::Meta;
+}
+
+/// A stub trait for the hooks that decide whether to retry.
+///
+/// All `FnMut(&E) -> bool` implement it, so `when` and `decide` share the same slot.
+///
+/// `elapsed` is the time elapsed since the retry started.
+#[doc(hidden)]
+pub trait Decide<E> {
+    fn decide(&mut self, err: E, elapsed: Duration) -> RetryDecision<E>;
+}
+
+impl<E, F: FnMut(&E) -> bool> Decide<E> for F {
+    fn decide(&mut self, err: E, _: Duration) -> RetryDecision<E> {
+        if self(&err) {
+            RetryDecision::Retry {
+                error: err,
+                after: None,
+            }
+        } else {
+            RetryDecision::Stop(err)
+        }
+    }
+}
+
+/// A stub trait for the hooks in the slot of [`Decide`], telling what's left once the
+/// conditions for retrying are replaced.
+///
+/// `when` and friends replace the conditions set before, but the guards set on top of them,
+/// like `safe_to_retry`, are kept around the new conditions, so they hold in any call order.
+#[doc(hidden)]
+pub trait ReplaceDecide<E> {
+    type With<D>;
+
+    fn replace<D>(self, decide: D) -> Self::With<D>;
+}
+
+impl<E, F: FnMut(&E) -> bool> ReplaceDecide<E> for F {
+    type With<D> = D;
+
+    fn replace<D>(self, decide: D) -> D {
+        decide
+    }
+}
+
+/// Decide that takes the ownership of the error, generated by `decide`.
+#[doc(hidden)]
+#[derive(Clone)]
+pub struct DecideFn<F>(pub(crate) F);
+
+impl<E, F> ReplaceDecide<E> for DecideFn<F> {
+    type With<D> = D;
+
+    fn replace<D>(self, decide: D) -> D {
+        decide
+    }
+}
+
+impl<E, F: FnMut(E) -> RetryDecision<E>> Decide<E> for DecideFn<F> {
+    fn decide(&mut self, err: E, _: Duration) -> RetryDecision<E> {
+        (self.0)(err)
+    }
+}
+
+/// Decide that receives the time elapsed, generated by `when_elapsed`.
+#[doc(hidden)]
+#[derive(Clone)]
+pub struct WhenElapsed<F>(pub(crate) F);
+
+impl<E, F> ReplaceDecide<E> for WhenElapsed<F> {
+    type With<D> = D;
+
+    fn replace<D>(self, decide: D) -> D {
+        decide
+    }
+}
+
+impl<E, F: FnMut(&E, Duration) -> bool> Decide<E> for WhenElapsed<F> {
+    fn decide(&mut self, err: E, elapsed: Duration) -> RetryDecision<E> {
+        if (self.0)(&err, elapsed) {
+            RetryDecision::Retry {
+                error: err,
+                after: None,
+            }
+        } else {
+            RetryDecision::Stop(err)
+        }
+    }
+}
+
+/// Decide that receives the metadata of the error, generated by `when_meta`.
+#[doc(hidden)]
+#[derive(Clone)]
+pub struct WhenMeta<F>(pub(crate) F);
+
+impl<E, F> ReplaceDecide<E> for WhenMeta<F> {
+    type With<D> = D;
+
+    fn replace<D>(self, decide: D) -> D {
+        decide
+    }
+}
+
+impl<E: ErrorMetadata, F: FnMut(&E, &E::Meta) -> bool> Decide<E> for WhenMeta<F> {
+    fn decide(&mut self, err: E, _: Duration) -> RetryDecision<E> {
+        if (self.0)(&err, err.metadata()) {
+            RetryDecision::Retry {
+                error: err,
+                after: None,
+            }
+        } else {
+            RetryDecision::Stop(err)
+        }
+    }
+}
+
+/// Decide by the class of the error, generated by `classify`.
+#[doc(hidden)]
+#[derive(Clone)]
+pub struct Classify<F>(pub(crate) F);
+
+impl<E, F> ReplaceDecide<E> for Classify<F> {
+    type With<D> = D;
+
+    fn replace<D>(self, decide: D) -> D {
+        decide
+    }
+}
+
+impl<E, F: FnMut(&E) -> ErrorClass> Decide<E> for Classify<F> {
+    fn decide(&mut self, err: E, _: Duration) -> RetryDecision<E> {
+        if (self.0)(&err).is_retryable() {
+            RetryDecision::Retry {
+                error: err,
+                after: None,
+            }
+        } else {
+            RetryDecision::Stop(err)
+        }
+    }
+}
+
+/// Decide that gives up once the same error repeats on top of another decide, generated by
+/// `give_up_on_repeat`.
+#[doc(hidden)]
+pub struct GiveUpOnRepeat<D, E, F> {
+    pub(crate) inner: D,
+    pub(crate) same: F,
+    pub(crate) times: usize,
+    pub(crate) previous: Option<E>,
+    pub(crate) repeats: usize,
+}
+
+impl<D: Clone, E, F: Clone> Clone for GiveUpOnRepeat<D, E, F> {
+    fn clone(&self) -> Self {
+        // The errors seen by a retry are not carried over to its clones.
+        GiveUpOnRepeat {
+            inner: self.inner.clone(),
+            same: self.same.clone(),
+            times: self.times,
+            previous: None,
+            repeats: 0,
+        }
+    }
+}
+
+impl<E, D: ReplaceDecide<E>, F> ReplaceDecide<E> for GiveUpOnRepeat<D, E, F> {
+    type With<N> = GiveUpOnRepeat<D::With<N>, E, F>;
+
+    fn replace<N>(self, decide: N) -> Self::With<N> {
+        GiveUpOnRepeat {
+            inner: self.inner.replace(decide),
+            same: self.same,
+            times: self.times,
+            previous: self.previous,
+            repeats: self.repeats,
+        }
+    }
+}
+
+impl<E: Clone, D: Decide<E>, F: FnMut(&E, &E) -> bool> Decide<E> for GiveUpOnRepeat<D, E, F> {
+    fn decide(&mut self, err: E, elapsed: Duration) -> RetryDecision<E> {
+        self.repeats = match &self.previous {
+            Some(previous) if (self.same)(previous, &err) => self.repeats + 1,
+            _ => 1,
+        };
+        self.previous = Some(err.clone());
+
+        if self.repeats >= self.times {
+            return RetryDecision::Stop(err);
+        }
+        self.inner.decide(err, elapsed)
+    }
+}
+
+/// Decide that only retries the errors raised before the operation took effect on top of
+/// another decide, generated by `safe_to_retry`.
+#[doc(hidden)]
+#[derive(Clone)]
+pub struct SafeToRetry<D, F> {
+    pub(crate) inner: D,
+    pub(crate) safe: F,
+}
+
+impl<E, D: ReplaceDecide<E>, F> ReplaceDecide<E> for SafeToRetry<D, F> {
+    type With<N> = SafeToRetry<D::With<N>, F>;
+
+    fn replace<N>(self, decide: N) -> Self::With<N> {
+        SafeToRetry {
+            inner: self.inner.replace(decide),
+            safe: self.safe,
+        }
+    }
+}
+
+impl<E, D: Decide<E>, F: FnMut(&E) -> bool> Decide<E> for SafeToRetry<D, F> {
+    fn decide(&mut self, err: E, elapsed: Duration) -> RetryDecision<E> {
+        if !(self.safe)(&err) {
+            return RetryDecision::Stop(err);
+        }
+        self.inner.decide(err, elapsed)
+    }
+}
+
+/// Decide that observes the errors decided not to retry on top of another decide, generated by
+/// `on_non_retryable`.
+#[doc(hidden)]
+#[derive(Clone)]
+pub struct OnNonRetryable<D, F> {
+    pub(crate) inner: D,
+    pub(crate) f: F,
+}
+
+impl<E, D: ReplaceDecide<E>, F> ReplaceDecide<E> for OnNonRetryable<D, F> {
+    type With<N> = OnNonRetryable<D::With<N>, F>;
+
+    fn replace<N>(self, decide: N) -> Self::With<N> {
+        OnNonRetryable {
+            inner: self.inner.replace(decide),
+            f: self.f,
+        }
+    }
+}
+
+impl<E, D: Decide<E>, F: FnMut(&E)> Decide<E> for OnNonRetryable<D, F> {
+    fn decide(&mut self, err: E, elapsed: Duration) -> RetryDecision<E> {
+        let decision = self.inner.decide(err, elapsed);
+        if let RetryDecision::Stop(err) = &decision {
+            (self.f)(err);
+        }
+        decision
+    }
+}
@@ -9,6 +9,9 @@ pub mod closure {}
 #[doc = include_str!("inside_mut_self.md")]
 pub mod inside_mut_self {}
 
+#[doc = include_str!("nested.md")]
+pub mod nested {}
+
 #[doc = include_str!("sqlx.md")]
 pub mod sqlx {}
 
@@ -0,0 +1,183 @@
+use core::time::Duration;
+
+use crate::backoff::BackoffBuilder;
+use crate::Backoff;
+
+/// Step is the next thing to do for a [`RetryDriver`], returned by [`RetryDriver::poll_step`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Step<T, E> {
+    /// Make an attempt, and feed its result back by [`RetryDriver::feed`].
+    Call,
+    /// Sleep for the duration, then poll the next step.
+    SleepFor(Duration),
+    /// The retry is done with the result.
+    Done(Result<T, E>),
+}
+
+#[derive(Debug)]
+enum DriverState<T, E> {
+    Idle,
+    Calling,
+    /// Sleep for the delay, which is `None` once it has been returned.
+    Sleeping(Option<Duration>),
+    Done(Option<Result<T, E>>),
+}
+
+/// RetryDriver drives a retry step by step, without any future or runtime.
+///
+/// It's a lower-level API beneath [`Retry`](crate::Retry) for external event loops that want
+/// to ask the retry what to do next. The caller performs the calls and the sleeps itself:
+///
+/// - [`Step::Call`]: make an attempt, and feed its result back by [`RetryDriver::feed`].
+/// - [`Step::SleepFor`]: sleep for the duration, then poll the next step.
+/// - [`Step::Done`]: the retry is done, either succeeded or gave up.
+///
+/// # Examples
+///
+/// ```
+/// use backon::ExponentialBuilder;
+/// use backon::RetryDriver;
+/// use backon::Step;
+///
+/// fn fetch() -> Result<String, String> {
+///     Ok("hello, world!".to_string())
+/// }
+///
+/// let mut driver = RetryDriver::new(ExponentialBuilder::default());
+/// let content = loop {
+///     match driver.poll_step() {
+///         Step::Call => driver.feed(fetch()),
+///         Step::SleepFor(dur) => std::thread::sleep(dur),
+///         Step::Done(result) => break result,
+///     }
+/// };
+/// assert_eq!(content.unwrap(), "hello, world!");
+/// ```
+#[derive(Debug)]
+pub struct RetryDriver<B: Backoff, T, E, RF = fn(&E) -> bool> {
+    backoff: B,
+    retryable: RF,
+    attempts: usize,
+    state: DriverState<T, E>,
+}
+
+impl<B: Backoff, T, E> RetryDriver<B, T, E> {
+    /// Create a new driver that retries with a backoff built by `builder`.
+    pub fn new<BB: BackoffBuilder<Backoff = B>>(builder: BB) -> Self {
+        RetryDriver {
+            backoff: builder.build(),
+            retryable: |_: &E| true,
+            attempts: 0,
+            state: DriverState::Idle,
+        }
+    }
+}
+
+impl<B: Backoff, T, E, RF: FnMut(&E) -> bool> RetryDriver<B, T, E, RF> {
+    /// Set the conditions for retrying.
+    ///
+    /// If not specified, all errors are considered retryable.
+    pub fn when<RN: FnMut(&E) -> bool>(self, retryable: RN) -> RetryDriver<B, T, E, RN> {
+        RetryDriver {
+            backoff: self.backoff,
+            retryable,
+            attempts: self.attempts,
+            state: self.state,
+        }
+    }
+
+    /// Return the number of attempts that have been started.
+    pub fn attempts(&self) -> usize {
+        self.attempts
+    }
+
+    /// Return the next step to take.
+    ///
+    /// [`Step::Call`] is returned again until the result of the attempt is fed.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if it's called again after returning [`Step::Done`].
+    pub fn poll_step(&mut self) -> Step<T, E> {
+        match &mut self.state {
+            DriverState::Sleeping(dur @ Some(_)) => Step::SleepFor(dur.take().unwrap()),
+            DriverState::Idle | DriverState::Sleeping(None) => {
+                self.attempts += 1;
+                self.state = DriverState::Calling;
+                Step::Call
+            }
+            DriverState::Calling => Step::Call,
+            DriverState::Done(result) => {
+                Step::Done(result.take().expect("RetryDriver polled after it's done"))
+            }
+        }
+    }
+
+    /// Feed the result of the attempt made for [`Step::Call`].
+    ///
+    /// The result is ignored if no attempt is expected.
+    pub fn feed(&mut self, result: Result<T, E>) {
+        if !matches!(self.state, DriverState::Calling) {
+            return;
+        }
+
+        self.state = match result {
+            Err(err) if (self.retryable)(&err) => match self.backoff.next() {
+                Some(dur) => DriverState::Sleeping(Some(dur)),
+                None => DriverState::Done(Some(Err(err))),
+            },
+            result => DriverState::Done(Some(result)),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test as test;
+
+    use super::*;
+    use crate::ConstantBuilder;
+
+    #[test]
+    fn test_retry_driver() {
+        let mut driver = RetryDriver::new(ConstantBuilder::default().with_max_times(2));
+
+        assert_eq!(driver.poll_step(), Step::<(), _>::Call);
+        // Call is returned again until the result is fed.
+        assert_eq!(driver.poll_step(), Step::Call);
+        driver.feed(Err("retryable"));
+        assert_eq!(driver.poll_step(), Step::SleepFor(Duration::from_secs(1)));
+        assert_eq!(driver.poll_step(), Step::Call);
+        driver.feed(Err("retryable"));
+        assert_eq!(driver.poll_step(), Step::SleepFor(Duration::from_secs(1)));
+        assert_eq!(driver.poll_step(), Step::Call);
+        driver.feed(Err("retryable"));
+        assert_eq!(driver.poll_step(), Step::Done(Err("retryable")));
+        assert_eq!(driver.attempts(), 3);
+    }
+
+    #[test]
+    fn test_retry_driver_when() {
+        let mut driver = RetryDriver::new(ConstantBuilder::default()).when(|e| *e == "retryable");
+
+        assert_eq!(driver.poll_step(), Step::<(), _>::Call);
+        driver.feed(Err("retryable"));
+        assert_eq!(driver.poll_step(), Step::SleepFor(Duration::from_secs(1)));
+        assert_eq!(driver.poll_step(), Step::Call);
+        driver.feed(Err("not retryable"));
+        assert_eq!(driver.poll_step(), Step::Done(Err("not retryable")));
+    }
+
+    #[test]
+    fn test_retry_driver_success() {
+        let mut driver = RetryDriver::new(ConstantBuilder::default());
+
+        assert_eq!(driver.poll_step(), Step::<_, &str>::Call);
+        driver.feed(Ok(42));
+        assert_eq!(driver.poll_step(), Step::Done(Ok(42)));
+        assert_eq!(driver.attempts(), 1);
+    }
+}
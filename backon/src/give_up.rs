@@ -0,0 +1,117 @@
+use core::fmt;
+
+/// GiveUpReason is the reason why a retry gave up, carried by [`RetryError`].
+///
+/// It's a machine-readable code for the terminal conditions of a retry, so the callers could
+/// branch on it without matching the error message.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GiveUpReason {
+    /// The error is retryable, but no more retry is allowed: the backoff is exhausted, the hook
+    /// set by [`Retry::adjust`](crate::Retry::adjust) returned `None`, or the max times is used
+    /// up.
+    Exhausted,
+    /// The error is retryable, but no token is left in the retry budget set by
+    /// [`Retry::budget`](crate::Retry::budget).
+    Budget,
+    /// The error is retryable, but the global retry limit set by
+    /// [`Retry::global_max_retries`](crate::Retry::global_max_retries) is reached.
+    GlobalLimit,
+    /// The whole retry didn't finish within the timeout set by
+    /// [`Retry::timeout`](crate::Retry::timeout).
+    Deadline,
+    /// The error is not retryable according to [`Retry::when`](crate::Retry::when) or the
+    /// other conditions for retrying.
+    NonRetryable,
+    /// Retrying is stopped on purpose, like by the hook set by
+    /// [`Retry::on_retry_ctl`](crate::Retry::on_retry_ctl), or by a sleeper that failed.
+    Aborted,
+    /// Retrying is disabled by the kill switch set by
+    /// [`Retry::kill_switch`](crate::Retry::kill_switch).
+    KillSwitch,
+}
+
+impl GiveUpReason {
+    /// Return the reason as a stable snake case code, like `"non_retryable"`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GiveUpReason::Exhausted => "exhausted",
+            GiveUpReason::Budget => "budget",
+            GiveUpReason::GlobalLimit => "global_limit",
+            GiveUpReason::Deadline => "deadline",
+            GiveUpReason::NonRetryable => "non_retryable",
+            GiveUpReason::Aborted => "aborted",
+            GiveUpReason::KillSwitch => "kill_switch",
+        }
+    }
+}
+
+impl fmt::Display for GiveUpReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// RetryError is the error of a retry that gave up, along with the reason why.
+///
+/// It's returned by the retries that opt in with [`Retry::with_reason`](crate::Retry::with_reason).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryError<E> {
+    error: E,
+    reason: GiveUpReason,
+    attempts: usize,
+}
+
+impl<E> RetryError<E> {
+    pub(crate) fn new(error: E, reason: GiveUpReason, attempts: usize) -> Self {
+        RetryError {
+            error,
+            reason,
+            attempts,
+        }
+    }
+
+    /// The last error returned by the retry.
+    pub fn error(&self) -> &E {
+        &self.error
+    }
+
+    /// The reason why the retry gave up.
+    pub fn reason(&self) -> GiveUpReason {
+        self.reason
+    }
+
+    /// The number of attempts that have been made.
+    pub fn attempts(&self) -> usize {
+        self.attempts
+    }
+
+    /// Consume the error, returning the last error returned by the retry.
+    pub fn into_inner(self) -> E {
+        self.error
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for RetryError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "retry gave up ({}) after {} attempts: {}",
+            self.reason, self.attempts, self.error
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: std::error::Error + 'static> std::error::Error for RetryError<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// A stub trait for the retry futures that know why they gave up.
+#[doc(hidden)]
+pub trait GiveUp {
+    /// The reason why the retry gave up, and the number of attempts made.
+    fn give_up(&self) -> (Option<GiveUpReason>, usize);
+}
@@ -0,0 +1,347 @@
+use core::future::Future;
+use core::pin::Pin;
+use core::task::Context;
+use core::task::Poll;
+use std::boxed::Box;
+use std::vec::Vec;
+
+use crate::backoff::BackoffBuilder;
+use crate::sleep::MaybeSleeper;
+use crate::Backoff;
+use crate::DefaultSleeper;
+use crate::Sleeper;
+
+/// Hedgeable will add hedging support for functions that produce futures with results.
+///
+/// Hedging is different from retrying: instead of waiting for an attempt to fail, a backup
+/// attempt is started if the previous attempts haven't finished after a delay, and the first
+/// success wins.
+///
+/// All types that implement `FnMut() -> impl Future<Output = Result<T, E>>` will be able to
+/// use `hedge`. The function is called once for every attempt, so it must be able to start
+/// the same operation again.
+pub trait Hedgeable<
+    B: BackoffBuilder,
+    T,
+    E,
+    Fut: Future<Output = Result<T, E>>,
+    FutureFn: FnMut() -> Fut,
+>
+{
+    /// Generate a new hedge, the delays of the backoff are used as the delays between
+    /// starting attempts.
+    fn hedge(self, builder: B) -> Hedge<B::Backoff, T, E, Fut, FutureFn>;
+}
+
+impl<B, T, E, Fut, FutureFn> Hedgeable<B, T, E, Fut, FutureFn> for FutureFn
+where
+    B: BackoffBuilder,
+    Fut: Future<Output = Result<T, E>>,
+    FutureFn: FnMut() -> Fut,
+{
+    fn hedge(self, builder: B) -> Hedge<B::Backoff, T, E, Fut, FutureFn> {
+        Hedge::new(self, builder.build())
+    }
+}
+
+/// Struct generated by [`Hedgeable`].
+///
+/// The first attempt is started immediately. Every time a delay from the backoff elapses,
+/// another attempt is started concurrently with the ones still in flight, until the backoff
+/// stops. The first attempt that succeeds wins, and all other attempts in flight are dropped,
+/// which cancels them.
+///
+/// A failed attempt doesn't start a new attempt by itself, the hedge keeps waiting for the
+/// other attempts and the next delay. The error of the last attempt is returned once all
+/// attempts have failed and the backoff has stopped. Combine it with [`Retry`](crate::Retry)
+/// to retry failures as well.
+///
+/// Every hedged attempt is extra load on the remote service, and all attempts in flight are
+/// polled whenever the hedge is woken. Keep the number of attempts small (`max_times`) and the
+/// delays close to the tail latency you want to cut, like the p95 latency.
+///
+/// # Examples
+///
+/// ```no_run
+/// use core::time::Duration;
+///
+/// use anyhow::Result;
+/// use backon::ConstantBuilder;
+/// use backon::Hedgeable;
+///
+/// async fn fetch() -> Result<String> {
+///     Ok(reqwest::get("https://www.rust-lang.org")
+///         .await?
+///         .text()
+///         .await?)
+/// }
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() -> Result<()> {
+///     // Start a backup request if the first one takes longer than 100ms,
+///     // and at most 2 backup requests in total.
+///     let content = fetch
+///         .hedge(
+///             ConstantBuilder::default()
+///                 .with_delay(Duration::from_millis(100))
+///                 .with_max_times(2),
+///         )
+///         .await?;
+///     println!("fetch succeeded: {}", content);
+///
+///     Ok(())
+/// }
+/// ```
+pub struct Hedge<
+    B: Backoff,
+    T,
+    E,
+    Fut: Future<Output = Result<T, E>>,
+    FutureFn: FnMut() -> Fut,
+    SF: MaybeSleeper = DefaultSleeper,
+> {
+    backoff: B,
+    future_fn: FutureFn,
+    sleep_fn: SF,
+
+    started: bool,
+    attempts: Vec<Pin<Box<Fut>>>,
+    sleep: Option<SF::Sleep>,
+}
+
+impl<B, T, E, Fut, FutureFn> Hedge<B, T, E, Fut, FutureFn>
+where
+    B: Backoff,
+    Fut: Future<Output = Result<T, E>>,
+    FutureFn: FnMut() -> Fut,
+{
+    /// Initiate a new hedge.
+    fn new(future_fn: FutureFn, backoff: B) -> Self {
+        Hedge {
+            backoff,
+            future_fn,
+            sleep_fn: DefaultSleeper::default(),
+
+            started: false,
+            attempts: Vec::new(),
+            sleep: None,
+        }
+    }
+}
+
+impl<B, T, E, Fut, FutureFn, SF> Hedge<B, T, E, Fut, FutureFn, SF>
+where
+    B: Backoff,
+    Fut: Future<Output = Result<T, E>>,
+    FutureFn: FnMut() -> Fut,
+    SF: MaybeSleeper,
+{
+    /// Set the sleeper for waiting between attempts.
+    ///
+    /// The sleeper should implement the [`Sleeper`] trait. The simplest way is to use a closure that returns a `Future<Output=()>`.
+    ///
+    /// If not specified, we use the [`DefaultSleeper`].
+    pub fn sleep<SN: Sleeper>(self, sleep_fn: SN) -> Hedge<B, T, E, Fut, FutureFn, SN> {
+        Hedge {
+            backoff: self.backoff,
+            future_fn: self.future_fn,
+            sleep_fn,
+
+            started: self.started,
+            attempts: self.attempts,
+            sleep: None,
+        }
+    }
+}
+
+impl<B, T, E, Fut, FutureFn, SF> Future for Hedge<B, T, E, Fut, FutureFn, SF>
+where
+    B: Backoff,
+    Fut: Future<Output = Result<T, E>>,
+    FutureFn: FnMut() -> Fut,
+    SF: Sleeper,
+{
+    type Output = Result<T, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // Safety: This is safe because we don't move the `Hedge` struct itself,
+        // only its internal state.
+        //
+        // We do the exactly same thing like `pin_project` but without depending on it directly.
+        let this = unsafe { self.get_unchecked_mut() };
+
+        if !this.started {
+            this.started = true;
+            this.start_attempt();
+        }
+
+        // Start a new attempt every time the delay elapses.
+        while let Some(sl) = &mut this.sleep {
+            // Safety: This is safe because we don't move the `Hedge` struct and this fut,
+            // only its internal state.
+            //
+            // We do the exactly same thing like `pin_project` but without depending on it directly.
+            let sl = unsafe { Pin::new_unchecked(sl) };
+            if sl.poll(cx).is_pending() {
+                break;
+            }
+            this.start_attempt();
+        }
+
+        let mut idx = 0;
+        while idx < this.attempts.len() {
+            match this.attempts[idx].as_mut().poll(cx) {
+                Poll::Pending => idx += 1,
+                Poll::Ready(Ok(v)) => {
+                    // Drop all other attempts to cancel them.
+                    this.attempts.clear();
+                    this.sleep = None;
+                    return Poll::Ready(Ok(v));
+                }
+                Poll::Ready(Err(err)) => {
+                    drop(this.attempts.remove(idx));
+                    if this.attempts.is_empty() && this.sleep.is_none() {
+                        return Poll::Ready(Err(err));
+                    }
+                }
+            }
+        }
+
+        Poll::Pending
+    }
+}
+
+impl<B, T, E, Fut, FutureFn, SF> Hedge<B, T, E, Fut, FutureFn, SF>
+where
+    B: Backoff,
+    Fut: Future<Output = Result<T, E>>,
+    FutureFn: FnMut() -> Fut,
+    SF: Sleeper,
+{
+    /// Start a new attempt and schedule the next one.
+    fn start_attempt(&mut self) {
+        self.attempts.push(Box::pin((self.future_fn)()));
+        self.sleep = self.backoff.next().map(|dur| self.sleep_fn.sleep(dur));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::future::pending;
+    use core::future::ready;
+    use core::time::Duration;
+    use std::sync::Arc;
+    use std::sync::Mutex;
+
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test as test;
+
+    #[cfg(not(target_arch = "wasm32"))]
+    use tokio::test;
+
+    use super::*;
+    use crate::ConstantBuilder;
+
+    /// Decrease the counter on drop to track the attempts in flight.
+    struct InFlight(Arc<Mutex<usize>>);
+
+    impl InFlight {
+        fn new(counter: &Arc<Mutex<usize>>) -> Self {
+            *counter.lock().unwrap() += 1;
+            InFlight(counter.clone())
+        }
+    }
+
+    impl Drop for InFlight {
+        fn drop(&mut self) {
+            *self.0.lock().unwrap() -= 1;
+        }
+    }
+
+    #[test]
+    async fn test_hedge_takes_first_success() {
+        let started = Arc::new(Mutex::new(0));
+        let in_flight = Arc::new(Mutex::new(0));
+
+        let result = {
+            let started = started.clone();
+            let in_flight = in_flight.clone();
+            move || {
+                let attempt = {
+                    let mut started = started.lock().unwrap();
+                    *started += 1;
+                    *started
+                };
+                let guard = InFlight::new(&in_flight);
+                async move {
+                    let _guard = guard;
+                    // The first attempt never finishes.
+                    if attempt == 1 {
+                        pending::<()>().await;
+                    }
+                    Ok::<_, ()>(attempt)
+                }
+            }
+        }
+        .hedge(ConstantBuilder::default().with_max_times(3))
+        .sleep({
+            let sleeps = Arc::new(Mutex::new(0));
+            move |_| {
+                let sleeps = sleeps.clone();
+                async move {
+                    // Only the first delay elapses.
+                    *sleeps.lock().unwrap() += 1;
+                    if *sleeps.lock().unwrap() > 1 {
+                        pending::<()>().await;
+                    }
+                }
+            }
+        })
+        .await;
+
+        assert_eq!(result, Ok(2));
+        assert_eq!(*started.lock().unwrap(), 2);
+        // The first attempt has been cancelled.
+        assert_eq!(*in_flight.lock().unwrap(), 0);
+    }
+
+    #[test]
+    async fn test_hedge_no_backup_if_fast() {
+        let started = Arc::new(Mutex::new(0));
+
+        let result = {
+            let started = started.clone();
+            move || {
+                *started.lock().unwrap() += 1;
+                ready(Ok::<_, ()>("hello"))
+            }
+        }
+        .hedge(ConstantBuilder::default().with_delay(Duration::from_millis(100)))
+        .sleep(|_| pending())
+        .await;
+
+        assert_eq!(result, Ok("hello"));
+        assert_eq!(*started.lock().unwrap(), 1);
+    }
+
+    #[test]
+    async fn test_hedge_all_failed() {
+        let started = Arc::new(Mutex::new(0));
+
+        let result = {
+            let started = started.clone();
+            move || {
+                let mut started = started.lock().unwrap();
+                *started += 1;
+                ready(Err::<(), _>(*started))
+            }
+        }
+        .hedge(ConstantBuilder::default().with_max_times(2))
+        .sleep(|_| ready(()))
+        .await;
+
+        // The error of the last attempt is returned.
+        assert_eq!(result, Err(3));
+        assert_eq!(*started.lock().unwrap(), 3);
+    }
+}
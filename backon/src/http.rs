@@ -0,0 +1,259 @@
+//! Helpers for retrying HTTP requests.
+
+use core::time::Duration;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+/// Parse the value of a `Retry-After` header into the delay to wait since `now`.
+///
+/// Both forms defined by [RFC 9110](https://www.rfc-editor.org/rfc/rfc9110#field.retry-after)
+/// are supported:
+///
+/// - delta-seconds, like `120`.
+/// - HTTP-date, like `Sun, 06 Nov 1994 08:49:37 GMT`. The obsolete RFC 850 and asctime
+///   formats are accepted as well.
+///
+/// Returns `None` if the value is malformed. A date in the past gives a zero delay.
+///
+/// This is useful together with [`Retry::adjust`](crate::Retry::adjust) to respect the delay
+/// requested by the server.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use std::time::SystemTime;
+///
+/// use backon::http::parse_retry_after;
+///
+/// let now = SystemTime::now();
+/// assert_eq!(parse_retry_after("120", now), Some(Duration::from_secs(120)));
+/// assert_eq!(
+///     parse_retry_after("Sun, 06 Nov 1994 08:49:37 GMT", now),
+///     Some(Duration::ZERO)
+/// );
+/// assert_eq!(parse_retry_after("soon", now), None);
+/// ```
+pub fn parse_retry_after(value: &str, now: SystemTime) -> Option<Duration> {
+    let value = value.trim();
+
+    if !value.is_empty() && value.bytes().all(|b| b.is_ascii_digit()) {
+        return value.parse().ok().map(Duration::from_secs);
+    }
+
+    let secs = parse_http_date(value)?;
+    // Dates before the epoch are in the past for sure.
+    let Ok(secs) = u64::try_from(secs) else {
+        return Some(Duration::ZERO);
+    };
+    let date = UNIX_EPOCH.checked_add(Duration::from_secs(secs))?;
+    Some(date.duration_since(now).unwrap_or(Duration::ZERO))
+}
+
+/// Parse an HTTP-date into the seconds since the unix epoch.
+fn parse_http_date(value: &str) -> Option<i64> {
+    let parts: std::vec::Vec<&str> = value.split_whitespace().collect();
+
+    let (day, month, year, time) = match parts.as_slice() {
+        // IMF-fixdate: `Sun, 06 Nov 1994 08:49:37 GMT`
+        [weekday, day, month, year, time, "GMT"] => {
+            parse_weekday(weekday.strip_suffix(',')?)?;
+            (*day, *month, parse_number(year, 4)?, *time)
+        }
+        // RFC 850: `Sunday, 06-Nov-94 08:49:37 GMT`
+        [weekday, date, time, "GMT"] => {
+            parse_weekday(weekday.strip_suffix(',')?)?;
+            let mut date = date.split('-');
+            let (day, month, year) = (date.next()?, date.next()?, date.next()?);
+            if date.next().is_some() {
+                return None;
+            }
+            // Two digit years are interpreted within 1970..2070.
+            let year = parse_number(year, 2)?;
+            let year = if year < 70 { 2000 + year } else { 1900 + year };
+            (day, month, year, *time)
+        }
+        // asctime: `Sun Nov  6 08:49:37 1994`
+        [weekday, month, day, time, year] => {
+            parse_weekday(weekday)?;
+            (*day, *month, parse_number(year, 4)?, *time)
+        }
+        _ => return None,
+    };
+
+    let day: u32 = match day.len() {
+        1 | 2 if is_digits(day) => day.parse().ok()?,
+        _ => return None,
+    };
+    let month = parse_month(month)?;
+    if day == 0 || day > days_in_month(year, month) {
+        return None;
+    }
+
+    let mut time = time.split(':');
+    let hour = parse_number(time.next()?, 2)?;
+    let minute = parse_number(time.next()?, 2)?;
+    let second = parse_number(time.next()?, 2)?;
+    // Leap seconds are allowed by the grammar.
+    if time.next().is_some() || hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+
+    let days = days_from_civil(year, month, day);
+    Some(days * 86400 + hour * 3600 + minute * 60 + second)
+}
+
+/// Parse a number with exactly `digits` ASCII digits.
+fn parse_number(value: &str, digits: usize) -> Option<i64> {
+    if value.len() != digits || !is_digits(value) {
+        return None;
+    }
+    value.parse().ok()
+}
+
+fn is_digits(value: &str) -> bool {
+    value.bytes().all(|b| b.is_ascii_digit())
+}
+
+fn parse_weekday(value: &str) -> Option<()> {
+    const WEEKDAYS: [(&str, &str); 7] = [
+        ("Mon", "Monday"),
+        ("Tue", "Tuesday"),
+        ("Wed", "Wednesday"),
+        ("Thu", "Thursday"),
+        ("Fri", "Friday"),
+        ("Sat", "Saturday"),
+        ("Sun", "Sunday"),
+    ];
+
+    WEEKDAYS
+        .iter()
+        .any(|(short, long)| value == *short || value == *long)
+        .then_some(())
+}
+
+fn parse_month(value: &str) -> Option<u32> {
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+
+    MONTHS
+        .iter()
+        .position(|m| *m == value)
+        .map(|idx| idx as u32 + 1)
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Count the days since the unix epoch of a date in the proleptic Gregorian calendar.
+///
+/// See <https://howardhinnant.github.io/date_algorithms.html#days_from_civil>.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let month = month as i64;
+    let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test as test;
+
+    use super::*;
+
+    /// `Sun, 06 Nov 1994 08:49:37 GMT`
+    const DATE: u64 = 784111777;
+
+    fn at(secs: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn test_parse_delta_seconds() {
+        let now = at(DATE);
+
+        assert_eq!(parse_retry_after("0", now), Some(Duration::ZERO));
+        assert_eq!(
+            parse_retry_after(" 120 ", now),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(parse_retry_after("-5", now), None);
+        assert_eq!(parse_retry_after("+5", now), None);
+        assert_eq!(parse_retry_after("1.5", now), None);
+        assert_eq!(parse_retry_after("", now), None);
+    }
+
+    #[test]
+    fn test_parse_http_date() {
+        let now = at(DATE - 30);
+        let expected = Some(Duration::from_secs(30));
+
+        assert_eq!(
+            parse_retry_after("Sun, 06 Nov 1994 08:49:37 GMT", now),
+            expected
+        );
+        assert_eq!(
+            parse_retry_after("Sunday, 06-Nov-94 08:49:37 GMT", now),
+            expected
+        );
+        assert_eq!(parse_retry_after("Sun Nov  6 08:49:37 1994", now), expected);
+    }
+
+    #[test]
+    fn test_parse_http_date_in_the_past() {
+        assert_eq!(
+            parse_retry_after("Sun, 06 Nov 1994 08:49:37 GMT", at(DATE + 30)),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(
+            parse_retry_after("Thu, 01 Jan 1960 00:00:00 GMT", at(DATE)),
+            Some(Duration::ZERO)
+        );
+    }
+
+    #[test]
+    fn test_parse_http_date_leap_year() {
+        // `Thu, 29 Feb 2024 00:00:00 GMT`
+        let now = at(1709164800);
+
+        assert_eq!(
+            parse_retry_after("Thu, 29 Feb 2024 00:00:10 GMT", now),
+            Some(Duration::from_secs(10))
+        );
+        assert_eq!(
+            parse_retry_after("Thu, 29 Feb 2023 00:00:10 GMT", now),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_malformed_http_date() {
+        let now = at(DATE);
+
+        for value in [
+            "Sun, 06 Nov 1994 08:49:37",
+            "Sun, 06 Nov 1994 08:49:37 UTC",
+            "Sun, 32 Nov 1994 08:49:37 GMT",
+            "Sun, 06 Foo 1994 08:49:37 GMT",
+            "Sun, 06 Nov 94 08:49:37 GMT",
+            "Sun, 06 Nov 1994 24:00:00 GMT",
+            "Sun, 06 Nov 1994 08:49 GMT",
+            "Foo, 06 Nov 1994 08:49:37 GMT",
+            "Sunday, 06-Nov-1994 08:49:37 GMT",
+            "Sun Nov 006 08:49:37 1994",
+        ] {
+            assert_eq!(parse_retry_after(value, now), None, "value: {value}");
+        }
+    }
+}
@@ -28,6 +28,15 @@
 //! - [`ConstantBuilder`]: backoff with a constant delay, limited to a specific number of attempts.
 //! - [`ExponentialBuilder`]: backoff with an exponential delay, also supports jitter.
 //! - [`FibonacciBuilder`]: backoff with a fibonacci delay, also supports jitter.
+//! - [`EqualJitterBuilder`]: backoff with an exponential delay and the "equal jitter" algorithm.
+//! - [`NoBackoff`]: backoff that never retries, the operation is attempted exactly once.
+//! - [`FnBackoff`]: backoff that asks a function for every delay, which could observe external state.
+//! - [`ChannelBackoff`]: backoff that reads delays pushed to a channel at runtime.
+//! - [`ScheduleBackoff`]: backoff that retries at the instants of a schedule, like fixed clock boundaries.
+//! - [`AdaptiveBuilder`]: backoff that remembers the failures of the previous retries, and heals after successes.
+//!
+//! Backoffs can be further composed with the combinators provided by [`BackoffBuilderExt`],
+//! and shared by concurrent retries with [`SharedBackoff`].
 //!
 //! # Sleep
 //!
@@ -37,7 +46,7 @@
 //!
 //! ## Default Sleeper
 //!
-//! Currently, BackON has 3 built-in Sleeper implementations for different
+//! Currently, BackON has 4 built-in Sleeper implementations for different
 //! environments, they are gated under their own features, which are enabled
 //! by default:
 //!
@@ -45,8 +54,14 @@
 //! |---------------------|--------------------|-------------|---------------|
 //! | [`TokioSleeper`]    | tokio-sleep        | non-wasm32  |  Yes          |
 //! | [`GlooTimersSleep`] | gloo-timers-sleep  |   wasm32    |  Yes          |
+//! | [`ThreadSleeper`]   | std                | non-wasm32  |  Yes          |
 //! | [`StdSleeper`]      | std-blocking-sleep |    std      |  No           |
 //!
+//! [`ThreadSleeper`] spawns a thread for every sleep, so it's never used as the default,
+//! pass it to `.sleep()` explicitly if `tokio-sleep` is disabled.
+//!
+//! To choose a sleeper at runtime, wrap it in a [`BoxSleeper`].
+//!
 //! ## Custom Sleeper
 //!
 //! If you do not want to use the built-in Sleeper, you CAN provide a custom
@@ -77,7 +92,24 @@
 //! will fallback to the empty sleeper, in which case, a compile-time error that
 //! `PleaseEnableAFeatureOrProvideACustomSleeper needs to implement Sleeper or
 //! BlockingSleeper` will be raised to remind you to choose or bring a real Sleeper
-//! implementation.
+//! implementation. Enable `tokio-sleep` on non-wasm32 targets, enable
+//! `gloo-timers-sleep` on wasm32, or call `.sleep()` to fix it.
+//!
+//! # no_std
+//!
+//! BackON is `no_std` and only depends on `core` with default features disabled, so the
+//! backoffs and the retries work on embedded targets with your own executor and [`Sleeper`].
+//! The `futures`, `rand-core` and `tracing` features are `no_std` as well.
+//!
+//! The `std` feature, enabled by default, adds everything that needs the standard library: the
+//! [`SystemClock`] used to measure the elapsed time, the shared state like [`SharedBackoff`],
+//! [`RetryBudget`] and [`RetryMetrics`], and the builtin sleepers. The `tower`, `http` and
+//! `reqwest` features require `std`.
+//!
+//! ```toml
+//! [dependencies]
+//! backon = { version = "1", default-features = false }
+//! ```
 //!
 //! # Retry
 //!
@@ -108,6 +140,8 @@
 //!         .notify(|err: &anyhow::Error, dur: Duration| {
 //!             println!("retrying {:?} after {:?}", err, dur);
 //!         })
+//!         // Give up if all attempts together take more than 30s
+//!         .timeout(Duration::from_secs(30))
 //!         .await?;
 //!     println!("fetch succeeded: {}", content);
 //!
@@ -145,32 +179,182 @@
 //!     Ok(())
 //! }
 //! ```
+//!
+//! To retry a blocking function from async code without stalling the runtime, enable the
+//! `tokio-blocking` feature and use `retry_blocking`, which runs every attempt on tokio's
+//! blocking pool.
 
 #![deny(missing_docs)]
 #![deny(unused_qualifications)]
 #![no_std]
 
-#[cfg(feature = "std-blocking-sleep")]
+#[cfg(any(feature = "std", feature = "std-blocking-sleep"))]
 extern crate std;
 
+mod adjust;
+
 mod backoff;
 pub use backoff::*;
 
+mod decide;
+pub use decide::ErrorClass;
+pub use decide::ErrorMetadata;
+pub use decide::RetryDecision;
+
+mod driver;
+pub use driver::RetryDriver;
+pub use driver::Step;
+
+#[cfg(feature = "std")]
+mod metrics;
+#[cfg(feature = "std")]
+pub use metrics::RetryMetrics;
+
+#[cfg(feature = "std")]
+mod budget;
+#[cfg(feature = "std")]
+pub use budget::RetryBudget;
+
+#[cfg(feature = "std")]
+mod limit;
+#[cfg(feature = "std")]
+pub use limit::GlobalRetryLimit;
+
+#[cfg(feature = "std")]
+mod clock;
+#[cfg(feature = "std")]
+pub use clock::Clock;
+#[cfg(feature = "std")]
+pub use clock::ManualClock;
+#[cfg(feature = "std")]
+pub use clock::SystemClock;
+
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+mod circuit_breaker;
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+pub use circuit_breaker::CircuitBreaker;
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+pub use circuit_breaker::CircuitFuture;
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+pub use circuit_breaker::CircuitOpen;
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+pub use circuit_breaker::CircuitState;
+
+#[cfg(feature = "std")]
+mod hedge;
+#[cfg(feature = "std")]
+pub use hedge::Hedge;
+#[cfg(feature = "std")]
+pub use hedge::Hedgeable;
+
+#[cfg(feature = "http")]
+pub mod http;
+
+mod notify;
+pub use notify::NotifyTiming;
+pub use notify::RetryContext;
+
+#[cfg(feature = "std")]
+mod poll_until;
+#[cfg(feature = "std")]
+pub use poll_until::poll_until;
+#[cfg(feature = "std")]
+pub use poll_until::PollError;
+#[cfg(feature = "std")]
+pub use poll_until::PollUntilFuture;
+
+mod progress;
+pub use progress::RetryState;
+pub use progress::RetryStats;
+
 mod retry;
+pub use retry::OnExhausted;
 pub use retry::Retry;
 pub use retry::Retryable;
+pub use retry::Timeout;
+pub use retry::WithReason;
+pub use retry::WithStats;
+
+#[cfg(feature = "std")]
+mod retry_each;
+#[cfg(feature = "std")]
+pub use retry_each::RetryEach;
+
+#[cfg(feature = "futures")]
+mod retry_stream;
+#[cfg(feature = "futures")]
+pub use retry_stream::RetryStream;
+#[cfg(feature = "futures")]
+pub use retry_stream::RetryableStream;
+
+#[cfg(feature = "tower")]
+mod retry_layer;
+#[cfg(feature = "tower")]
+pub use retry_layer::RetryLayer;
+#[cfg(feature = "tower")]
+pub use retry_layer::RetryService;
+#[cfg(feature = "tower")]
+pub use retry_layer::RetryServiceFuture;
+
+#[cfg(all(not(target_arch = "wasm32"), feature = "tokio-blocking"))]
+mod retry_blocking;
+#[cfg(all(not(target_arch = "wasm32"), feature = "tokio-blocking"))]
+pub use retry_blocking::retry_blocking;
+#[cfg(all(not(target_arch = "wasm32"), feature = "tokio-blocking"))]
+pub use retry_blocking::BlockingAttempt;
+
+mod retry_option;
+pub use retry_option::RetryOption;
+pub use retry_option::RetryableOption;
 
 mod retry_with_context;
+pub use retry_with_context::assert_retryable_with_context;
+#[cfg(feature = "std")]
+pub use retry_with_context::borrow_context;
 pub use retry_with_context::RetryWithContext;
 pub use retry_with_context::RetryableWithContext;
 
+#[cfg(feature = "reqwest")]
+mod send_retry;
+#[cfg(feature = "reqwest")]
+pub use send_retry::RetryableRequest;
+#[cfg(feature = "reqwest")]
+pub use send_retry::SendError;
+#[cfg(feature = "reqwest")]
+pub use send_retry::SendRetry;
+
 mod sleep;
+#[cfg(feature = "std")]
+pub use sleep::BoxSleeper;
 pub use sleep::DefaultSleeper;
+#[cfg(feature = "std")]
+pub use sleep::DynSleeper;
 #[cfg(all(target_arch = "wasm32", feature = "gloo-timers-sleep"))]
 pub use sleep::GlooTimersSleep;
+pub use sleep::NoSleeper;
+pub use sleep::ScaledSleeper;
+pub use sleep::SleepError;
 pub use sleep::Sleeper;
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+pub use sleep::ThreadSleep;
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+pub use sleep::ThreadSleeper;
 #[cfg(all(not(target_arch = "wasm32"), feature = "tokio-sleep"))]
 pub use sleep::TokioSleeper;
+pub use sleep::TrySleeper;
+
+mod throttle;
+pub use throttle::PermitSource;
+pub use throttle::Throttled;
+pub use throttle::ThrottledSleep;
+
+mod timeout;
+pub use timeout::AttemptTimedOut;
+pub use timeout::RetryTimedOut;
+
+mod give_up;
+pub use give_up::GiveUpReason;
+pub use give_up::RetryError;
 
 mod blocking_retry;
 pub use blocking_retry::{BlockingRetry, BlockingRetryable};
@@ -184,5 +368,8 @@ pub use blocking_sleep::DefaultBlockingSleeper;
 #[cfg(feature = "std-blocking-sleep")]
 pub use blocking_sleep::StdSleeper;
 
+#[cfg(feature = "tracing")]
+mod trace;
+
 #[cfg(docsrs)]
 pub mod docs;
@@ -0,0 +1,115 @@
+use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::Ordering;
+
+/// GlobalRetryLimit caps the total number of retries made by all retries sharing it.
+///
+/// It's a counter shared by all retries of a policy, set by
+/// [`Retry::global_max_retries`](crate::Retry::global_max_retries). Every retry takes one from
+/// the limit before backing off, and once `max` retries have been made in total, every retry
+/// gives up right away. It's a crude circuit breaker that trips for good, call
+/// [`GlobalRetryLimit::reset`] to allow retrying again.
+///
+/// Unlike [`RetryBudget`](crate::RetryBudget), successes don't give the retries back.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::sync::Arc;
+///
+/// use anyhow::Result;
+/// use backon::ExponentialBuilder;
+/// use backon::GlobalRetryLimit;
+/// use backon::Retryable;
+///
+/// async fn fetch() -> Result<String> {
+///     Ok(reqwest::get("https://www.rust-lang.org")
+///         .await?
+///         .text()
+///         .await?)
+/// }
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() -> Result<()> {
+///     // Shared by all calls of the policy.
+///     let limit = Arc::new(GlobalRetryLimit::new(1000));
+///
+///     let content = fetch
+///         .retry(ExponentialBuilder::default())
+///         .global_max_retries(limit.clone())
+///         .await?;
+///     println!("fetch succeeded: {}", content);
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug)]
+pub struct GlobalRetryLimit {
+    used: AtomicUsize,
+    max: usize,
+}
+
+impl GlobalRetryLimit {
+    /// Create a new limit that allows at most `max` retries in total.
+    pub fn new(max: usize) -> Self {
+        GlobalRetryLimit {
+            used: AtomicUsize::new(0),
+            max,
+        }
+    }
+
+    /// Return the number of retries that have been made.
+    pub fn used(&self) -> usize {
+        self.used.load(Ordering::Relaxed)
+    }
+
+    /// Return the number of retries left.
+    pub fn remaining(&self) -> usize {
+        self.max.saturating_sub(self.used())
+    }
+
+    /// Take a retry from the limit, return `false` if the limit is reached.
+    pub fn acquire(&self) -> bool {
+        self.used
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| {
+                (used < self.max).then_some(used + 1)
+            })
+            .is_ok()
+    }
+
+    /// Give back a retry taken by [`GlobalRetryLimit::acquire`] that is not made.
+    pub(crate) fn release(&self) {
+        let _ = self
+            .used
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| {
+                used.checked_sub(1)
+            });
+    }
+
+    /// Reset the number of retries made to zero.
+    pub fn reset(&self) {
+        self.used.store(0, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test as test;
+
+    use super::*;
+
+    #[test]
+    fn test_global_retry_limit() {
+        let limit = GlobalRetryLimit::new(2);
+
+        assert!(limit.acquire());
+        assert!(limit.acquire());
+        assert!(!limit.acquire());
+        assert_eq!(limit.used(), 2);
+        assert_eq!(limit.remaining(), 0);
+
+        limit.reset();
+        assert_eq!(limit.remaining(), 2);
+        assert!(limit.acquire());
+    }
+}
@@ -0,0 +1,114 @@
+use core::sync::atomic::AtomicU64;
+use core::sync::atomic::Ordering;
+use core::time::Duration;
+
+/// RetryMetrics aggregates the statistics of all retries sharing it.
+///
+/// Set it by [`Retry::metrics`](crate::Retry::metrics), and share the same metrics with all
+/// retries of a policy to read cumulative numbers for a dashboard. It's updated as the retries
+/// run, so the numbers include the retries still in flight. Use
+/// [`Retry::with_stats`](crate::Retry::with_stats) instead for the statistics of a single
+/// retry.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::sync::Arc;
+///
+/// use anyhow::Result;
+/// use backon::ExponentialBuilder;
+/// use backon::RetryMetrics;
+/// use backon::Retryable;
+///
+/// async fn fetch() -> Result<String> {
+///     Ok(reqwest::get("https://www.rust-lang.org")
+///         .await?
+///         .text()
+///         .await?)
+/// }
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() -> Result<()> {
+///     let metrics = Arc::new(RetryMetrics::new());
+///
+///     for _ in 0..3 {
+///         let _ = fetch
+///             .retry(ExponentialBuilder::default())
+///             .metrics(metrics.clone())
+///             .await;
+///     }
+///     println!(
+///         "{} succeeded, {} failed, {} retries made",
+///         metrics.successes(),
+///         metrics.failures(),
+///         metrics.retries()
+///     );
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct RetryMetrics {
+    attempts: AtomicU64,
+    retries: AtomicU64,
+    slept_nanos: AtomicU64,
+    successes: AtomicU64,
+    failures: AtomicU64,
+}
+
+impl RetryMetrics {
+    /// Create new metrics with all counters at zero.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Return the number of attempts that have been started.
+    pub fn attempts(&self) -> u64 {
+        self.attempts.load(Ordering::Relaxed)
+    }
+
+    /// Return the number of retries that have been scheduled after a failed attempt.
+    pub fn retries(&self) -> u64 {
+        self.retries.load(Ordering::Relaxed)
+    }
+
+    /// Return the sum of all delays handed to the sleepers, including the initial delays.
+    pub fn slept(&self) -> Duration {
+        Duration::from_nanos(self.slept_nanos.load(Ordering::Relaxed))
+    }
+
+    /// Return the number of retries that succeeded.
+    pub fn successes(&self) -> u64 {
+        self.successes.load(Ordering::Relaxed)
+    }
+
+    /// Return the number of retries that returned an error.
+    pub fn failures(&self) -> u64 {
+        self.failures.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn record_attempt(&self) {
+        self.attempts.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_retry(&self) {
+        self.retries.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_sleep(&self, dur: Duration) {
+        let nanos = u64::try_from(dur.as_nanos()).unwrap_or(u64::MAX);
+        let _ = self
+            .slept_nanos
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |slept| {
+                Some(slept.saturating_add(nanos))
+            });
+    }
+
+    pub(crate) fn record_success(&self) {
+        self.successes.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_failure(&self) {
+        self.failures.fetch_add(1, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,204 @@
+use core::ops::ControlFlow;
+use core::time::Duration;
+
+/// RetryContext carries the details of a retry that is about to happen.
+///
+/// It's passed to the hook set by [`Retry::on_retry`][crate::Retry::on_retry] and friends.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct RetryContext<'a, E> {
+    /// The number of the attempt that just failed, starting from `1`.
+    pub attempt: usize,
+    /// The error returned by the failed attempt.
+    pub error: &'a E,
+    /// The delay before the next attempt.
+    pub next_delay: Duration,
+    /// The time elapsed since the retry started.
+    ///
+    /// Measured with the [`Clock`](crate::Clock) set by [`Retry::clock`](crate::Retry::clock),
+    /// or with [`std::time::Instant`] when the `std` feature is enabled on non-wasm32 targets,
+    /// always [`Duration::ZERO`] otherwise.
+    pub elapsed: Duration,
+    /// The number of retries left after the next attempt, if known.
+    ///
+    /// It's given by [`Backoff::remaining`](crate::Backoff::remaining), `Some(0)` means the next
+    /// attempt is the final one.
+    pub remaining: Option<usize>,
+}
+
+/// NotifyTiming controls when the notify hooks are invoked, set by
+/// [`Retry::notify_timing`][crate::Retry::notify_timing].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NotifyTiming {
+    /// Invoke the hooks when the sleep before the next attempt starts.
+    #[default]
+    BeforeSleep,
+    /// Invoke the hooks when the sleep is over, right before the next attempt starts.
+    AfterSleep,
+}
+
+/// A stub trait for the hooks that could be invoked before every retry.
+///
+/// All `FnMut(&E, Duration)` implement it by receiving [`RetryContext::error`] and
+/// [`RetryContext::next_delay`], so `notify` stays a thin wrapper of `on_retry`.
+#[doc(hidden)]
+pub trait Notify<E> {
+    fn notify(&mut self, ctx: RetryContext<'_, E>);
+
+    /// Notify and decide whether to continue retrying, only the hooks set by `on_retry_ctl`
+    /// could stop it.
+    fn notify_ctl(&mut self, ctx: RetryContext<'_, E>) -> ControlFlow<()> {
+        self.notify(ctx);
+        ControlFlow::Continue(())
+    }
+}
+
+impl<E, F: FnMut(&E, Duration)> Notify<E> for F {
+    fn notify(&mut self, ctx: RetryContext<'_, E>) {
+        self(ctx.error, ctx.next_delay)
+    }
+}
+
+/// A stub trait for the hooks in the slot of [`Notify`], telling what's left once the notify
+/// hook is replaced.
+///
+/// `notify` and friends replace the hook set before, but the hooks set on top of it, like
+/// `on_threshold`, are kept around the new hook, so they hold in any call order.
+#[doc(hidden)]
+pub trait ReplaceNotify<E> {
+    type With<N>;
+
+    fn replace<N>(self, notify: N) -> Self::With<N>;
+}
+
+impl<E, F: FnMut(&E, Duration)> ReplaceNotify<E> for F {
+    type With<N> = N;
+
+    fn replace<N>(self, notify: N) -> N {
+        notify
+    }
+}
+
+/// Notify that receives the whole [`RetryContext`], generated by `on_retry`.
+#[doc(hidden)]
+#[derive(Clone)]
+pub struct OnRetry<F>(pub(crate) F);
+
+impl<E, F> ReplaceNotify<E> for OnRetry<F> {
+    type With<N> = N;
+
+    fn replace<N>(self, notify: N) -> N {
+        notify
+    }
+}
+
+impl<E, F: FnMut(RetryContext<'_, E>)> Notify<E> for OnRetry<F> {
+    fn notify(&mut self, ctx: RetryContext<'_, E>) {
+        (self.0)(ctx)
+    }
+}
+
+/// Notify that could stop retrying, generated by `on_retry_ctl`.
+#[doc(hidden)]
+#[derive(Clone)]
+pub struct OnRetryCtl<F>(pub(crate) F);
+
+impl<E, F> ReplaceNotify<E> for OnRetryCtl<F> {
+    type With<N> = N;
+
+    fn replace<N>(self, notify: N) -> N {
+        notify
+    }
+}
+
+impl<E, F: FnMut(&E, Duration) -> ControlFlow<()>> Notify<E> for OnRetryCtl<F> {
+    fn notify(&mut self, ctx: RetryContext<'_, E>) {
+        let _ = self.notify_ctl(ctx);
+    }
+
+    fn notify_ctl(&mut self, ctx: RetryContext<'_, E>) -> ControlFlow<()> {
+        (self.0)(ctx.error, ctx.next_delay)
+    }
+}
+
+/// Notify that fires once the retry crosses a number of attempts on top of another notify,
+/// generated by `on_threshold`.
+#[doc(hidden)]
+pub struct OnThreshold<N, F> {
+    pub(crate) inner: N,
+    pub(crate) attempts: usize,
+    pub(crate) hook: F,
+    pub(crate) fired: bool,
+}
+
+impl<N: Clone, F: Clone> Clone for OnThreshold<N, F> {
+    fn clone(&self) -> Self {
+        // The clones start over, so they could fire again.
+        OnThreshold {
+            inner: self.inner.clone(),
+            attempts: self.attempts,
+            hook: self.hook.clone(),
+            fired: false,
+        }
+    }
+}
+
+impl<E, N: ReplaceNotify<E>, F> ReplaceNotify<E> for OnThreshold<N, F> {
+    type With<M> = OnThreshold<N::With<M>, F>;
+
+    fn replace<M>(self, notify: M) -> Self::With<M> {
+        OnThreshold {
+            inner: self.inner.replace(notify),
+            attempts: self.attempts,
+            hook: self.hook,
+            fired: self.fired,
+        }
+    }
+}
+
+impl<E, N: Notify<E>, F: FnMut(&E)> Notify<E> for OnThreshold<N, F> {
+    fn notify(&mut self, ctx: RetryContext<'_, E>) {
+        let _ = self.notify_ctl(ctx);
+    }
+
+    fn notify_ctl(&mut self, ctx: RetryContext<'_, E>) -> ControlFlow<()> {
+        if !self.fired && ctx.attempt >= self.attempts {
+            self.fired = true;
+            (self.hook)(ctx.error);
+        }
+        self.inner.notify_ctl(ctx)
+    }
+}
+
+/// A stub trait for the hooks of [`RetryWithContext`](crate::RetryWithContext) that could see
+/// the context.
+///
+/// The hooks set by `notify` and `on_retry` implement it by ignoring the context, so
+/// `notify_with_context` shares the same slot.
+#[doc(hidden)]
+pub trait NotifyWithContext<Ctx, E> {
+    fn notify(&mut self, context: &Ctx, ctx: RetryContext<'_, E>);
+}
+
+impl<Ctx, E, F: FnMut(&E, Duration)> NotifyWithContext<Ctx, E> for F {
+    fn notify(&mut self, _: &Ctx, ctx: RetryContext<'_, E>) {
+        Notify::notify(self, ctx)
+    }
+}
+
+impl<Ctx, E, F: FnMut(RetryContext<'_, E>)> NotifyWithContext<Ctx, E> for OnRetry<F> {
+    fn notify(&mut self, _: &Ctx, ctx: RetryContext<'_, E>) {
+        Notify::notify(self, ctx)
+    }
+}
+
+/// Notify that borrows the context, generated by `notify_with_context`.
+#[doc(hidden)]
+#[derive(Clone)]
+pub struct NotifyFnWithContext<F>(pub(crate) F);
+
+impl<Ctx, E, F: FnMut(&Ctx, &E, Duration)> NotifyWithContext<Ctx, E> for NotifyFnWithContext<F> {
+    fn notify(&mut self, context: &Ctx, ctx: RetryContext<'_, E>) {
+        (self.0)(context, ctx.error, ctx.next_delay)
+    }
+}
@@ -0,0 +1,202 @@
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::Context;
+use core::task::Poll;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use crate::backoff::BackoffBuilder;
+use crate::Retry;
+use crate::Retryable;
+
+/// Poll an operation with backoff until its value satisfies `until`.
+///
+/// This is for polling "is it done yet?", like waiting for a job to finish: `future_fn` is
+/// called again as long as it succeeds with a value that fails `until`, sleeping by the backoff
+/// built from `builder` between the calls. The first value that satisfies `until` is returned.
+///
+/// The calls that fail are not retried by default, the error is returned as
+/// [`PollError::Failed`] right away. Once the backoff runs out, the last value is returned as
+/// [`PollError::Unmet`]. The returned [`Retry`] could be configured as usual, for example call
+/// [`Retry::when`] to retry some of the errors as well.
+///
+/// # Examples
+///
+/// ```no_run
+/// use anyhow::Result;
+/// use backon::poll_until;
+/// use backon::ConstantBuilder;
+///
+/// async fn job_status() -> reqwest::Result<String> {
+///     Ok(reqwest::get("https://example.com/jobs/42")
+///         .await?
+///         .text()
+///         .await?)
+/// }
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() -> Result<()> {
+///     let status = poll_until(
+///         ConstantBuilder::default().with_max_times(10),
+///         job_status,
+///         |status: &String| status == "done",
+///     )
+///     .await?;
+///     println!("job finished: {}", status);
+///
+///     Ok(())
+/// }
+/// ```
+#[allow(clippy::type_complexity)]
+pub fn poll_until<B, T, E, Fut, FutureFn, P>(
+    builder: B,
+    mut future_fn: FutureFn,
+    until: P,
+) -> Retry<
+    B::Backoff,
+    T,
+    PollError<T, E>,
+    PollUntilFuture<Fut, P>,
+    impl FnMut() -> PollUntilFuture<Fut, P>,
+>
+where
+    B: BackoffBuilder,
+    Fut: Future<Output = Result<T, E>>,
+    FutureFn: FnMut() -> Fut,
+    P: FnMut(&T) -> bool,
+{
+    let until = Arc::new(Mutex::new(until));
+    let poll = move || PollUntilFuture {
+        inner: future_fn(),
+        until: until.clone(),
+    };
+    poll.retry(builder)
+        .when(PollError::is_unmet as fn(&PollError<T, E>) -> bool)
+}
+
+/// PollError is the error returned by [`poll_until`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PollError<T, E> {
+    /// The backoff ran out before the value satisfied the condition, with the last value.
+    Unmet(T),
+    /// The operation failed.
+    Failed(E),
+}
+
+impl<T, E> PollError<T, E> {
+    /// Whether the value didn't satisfy the condition.
+    pub fn is_unmet(&self) -> bool {
+        matches!(self, PollError::Unmet(_))
+    }
+}
+
+impl<T, E: fmt::Display> fmt::Display for PollError<T, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PollError::Unmet(_) => write!(f, "condition not met after polling"),
+            PollError::Failed(err) => write!(f, "polling failed: {}", err),
+        }
+    }
+}
+
+impl<T: fmt::Debug, E: std::error::Error + 'static> std::error::Error for PollError<T, E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PollError::Unmet(_) => None,
+            PollError::Failed(err) => Some(err),
+        }
+    }
+}
+
+/// PollUntilFuture is a single call made by [`poll_until`], checking its value.
+pub struct PollUntilFuture<Fut, P> {
+    inner: Fut,
+    until: Arc<Mutex<P>>,
+}
+
+impl<T, E, Fut, P> Future for PollUntilFuture<Fut, P>
+where
+    Fut: Future<Output = Result<T, E>>,
+    P: FnMut(&T) -> bool,
+{
+    type Output = Result<T, PollError<T, E>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // We do the exactly same thing like `pin_project` but without depending on it directly.
+        let this = unsafe { self.get_unchecked_mut() };
+        let inner = unsafe { Pin::new_unchecked(&mut this.inner) };
+        match inner.poll(cx) {
+            Poll::Pending => Poll::Pending,
+            Poll::Ready(Err(err)) => Poll::Ready(Err(PollError::Failed(err))),
+            Poll::Ready(Ok(value)) => {
+                let mut until = this.until.lock().unwrap_or_else(|err| err.into_inner());
+                if until(&value) {
+                    Poll::Ready(Ok(value))
+                } else {
+                    Poll::Ready(Err(PollError::Unmet(value)))
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::future::ready;
+    use core::time::Duration;
+    use std::vec::Vec;
+
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test as test;
+
+    #[cfg(not(target_arch = "wasm32"))]
+    use tokio::test;
+
+    use super::*;
+    use crate::ConstantBuilder;
+
+    #[test]
+    async fn test_poll_until() {
+        let slept = Arc::new(Mutex::new(Vec::new()));
+        let sleep = {
+            let slept = slept.clone();
+            move |dur| {
+                slept.lock().unwrap().push(dur);
+                ready(())
+            }
+        };
+        let builder = ConstantBuilder::default()
+            .with_delay(Duration::from_millis(10))
+            .with_max_times(5);
+
+        // Polled until the value satisfies the condition.
+        let mut count = 0;
+        let result = poll_until(
+            builder,
+            || {
+                count += 1;
+                ready(Ok::<_, ()>(count))
+            },
+            |count| *count >= 3,
+        )
+        .sleep(sleep.clone())
+        .await;
+        assert_eq!(result, Ok(3));
+        assert_eq!(slept.lock().unwrap().len(), 2);
+
+        // The last value is returned once the backoff runs out.
+        let result = poll_until(builder, || ready(Ok::<_, ()>(0)), |_| false)
+            .sleep(sleep.clone())
+            .await;
+        assert_eq!(result, Err(PollError::Unmet(0)));
+
+        // Errors are not retried by default.
+        slept.lock().unwrap().clear();
+        let result = poll_until(builder, || ready(Err::<u32, _>("fail")), |_| true)
+            .sleep(sleep)
+            .await;
+        assert_eq!(result, Err(PollError::Failed("fail")));
+        assert!(slept.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,360 @@
+use core::time::Duration;
+
+/// RetryStats carries the statistics of a finished retry.
+///
+/// It's returned along with the result by [`Retry::with_stats`][crate::Retry::with_stats].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct RetryStats {
+    /// The number of attempts that have been made, starting from `1`.
+    pub attempts: usize,
+    /// The sum of all delays slept between the attempts, including the initial delay.
+    ///
+    /// It's the sum of the delays handed to the sleeper, which tells how much of the time was
+    /// spent in backoff rather than in the attempts themselves.
+    pub slept: Duration,
+    /// The attempt that succeeded, or `None` if the retry failed.
+    ///
+    /// Attempts are one-based like [`RetryStats::attempts`]: `Some(1)` means the first attempt
+    /// succeeded without retrying. The succeeded attempt is always the last one, so it equals
+    /// `attempts` on success.
+    pub succeeded_attempt: Option<usize>,
+}
+
+/// RetryState is the resumable state of a retry, used to pause a retry and resume it later.
+///
+/// Take a snapshot by [`Retry::state`][crate::Retry::state], store it anywhere, and resume a
+/// new retry from it by [`Retry::resume`][crate::Retry::resume], possibly in another process.
+/// The in-flight attempt can't be saved, so the resumed retry starts with a new attempt.
+///
+/// The sleep future in progress can't be saved either. Instead, the rest of the sleep is
+/// recorded in [`RetryState::sleep_left`] and slept by the resumed retry before its first
+/// attempt, so a retry dropped while sleeping and recreated from its state doesn't retry
+/// early. The rest is measured with the clock of the retry, without a clock, like on wasm32,
+/// the whole delay is slept again.
+///
+/// The backoff is not part of the state: rebuild it at the same position with
+/// `with_starting_attempt(state.attempts)` on the builder, like
+/// [`ExponentialBuilder::with_starting_attempt`][crate::ExponentialBuilder::with_starting_attempt].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct RetryState {
+    /// The number of attempts that have been made, starting from `0`.
+    pub attempts: usize,
+    /// The time elapsed since the retry started, including the time before it was resumed.
+    pub elapsed: Duration,
+    /// The rest of the sleep in progress when the state was taken, zero if it was not sleeping.
+    pub sleep_left: Duration,
+}
+
+impl RetryState {
+    /// Create a state restored from the number of attempts made and the time elapsed.
+    pub fn new(attempts: usize, elapsed: Duration) -> Self {
+        RetryState {
+            attempts,
+            elapsed,
+            sleep_left: Duration::ZERO,
+        }
+    }
+}
+
+/// Progress tracks how far a retry has gone.
+#[derive(Default)]
+pub(crate) struct Progress {
+    /// The number of attempts that have been started.
+    pub(crate) attempts: usize,
+    /// The reason why the retry gave up, if it did.
+    pub(crate) give_up: Option<crate::GiveUpReason>,
+    /// The sum of all delays slept so far.
+    pub(crate) slept: Duration,
+    /// The attempt that succeeded, if any.
+    pub(crate) succeeded: Option<usize>,
+    /// The time elapsed before the retry was resumed.
+    resumed: Duration,
+    /// When the last attempt completed, if the gap since then is to be measured.
+    completed: Option<Completion>,
+    /// The last delay given by the backoff.
+    pub(crate) last_delay: Option<Duration>,
+    /// The sleep in progress, if any.
+    sleeping: Option<Sleep>,
+    /// The rest of the sleep to take before the first attempt after resuming.
+    sleep_left: Option<Duration>,
+
+    /// Whether to read the time, only if something consumes it.
+    #[cfg(feature = "std")]
+    timed: bool,
+    #[cfg(feature = "std")]
+    start: Option<Timestamp>,
+    /// When the current attempt started.
+    #[cfg(feature = "std")]
+    attempt_start: Option<Timestamp>,
+    #[cfg(feature = "std")]
+    clock: Option<TimeSource>,
+    #[cfg(feature = "std")]
+    metrics: Option<std::sync::Arc<crate::RetryMetrics>>,
+}
+
+/// A sleep in progress.
+#[derive(Clone, Copy)]
+struct Sleep {
+    /// The delay to sleep.
+    dur: Duration,
+    /// When the sleep started, if there is a clock.
+    #[cfg(feature = "std")]
+    at: Option<Timestamp>,
+}
+
+/// When an attempt completed.
+#[derive(Clone, Copy)]
+struct Completion {
+    /// The sum of all delays slept by then.
+    slept: Duration,
+    /// The time read from the clock, if there is one.
+    #[cfg(feature = "std")]
+    at: Option<Timestamp>,
+}
+
+/// The source of time used to measure the elapsed time.
+#[cfg(feature = "std")]
+#[derive(Clone)]
+enum TimeSource {
+    Clock(std::sync::Arc<dyn crate::Clock + Send + Sync>),
+    /// A function that returns the monotonic time elapsed since an arbitrary point.
+    Elapsed(std::sync::Arc<std::sync::Mutex<dyn FnMut() -> Duration + Send>>),
+}
+
+/// A point in time read from a [`TimeSource`].
+#[cfg(feature = "std")]
+#[derive(Clone, Copy)]
+enum Timestamp {
+    Instant(std::time::Instant),
+    Elapsed(Duration),
+}
+
+#[cfg(feature = "std")]
+impl Timestamp {
+    /// The time elapsed from `earlier` to this timestamp, or zero if they are not comparable.
+    fn since(self, earlier: Timestamp) -> Duration {
+        match (self, earlier) {
+            (Timestamp::Instant(now), Timestamp::Instant(start)) => {
+                now.saturating_duration_since(start)
+            }
+            (Timestamp::Elapsed(now), Timestamp::Elapsed(start)) => now.saturating_sub(start),
+            _ => Duration::ZERO,
+        }
+    }
+}
+
+impl Progress {
+    /// Use given clock to measure the elapsed time.
+    #[cfg(feature = "std")]
+    pub(crate) fn set_clock(&mut self, clock: impl crate::Clock + Send + Sync + 'static) {
+        self.clock = Some(TimeSource::Clock(std::sync::Arc::new(clock)));
+        self.timed = true;
+    }
+
+    /// Use given function returning the monotonic elapsed time to measure the elapsed time.
+    #[cfg(feature = "std")]
+    pub(crate) fn set_time_source(&mut self, f: impl FnMut() -> Duration + Send + 'static) {
+        self.clock = Some(TimeSource::Elapsed(std::sync::Arc::new(
+            std::sync::Mutex::new(f),
+        )));
+        self.timed = true;
+    }
+
+    /// Measure the time, for the hooks that read the elapsed time.
+    ///
+    /// The time is not read otherwise, so the retries that don't need it don't pay for it.
+    pub(crate) fn measure_time(&mut self) {
+        #[cfg(feature = "std")]
+        {
+            self.timed = true;
+        }
+    }
+
+    /// Record the progress into given metrics as well.
+    #[cfg(feature = "std")]
+    pub(crate) fn set_metrics(&mut self, metrics: std::sync::Arc<crate::RetryMetrics>) {
+        self.metrics = Some(metrics);
+    }
+
+    /// Record into the metrics if there are any.
+    #[cfg(feature = "std")]
+    fn record(&self, f: impl FnOnce(&crate::RetryMetrics)) {
+        if let Some(metrics) = &self.metrics {
+            f(metrics);
+        }
+    }
+
+    /// Continue from given state instead of starting over.
+    pub(crate) fn resume(&mut self, state: RetryState) {
+        self.attempts = state.attempts;
+        self.resumed = state.elapsed;
+        self.sleep_left = Some(state.sleep_left).filter(|dur| !dur.is_zero());
+    }
+
+    /// The resumable state of the retry so far.
+    pub(crate) fn state(&self) -> RetryState {
+        RetryState {
+            attempts: self.attempts,
+            elapsed: self.elapsed(),
+            sleep_left: self.sleep_left(),
+        }
+    }
+
+    /// Create a new progress that has not started yet, keeping the clock and the metrics.
+    pub(crate) fn fresh(&self) -> Self {
+        Progress {
+            #[cfg(feature = "std")]
+            timed: self.timed,
+            #[cfg(feature = "std")]
+            clock: self.clock.clone(),
+            #[cfg(feature = "std")]
+            metrics: self.metrics.clone(),
+            ..Progress::default()
+        }
+    }
+
+    /// Read the current time from the clock or the time source, if the time is measured.
+    ///
+    /// Without either set, [`SystemClock`](crate::SystemClock) is used except on wasm32, where
+    /// it's not available.
+    #[cfg(feature = "std")]
+    fn now(&self) -> Option<Timestamp> {
+        if !self.timed {
+            return None;
+        }
+        match &self.clock {
+            Some(TimeSource::Clock(clock)) => Some(Timestamp::Instant(clock.now())),
+            Some(TimeSource::Elapsed(f)) => {
+                let mut f = f.lock().unwrap_or_else(|err| err.into_inner());
+                Some(Timestamp::Elapsed(f()))
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            None => Some(Timestamp::Instant(crate::Clock::now(&crate::SystemClock))),
+            #[cfg(target_arch = "wasm32")]
+            None => None,
+        }
+    }
+
+    /// Start measuring the elapsed time if it's not started yet.
+    pub(crate) fn start(&mut self) {
+        #[cfg(feature = "std")]
+        if self.start.is_none() {
+            self.start = self.now();
+        }
+    }
+
+    /// Record the start of a new attempt.
+    pub(crate) fn start_attempt(&mut self) {
+        self.start();
+        self.attempts += 1;
+        #[cfg(feature = "std")]
+        {
+            self.attempt_start = self.now();
+        }
+        #[cfg(feature = "std")]
+        self.record(|m| m.record_attempt());
+    }
+
+    /// Record that a retry will be made after the current attempt.
+    pub(crate) fn retry(&self) {
+        #[cfg(feature = "std")]
+        self.record(|m| m.record_retry());
+    }
+
+    /// Record that the retry failed.
+    pub(crate) fn fail(&self) {
+        #[cfg(feature = "std")]
+        self.record(|m| m.record_failure());
+    }
+
+    /// Record that the current attempt succeeded.
+    pub(crate) fn succeed(&mut self) {
+        self.succeeded = Some(self.attempts);
+        #[cfg(feature = "std")]
+        self.record(|m| m.record_success());
+    }
+
+    /// Record that the current attempt completed, to measure the gap before the next one.
+    pub(crate) fn complete_attempt(&mut self) {
+        self.completed = Some(Completion {
+            slept: self.slept,
+            #[cfg(feature = "std")]
+            at: self.now(),
+        });
+    }
+
+    /// Take the time passed since the last attempt completed, if it's recorded.
+    ///
+    /// It's measured by the clock if there is one, or by the delays slept since then otherwise.
+    pub(crate) fn take_since_completed(&mut self) -> Option<Duration> {
+        let completed = self.completed.take()?;
+        #[cfg(feature = "std")]
+        if let (Some(at), Some(now)) = (completed.at, self.now()) {
+            return Some(now.since(at));
+        }
+        Some(self.slept.saturating_sub(completed.slept))
+    }
+
+    /// Take the rest of the sleep to take after resuming, if any.
+    pub(crate) fn take_sleep_left(&mut self) -> Option<Duration> {
+        self.sleep_left.take()
+    }
+
+    /// The rest of the sleep in progress, measured by the clock if there is one.
+    fn sleep_left(&self) -> Duration {
+        let Some(sleep) = self.sleeping else {
+            return Duration::ZERO;
+        };
+        #[cfg(feature = "std")]
+        if let (Some(at), Some(now)) = (sleep.at, self.now()) {
+            return sleep.dur.saturating_sub(now.since(at));
+        }
+        sleep.dur
+    }
+
+    /// Record a sleep of given duration before the next attempt.
+    pub(crate) fn start_sleep(&mut self, dur: Duration) {
+        self.sleeping = Some(Sleep {
+            dur,
+            #[cfg(feature = "std")]
+            at: self.now(),
+        });
+        self.slept = self.slept.saturating_add(dur);
+        #[cfg(feature = "std")]
+        self.record(|m| m.record_sleep(dur));
+    }
+
+    /// Record that the sleep in progress has completed.
+    pub(crate) fn end_sleep(&mut self) {
+        self.sleeping = None;
+    }
+
+    /// The statistics of the retry so far.
+    pub(crate) fn stats(&self) -> RetryStats {
+        RetryStats {
+            attempts: self.attempts,
+            slept: self.slept,
+            succeeded_attempt: self.succeeded,
+        }
+    }
+
+    /// The time elapsed since the current attempt started, zero without a clock.
+    #[cfg(feature = "std")]
+    pub(crate) fn attempt_elapsed(&self) -> Duration {
+        match (self.attempt_start, self.now()) {
+            (Some(start), Some(now)) => now.since(start),
+            _ => Duration::ZERO,
+        }
+    }
+
+    /// The time elapsed since the retry started.
+    pub(crate) fn elapsed(&self) -> Duration {
+        #[cfg(feature = "std")]
+        if let (Some(start), Some(now)) = (self.start, self.now()) {
+            return self.resumed.saturating_add(now.since(start));
+        }
+        self.resumed
+    }
+}
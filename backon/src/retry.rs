@@ -1,15 +1,55 @@
+use core::fmt;
 use core::future::Future;
+use core::ops::ControlFlow;
 use core::pin::Pin;
 use core::task::ready;
 use core::task::Context;
 use core::task::Poll;
 use core::time::Duration;
 
+use crate::adjust::Adjust;
+#[cfg(feature = "std")]
+use crate::adjust::BackoffByKey;
+use crate::adjust::BackoffFor;
+use crate::adjust::NoSleepWhen;
+use crate::backoff::new_rng;
 use crate::backoff::BackoffBuilder;
+use crate::decide::Classify;
+use crate::decide::Decide;
+use crate::decide::DecideFn;
+use crate::decide::GiveUpOnRepeat;
+use crate::decide::OnNonRetryable;
+use crate::decide::ReplaceDecide;
+use crate::decide::SafeToRetry;
+use crate::decide::WhenElapsed;
+use crate::decide::WhenMeta;
+use crate::give_up::GiveUp;
+use crate::notify::Notify;
+use crate::notify::OnRetry;
+use crate::notify::OnRetryCtl;
+use crate::notify::OnThreshold;
+use crate::notify::ReplaceNotify;
+use crate::progress::Progress;
+use crate::sleep::Fallible;
 use crate::sleep::MaybeSleeper;
+use crate::sleep::MaybeTrySleeper;
+use crate::sleep::RetrySleeper;
 use crate::Backoff;
 use crate::DefaultSleeper;
+use crate::ErrorClass;
+use crate::ErrorMetadata;
+use crate::GiveUpReason;
+use crate::NotifyTiming;
+use crate::PermitSource;
+use crate::RetryContext;
+use crate::RetryDecision;
+use crate::RetryError;
+use crate::RetryState;
+use crate::RetryStats;
+use crate::SleepError;
 use crate::Sleeper;
+use crate::Throttled;
+use crate::TrySleeper;
 
 /// Retryable will add retry support for functions that produce futures with results.
 ///
@@ -38,6 +78,15 @@ use crate::Sleeper;
 ///     Err(anyhow::anyhow!(x))
 /// }
 /// ```
+///
+/// - Closures calling a `&self` method, like `|| async { self.fetch().await }` in a method or
+///   `|| async { client.fetch().await }` over a local `client`
+///
+/// Shared borrows need no context: every attempt borrows the receiver again for as long as the
+/// retry lives, so the receiver must outlive the retry and can't be mutated meanwhile. It
+/// applies to anything reached through a shared reference, like a field behind `&self`.
+/// Methods taking `&mut self` can't be borrowed by more than one attempt, retry them with
+/// [`RetryableWithContext`](crate::RetryableWithContext) instead.
 pub trait Retryable<
     B: BackoffBuilder,
     T,
@@ -61,22 +110,95 @@ where
     }
 }
 
+/// Retry a block of async code with a backoff.
+///
+/// `retry!(builder, { ... })` expands to `(|| async move { ... }).retry(builder)`, sparing the
+/// closure for quick scripts. The block is run for every attempt and must evaluate to a
+/// `Result`. The expansion is a [`Retry`], so it can be configured further before being
+/// awaited.
+///
+/// The conditions for retrying and the notify function could be given inline, in this order,
+/// as `when = ...` and `notify = ...`, which are the same as [`Retry::when`] and
+/// [`Retry::notify`].
+///
+/// The variables used by the block are moved into every attempt, so borrow them beforehand,
+/// like `let client = &client;`, to use them across attempts.
+///
+/// # Examples
+///
+/// ```no_run
+/// use anyhow::Result;
+/// use backon::retry;
+/// use backon::ExponentialBuilder;
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() -> Result<()> {
+///     let client = reqwest::Client::new();
+///     let client = &client;
+///
+///     let content = retry!(
+///         ExponentialBuilder::default(),
+///         { client.get("https://www.rust-lang.org").send().await?.text().await },
+///         when = |e: &reqwest::Error| e.is_timeout(),
+///         notify = |e, dur| println!("retrying {:?} after {:?}", e, dur),
+///     )
+///     .await?;
+///     println!("fetch succeeded: {}", content);
+///
+///     Ok(())
+/// }
+/// ```
+#[macro_export]
+macro_rules! retry {
+    (
+        $builder:expr,
+        $body:block
+        $(, when = $when:expr)?
+        $(, notify = $notify:expr)?
+        $(,)?
+    ) => {{
+        let retry = $crate::Retryable::retry(|| async move $body, $builder);
+        $(let retry = retry.when($when);)?
+        $(let retry = retry.notify($notify);)?
+        retry
+    }};
+}
+
+/// The hook set by [`Retry::on_attempt_complete`].
+#[cfg(feature = "std")]
+type AttemptHook<T, E> =
+    std::sync::Arc<std::sync::Mutex<dyn FnMut(usize, Duration, &Result<T, E>) + Send>>;
+
 /// Struct generated by [`Retryable`].
+///
+/// The retry is fused: once it has returned the result, polling it again returns
+/// [`Poll::Pending`] instead of panicking or retrying again. With the `futures` feature enabled,
+/// it implements `FusedFuture` to report it, and so do the futures wrapping it.
 pub struct Retry<
     B: Backoff,
     T,
     E,
     Fut: Future<Output = Result<T, E>>,
     FutureFn: FnMut() -> Fut,
-    SF: MaybeSleeper = DefaultSleeper,
+    SF: MaybeTrySleeper = DefaultSleeper,
     RF = fn(&E) -> bool,
     NF = fn(&E, Duration),
+    AF = fn(&E, Option<Duration>) -> Option<Duration>,
+    GF = fn(&E, usize),
 > {
-    backoff: B,
-    retryable: RF,
-    notify: NF,
-    future_fn: FutureFn,
-    sleep_fn: SF,
+    pub(crate) backoff: B,
+    pub(crate) retryable: RF,
+    pub(crate) notify: NF,
+    pub(crate) on_giveup: GF,
+    adjust: AF,
+    pub(crate) future_fn: FutureFn,
+    pub(crate) sleep_fn: SF,
+    #[cfg(feature = "tracing")]
+    pub(crate) tracer: Option<crate::trace::Tracer<E>>,
+    #[cfg(feature = "std")]
+    on_attempt: Option<AttemptHook<T, E>>,
+    progress: Progress,
+    options: Options,
 
     state: State<T, E, Fut, SF::Sleep>,
 }
@@ -93,21 +215,31 @@ where
             backoff,
             retryable: |_: &E| true,
             notify: |_: &E, _: Duration| {},
+            on_giveup: |_: &E, _: usize| {},
+            adjust: |_: &E, dur: Option<Duration>| dur,
             future_fn,
             sleep_fn: DefaultSleeper::default(),
+            #[cfg(feature = "tracing")]
+            tracer: None,
+            #[cfg(feature = "std")]
+            on_attempt: None,
+            progress: Progress::default(),
+            options: Options::default(),
             state: State::Idle,
         }
     }
 }
 
-impl<B, T, E, Fut, FutureFn, SF, RF, NF> Retry<B, T, E, Fut, FutureFn, SF, RF, NF>
+impl<B, T, E, Fut, FutureFn, SF, RF, NF, AF, GF> Retry<B, T, E, Fut, FutureFn, SF, RF, NF, AF, GF>
 where
     B: Backoff,
     Fut: Future<Output = Result<T, E>>,
     FutureFn: FnMut() -> Fut,
-    SF: MaybeSleeper,
-    RF: FnMut(&E) -> bool,
-    NF: FnMut(&E, Duration),
+    SF: MaybeTrySleeper,
+    RF: Decide<E>,
+    NF: Notify<E>,
+    AF: Adjust<E>,
+    GF: FnMut(&E, usize),
 {
     /// Set the sleeper for retrying.
     ///
@@ -139,21 +271,127 @@ where
     ///     Ok(())
     /// }
     /// ```
-    pub fn sleep<SN: Sleeper>(self, sleep_fn: SN) -> Retry<B, T, E, Fut, FutureFn, SN, RF, NF> {
+    pub fn sleep<SN: Sleeper>(
+        self,
+        sleep_fn: SN,
+    ) -> Retry<B, T, E, Fut, FutureFn, SN, RF, NF, AF, GF> {
         Retry {
             backoff: self.backoff,
             retryable: self.retryable,
             notify: self.notify,
+            on_giveup: self.on_giveup,
+            adjust: self.adjust,
             future_fn: self.future_fn,
             sleep_fn,
+            #[cfg(feature = "tracing")]
+            tracer: self.tracer,
+            #[cfg(feature = "std")]
+            on_attempt: self.on_attempt,
+            progress: self.progress,
+            options: self.options,
+            state: State::Idle,
+        }
+    }
+
+    /// Set a sleeper that could fail for retrying.
+    ///
+    /// The sleeper should implement the [`TrySleeper`] trait. If sleeping fails, the retry stops
+    /// immediately and returns the [`SleepError`] converted into the error of the retry.
+    ///
+    /// ```no_run
+    /// use std::future::ready;
+    /// use std::future::Ready;
+    /// use std::time::Duration;
+    ///
+    /// use backon::ExponentialBuilder;
+    /// use backon::Retryable;
+    /// use backon::SleepError;
+    /// use backon::TrySleeper;
+    ///
+    /// #[derive(Debug)]
+    /// enum FetchError {
+    ///     Http(reqwest::Error),
+    ///     Sleep(SleepError),
+    /// }
+    ///
+    /// impl From<SleepError> for FetchError {
+    ///     fn from(err: SleepError) -> Self {
+    ///         FetchError::Sleep(err)
+    ///     }
+    /// }
+    ///
+    /// /// A sleeper of a runtime that has been shut down.
+    /// struct ClosedSleeper;
+    ///
+    /// impl TrySleeper for ClosedSleeper {
+    ///     type Sleep = Ready<Result<(), SleepError>>;
+    ///
+    ///     fn try_sleep(&self, _: Duration) -> Self::Sleep {
+    ///         ready(Err(SleepError::new("runtime is shut down")))
+    ///     }
+    /// }
+    ///
+    /// async fn fetch() -> Result<String, FetchError> {
+    ///     let resp = reqwest::get("https://www.rust-lang.org")
+    ///         .await
+    ///         .map_err(FetchError::Http)?;
+    ///     resp.text().await.map_err(FetchError::Http)
+    /// }
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() {
+    ///     let content = fetch
+    ///         .retry(ExponentialBuilder::default())
+    ///         .try_sleep(ClosedSleeper)
+    ///         .await;
+    ///     println!("fetch result: {:?}", content);
+    /// }
+    /// ```
+    pub fn try_sleep<SN: TrySleeper>(
+        self,
+        sleep_fn: SN,
+    ) -> Retry<B, T, E, Fut, FutureFn, Fallible<SN>, RF, NF, AF, GF>
+    where
+        E: From<SleepError>,
+    {
+        Retry {
+            backoff: self.backoff,
+            retryable: self.retryable,
+            notify: self.notify,
+            on_giveup: self.on_giveup,
+            adjust: self.adjust,
+            future_fn: self.future_fn,
+            sleep_fn: Fallible(sleep_fn),
+            #[cfg(feature = "tracing")]
+            tracer: self.tracer,
+            #[cfg(feature = "std")]
+            on_attempt: self.on_attempt,
+            progress: self.progress,
+            options: self.options,
             state: State::Idle,
         }
     }
 
+    /// Skip all sleeps, it's a shorthand of `.sleep(NoSleeper)`.
+    ///
+    /// Every attempt is made right after the previous one fails, while the backoff is still
+    /// consumed and the attempts are still counted, so the retry stops after `max_times` as
+    /// usual. It's handy in tests, or to retry as fast as possible. See [`NoSleeper`] for
+    /// details.
+    ///
+    /// [`NoSleeper`]: crate::NoSleeper
+    pub fn no_sleep(self) -> Retry<B, T, E, Fut, FutureFn, crate::NoSleeper, RF, NF, AF, GF> {
+        self.sleep(crate::NoSleeper)
+    }
+
     /// Set the conditions for retrying.
     ///
     /// If not specified, all errors are considered retryable.
     ///
+    /// `when`, `when_elapsed`, `when_meta`, `classify` and `decide` share the same slot, setting
+    /// one will replace the others. The guards set on top of them, like
+    /// [`Retry::safe_to_retry`], are kept.
+    ///
     /// # Examples
     ///
     /// ```no_run
@@ -179,25 +417,37 @@ where
     ///     Ok(())
     /// }
     /// ```
+    #[allow(clippy::type_complexity)]
     pub fn when<RN: FnMut(&E) -> bool>(
         self,
         retryable: RN,
-    ) -> Retry<B, T, E, Fut, FutureFn, SF, RN, NF> {
+    ) -> Retry<B, T, E, Fut, FutureFn, SF, RF::With<RN>, NF, AF, GF>
+    where
+        RF: ReplaceDecide<E>,
+    {
         Retry {
             backoff: self.backoff,
-            retryable,
+            retryable: self.retryable.replace(retryable),
             notify: self.notify,
+            on_giveup: self.on_giveup,
+            adjust: self.adjust,
             future_fn: self.future_fn,
             sleep_fn: self.sleep_fn,
+            #[cfg(feature = "tracing")]
+            tracer: self.tracer,
+            #[cfg(feature = "std")]
+            on_attempt: self.on_attempt,
+            progress: self.progress,
+            options: self.options,
             state: self.state,
         }
     }
 
-    /// Set to notify for all retry attempts.
+    /// Set the conditions for retrying with the time elapsed since the retry started.
     ///
-    /// When a retry happens, the input function will be invoked with the error and the sleep duration before pausing.
-    ///
-    /// If not specified, this operation does nothing.
+    /// It's the same as [`Retry::when`], except that the function also receives the time
+    /// elapsed, measured like [`RetryContext::elapsed`]. It's useful to give up on some errors
+    /// after a while, without bounding the whole retry.
     ///
     /// # Examples
     ///
@@ -218,9 +468,10 @@ where
     /// #[tokio::main(flavor = "current_thread")]
     /// async fn main() -> Result<()> {
     ///     let content = fetch
-    ///         .retry(ExponentialBuilder::default())
-    ///         .notify(|err: &anyhow::Error, dur: Duration| {
-    ///             println!("retrying error {:?} with sleeping {:?}", err, dur);
+    ///         .retry(ExponentialBuilder::default().without_max_times())
+    ///         // Stop retrying auth errors after 30s in total.
+    ///         .when_elapsed(|e, elapsed| {
+    ///             e.to_string() != "unauthorized" || elapsed < Duration::from_secs(30)
     ///         })
     ///         .await?;
     ///     println!("fetch succeeded: {}", content);
@@ -228,224 +479,5040 @@ where
     ///     Ok(())
     /// }
     /// ```
-    pub fn notify<NN: FnMut(&E, Duration)>(
-        self,
-        notify: NN,
-    ) -> Retry<B, T, E, Fut, FutureFn, SF, RF, NN> {
+    #[allow(clippy::type_complexity)]
+    pub fn when_elapsed<RN: FnMut(&E, Duration) -> bool>(
+        mut self,
+        retryable: RN,
+    ) -> Retry<B, T, E, Fut, FutureFn, SF, RF::With<WhenElapsed<RN>>, NF, AF, GF>
+    where
+        RF: ReplaceDecide<E>,
+    {
+        self.progress.measure_time();
         Retry {
             backoff: self.backoff,
-            retryable: self.retryable,
-            notify,
-            sleep_fn: self.sleep_fn,
+            retryable: self.retryable.replace(WhenElapsed(retryable)),
+            notify: self.notify,
+            on_giveup: self.on_giveup,
+            adjust: self.adjust,
             future_fn: self.future_fn,
+            sleep_fn: self.sleep_fn,
+            #[cfg(feature = "tracing")]
+            tracer: self.tracer,
+            #[cfg(feature = "std")]
+            on_attempt: self.on_attempt,
+            progress: self.progress,
+            options: self.options,
             state: self.state,
         }
     }
-}
-
-/// State maintains internal state of retry.
-#[derive(Default)]
-enum State<T, E, Fut: Future<Output = Result<T, E>>, SleepFut: Future<Output = ()>> {
-    #[default]
-    Idle,
-    Polling(Fut),
-    Sleeping(SleepFut),
-}
-
-impl<B, T, E, Fut, FutureFn, SF, RF, NF> Future for Retry<B, T, E, Fut, FutureFn, SF, RF, NF>
-where
-    B: Backoff,
-    Fut: Future<Output = Result<T, E>>,
-    FutureFn: FnMut() -> Fut,
-    SF: Sleeper,
-    RF: FnMut(&E) -> bool,
-    NF: FnMut(&E, Duration),
-{
-    type Output = Result<T, E>;
-
-    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        // Safety: This is safe because we don't move the `Retry` struct itself,
-        // only its internal state.
-        //
-        // We do the exactly same thing like `pin_project` but without depending on it directly.
-        let this = unsafe { self.get_unchecked_mut() };
-
-        loop {
-            match &mut this.state {
-                State::Idle => {
-                    let fut = (this.future_fn)();
-                    this.state = State::Polling(fut);
-                    continue;
-                }
-                State::Polling(fut) => {
-                    // Safety: This is safe because we don't move the `Retry` struct and this fut,
-                    // only its internal state.
-                    //
-                    // We do the exactly same thing like `pin_project` but without depending on it directly.
-                    let mut fut = unsafe { Pin::new_unchecked(fut) };
-
-                    match ready!(fut.as_mut().poll(cx)) {
-                        Ok(v) => return Poll::Ready(Ok(v)),
-                        Err(err) => {
-                            // If input error is not retryable, return error directly.
-                            if !(this.retryable)(&err) {
-                                return Poll::Ready(Err(err));
-                            }
-                            match this.backoff.next() {
-                                None => return Poll::Ready(Err(err)),
-                                Some(dur) => {
-                                    (this.notify)(&err, dur);
-                                    this.state = State::Sleeping(this.sleep_fn.sleep(dur));
-                                    continue;
-                                }
-                            }
-                        }
-                    }
-                }
-                State::Sleeping(sl) => {
-                    // Safety: This is safe because we don't move the `Retry` struct and this fut,
-                    // only its internal state.
-                    //
-                    // We do the exactly same thing like `pin_project` but without depending on it directly.
-                    let mut sl = unsafe { Pin::new_unchecked(sl) };
 
-                    ready!(sl.as_mut().poll(cx));
-                    this.state = State::Idle;
-                    continue;
-                }
-            }
+    /// Set the conditions for retrying by the metadata carried by the error.
+    ///
+    /// It's the same as [`Retry::when`], except that the function also receives the metadata
+    /// of the error given by [`ErrorMetadata`], like a gRPC status code. It's more robust than
+    /// matching on the message of the error.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use backon::ConstantBuilder;
+    /// use backon::ErrorMetadata;
+    /// use backon::Retryable;
+    ///
+    /// #[derive(Debug, Clone, Copy, PartialEq)]
+    /// enum Code {
+    ///     InvalidArgument,
+    ///     Unavailable,
+    ///     ResourceExhausted,
+    /// }
+    ///
+    /// #[derive(Debug)]
+    /// struct Status {
+    ///     code: Code,
+    ///     message: String,
+    /// }
+    ///
+    /// impl ErrorMetadata for Status {
+    ///     type Meta = Code;
+    ///
+    ///     fn metadata(&self) -> &Code {
+    ///         &self.code
+    ///     }
+    /// }
+    ///
+    /// async fn call() -> Result<String, Status> {
+    ///     Err(Status {
+    ///         code: Code::InvalidArgument,
+    ///         message: "missing field `name`".to_string(),
+    ///     })
+    /// }
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() {
+    ///     let result = call
+    ///         .retry(ConstantBuilder::default())
+    ///         .when_meta(|_, code| matches!(code, Code::Unavailable | Code::ResourceExhausted))
+    ///         .await;
+    ///
+    ///     // The invalid argument is not retried.
+    ///     assert_eq!(result.unwrap_err().code, Code::InvalidArgument);
+    /// }
+    /// ```
+    #[allow(clippy::type_complexity)]
+    pub fn when_meta<RN: FnMut(&E, &E::Meta) -> bool>(
+        self,
+        retryable: RN,
+    ) -> Retry<B, T, E, Fut, FutureFn, SF, RF::With<WhenMeta<RN>>, NF, AF, GF>
+    where
+        E: ErrorMetadata,
+        RF: ReplaceDecide<E>,
+    {
+        Retry {
+            backoff: self.backoff,
+            retryable: self.retryable.replace(WhenMeta(retryable)),
+            notify: self.notify,
+            on_giveup: self.on_giveup,
+            adjust: self.adjust,
+            future_fn: self.future_fn,
+            sleep_fn: self.sleep_fn,
+            #[cfg(feature = "tracing")]
+            tracer: self.tracer,
+            #[cfg(feature = "std")]
+            on_attempt: self.on_attempt,
+            progress: self.progress,
+            options: self.options,
+            state: self.state,
         }
     }
-}
-
-#[cfg(test)]
-#[cfg(any(feature = "tokio-sleep", feature = "gloo-timers-sleep",))]
-mod default_sleeper_tests {
-    extern crate alloc;
-
-    use alloc::string::ToString;
-    use alloc::vec;
-    use alloc::vec::Vec;
-    use core::time::Duration;
-    use tokio::sync::Mutex;
-
-    #[cfg(target_arch = "wasm32")]
-    use wasm_bindgen_test::wasm_bindgen_test as test;
 
-    #[cfg(not(target_arch = "wasm32"))]
-    use tokio::test;
-
-    use super::*;
-    use crate::ExponentialBuilder;
-
-    async fn always_error() -> anyhow::Result<()> {
-        Err(anyhow::anyhow!("test_query meets error"))
+    /// Set the conditions for retrying by the class of the error.
+    ///
+    /// The input function classifies every error into an [`ErrorClass`], and the class
+    /// decides whether to retry: [`ErrorClass::Transient`] and [`ErrorClass::RateLimited`] are
+    /// retried, [`ErrorClass::Permanent`] is returned right away.
+    ///
+    /// Pass the same function to [`Retry::backoff_by_key`] to back off by the class as well,
+    /// like waiting longer when rate limited.
+    ///
+    /// `when`, `when_elapsed`, `when_meta`, `classify` and `decide` share the same slot, setting
+    /// one will replace the others. The guards set on top of them, like
+    /// [`Retry::safe_to_retry`], are kept.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use core::time::Duration;
+    ///
+    /// use anyhow::Result;
+    /// use backon::ConstantBuilder;
+    /// use backon::ErrorClass;
+    /// use backon::ExponentialBuilder;
+    /// use backon::Retryable;
+    ///
+    /// async fn fetch() -> Result<String> {
+    ///     Ok(reqwest::get("https://www.rust-lang.org")
+    ///         .await?
+    ///         .error_for_status()?
+    ///         .text()
+    ///         .await?)
+    /// }
+    ///
+    /// fn classify(err: &anyhow::Error) -> ErrorClass {
+    ///     match err.downcast_ref::<reqwest::Error>().and_then(|e| e.status()) {
+    ///         Some(status) if status == 429 => ErrorClass::RateLimited,
+    ///         Some(status) if status.is_client_error() => ErrorClass::Permanent,
+    ///         _ => ErrorClass::Transient,
+    ///     }
+    /// }
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<()> {
+    ///     let content = fetch
+    ///         .retry(ExponentialBuilder::default())
+    ///         .classify(classify)
+    ///         .backoff_by_key(
+    ///             classify,
+    ///             [(
+    ///                 ErrorClass::RateLimited,
+    ///                 ConstantBuilder::default().with_delay(Duration::from_secs(30)),
+    ///             )],
+    ///         )
+    ///         .await?;
+    ///     println!("fetch succeeded: {}", content);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    #[allow(clippy::type_complexity)]
+    pub fn classify<CN: FnMut(&E) -> ErrorClass>(
+        self,
+        classify: CN,
+    ) -> Retry<B, T, E, Fut, FutureFn, SF, RF::With<Classify<CN>>, NF, AF, GF>
+    where
+        RF: ReplaceDecide<E>,
+    {
+        Retry {
+            backoff: self.backoff,
+            retryable: self.retryable.replace(Classify(classify)),
+            notify: self.notify,
+            on_giveup: self.on_giveup,
+            adjust: self.adjust,
+            future_fn: self.future_fn,
+            sleep_fn: self.sleep_fn,
+            #[cfg(feature = "tracing")]
+            tracer: self.tracer,
+            #[cfg(feature = "std")]
+            on_attempt: self.on_attempt,
+            progress: self.progress,
+            options: self.options,
+            state: self.state,
+        }
     }
 
-    #[test]
-    async fn test_retry() {
-        let result = always_error
-            .retry(ExponentialBuilder::default().with_min_delay(Duration::from_millis(1)))
-            .await;
-
-        assert!(result.is_err());
-        assert_eq!("test_query meets error", result.unwrap_err().to_string());
+    /// Set to give up once the same error occurs `times` times in a row.
+    ///
+    /// A deterministic failure that is classified retryable, like a malformed request, would be
+    /// retried until the backoff is exhausted. This stops retrying it early: every error is
+    /// compared with the previous one by the input function, and the error is returned once
+    /// `times` consecutive errors are the same, including the first one. A different error
+    /// starts counting again. For example, `times` of `3` retries the same error twice and
+    /// gives up on its third occurrence in a row.
+    ///
+    /// It works on top of the hook set by [`Retry::when`], [`Retry::when_elapsed`],
+    /// [`Retry::when_meta`], [`Retry::classify`] or [`Retry::decide`], and is kept when they
+    /// are set afterwards. The previous error is kept to be compared, so the error must
+    /// implement [`Clone`].
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if `times` is less than `2`, which would give up on the first
+    /// error without comparing it with anything.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use anyhow::Result;
+    /// use backon::ExponentialBuilder;
+    /// use backon::Retryable;
+    ///
+    /// async fn fetch() -> Result<String, String> {
+    ///     let resp = reqwest::get("https://www.rust-lang.org")
+    ///         .await
+    ///         .map_err(|e| e.to_string())?;
+    ///     resp.text().await.map_err(|e| e.to_string())
+    /// }
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<()> {
+    ///     let content = fetch
+    ///         .retry(ExponentialBuilder::default())
+    ///         // Stop after the same error three times in a row.
+    ///         .give_up_on_repeat(3, |a, b| a == b)
+    ///         .await
+    ///         .map_err(anyhow::Error::msg)?;
+    ///     println!("fetch succeeded: {}", content);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    #[allow(clippy::type_complexity)]
+    pub fn give_up_on_repeat<SN: FnMut(&E, &E) -> bool>(
+        self,
+        times: usize,
+        same: SN,
+    ) -> Retry<B, T, E, Fut, FutureFn, SF, GiveUpOnRepeat<RF, E, SN>, NF, AF, GF> {
+        assert!(
+            times >= 2,
+            "invalid repeat times {times}: must be at least 2"
+        );
+        Retry {
+            backoff: self.backoff,
+            retryable: GiveUpOnRepeat {
+                inner: self.retryable,
+                same,
+                times,
+                previous: None,
+                repeats: 0,
+            },
+            notify: self.notify,
+            on_giveup: self.on_giveup,
+            adjust: self.adjust,
+            future_fn: self.future_fn,
+            sleep_fn: self.sleep_fn,
+            #[cfg(feature = "tracing")]
+            tracer: self.tracer,
+            #[cfg(feature = "std")]
+            on_attempt: self.on_attempt,
+            progress: self.progress,
+            options: self.options,
+            state: self.state,
+        }
     }
 
-    #[test]
+    /// Set to only retry the errors raised before the operation took effect.
+    ///
+    /// Retrying is only safe for non-idempotent operations, like an HTTP `POST`, if the failed
+    /// attempt definitely had no effect. A connection refused before the request was sent is
+    /// safe to retry, but a response lost after the request was sent is not: the server may have
+    /// handled it, and retrying would apply it twice.
+    ///
+    /// The input function answers "did the operation definitely not take effect", which is
+    /// distinct from [`Retry::when`] answering "is the error transient". An error is only
+    /// retried if both return `true`. It works on top of the hook set by [`Retry::when`],
+    /// [`Retry::when_elapsed`], [`Retry::when_meta`], [`Retry::classify`] or [`Retry::decide`],
+    /// and is kept when they are set afterwards, so it holds in any call order.
+    ///
+    /// Idempotent operations don't need it, they are safe to retry whenever the error is
+    /// transient.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use anyhow::Result;
+    /// use backon::ExponentialBuilder;
+    /// use backon::Retryable;
+    ///
+    /// async fn create_order() -> reqwest::Result<String> {
+    ///     reqwest::Client::new()
+    ///         .post("https://example.com/orders")
+    ///         .body("{}")
+    ///         .send()
+    ///         .await?
+    ///         .error_for_status()?
+    ///         .text()
+    ///         .await
+    /// }
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<()> {
+    ///     let order = create_order
+    ///         .retry(ExponentialBuilder::default())
+    ///         .when(|e| e.is_connect() || e.is_timeout())
+    ///         // The request was never sent if the connection failed, a timeout may have
+    ///         // happened after the order was created.
+    ///         .safe_to_retry(|e| e.is_connect())
+    ///         .await?;
+    ///     println!("order created: {}", order);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    #[allow(clippy::type_complexity)]
+    pub fn safe_to_retry<SN: FnMut(&E) -> bool>(
+        self,
+        safe: SN,
+    ) -> Retry<B, T, E, Fut, FutureFn, SF, SafeToRetry<RF, SN>, NF, AF, GF> {
+        Retry {
+            backoff: self.backoff,
+            retryable: SafeToRetry {
+                inner: self.retryable,
+                safe,
+            },
+            notify: self.notify,
+            on_giveup: self.on_giveup,
+            adjust: self.adjust,
+            future_fn: self.future_fn,
+            sleep_fn: self.sleep_fn,
+            #[cfg(feature = "tracing")]
+            tracer: self.tracer,
+            #[cfg(feature = "std")]
+            on_attempt: self.on_attempt,
+            progress: self.progress,
+            options: self.options,
+            state: self.state,
+        }
+    }
+
+    /// Set to notify when an error is decided not to be retried.
+    ///
+    /// The input function will be invoked with the error classified non-retryable by the hook
+    /// set by [`Retry::when`], [`Retry::when_elapsed`], [`Retry::classify`] or
+    /// [`Retry::decide`], right before the retry gives up. It's not invoked when the retry
+    /// gives up on a retryable error, like when the backoff is exhausted, which tells permanent
+    /// failures apart from the transient ones that kept failing.
+    ///
+    /// It works on top of the hooks deciding whether to retry, including
+    /// [`Retry::give_up_on_repeat`] and [`Retry::safe_to_retry`], and is kept when the
+    /// conditions are set afterwards by [`Retry::when`] and friends. It's independent of
+    /// [`Retry::on_giveup`] and [`Retry::on_exhausted`], which are invoked as well.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::sync::atomic::AtomicUsize;
+    /// use std::sync::atomic::Ordering;
+    ///
+    /// use anyhow::Result;
+    /// use backon::ExponentialBuilder;
+    /// use backon::Retryable;
+    ///
+    /// static PERMANENT_FAILURES: AtomicUsize = AtomicUsize::new(0);
+    ///
+    /// async fn fetch() -> Result<String> {
+    ///     Ok(reqwest::get("https://www.rust-lang.org")
+    ///         .await?
+    ///         .text()
+    ///         .await?)
+    /// }
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<()> {
+    ///     let content = fetch
+    ///         .retry(ExponentialBuilder::default())
+    ///         .when(|e| e.to_string() == "EOF")
+    ///         .on_non_retryable(|_| {
+    ///             PERMANENT_FAILURES.fetch_add(1, Ordering::Relaxed);
+    ///         })
+    ///         .await?;
+    ///     println!("fetch succeeded: {}", content);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    #[allow(clippy::type_complexity)]
+    pub fn on_non_retryable<FN: FnMut(&E)>(
+        self,
+        f: FN,
+    ) -> Retry<B, T, E, Fut, FutureFn, SF, OnNonRetryable<RF, FN>, NF, AF, GF> {
+        Retry {
+            backoff: self.backoff,
+            retryable: OnNonRetryable {
+                inner: self.retryable,
+                f,
+            },
+            notify: self.notify,
+            on_giveup: self.on_giveup,
+            adjust: self.adjust,
+            future_fn: self.future_fn,
+            sleep_fn: self.sleep_fn,
+            #[cfg(feature = "tracing")]
+            tracer: self.tracer,
+            #[cfg(feature = "std")]
+            on_attempt: self.on_attempt,
+            progress: self.progress,
+            options: self.options,
+            state: self.state,
+        }
+    }
+
+    /// Set to notify for all retry attempts.
+    ///
+    /// When a retry happens, the input function will be invoked with the error and the sleep duration before pausing.
+    ///
+    /// If not specified, this operation does nothing.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use core::time::Duration;
+    ///
+    /// use anyhow::Result;
+    /// use backon::ExponentialBuilder;
+    /// use backon::Retryable;
+    ///
+    /// async fn fetch() -> Result<String> {
+    ///     Ok(reqwest::get("https://www.rust-lang.org")
+    ///         .await?
+    ///         .text()
+    ///         .await?)
+    /// }
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<()> {
+    ///     let content = fetch
+    ///         .retry(ExponentialBuilder::default())
+    ///         .notify(|err: &anyhow::Error, dur: Duration| {
+    ///             println!("retrying error {:?} with sleeping {:?}", err, dur);
+    ///         })
+    ///         .await?;
+    ///     println!("fetch succeeded: {}", content);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    #[allow(clippy::type_complexity)]
+    pub fn notify<NN: FnMut(&E, Duration)>(
+        self,
+        notify: NN,
+    ) -> Retry<B, T, E, Fut, FutureFn, SF, RF, NF::With<NN>, AF, GF>
+    where
+        NF: ReplaceNotify<E>,
+    {
+        Retry {
+            backoff: self.backoff,
+            retryable: self.retryable,
+            notify: self.notify.replace(notify),
+            on_giveup: self.on_giveup,
+            adjust: self.adjust,
+            sleep_fn: self.sleep_fn,
+            future_fn: self.future_fn,
+            #[cfg(feature = "tracing")]
+            tracer: self.tracer,
+            #[cfg(feature = "std")]
+            on_attempt: self.on_attempt,
+            progress: self.progress,
+            options: self.options,
+            state: self.state,
+        }
+    }
+
+    /// Set to notify for all retry attempts with the whole [`RetryContext`].
+    ///
+    /// It's invoked at the same time as [`Retry::notify`], but also receives the number of
+    /// the failed attempt and the time elapsed since the first attempt started.
+    ///
+    /// `notify` and `on_retry` share the same slot, setting one will replace the other.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use anyhow::Result;
+    /// use backon::ExponentialBuilder;
+    /// use backon::RetryContext;
+    /// use backon::Retryable;
+    ///
+    /// async fn fetch() -> Result<String> {
+    ///     Ok(reqwest::get("https://www.rust-lang.org")
+    ///         .await?
+    ///         .text()
+    ///         .await?)
+    /// }
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<()> {
+    ///     let content = fetch
+    ///         .retry(ExponentialBuilder::default())
+    ///         .on_retry(|ctx: RetryContext<'_, anyhow::Error>| {
+    ///             println!(
+    ///                 "attempt {} failed after {:?}: {:?}, retrying in {:?}",
+    ///                 ctx.attempt, ctx.elapsed, ctx.error, ctx.next_delay
+    ///             );
+    ///         })
+    ///         .await?;
+    ///     println!("fetch succeeded: {}", content);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    #[allow(clippy::type_complexity)]
+    pub fn on_retry<FN: FnMut(RetryContext<'_, E>)>(
+        mut self,
+        on_retry: FN,
+    ) -> Retry<B, T, E, Fut, FutureFn, SF, RF, NF::With<OnRetry<FN>>, AF, GF>
+    where
+        NF: ReplaceNotify<E>,
+    {
+        self.progress.measure_time();
+        Retry {
+            backoff: self.backoff,
+            retryable: self.retryable,
+            notify: self.notify.replace(OnRetry(on_retry)),
+            on_giveup: self.on_giveup,
+            adjust: self.adjust,
+            future_fn: self.future_fn,
+            sleep_fn: self.sleep_fn,
+            #[cfg(feature = "tracing")]
+            tracer: self.tracer,
+            #[cfg(feature = "std")]
+            on_attempt: self.on_attempt,
+            progress: self.progress,
+            options: self.options,
+            state: self.state,
+        }
+    }
+
+    /// Set to notify for all retry attempts, and decide whether to keep retrying.
+    ///
+    /// It's invoked at the same time as [`Retry::notify`], with the error and the sleep
+    /// duration. Returning [`ControlFlow::Break`] stops retrying right away: the retry gives up
+    /// without sleeping and returns the error, as if it's not retryable. It gives the notify
+    /// point a veto, for example to abort on a shutdown flag found while logging the retry,
+    /// without waiting for the next check by [`Retry::when`].
+    ///
+    /// With [`NotifyTiming::AfterSleep`], the hook is invoked after the sleep, so the retry gives
+    /// up after sleeping instead.
+    ///
+    /// `notify`, `on_retry` and `on_retry_ctl` share the same slot, setting one will replace the
+    /// others. The hook set by [`Retry::on_threshold`] is kept.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::ops::ControlFlow;
+    /// use std::sync::atomic::AtomicBool;
+    /// use std::sync::atomic::Ordering;
+    ///
+    /// use anyhow::Result;
+    /// use backon::ExponentialBuilder;
+    /// use backon::Retryable;
+    ///
+    /// static SHUTDOWN: AtomicBool = AtomicBool::new(false);
+    ///
+    /// async fn fetch() -> Result<String> {
+    ///     Ok(reqwest::get("https://www.rust-lang.org")
+    ///         .await?
+    ///         .text()
+    ///         .await?)
+    /// }
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<()> {
+    ///     let content = fetch
+    ///         .retry(ExponentialBuilder::default())
+    ///         .on_retry_ctl(|err, dur| {
+    ///             if SHUTDOWN.load(Ordering::Relaxed) {
+    ///                 return ControlFlow::Break(());
+    ///             }
+    ///             println!("retrying {:?} after {:?}", err, dur);
+    ///             ControlFlow::Continue(())
+    ///         })
+    ///         .await?;
+    ///     println!("fetch succeeded: {}", content);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    #[allow(clippy::type_complexity)]
+    pub fn on_retry_ctl<FN: FnMut(&E, Duration) -> ControlFlow<()>>(
+        self,
+        on_retry: FN,
+    ) -> Retry<B, T, E, Fut, FutureFn, SF, RF, NF::With<OnRetryCtl<FN>>, AF, GF>
+    where
+        NF: ReplaceNotify<E>,
+    {
+        Retry {
+            backoff: self.backoff,
+            retryable: self.retryable,
+            notify: self.notify.replace(OnRetryCtl(on_retry)),
+            on_giveup: self.on_giveup,
+            adjust: self.adjust,
+            future_fn: self.future_fn,
+            sleep_fn: self.sleep_fn,
+            #[cfg(feature = "tracing")]
+            tracer: self.tracer,
+            #[cfg(feature = "std")]
+            on_attempt: self.on_attempt,
+            progress: self.progress,
+            options: self.options,
+            state: self.state,
+        }
+    }
+
+    /// Set to notify once when the retry takes unusually long.
+    ///
+    /// The input function is invoked only once, with the error of the attempt numbered
+    /// `attempts` (starting from `1`) when a retry is about to happen after it. It tells a
+    /// retry that is going wrong from normal flakiness, for example to escalate the log level
+    /// without logging every attempt. It's not invoked if the retry succeeds or gives up
+    /// before that.
+    ///
+    /// It works on top of the hook set by [`Retry::notify`], [`Retry::on_retry`] or
+    /// [`Retry::on_retry_ctl`], and is kept when they are set afterwards.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use anyhow::Result;
+    /// use backon::ExponentialBuilder;
+    /// use backon::Retryable;
+    ///
+    /// async fn fetch() -> Result<String> {
+    ///     Ok(reqwest::get("https://www.rust-lang.org")
+    ///         .await?
+    ///         .text()
+    ///         .await?)
+    /// }
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<()> {
+    ///     let content = fetch
+    ///         .retry(ExponentialBuilder::default().with_max_times(10))
+    ///         .notify(|err, dur| println!("retrying {:?} after {:?}", err, dur))
+    ///         .on_threshold(5, |err| eprintln!("fetch keeps failing: {:?}", err))
+    ///         .await?;
+    ///     println!("fetch succeeded: {}", content);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    #[allow(clippy::type_complexity)]
+    pub fn on_threshold<TN: FnMut(&E)>(
+        self,
+        attempts: usize,
+        hook: TN,
+    ) -> Retry<B, T, E, Fut, FutureFn, SF, RF, OnThreshold<NF, TN>, AF, GF> {
+        Retry {
+            backoff: self.backoff,
+            retryable: self.retryable,
+            notify: OnThreshold {
+                inner: self.notify,
+                attempts,
+                hook,
+                fired: false,
+            },
+            on_giveup: self.on_giveup,
+            adjust: self.adjust,
+            future_fn: self.future_fn,
+            sleep_fn: self.sleep_fn,
+            #[cfg(feature = "tracing")]
+            tracer: self.tracer,
+            #[cfg(feature = "std")]
+            on_attempt: self.on_attempt,
+            progress: self.progress,
+            options: self.options,
+            state: self.state,
+        }
+    }
+
+    /// Set to notify when the retry gives up.
+    ///
+    /// The input function will be invoked exactly once with the last error and the number of
+    /// attempts made, when the error is not retryable or no more retry will happen. It's not invoked
+    /// if the retry succeeds.
+    ///
+    /// If not specified, this operation does nothing.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use anyhow::Result;
+    /// use backon::ExponentialBuilder;
+    /// use backon::Retryable;
+    ///
+    /// async fn fetch() -> Result<String> {
+    ///     Ok(reqwest::get("https://www.rust-lang.org")
+    ///         .await?
+    ///         .text()
+    ///         .await?)
+    /// }
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<()> {
+    ///     let content = fetch
+    ///         .retry(ExponentialBuilder::default())
+    ///         .on_giveup(|err: &anyhow::Error, attempts: usize| {
+    ///             println!("giving up after {} attempts: {:?}", attempts, err);
+    ///         })
+    ///         .await?;
+    ///     println!("fetch succeeded: {}", content);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn on_giveup<GN: FnMut(&E, usize)>(
+        self,
+        on_giveup: GN,
+    ) -> Retry<B, T, E, Fut, FutureFn, SF, RF, NF, AF, GN> {
+        Retry {
+            backoff: self.backoff,
+            retryable: self.retryable,
+            notify: self.notify,
+            on_giveup,
+            adjust: self.adjust,
+            future_fn: self.future_fn,
+            sleep_fn: self.sleep_fn,
+            #[cfg(feature = "tracing")]
+            tracer: self.tracer,
+            #[cfg(feature = "std")]
+            on_attempt: self.on_attempt,
+            progress: self.progress,
+            options: self.options,
+            state: self.state,
+        }
+    }
+
+    /// Set to adjust the delay of every retry attempt.
+    ///
+    /// When a retryable error happens, the input function will be invoked with the error and the
+    /// next delay given by the backoff, which is `None` if the backoff has been exhausted. The
+    /// returned delay is used instead: returning `Some` retries after that delay, and returning
+    /// `None` stops retrying and returns the error.
+    ///
+    /// The function is not invoked for non-retryable errors. The backoff is consumed as usual
+    /// no matter what delay is returned.
+    ///
+    /// If not specified, the delay given by the backoff is used as is.
+    ///
+    /// `adjust` and `backoff_for` share the same slot, setting one will replace the other.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use core::time::Duration;
+    ///
+    /// use anyhow::Result;
+    /// use backon::ExponentialBuilder;
+    /// use backon::Retryable;
+    ///
+    /// #[derive(Debug)]
+    /// struct TooManyRequests {
+    ///     retry_after: Option<Duration>,
+    /// }
+    ///
+    /// async fn fetch() -> Result<String, TooManyRequests> {
+    ///     Err(TooManyRequests {
+    ///         retry_after: Some(Duration::from_secs(10)),
+    ///     })
+    /// }
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<()> {
+    ///     let content = fetch
+    ///         .retry(ExponentialBuilder::default())
+    ///         // Respect the delay given by the server while the backoff is not exhausted.
+    ///         .adjust(|err, dur| dur.map(|dur| err.retry_after.unwrap_or(dur)))
+    ///         .await;
+    ///     println!("fetch result: {:?}", content);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn adjust<AN: FnMut(&E, Option<Duration>) -> Option<Duration>>(
+        self,
+        adjust: AN,
+    ) -> Retry<B, T, E, Fut, FutureFn, SF, RF, NF, AN, GF> {
+        Retry {
+            backoff: self.backoff,
+            retryable: self.retryable,
+            notify: self.notify,
+            on_giveup: self.on_giveup,
+            adjust,
+            future_fn: self.future_fn,
+            sleep_fn: self.sleep_fn,
+            #[cfg(feature = "tracing")]
+            tracer: self.tracer,
+            #[cfg(feature = "std")]
+            on_attempt: self.on_attempt,
+            progress: self.progress,
+            options: self.options,
+            state: self.state,
+        }
+    }
+
+    /// Set to decide whether to retry by taking the ownership of the error.
+    ///
+    /// The input function will be invoked with every error, and returns a [`RetryDecision`]:
+    ///
+    /// - [`RetryDecision::Retry`] retries after the given delay, or the delay given by the
+    ///   backoff if it's `None`. The backoff is consumed either way and still decides how many
+    ///   times to retry, the delay is only replaced.
+    /// - [`RetryDecision::Stop`] returns the error directly without consuming the backoff.
+    ///
+    /// It subsumes [`Retry::when`] and [`Retry::adjust`] for full control in one hook. The hooks
+    /// set by [`Retry::adjust`] and [`Retry::backoff_for`] are still invoked, but the delay
+    /// given by the decision takes precedence.
+    ///
+    /// `when` and `decide` share the same slot, setting one will replace the other.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use core::time::Duration;
+    ///
+    /// use anyhow::Result;
+    /// use backon::ExponentialBuilder;
+    /// use backon::RetryDecision;
+    /// use backon::Retryable;
+    ///
+    /// async fn fetch() -> Result<String, reqwest::Error> {
+    ///     reqwest::get("https://www.rust-lang.org")
+    ///         .await?
+    ///         .error_for_status()?
+    ///         .text()
+    ///         .await
+    /// }
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<()> {
+    ///     let content = fetch
+    ///         .retry(ExponentialBuilder::default())
+    ///         .decide(|e: reqwest::Error| match e.status() {
+    ///             // Wait longer if we are rate limited.
+    ///             Some(reqwest::StatusCode::TOO_MANY_REQUESTS) => RetryDecision::Retry {
+    ///                 error: e,
+    ///                 after: Some(Duration::from_secs(30)),
+    ///             },
+    ///             Some(status) if status.is_client_error() => RetryDecision::Stop(e),
+    ///             _ => RetryDecision::Retry {
+    ///                 error: e,
+    ///                 after: None,
+    ///             },
+    ///         })
+    ///         .await?;
+    ///     println!("fetch succeeded: {}", content);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    #[allow(clippy::type_complexity)]
+    pub fn decide<DN: FnMut(E) -> RetryDecision<E>>(
+        self,
+        decide: DN,
+    ) -> Retry<B, T, E, Fut, FutureFn, SF, RF::With<DecideFn<DN>>, NF, AF, GF>
+    where
+        RF: ReplaceDecide<E>,
+    {
+        Retry {
+            backoff: self.backoff,
+            retryable: self.retryable.replace(DecideFn(decide)),
+            notify: self.notify,
+            on_giveup: self.on_giveup,
+            adjust: self.adjust,
+            future_fn: self.future_fn,
+            sleep_fn: self.sleep_fn,
+            #[cfg(feature = "tracing")]
+            tracer: self.tracer,
+            #[cfg(feature = "std")]
+            on_attempt: self.on_attempt,
+            progress: self.progress,
+            options: self.options,
+            state: self.state,
+        }
+    }
+
+    /// Set to pick the delay of every retry attempt by the error.
+    ///
+    /// When a retryable error happens, the input function will be invoked with the error. The
+    /// returned delay is used instead of the delay given by the backoff, and returning `None`
+    /// falls back to the backoff. The backoff still decides how many times to retry.
+    ///
+    /// `adjust` and `backoff_for` share the same slot, setting one will replace the other.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use core::time::Duration;
+    ///
+    /// use anyhow::Result;
+    /// use backon::ExponentialBuilder;
+    /// use backon::Retryable;
+    ///
+    /// async fn fetch() -> Result<String> {
+    ///     Ok(reqwest::get("https://www.rust-lang.org")
+    ///         .await?
+    ///         .error_for_status()?
+    ///         .text()
+    ///         .await?)
+    /// }
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<()> {
+    ///     let content = fetch
+    ///         .retry(ExponentialBuilder::default())
+    ///         .backoff_for(|e: &anyhow::Error| {
+    ///             let status = e.downcast_ref::<reqwest::Error>()?.status()?;
+    ///             // Wait longer if we are rate limited.
+    ///             (status == reqwest::StatusCode::TOO_MANY_REQUESTS).then(|| Duration::from_secs(30))
+    ///         })
+    ///         .await?;
+    ///     println!("fetch succeeded: {}", content);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn backoff_for<FN: FnMut(&E) -> Option<Duration>>(
+        self,
+        backoff_for: FN,
+    ) -> Retry<B, T, E, Fut, FutureFn, SF, RF, NF, BackoffFor<FN>, GF> {
+        Retry {
+            backoff: self.backoff,
+            retryable: self.retryable,
+            notify: self.notify,
+            on_giveup: self.on_giveup,
+            adjust: BackoffFor(backoff_for),
+            future_fn: self.future_fn,
+            sleep_fn: self.sleep_fn,
+            #[cfg(feature = "tracing")]
+            tracer: self.tracer,
+            #[cfg(feature = "std")]
+            on_attempt: self.on_attempt,
+            progress: self.progress,
+            options: self.options,
+            state: self.state,
+        }
+    }
+
+    /// Set to pick the backoff of every retry attempt by the kind of the error.
+    ///
+    /// When a retryable error happens, `classify` is invoked with the error to get its key, and
+    /// the backoff registered with that key gives the delay, or stops retrying if it's
+    /// exhausted. Every keyed backoff is built once and keeps its own position, so the errors
+    /// of one kind don't advance the backoffs of the other kinds. Errors with a key that is
+    /// not registered use the backoff of the retry, which is advanced on every failure and
+    /// still limits the retries of all kinds: the retry stops once it's exhausted.
+    ///
+    /// `adjust`, `backoff_for` and `backoff_by_key` share the same slot, setting one will
+    /// replace the other.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use core::time::Duration;
+    ///
+    /// use anyhow::Result;
+    /// use backon::ConstantBuilder;
+    /// use backon::ExponentialBuilder;
+    /// use backon::Retryable;
+    ///
+    /// #[derive(PartialEq, Eq, Hash)]
+    /// enum Kind {
+    ///     RateLimited,
+    ///     Other,
+    /// }
+    ///
+    /// async fn fetch() -> Result<String> {
+    ///     Ok(reqwest::get("https://www.rust-lang.org")
+    ///         .await?
+    ///         .error_for_status()?
+    ///         .text()
+    ///         .await?)
+    /// }
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<()> {
+    ///     let content = fetch
+    ///         .retry(ExponentialBuilder::default())
+    ///         .backoff_by_key(
+    ///             |e: &anyhow::Error| match e
+    ///                 .downcast_ref::<reqwest::Error>()
+    ///                 .and_then(|e| e.status())
+    ///             {
+    ///                 Some(reqwest::StatusCode::TOO_MANY_REQUESTS) => Kind::RateLimited,
+    ///                 _ => Kind::Other,
+    ///             },
+    ///             [(
+    ///                 Kind::RateLimited,
+    ///                 ConstantBuilder::default()
+    ///                     .with_delay(Duration::from_secs(30))
+    ///                     .with_max_times(10),
+    ///             )],
+    ///         )
+    ///         .await?;
+    ///     println!("fetch succeeded: {}", content);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    #[cfg(feature = "std")]
+    #[allow(clippy::type_complexity)]
+    pub fn backoff_by_key<K, KB, CN>(
+        self,
+        classify: CN,
+        backoffs: impl IntoIterator<Item = (K, KB)>,
+    ) -> Retry<B, T, E, Fut, FutureFn, SF, RF, NF, BackoffByKey<K, KB::Backoff, CN>, GF>
+    where
+        K: Eq + core::hash::Hash,
+        KB: BackoffBuilder,
+        CN: FnMut(&E) -> K,
+    {
+        Retry {
+            backoff: self.backoff,
+            retryable: self.retryable,
+            notify: self.notify,
+            on_giveup: self.on_giveup,
+            adjust: BackoffByKey {
+                classify,
+                backoffs: backoffs
+                    .into_iter()
+                    .map(|(key, builder)| (key, builder.build()))
+                    .collect(),
+            },
+            future_fn: self.future_fn,
+            sleep_fn: self.sleep_fn,
+            #[cfg(feature = "tracing")]
+            tracer: self.tracer,
+            #[cfg(feature = "std")]
+            on_attempt: self.on_attempt,
+            progress: self.progress,
+            options: self.options,
+            state: self.state,
+        }
+    }
+
+    /// Set to map the error into another type once the retries are exhausted.
+    ///
+    /// The input function will be invoked with the last error and the number of attempts made,
+    /// only when the error is retryable but no more retry will happen, that is the backoff is
+    /// exhausted, the hook set by [`Retry::adjust`] returned `None`, or the max times, the retry
+    /// budget or the global retry limit is used up.
+    ///
+    /// Errors that are not retryable according to [`Retry::when`] are not considered exhausted,
+    /// they are converted with [`From`] instead.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use anyhow::Result;
+    /// use backon::ExponentialBuilder;
+    /// use backon::Retryable;
+    ///
+    /// #[derive(Debug)]
+    /// enum FetchError {
+    ///     GaveUp { attempts: usize, last: reqwest::Error },
+    ///     Other(reqwest::Error),
+    /// }
+    ///
+    /// impl From<reqwest::Error> for FetchError {
+    ///     fn from(err: reqwest::Error) -> Self {
+    ///         FetchError::Other(err)
+    ///     }
+    /// }
+    ///
+    /// async fn fetch() -> Result<String, reqwest::Error> {
+    ///     reqwest::get("https://www.rust-lang.org").await?.text().await
+    /// }
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<()> {
+    ///     let content = fetch
+    ///         .retry(ExponentialBuilder::default())
+    ///         .when(|e| e.is_timeout())
+    ///         .on_exhausted(|last, attempts| FetchError::GaveUp { attempts, last })
+    ///         .await;
+    ///     println!("fetch result: {:?}", content);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn on_exhausted<E2: From<E>, XF: FnMut(E, usize) -> E2>(
+        self,
+        on_exhausted: XF,
+    ) -> OnExhausted<B, T, E, Fut, FutureFn, SF, RF, NF, AF, GF, XF> {
+        OnExhausted {
+            retry: self,
+            on_exhausted,
+        }
+    }
+
+    /// Give up if the whole retry doesn't finish within given timeout.
+    ///
+    /// The timeout covers all attempts and sleeps together, it's the simplest way to bound how
+    /// long a retry may take. The retry is raced against a timer started on the first poll,
+    /// and fails with [`RetryTimedOut`](crate::RetryTimedOut) once the timer fires, dropping
+    /// the in-flight attempt or sleep.
+    ///
+    /// The timer is a sleep of the [`DefaultSleeper`], independent from the sleeper set by
+    /// [`Retry::sleep`], so a [`NoSleeper`](crate::NoSleeper), a scaled or a throttled sleeper
+    /// never shortens the timeout or holds a permit for it. Use [`Retry::timeout_with`] to time
+    /// the retry with another sleeper. Set it last, after all other options of the retry.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use core::time::Duration;
+    ///
+    /// use anyhow::Result;
+    /// use backon::ExponentialBuilder;
+    /// use backon::Retryable;
+    ///
+    /// async fn fetch() -> Result<String> {
+    ///     Ok(reqwest::get("https://www.rust-lang.org")
+    ///         .await?
+    ///         .text()
+    ///         .await?)
+    /// }
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<()> {
+    ///     let content = fetch
+    ///         .retry(ExponentialBuilder::default())
+    ///         .timeout(Duration::from_secs(30))
+    ///         .await?;
+    ///     println!("fetch succeeded: {}", content);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn timeout(self, timeout: Duration) -> Timeout<B, T, E, Fut, FutureFn, SF, RF, NF, AF, GF> {
+        Timeout {
+            retry: self,
+            timeout,
+            timer_fn: DefaultSleeper::default(),
+            timer: None,
+        }
+    }
+
+    /// Give up if the whole retry doesn't finish within given timeout, timed by given sleeper.
+    ///
+    /// It's the same as [`Retry::timeout`], except that the timer is a sleep of `timer`
+    /// instead of the [`DefaultSleeper`]. It's useful on the targets without a default sleeper,
+    /// or to time the retry with a fake clock in tests. The timer should really sleep, a sleeper
+    /// that completes right away times out the retry on the first pending attempt.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use core::time::Duration;
+    ///
+    /// use anyhow::Result;
+    /// use backon::ExponentialBuilder;
+    /// use backon::Retryable;
+    ///
+    /// async fn fetch() -> Result<String> {
+    ///     Ok(reqwest::get("https://www.rust-lang.org")
+    ///         .await?
+    ///         .text()
+    ///         .await?)
+    /// }
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<()> {
+    ///     let content = fetch
+    ///         .retry(ExponentialBuilder::default())
+    ///         .timeout_with(Duration::from_secs(30), tokio::time::sleep)
+    ///         .await?;
+    ///     println!("fetch succeeded: {}", content);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    #[allow(clippy::type_complexity)]
+    pub fn timeout_with<TS: Sleeper>(
+        self,
+        timeout: Duration,
+        timer: TS,
+    ) -> Timeout<B, T, E, Fut, FutureFn, SF, RF, NF, AF, GF, TS> {
+        Timeout {
+            retry: self,
+            timeout,
+            timer_fn: timer,
+            timer: None,
+        }
+    }
+
+    /// Return the [`RetryStats`] along with the result.
+    ///
+    /// The stats are returned whether the retry succeeded or not, so it's possible to tell
+    /// "slow because of backoff" (large [`RetryStats::slept`]) from "slow because the attempts
+    /// are slow".
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use anyhow::Result;
+    /// use backon::ExponentialBuilder;
+    /// use backon::Retryable;
+    ///
+    /// async fn fetch() -> Result<String> {
+    ///     Ok(reqwest::get("https://www.rust-lang.org")
+    ///         .await?
+    ///         .text()
+    ///         .await?)
+    /// }
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<()> {
+    ///     let (content, stats) = fetch
+    ///         .retry(ExponentialBuilder::default())
+    ///         .with_stats()
+    ///         .await;
+    ///     println!(
+    ///         "fetch finished after {} attempts, slept {:?}",
+    ///         stats.attempts, stats.slept
+    ///     );
+    ///     if stats.succeeded_attempt > Some(1) {
+    ///         println!("fetch succeeded only after retrying");
+    ///     }
+    ///     println!("fetch result: {:?}", content);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn with_stats(self) -> WithStats<B, T, E, Fut, FutureFn, SF, RF, NF, AF, GF> {
+        WithStats { retry: self }
+    }
+
+    /// Return a [`RetryError`] carrying the reason why the retry gave up.
+    ///
+    /// Every terminal condition of the retry is reported as a [`GiveUpReason`], like
+    /// [`GiveUpReason::Exhausted`] when no more retry is allowed, or
+    /// [`GiveUpReason::NonRetryable`] for the errors that are not retryable, so the callers
+    /// could branch on it without matching the error message. Set it last, after all other
+    /// options of the retry. To report [`GiveUpReason::Deadline`], call
+    /// [`Timeout::with_reason`] on the retry bounded by [`Retry::timeout`] instead.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use anyhow::Result;
+    /// use backon::ExponentialBuilder;
+    /// use backon::GiveUpReason;
+    /// use backon::Retryable;
+    ///
+    /// async fn fetch() -> Result<String, reqwest::Error> {
+    ///     reqwest::get("https://www.rust-lang.org").await?.text().await
+    /// }
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<()> {
+    ///     let content = fetch
+    ///         .retry(ExponentialBuilder::default())
+    ///         .when(|e| e.is_timeout())
+    ///         .with_reason()
+    ///         .await;
+    ///     match content {
+    ///         Ok(content) => println!("fetch succeeded: {}", content),
+    ///         Err(err) if err.reason() == GiveUpReason::Exhausted => {
+    ///             println!("fetch kept timing out after {} attempts", err.attempts())
+    ///         }
+    ///         Err(err) => return Err(err.into_inner().into()),
+    ///     }
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn with_reason(self) -> WithReason<Self> {
+        WithReason { inner: self }
+    }
+
+    /// Set the clock to measure the time elapsed since the retry started.
+    ///
+    /// The elapsed time is reported by [`RetryContext::elapsed`]. If not specified,
+    /// [`SystemClock`](crate::SystemClock) is used, except on wasm32 where no time is measured.
+    /// Use [`ManualClock`](crate::ManualClock) to control the time in tests, and
+    /// [`Retry::time_source`] to measure the time on wasm32.
+    ///
+    /// The time is only read if something consumes it: a clock set by this method or
+    /// [`Retry::time_source`], or a hook reading the elapsed time like [`Retry::on_retry`],
+    /// [`Retry::when_elapsed`], [`Retry::on_attempt_complete`] and [`Retry::min_gap`]. Other
+    /// retries never read the time. Set `.clock(SystemClock)` to measure the time of
+    /// [`Retry::state`] without such a hook.
+    ///
+    /// `clock` and `time_source` share the same slot, setting one will replace the other.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use core::future::ready;
+    ///
+    /// use anyhow::Result;
+    /// use backon::ExponentialBuilder;
+    /// use backon::ManualClock;
+    /// use backon::Retryable;
+    ///
+    /// async fn fetch() -> Result<String> {
+    ///     Ok(reqwest::get("https://www.rust-lang.org")
+    ///         .await?
+    ///         .text()
+    ///         .await?)
+    /// }
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<()> {
+    ///     let clock = ManualClock::new();
+    ///
+    ///     let content = fetch
+    ///         .retry(ExponentialBuilder::default())
+    ///         .clock(clock.clone())
+    ///         // Move the clock forward instead of sleeping.
+    ///         .sleep(move |dur| {
+    ///             clock.advance(dur);
+    ///             ready(())
+    ///         })
+    ///         .on_retry(|ctx| println!("retrying after {:?}", ctx.elapsed))
+    ///         .await?;
+    ///     println!("fetch succeeded: {}", content);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn clock(mut self, clock: impl crate::Clock + Send + Sync + 'static) -> Self {
+        self.progress.set_clock(clock);
+        self
+    }
+
+    /// Set a function returning the monotonic time elapsed since an arbitrary point, to measure
+    /// the time elapsed since the retry started.
+    ///
+    /// It's a narrower alternative to [`Retry::clock`] that doesn't involve [`Instant`], which
+    /// is not available on `wasm32-unknown-unknown`. Without it, no time is measured on wasm32,
+    /// so the features relying on the elapsed time, like [`Retry::when_elapsed`] and
+    /// [`RetryContext::elapsed`], see zero. Back it with `performance.now()` to measure the
+    /// time on wasm32.
+    ///
+    /// `time_source` and `clock` share the same slot, setting one will replace the other.
+    ///
+    /// [`Instant`]: std::time::Instant
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// use core::time::Duration;
+    ///
+    /// use anyhow::Result;
+    /// use backon::ExponentialBuilder;
+    /// use backon::Retryable;
+    ///
+    /// async fn fetch() -> Result<String> {
+    ///     Ok("hello, world!".to_string())
+    /// }
+    ///
+    /// async fn run() -> Result<()> {
+    ///     let content = fetch
+    ///         .retry(ExponentialBuilder::default())
+    ///         .time_source(|| {
+    ///             let millis = web_sys::window().unwrap().performance().unwrap().now();
+    ///             Duration::from_secs_f64(millis / 1000.0)
+    ///         })
+    ///         .when_elapsed(|_, elapsed| elapsed < Duration::from_secs(30))
+    ///         .await?;
+    ///     println!("fetch succeeded: {}", content);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn time_source(mut self, time_source: impl FnMut() -> Duration + Send + 'static) -> Self {
+        self.progress.set_time_source(time_source);
+        self
+    }
+
+    /// Take a snapshot of the resumable state of this retry.
+    ///
+    /// The retry can be dropped after taking the snapshot, and resumed later by
+    /// [`Retry::resume`]. Take it from a pinned retry between polls, for example when a pause
+    /// is requested.
+    ///
+    /// A retry is not resumable by itself: the sleep future it holds while sleeping is lost
+    /// once it's dropped. Executors that drop futures and recreate them should take a snapshot
+    /// before dropping the retry and resume a new one from it, which sleeps the rest of the
+    /// delay given by [`RetryState::sleep_left`] before the next attempt.
+    ///
+    /// The elapsed time and the rest of the sleep are only measured if the retry reads the
+    /// time, see [`Retry::clock`].
+    pub fn state(&self) -> RetryState {
+        self.progress.state()
+    }
+
+    /// Resume the retry from a [`RetryState`] taken from a paused retry.
+    ///
+    /// The attempts continue counting from the state, and the elapsed time includes the time
+    /// elapsed before the pause. The in-flight attempt of the paused retry is lost, so the first
+    /// attempt is made right away, after sleeping the rest of the sleep the paused retry was in,
+    /// if any. The backoff is not restored: build it with
+    /// `with_starting_attempt(state.attempts)` to continue at the same position.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use anyhow::Result;
+    /// use backon::ExponentialBuilder;
+    /// use backon::RetryState;
+    /// use backon::Retryable;
+    ///
+    /// async fn fetch() -> Result<String> {
+    ///     Ok(reqwest::get("https://www.rust-lang.org")
+    ///         .await?
+    ///         .text()
+    ///         .await?)
+    /// }
+    ///
+    /// async fn resume(state: RetryState) -> Result<String> {
+    ///     fetch
+    ///         .retry(ExponentialBuilder::default().with_starting_attempt(state.attempts))
+    ///         .resume(state)
+    ///         .await
+    /// }
+    /// ```
+    pub fn resume(mut self, state: RetryState) -> Self {
+        self.progress.resume(state);
+        self
+    }
+
+    /// Set to retry immediately without sleeping for some errors.
+    ///
+    /// When a retryable error matches the input function, the retry skips sleeping and makes the
+    /// next attempt right away, while other errors back off as usual. Hooks set by
+    /// [`Retry::notify`] and friends see a delay of [`Duration::ZERO`] for these retries.
+    ///
+    /// By default, the backoff is still consumed by every immediate retry, so the backoff keeps
+    /// limiting the number of retries, and the delays of later retries keep growing. Call
+    /// [`Retry::no_sleep_keeps_backoff`] to leave the backoff untouched instead.
+    ///
+    /// It works on top of the hook set by [`Retry::adjust`] or [`Retry::backoff_for`], call it
+    /// after them.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use anyhow::Result;
+    /// use backon::ExponentialBuilder;
+    /// use backon::Retryable;
+    ///
+    /// async fn read() -> Result<String> {
+    ///     Ok(reqwest::get("https://www.rust-lang.org")
+    ///         .await?
+    ///         .text()
+    ///         .await?)
+    /// }
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<()> {
+    ///     let content = read
+    ///         .retry(ExponentialBuilder::default())
+    ///         // Retry a stale read immediately, back off on other errors.
+    ///         .no_sleep_when(|e| e.to_string() == "stale read")
+    ///         .await?;
+    ///     println!("read succeeded: {}", content);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    #[allow(clippy::type_complexity)]
+    pub fn no_sleep_when<SN: FnMut(&E) -> bool>(
+        self,
+        no_sleep: SN,
+    ) -> Retry<B, T, E, Fut, FutureFn, SF, RF, NF, NoSleepWhen<AF, SN>, GF> {
+        Retry {
+            backoff: self.backoff,
+            retryable: self.retryable,
+            notify: self.notify,
+            on_giveup: self.on_giveup,
+            adjust: NoSleepWhen {
+                inner: self.adjust,
+                no_sleep,
+            },
+            future_fn: self.future_fn,
+            sleep_fn: self.sleep_fn,
+            #[cfg(feature = "tracing")]
+            tracer: self.tracer,
+            #[cfg(feature = "std")]
+            on_attempt: self.on_attempt,
+            progress: self.progress,
+            options: self.options,
+            state: self.state,
+        }
+    }
+
+    /// Don't consume the backoff for the immediate retries set by [`Retry::no_sleep_when`].
+    ///
+    /// The backoff is left untouched, so the next delay and the number of retries left stay the
+    /// same. Make sure the errors matched by `no_sleep_when` eventually go away, otherwise the
+    /// retry never ends.
+    pub fn no_sleep_keeps_backoff(mut self) -> Self {
+        self.options.no_sleep_keeps_backoff = true;
+        self
+    }
+
+    /// Set a kill switch that disables retrying while it's `true`.
+    ///
+    /// It's a policy toggle rather than a cancellation: share the same switch with all retries,
+    /// and flip it during an incident to stop them from adding load. The switch is checked after
+    /// every failed attempt, before backing off. If it's set, the error is returned right away
+    /// as if it were not retryable, so a retry started while the switch is set makes exactly one
+    /// attempt. An attempt in flight is never aborted.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::sync::atomic::AtomicBool;
+    /// use std::sync::Arc;
+    ///
+    /// use anyhow::Result;
+    /// use backon::ExponentialBuilder;
+    /// use backon::Retryable;
+    ///
+    /// async fn fetch() -> Result<String> {
+    ///     Ok(reqwest::get("https://www.rust-lang.org")
+    ///         .await?
+    ///         .text()
+    ///         .await?)
+    /// }
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<()> {
+    ///     // Usually a global flag controlled by the ops.
+    ///     let no_retry = Arc::new(AtomicBool::new(false));
+    ///
+    ///     let content = fetch
+    ///         .retry(ExponentialBuilder::default())
+    ///         .kill_switch(no_retry.clone())
+    ///         .await?;
+    ///     println!("fetch succeeded: {}", content);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn kill_switch(mut self, switch: std::sync::Arc<core::sync::atomic::AtomicBool>) -> Self {
+        self.options.kill_switch = Some(switch);
+        self
+    }
+
+    /// Record the statistics of this retry into shared [`RetryMetrics`](crate::RetryMetrics).
+    ///
+    /// The metrics are updated as the retry runs: every attempt started, every retry scheduled
+    /// and its delay, and whether the retry succeeded or failed in the end. Share the same
+    /// metrics with all retries of a policy to get the cumulative numbers.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::sync::Arc;
+    ///
+    /// use anyhow::Result;
+    /// use backon::ExponentialBuilder;
+    /// use backon::RetryMetrics;
+    /// use backon::Retryable;
+    ///
+    /// async fn fetch() -> Result<String> {
+    ///     Ok(reqwest::get("https://www.rust-lang.org")
+    ///         .await?
+    ///         .text()
+    ///         .await?)
+    /// }
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<()> {
+    ///     let metrics = Arc::new(RetryMetrics::new());
+    ///
+    ///     let content = fetch
+    ///         .retry(ExponentialBuilder::default())
+    ///         .metrics(metrics.clone())
+    ///         .await?;
+    ///     println!("fetch succeeded after {} attempts", metrics.attempts());
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn metrics(mut self, metrics: std::sync::Arc<crate::RetryMetrics>) -> Self {
+        self.progress.set_metrics(metrics);
+        self
+    }
+
+    /// Limit the retries with a shared [`RetryBudget`](crate::RetryBudget).
+    ///
+    /// Before every retry, a token is withdrawn from the budget, and the retry gives up right
+    /// away with the last error if no token is left. No token is withdrawn for a retry refused by
+    /// the max times or the [global retry limit](Retry::global_max_retries). Every success of the
+    /// retry refills the budget. Share the same budget with all retries calling a service, so the retries stay
+    /// a fraction of the requests and don't pile up while the service is down.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::sync::Arc;
+    ///
+    /// use anyhow::Result;
+    /// use backon::ExponentialBuilder;
+    /// use backon::RetryBudget;
+    /// use backon::Retryable;
+    ///
+    /// async fn fetch() -> Result<String> {
+    ///     Ok(reqwest::get("https://www.rust-lang.org")
+    ///         .await?
+    ///         .text()
+    ///         .await?)
+    /// }
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<()> {
+    ///     // Allow a retry for every 5 successes.
+    ///     let budget = Arc::new(RetryBudget::new(10, 0.2));
+    ///
+    ///     let content = fetch
+    ///         .retry(ExponentialBuilder::default())
+    ///         .budget(budget)
+    ///         .await?;
+    ///     println!("fetch succeeded: {}", content);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn budget(mut self, budget: std::sync::Arc<crate::RetryBudget>) -> Self {
+        self.options.budget = Some(budget);
+        self
+    }
+
+    /// Heal given [`AdaptiveBuilder`](crate::AdaptiveBuilder) when this retry succeeds.
+    ///
+    /// The success moves the position of the adaptive backoff one step back, so the following
+    /// retries start from shorter delays. It's usually the builder this retry is built from,
+    /// see [`AdaptiveBuilder`](crate::AdaptiveBuilder) for an example.
+    #[cfg(feature = "std")]
+    pub fn heal<AB>(mut self, backoff: &crate::AdaptiveBuilder<AB>) -> Self {
+        self.options.heal = Some(backoff.position_handle());
+        self
+    }
+
+    /// Cap the total number of retries with a shared [`GlobalRetryLimit`](crate::GlobalRetryLimit).
+    ///
+    /// Before every retry, one is taken from the limit, and the retry gives up right away with
+    /// the last error once the limit is reached. Share the same limit with all retries of a
+    /// policy to cap the retries they make in total, separately from the max times of every
+    /// backoff.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::sync::Arc;
+    ///
+    /// use anyhow::Result;
+    /// use backon::ExponentialBuilder;
+    /// use backon::GlobalRetryLimit;
+    /// use backon::Retryable;
+    ///
+    /// async fn fetch() -> Result<String> {
+    ///     Ok(reqwest::get("https://www.rust-lang.org")
+    ///         .await?
+    ///         .text()
+    ///         .await?)
+    /// }
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<()> {
+    ///     let limit = Arc::new(GlobalRetryLimit::new(1000));
+    ///
+    ///     let content = fetch
+    ///         .retry(ExponentialBuilder::default())
+    ///         .global_max_retries(limit)
+    ///         .await?;
+    ///     println!("fetch succeeded: {}", content);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn global_max_retries(mut self, limit: std::sync::Arc<crate::GlobalRetryLimit>) -> Self {
+        self.options.global_limit = Some(limit);
+        self
+    }
+
+    /// Set when the hook set by [`Retry::notify`] and friends is invoked.
+    ///
+    /// By default, it's invoked with [`NotifyTiming::BeforeSleep`] when the sleep starts. Use
+    /// [`NotifyTiming::AfterSleep`] to invoke it once the sleep is over, right before the next
+    /// attempt starts, so a timestamp logged by the hook reflects the actual retry moment.
+    /// The hook receives the same delay in both cases, while [`RetryContext::elapsed`] is
+    /// measured when the hook is invoked.
+    ///
+    /// Retries that don't sleep, like the ones set by [`Retry::no_sleep_when`], are notified
+    /// right away in both cases.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use anyhow::Result;
+    /// use backon::ExponentialBuilder;
+    /// use backon::NotifyTiming;
+    /// use backon::Retryable;
+    ///
+    /// async fn fetch() -> Result<String> {
+    ///     Ok(reqwest::get("https://www.rust-lang.org")
+    ///         .await?
+    ///         .text()
+    ///         .await?)
+    /// }
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<()> {
+    ///     let content = fetch
+    ///         .retry(ExponentialBuilder::default())
+    ///         .notify(|err, _| println!("retrying now after {:?}", err))
+    ///         .notify_timing(NotifyTiming::AfterSleep)
+    ///         .await?;
+    ///     println!("fetch succeeded: {}", content);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn notify_timing(mut self, timing: NotifyTiming) -> Self {
+        self.options.notify_timing = timing;
+        self
+    }
+
+    /// Set whether to yield to the executor before retrying with a zero delay.
+    ///
+    /// When the delay is zero and the errors are immediate, the retry could loop without ever
+    /// returning to the executor, starving the other tasks on the same thread. By default, the
+    /// retry returns `Poll::Pending` once and wakes itself up before a zero delay, including the
+    /// immediate retries set by [`Retry::no_sleep_when`]. The sleeper is still called with the
+    /// zero delay afterwards.
+    ///
+    /// Pass `false` to retry right away without yielding.
+    pub fn yield_on_zero_delay(mut self, yield_on_zero_delay: bool) -> Self {
+        self.options.yield_on_zero_delay = yield_on_zero_delay;
+        self
+    }
+
+    /// Set the number of retries to make right away before backing off.
+    ///
+    /// The first `n` retryable errors are retried with a zero delay to absorb transient blips,
+    /// then the backoff takes over from its first delay, unlike a backoff with a zero
+    /// `min_delay` whose whole curve is shifted. Hooks set by [`Retry::notify`] and friends see
+    /// a delay of [`Duration::ZERO`] for these retries.
+    ///
+    /// The immediate retries don't consume the backoff, so they are made on top of its
+    /// `max_times`: with `immediate_retries(2)` and a backoff of `max_times(3)`, there are at
+    /// most `1 + 2 + 3` attempts.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use anyhow::Result;
+    /// use backon::ExponentialBuilder;
+    /// use backon::Retryable;
+    ///
+    /// async fn fetch() -> Result<String> {
+    ///     Ok(reqwest::get("https://www.rust-lang.org")
+    ///         .await?
+    ///         .text()
+    ///         .await?)
+    /// }
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<()> {
+    ///     let content = fetch
+    ///         .retry(ExponentialBuilder::default())
+    ///         .immediate_retries(2)
+    ///         .await?;
+    ///     println!("fetch succeeded: {}", content);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn immediate_retries(mut self, n: usize) -> Self {
+        self.options.immediate_retries = n;
+        self
+    }
+
+    /// Retry the first error without checking whether it's retryable.
+    ///
+    /// The hook set by [`Retry::when`] or its alternatives is skipped on the first failure, so
+    /// the first error is always retried, which absorbs the errors of a cold start like a
+    /// connection that isn't established yet. The following errors are checked as usual. The
+    /// first retry still takes its delay from the backoff, and is not made if the backoff gives
+    /// no retry at all.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use anyhow::Result;
+    /// use backon::ExponentialBuilder;
+    /// use backon::Retryable;
+    ///
+    /// async fn fetch() -> Result<String> {
+    ///     Ok(reqwest::get("https://www.rust-lang.org")
+    ///         .await?
+    ///         .text()
+    ///         .await?)
+    /// }
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<()> {
+    ///     let content = fetch
+    ///         .retry(ExponentialBuilder::default())
+    ///         .when(|e| e.to_string() == "EOF")
+    ///         .always_retry_first()
+    ///         .await?;
+    ///     println!("fetch succeeded: {}", content);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn always_retry_first(mut self) -> Self {
+        self.options.always_retry_first = true;
+        self
+    }
+
+    /// Keep at least `gap` between the end of an attempt and the start of the next one.
+    ///
+    /// Some services ask for a minimum gap between calls on top of the backoff. If the delay
+    /// given by the backoff is shorter, the sleep is extended to the rest of the gap. Unlike the
+    /// `min_delay` of the backoffs, the gap is measured from the completion of the failed
+    /// attempt with the clock set by [`Retry::clock`] or [`Retry::time_source`], so the time
+    /// spent in the hooks counts towards it, and it applies to the retries made without
+    /// sleeping, like [`Retry::immediate_retries`], as well. Without a clock, like on wasm32,
+    /// the delays slept since the completion are counted instead.
+    ///
+    /// The extended sleeps are performed by the configured sleeper, and counted in
+    /// [`RetryStats::slept`], but the hooks set by [`Retry::notify`] and friends see the delay
+    /// given by the backoff.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use core::time::Duration;
+    ///
+    /// use anyhow::Result;
+    /// use backon::ExponentialBuilder;
+    /// use backon::Retryable;
+    ///
+    /// async fn fetch() -> Result<String> {
+    ///     Ok(reqwest::get("https://www.rust-lang.org")
+    ///         .await?
+    ///         .text()
+    ///         .await?)
+    /// }
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<()> {
+    ///     let content = fetch
+    ///         .retry(ExponentialBuilder::default().with_min_delay(Duration::from_millis(10)))
+    ///         // Call at most once every 500ms.
+    ///         .min_gap(Duration::from_millis(500))
+    ///         .await?;
+    ///     println!("fetch succeeded: {}", content);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn min_gap(mut self, gap: Duration) -> Self {
+        self.options.min_gap = Some(gap);
+        self.progress.measure_time();
+        self
+    }
+
+    /// Override the max times of retries for this retry only.
+    ///
+    /// It takes precedence over the `max_times` of the backoff, so a shared policy could be
+    /// used with fewer or more retries at a single call site. With a lower `n`, the retry gives
+    /// up after `n` retries. With a higher `n`, the last delay given by the backoff is repeated
+    /// once the backoff runs out. All retries count towards `n`, including the ones made
+    /// without sleeping like [`Retry::immediate_retries`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use anyhow::Result;
+    /// use backon::ExponentialBuilder;
+    /// use backon::Retryable;
+    ///
+    /// const POLICY: ExponentialBuilder = ExponentialBuilder::new().with_max_times(5);
+    ///
+    /// async fn fetch() -> Result<String> {
+    ///     Ok(reqwest::get("https://www.rust-lang.org")
+    ///         .await?
+    ///         .text()
+    ///         .await?)
+    /// }
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<()> {
+    ///     let content = fetch
+    ///         .retry(POLICY)
+    ///         // This call site can't wait long, retry at most once.
+    ///         .override_max_times(1)
+    ///         .await?;
+    ///     println!("fetch succeeded: {}", content);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn override_max_times(mut self, n: usize) -> Self {
+        self.options.max_times = Some(n);
+        self
+    }
+
+    /// Set the max number of attempts that could be started in a single poll, `32` by default.
+    ///
+    /// If the attempts fail and the sleeps complete immediately, the retry could make many
+    /// attempts within a single poll and block the executor. Once `n` attempts are started in
+    /// a poll, the retry returns `Poll::Pending` and wakes itself up to continue in the next
+    /// poll. It works along with [`Retry::yield_on_zero_delay`], which yields before every
+    /// zero delay.
+    ///
+    /// Pass `usize::MAX` to never yield. `0` is treated as `1`, so an attempt is always made.
+    pub fn attempts_per_poll(mut self, n: usize) -> Self {
+        self.options.attempts_per_poll = n.max(1);
+        self
+    }
+
+    /// Set a delay before the first attempt.
+    ///
+    /// The delay is performed by the configured sleeper without consuming the backoff. It's useful
+    /// when the resource is known to be not ready immediately.
+    ///
+    /// The initial delay counts towards the time elapsed since the retry started.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use core::time::Duration;
+    ///
+    /// use anyhow::Result;
+    /// use backon::ConstantBuilder;
+    /// use backon::Retryable;
+    ///
+    /// async fn poll_job() -> Result<String> {
+    ///     Ok(reqwest::get("https://www.rust-lang.org")
+    ///         .await?
+    ///         .text()
+    ///         .await?)
+    /// }
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<()> {
+    ///     let content = poll_job
+    ///         .retry(ConstantBuilder::default())
+    ///         .initial_delay(Duration::from_secs(5))
+    ///         .await?;
+    ///     println!("job finished: {}", content);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn initial_delay(mut self, delay: Duration) -> Self {
+        self.options.initial_delay = Some(delay);
+        self
+    }
+
+    /// Sleep a random delay within `[0, spread)` before the first attempt.
+    ///
+    /// When many clients start at the same instant, like after a deploy, their retries stay
+    /// aligned. The random offset spreads their first attempts out, independent of the backoff.
+    /// It's added on top of the [`Retry::initial_delay`] if both are set, and doesn't consume
+    /// the backoff.
+    ///
+    /// The offset is drawn from a generator seeded like the jitter of the builtin backoffs,
+    /// use [`Retry::spread_seed`] to make it reproducible.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use core::time::Duration;
+    ///
+    /// use anyhow::Result;
+    /// use backon::ExponentialBuilder;
+    /// use backon::Retryable;
+    ///
+    /// async fn fetch() -> Result<String> {
+    ///     Ok(reqwest::get("https://www.rust-lang.org")
+    ///         .await?
+    ///         .text()
+    ///         .await?)
+    /// }
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<()> {
+    ///     let content = fetch
+    ///         .retry(ExponentialBuilder::default())
+    ///         .spread(Duration::from_secs(1))
+    ///         .await?;
+    ///     println!("fetch succeeded: {}", content);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn spread(mut self, spread: Duration) -> Self {
+        self.options.spread = Some(spread);
+        self
+    }
+
+    /// Set the seed value for the random offset set by [`Retry::spread`].
+    ///
+    /// If no seed is given, a random seed is used in std and default seed is used in no_std.
+    pub fn spread_seed(mut self, seed: u64) -> Self {
+        self.options.spread_seed = Some(seed);
+        self
+    }
+
+    /// Instrument this retry with [`tracing`].
+    ///
+    /// A span is entered every time this retry is polled, and an event carrying the attempt
+    /// number, the error and the upcoming delay is emitted at given `level` for every failed attempt.
+    ///
+    /// The fields of the events follow the OpenTelemetry semantic conventions, so they map onto
+    /// the attributes of an OpenTelemetry exporter as they are:
+    ///
+    /// - `retry.attempt`: the number of the failed attempt, starting from `1`.
+    /// - `retry.max_attempts`: the number of attempts the backoff allows, if it's known.
+    /// - `retry.delay_ms`: the delay before the next attempt in milliseconds, absent when giving up.
+    /// - `error.type`: the type name of the error.
+    /// - `exception.message`: the error message.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use anyhow::Result;
+    /// use backon::ExponentialBuilder;
+    /// use backon::Retryable;
+    ///
+    /// async fn fetch() -> Result<String> {
+    ///     Ok(reqwest::get("https://www.rust-lang.org")
+    ///         .await?
+    ///         .text()
+    ///         .await?)
+    /// }
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<()> {
+    ///     let content = fetch
+    ///         .retry(ExponentialBuilder::default())
+    ///         .trace(tracing::Level::WARN)
+    ///         .await?;
+    ///     println!("fetch succeeded: {}", content);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    #[cfg(feature = "tracing")]
+    pub fn trace(mut self, level: tracing::Level) -> Self
+    where
+        E: fmt::Display,
+    {
+        self.tracer = Some(crate::trace::Tracer::new(
+            level,
+            self.backoff.remaining().map(|n| n.saturating_add(1)),
+        ));
+        self
+    }
+
+    /// Set to observe every attempt when it completes.
+    ///
+    /// The input function is invoked when the future of every attempt resolves, with the
+    /// number of the attempt (starting from `1`), how long the attempt took, and a reference to
+    /// its result. Unlike [`RetryStats`] which sums up the whole retry, it tells the latency of
+    /// every single attempt apart from the time slept in backoff, for example to record it in
+    /// a histogram.
+    ///
+    /// The duration is measured from the start of the attempt to its resolution, with the
+    /// clock set by [`Retry::clock`] or [`Retry::time_source`]. Without a clock, like on
+    /// wasm32, it's always [`Duration::ZERO`].
+    ///
+    /// The hook is shared by the clones of the retry.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use anyhow::Result;
+    /// use backon::ExponentialBuilder;
+    /// use backon::Retryable;
+    ///
+    /// async fn fetch() -> Result<String> {
+    ///     Ok(reqwest::get("https://www.rust-lang.org")
+    ///         .await?
+    ///         .text()
+    ///         .await?)
+    /// }
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<()> {
+    ///     let content = fetch
+    ///         .retry(ExponentialBuilder::default())
+    ///         .on_attempt_complete(|attempt, took, result: &Result<String>| {
+    ///             println!("attempt {} took {:?}, ok: {}", attempt, took, result.is_ok());
+    ///         })
+    ///         .await?;
+    ///     println!("fetch succeeded: {}", content);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn on_attempt_complete(
+        mut self,
+        f: impl FnMut(usize, Duration, &Result<T, E>) + Send + 'static,
+    ) -> Self {
+        self.on_attempt = Some(std::sync::Arc::new(std::sync::Mutex::new(f)));
+        self.progress.measure_time();
+        self
+    }
+
+    /// Erase the type of this retry by boxing it.
+    ///
+    /// The type of a retry is deeply generic and usually unnameable, which makes it hard to
+    /// store retries of different operations in a collection, like a task queue. The boxed
+    /// retry has a type that could be named: `Pin<Box<dyn Future<Output = Result<T, E>> + Send + 'a>>`.
+    ///
+    /// The retry must be `Send`, which requires its operation, hooks, sleeper and the futures
+    /// they create to be `Send`. Use [`Retry::boxed_local`] otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::future::Future;
+    /// use std::pin::Pin;
+    ///
+    /// use anyhow::Result;
+    /// use backon::ExponentialBuilder;
+    /// use backon::Retryable;
+    ///
+    /// async fn fetch() -> Result<String> {
+    ///     Ok(reqwest::get("https://www.rust-lang.org")
+    ///         .await?
+    ///         .text()
+    ///         .await?)
+    /// }
+    ///
+    /// async fn ping() -> Result<String> {
+    ///     Ok("pong".to_string())
+    /// }
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<()> {
+    ///     let tasks: Vec<Pin<Box<dyn Future<Output = Result<String>> + Send>>> = vec![
+    ///         fetch.retry(ExponentialBuilder::default()).boxed(),
+    ///         ping.retry(ExponentialBuilder::default()).when(|_| false).boxed(),
+    ///     ];
+    ///     for task in tasks {
+    ///         println!("task finished: {:?}", task.await);
+    ///     }
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn boxed<'a>(self) -> Pin<std::boxed::Box<dyn Future<Output = Result<T, E>> + Send + 'a>>
+    where
+        Self: Future<Output = Result<T, E>> + Send + 'a,
+    {
+        std::boxed::Box::pin(self)
+    }
+
+    /// Erase the type of this retry by boxing it, without requiring it to be `Send`.
+    ///
+    /// It's the same as [`Retry::boxed`] but for retries that are not `Send`, like the ones
+    /// running on wasm32 or a single threaded executor.
+    #[cfg(feature = "std")]
+    pub fn boxed_local<'a>(self) -> Pin<std::boxed::Box<dyn Future<Output = Result<T, E>> + 'a>>
+    where
+        Self: Future<Output = Result<T, E>> + 'a,
+    {
+        std::boxed::Box::pin(self)
+    }
+}
+
+impl<B, T, E, Fut, FutureFn, SF, RF, NF, AF, GF> Retry<B, T, E, Fut, FutureFn, SF, RF, NF, AF, GF>
+where
+    B: Backoff,
+    Fut: Future<Output = Result<T, E>>,
+    FutureFn: FnMut() -> Fut,
+    SF: Sleeper + Clone,
+    RF: Decide<E>,
+    NF: Notify<E>,
+    AF: Adjust<E>,
+    GF: FnMut(&E, usize),
+{
+    /// Limit how many retries sleep at the same time.
+    ///
+    /// Before every sleep, a permit is acquired from `permits` and held until the sleep is over.
+    /// Sharing the same permit source, like a semaphore, across retries caps the number of
+    /// retries in flight process-wide, so a flood of failures doesn't stampede the backend once
+    /// their delays elapse. The delay starts once the permit is acquired.
+    ///
+    /// The permits wrap the current sleeper, so call this after [`Retry::sleep`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::future::Future;
+    /// use std::pin::Pin;
+    /// use std::sync::Arc;
+    ///
+    /// use anyhow::Result;
+    /// use backon::ExponentialBuilder;
+    /// use backon::PermitSource;
+    /// use backon::Retryable;
+    /// use tokio::sync::OwnedSemaphorePermit;
+    /// use tokio::sync::Semaphore;
+    ///
+    /// #[derive(Clone)]
+    /// struct Permits(Arc<Semaphore>);
+    ///
+    /// impl PermitSource for Permits {
+    ///     type Permit = OwnedSemaphorePermit;
+    ///     type Acquire = Pin<Box<dyn Future<Output = OwnedSemaphorePermit> + Send>>;
+    ///
+    ///     fn acquire(&self) -> Self::Acquire {
+    ///         let semaphore = self.0.clone();
+    ///         Box::pin(async move {
+    ///             semaphore
+    ///                 .acquire_owned()
+    ///                 .await
+    ///                 .expect("semaphore must not be closed")
+    ///         })
+    ///     }
+    /// }
+    ///
+    /// async fn fetch() -> Result<String> {
+    ///     Ok(reqwest::get("https://www.rust-lang.org")
+    ///         .await?
+    ///         .text()
+    ///         .await?)
+    /// }
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<()> {
+    ///     let permits = Permits(Arc::new(Semaphore::new(16)));
+    ///
+    ///     let content = fetch
+    ///         .retry(ExponentialBuilder::default())
+    ///         .throttle(permits.clone())
+    ///         .await?;
+    ///     println!("fetch succeeded: {}", content);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    #[allow(clippy::type_complexity)]
+    pub fn throttle<P: PermitSource>(
+        self,
+        permits: P,
+    ) -> Retry<B, T, E, Fut, FutureFn, Throttled<SF, P>, RF, NF, AF, GF> {
+        Retry {
+            backoff: self.backoff,
+            retryable: self.retryable,
+            notify: self.notify,
+            on_giveup: self.on_giveup,
+            adjust: self.adjust,
+            future_fn: self.future_fn,
+            sleep_fn: Throttled::new(self.sleep_fn, permits),
+            #[cfg(feature = "tracing")]
+            tracer: self.tracer,
+            #[cfg(feature = "std")]
+            on_attempt: self.on_attempt,
+            progress: self.progress,
+            options: self.options,
+            state: State::Idle,
+        }
+    }
+}
+
+/// Show the backoff, the settings and the number of attempts made, the hooks are omitted.
+impl<B, T, E, Fut, FutureFn, SF, RF, NF, AF, GF> fmt::Debug
+    for Retry<B, T, E, Fut, FutureFn, SF, RF, NF, AF, GF>
+where
+    B: Backoff + fmt::Debug,
+    Fut: Future<Output = Result<T, E>>,
+    FutureFn: FnMut() -> Fut,
+    SF: MaybeTrySleeper,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Retry")
+            .field("backoff", &self.backoff)
+            .field("options", &self.options)
+            .field("attempts", &self.progress.attempts)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Cloning a retry clones its configuration into a fresh retry that has not started yet.
+///
+/// It allows defining one policy and spawning many retries from it. The in-flight future, the
+/// number of attempts and the time elapsed are not cloned. The backoff is cloned as is, so clone
+/// the retry before polling it to give every retry the full sequence.
+impl<B, T, E, Fut, FutureFn, SF, RF, NF, AF, GF> Clone
+    for Retry<B, T, E, Fut, FutureFn, SF, RF, NF, AF, GF>
+where
+    B: Backoff + Clone,
+    Fut: Future<Output = Result<T, E>>,
+    FutureFn: FnMut() -> Fut + Clone,
+    SF: MaybeTrySleeper + Clone,
+    RF: Clone,
+    NF: Clone,
+    AF: Clone,
+    GF: Clone,
+{
+    fn clone(&self) -> Self {
+        Retry {
+            backoff: self.backoff.clone(),
+            retryable: self.retryable.clone(),
+            notify: self.notify.clone(),
+            on_giveup: self.on_giveup.clone(),
+            adjust: self.adjust.clone(),
+            future_fn: self.future_fn.clone(),
+            sleep_fn: self.sleep_fn.clone(),
+            #[cfg(feature = "tracing")]
+            tracer: self.tracer.clone(),
+            #[cfg(feature = "std")]
+            on_attempt: self.on_attempt.clone(),
+            progress: self.progress.fresh(),
+            options: self.options.clone(),
+            state: State::Idle,
+        }
+    }
+}
+
+/// Options holds the settings of a retry that don't affect its type.
+#[derive(Debug, Clone)]
+struct Options {
+    initial_delay: Option<Duration>,
+    spread: Option<Duration>,
+    spread_seed: Option<u64>,
+    no_sleep_keeps_backoff: bool,
+    yield_on_zero_delay: bool,
+    immediate_retries: usize,
+    always_retry_first: bool,
+    attempts_per_poll: usize,
+    notify_timing: NotifyTiming,
+    min_gap: Option<Duration>,
+    max_times: Option<usize>,
+    #[cfg(feature = "std")]
+    kill_switch: Option<std::sync::Arc<core::sync::atomic::AtomicBool>>,
+    #[cfg(feature = "std")]
+    budget: Option<std::sync::Arc<crate::RetryBudget>>,
+    #[cfg(feature = "std")]
+    global_limit: Option<std::sync::Arc<crate::GlobalRetryLimit>>,
+    #[cfg(feature = "std")]
+    heal: Option<std::sync::Arc<core::sync::atomic::AtomicUsize>>,
+}
+
+impl Options {
+    /// Whether the kill switch is set to disable retrying.
+    fn killed(&self) -> bool {
+        #[cfg(feature = "std")]
+        if let Some(switch) = &self.kill_switch {
+            return switch.load(core::sync::atomic::Ordering::Relaxed);
+        }
+        false
+    }
+
+    /// Record a success into the retry budget.
+    fn deposit_budget(&self) {
+        #[cfg(feature = "std")]
+        if let Some(budget) = &self.budget {
+            budget.deposit();
+        }
+    }
+
+    /// Move the position of the adaptive backoff one step back after a success.
+    fn heal(&self) {
+        #[cfg(feature = "std")]
+        if let Some(position) = &self.heal {
+            crate::backoff::heal(position);
+        }
+    }
+
+    /// Take the next delay from the backoff.
+    ///
+    /// If the max times is overridden, the last delay is repeated after the backoff runs out.
+    fn next_delay(&self, backoff: &mut impl Backoff, progress: &mut Progress) -> Option<Duration> {
+        match backoff.next() {
+            Some(dur) => {
+                progress.last_delay = Some(dur);
+                Some(dur)
+            }
+            None if self.max_times.is_some() => Some(progress.last_delay.unwrap_or_default()),
+            None => None,
+        }
+    }
+
+    /// Withdraw a retry from the retry budget, return `false` if there is no budget left.
+    fn withdraw_budget(&self) -> bool {
+        #[cfg(feature = "std")]
+        if let Some(budget) = &self.budget {
+            return budget.withdraw();
+        }
+        true
+    }
+
+    /// Take a retry from the global retry limit, return `false` if the limit is reached.
+    fn acquire_global_limit(&self) -> bool {
+        #[cfg(feature = "std")]
+        if let Some(limit) = &self.global_limit {
+            return limit.acquire();
+        }
+        true
+    }
+
+    /// Give back the retry taken from the global retry limit if it's not made.
+    fn release_global_limit(&self) {
+        #[cfg(feature = "std")]
+        if let Some(limit) = &self.global_limit {
+            limit.release();
+        }
+    }
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            initial_delay: None,
+            spread: None,
+            spread_seed: None,
+            no_sleep_keeps_backoff: false,
+            yield_on_zero_delay: true,
+            immediate_retries: 0,
+            always_retry_first: false,
+            attempts_per_poll: DEFAULT_ATTEMPTS_PER_POLL,
+            notify_timing: NotifyTiming::BeforeSleep,
+            min_gap: None,
+            max_times: None,
+            #[cfg(feature = "std")]
+            kill_switch: None,
+            #[cfg(feature = "std")]
+            budget: None,
+            #[cfg(feature = "std")]
+            global_limit: None,
+            #[cfg(feature = "std")]
+            heal: None,
+        }
+    }
+}
+
+/// The default number of attempts that could be started in a single poll.
+const DEFAULT_ATTEMPTS_PER_POLL: usize = 32;
+
+/// State maintains internal state of retry.
+#[derive(Default)]
+enum State<T, E, Fut: Future<Output = Result<T, E>>, SleepFut> {
+    #[default]
+    Idle,
+    Polling(Fut),
+    /// Sleep before the next attempt, with the error and the delay to notify after the sleep.
+    Sleeping(SleepFut, Option<(E, Duration)>),
+    /// Yield once before sleeping the zero delay, or retrying immediately if `None`.
+    ///
+    /// The error is kept to notify after the sleep.
+    Yielding(Option<Duration>, Option<E>),
+    /// The result has been returned.
+    Done,
+}
+
+impl<B, T, E, Fut, FutureFn, SF, RF, NF, AF, GF> Future
+    for Retry<B, T, E, Fut, FutureFn, SF, RF, NF, AF, GF>
+where
+    B: Backoff,
+    Fut: Future<Output = Result<T, E>>,
+    FutureFn: FnMut() -> Fut,
+    SF: RetrySleeper<E>,
+    RF: Decide<E>,
+    NF: Notify<E>,
+    AF: Adjust<E>,
+    GF: FnMut(&E, usize),
+{
+    type Output = Result<T, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // Safety: This is safe because we don't move the `Retry` struct itself,
+        // only its internal state.
+        //
+        // We do the exactly same thing like `pin_project` but without depending on it directly.
+        let this = unsafe { self.get_unchecked_mut() };
+
+        #[cfg(feature = "tracing")]
+        let _span = this.tracer.as_ref().map(|t| t.enter());
+
+        // The number of attempts started in this poll.
+        let mut attempts = 0;
+        loop {
+            match &mut this.state {
+                State::Idle => {
+                    // Sleep the rest of the sleep that was in progress when the state was taken.
+                    if let Some(dur) = this.progress.take_sleep_left() {
+                        this.progress.start();
+                        this.progress.start_sleep(dur);
+                        this.state = State::Sleeping(
+                            this.sleep_fn.retry_sleep(dur, this.progress.attempts),
+                            None,
+                        );
+                        continue;
+                    }
+                    if let Some(spread) = this.options.spread.take() {
+                        let offset = spread.mul_f64(new_rng(this.options.spread_seed).f64());
+                        let delay = this.options.initial_delay.unwrap_or_default();
+                        this.options.initial_delay = Some(delay.saturating_add(offset));
+                    }
+                    if let Some(dur) = this.options.initial_delay.take() {
+                        this.progress.start();
+                        this.progress.start_sleep(dur);
+                        this.state = State::Sleeping(
+                            this.sleep_fn.retry_sleep(dur, this.progress.attempts),
+                            None,
+                        );
+                        continue;
+                    }
+                    // Sleep the rest of the minimum gap since the last attempt completed.
+                    if let (Some(gap), Some(passed)) =
+                        (this.options.min_gap, this.progress.take_since_completed())
+                    {
+                        let rest = gap.saturating_sub(passed);
+                        if !rest.is_zero() {
+                            this.progress.start_sleep(rest);
+                            this.state = State::Sleeping(
+                                this.sleep_fn.retry_sleep(rest, this.progress.attempts),
+                                None,
+                            );
+                            continue;
+                        }
+                    }
+
+                    // Give other tasks a chance to run if the attempts keep failing immediately.
+                    if attempts >= this.options.attempts_per_poll {
+                        cx.waker().wake_by_ref();
+                        return Poll::Pending;
+                    }
+                    attempts += 1;
+
+                    this.progress.start_attempt();
+                    let fut = (this.future_fn)();
+                    this.state = State::Polling(fut);
+                    continue;
+                }
+                State::Polling(fut) => {
+                    // Safety: This is safe because we don't move the `Retry` struct and this fut,
+                    // only its internal state.
+                    //
+                    // We do the exactly same thing like `pin_project` but without depending on it directly.
+                    let mut fut = unsafe { Pin::new_unchecked(fut) };
+
+                    let result = ready!(fut.as_mut().poll(cx));
+                    #[cfg(feature = "std")]
+                    if let Some(hook) = &this.on_attempt {
+                        let mut hook = hook.lock().unwrap_or_else(|err| err.into_inner());
+                        hook(
+                            this.progress.attempts,
+                            this.progress.attempt_elapsed(),
+                            &result,
+                        );
+                    }
+                    match result {
+                        Ok(v) => {
+                            this.progress.succeed();
+                            this.options.deposit_budget();
+                            this.options.heal();
+                            this.state = State::Done;
+                            return Poll::Ready(Ok(v));
+                        }
+                        Err(err) => {
+                            if this.options.min_gap.is_some() {
+                                this.progress.complete_attempt();
+                            }
+                            // Non-retryable errors are returned directly without consuming the backoff.
+                            let mut no_sleep = false;
+                            // The first error skips the retryable check if asked to.
+                            let decision =
+                                if this.options.always_retry_first && this.progress.attempts == 1 {
+                                    RetryDecision::Retry {
+                                        error: err,
+                                        after: None,
+                                    }
+                                } else {
+                                    this.retryable.decide(err, this.progress.elapsed())
+                                };
+                            // The reason to give up if no more retry will happen.
+                            let (err, next, reason) = match decision {
+                                // Retrying is disabled by the kill switch.
+                                RetryDecision::Retry { error, .. } if this.options.killed() => {
+                                    (error, None, GiveUpReason::KillSwitch)
+                                }
+                                // The immediate retries are made before consulting the backoff.
+                                RetryDecision::Retry { error, .. }
+                                    if this.progress.attempts <= this.options.immediate_retries =>
+                                {
+                                    no_sleep = true;
+                                    (error, Some(Duration::ZERO), GiveUpReason::Exhausted)
+                                }
+                                RetryDecision::Retry { error, .. }
+                                    if this.adjust.no_sleep(&error) =>
+                                {
+                                    no_sleep = true;
+                                    let next = if this.options.no_sleep_keeps_backoff {
+                                        Some(Duration::ZERO)
+                                    } else {
+                                        this.options
+                                            .next_delay(&mut this.backoff, &mut this.progress)
+                                            .map(|_| Duration::ZERO)
+                                    };
+                                    (error, next, GiveUpReason::Exhausted)
+                                }
+                                RetryDecision::Retry { error, after } => {
+                                    let next = this
+                                        .options
+                                        .next_delay(&mut this.backoff, &mut this.progress);
+                                    let next = this.adjust.adjust(&error, next);
+                                    // The backoff still decides whether to retry, only the delay is replaced.
+                                    (
+                                        error,
+                                        next.map(|dur| after.unwrap_or(dur)),
+                                        GiveUpReason::Exhausted,
+                                    )
+                                }
+                                RetryDecision::Stop(error) => {
+                                    (error, None, GiveUpReason::NonRetryable)
+                                }
+                            };
+                            // Give up if the max times set for this retry, the global retry limit
+                            // or the retry budget is used up. The budget is withdrawn last, and the
+                            // global limit is given back if the budget refuses, so nothing is used
+                            // up by a retry that is not made.
+                            let max_times_used = matches!(
+                                this.options.max_times,
+                                Some(n) if this.progress.attempts > n
+                            );
+                            let (next, reason) = match next {
+                                Some(_) if max_times_used => (None, GiveUpReason::Exhausted),
+                                Some(_) if !this.options.acquire_global_limit() => {
+                                    (None, GiveUpReason::GlobalLimit)
+                                }
+                                Some(_) if !this.options.withdraw_budget() => {
+                                    this.options.release_global_limit();
+                                    (None, GiveUpReason::Budget)
+                                }
+                                next => (next, reason),
+                            };
+                            #[cfg(feature = "tracing")]
+                            if let Some(tracer) = &this.tracer {
+                                tracer.record(this.progress.attempts, &err, next);
+                            }
+                            match next {
+                                None => {
+                                    this.progress.give_up = Some(reason);
+                                    this.progress.fail();
+                                    (this.on_giveup)(&err, this.progress.attempts);
+                                    this.state = State::Done;
+                                    return Poll::Ready(Err(err));
+                                }
+                                Some(dur) => {
+                                    // There is no sleep to wait for without sleeping.
+                                    let pending = if no_sleep
+                                        || this.options.notify_timing == NotifyTiming::BeforeSleep
+                                    {
+                                        let ctl = this.notify.notify_ctl(RetryContext {
+                                            attempt: this.progress.attempts,
+                                            error: &err,
+                                            next_delay: dur,
+                                            elapsed: this.progress.elapsed(),
+                                            remaining: this.backoff.remaining(),
+                                        });
+                                        // The hook stops retrying.
+                                        if ctl.is_break() {
+                                            this.progress.give_up = Some(GiveUpReason::Aborted);
+                                            this.progress.fail();
+                                            (this.on_giveup)(&err, this.progress.attempts);
+                                            this.state = State::Done;
+                                            return Poll::Ready(Err(err));
+                                        }
+                                        None
+                                    } else {
+                                        Some(err)
+                                    };
+                                    this.progress.retry();
+                                    let yield_now =
+                                        dur.is_zero() && this.options.yield_on_zero_delay;
+                                    if no_sleep {
+                                        this.state = if yield_now {
+                                            State::Yielding(None, None)
+                                        } else {
+                                            State::Idle
+                                        };
+                                        continue;
+                                    }
+                                    this.progress.start_sleep(dur);
+                                    this.state = if yield_now {
+                                        State::Yielding(Some(dur), pending)
+                                    } else {
+                                        State::Sleeping(
+                                            this.sleep_fn.retry_sleep(dur, this.progress.attempts),
+                                            pending.map(|err| (err, dur)),
+                                        )
+                                    };
+                                    continue;
+                                }
+                            }
+                        }
+                    }
+                }
+                State::Sleeping(sl, pending) => {
+                    // Safety: This is safe because we don't move the `Retry` struct and this fut,
+                    // only its internal state.
+                    //
+                    // We do the exactly same thing like `pin_project` but without depending on it directly.
+                    let mut sl = unsafe { Pin::new_unchecked(sl) };
+
+                    if let Err(err) = ready!(sl.as_mut().poll(cx)) {
+                        this.progress.give_up = Some(GiveUpReason::Aborted);
+                        this.progress.fail();
+                        this.state = State::Done;
+                        return Poll::Ready(Err(SF::into_error(err)));
+                    }
+                    this.progress.end_sleep();
+                    if let Some((err, dur)) = pending.take() {
+                        let ctl = this.notify.notify_ctl(RetryContext {
+                            attempt: this.progress.attempts,
+                            error: &err,
+                            next_delay: dur,
+                            elapsed: this.progress.elapsed(),
+                            remaining: this.backoff.remaining(),
+                        });
+                        // The hook stops retrying.
+                        if ctl.is_break() {
+                            this.progress.give_up = Some(GiveUpReason::Aborted);
+                            this.progress.fail();
+                            (this.on_giveup)(&err, this.progress.attempts);
+                            this.state = State::Done;
+                            return Poll::Ready(Err(err));
+                        }
+                    }
+                    this.state = State::Idle;
+                    continue;
+                }
+                State::Yielding(next, pending) => {
+                    // Give other tasks a chance to run before the zero delay.
+                    this.state = match *next {
+                        Some(dur) => State::Sleeping(
+                            this.sleep_fn.retry_sleep(dur, this.progress.attempts),
+                            pending.take().map(|err| (err, dur)),
+                        ),
+                        None => State::Idle,
+                    };
+                    cx.waker().wake_by_ref();
+                    return Poll::Pending;
+                }
+                State::Done => return Poll::Pending,
+            }
+        }
+    }
+}
+
+#[cfg(feature = "futures")]
+impl<B, T, E, Fut, FutureFn, SF, RF, NF, AF, GF> futures_core::FusedFuture
+    for Retry<B, T, E, Fut, FutureFn, SF, RF, NF, AF, GF>
+where
+    B: Backoff,
+    Fut: Future<Output = Result<T, E>>,
+    FutureFn: FnMut() -> Fut,
+    SF: RetrySleeper<E>,
+    RF: Decide<E>,
+    NF: Notify<E>,
+    AF: Adjust<E>,
+    GF: FnMut(&E, usize),
+{
+    fn is_terminated(&self) -> bool {
+        matches!(self.state, State::Done)
+    }
+}
+
+/// Future generated by [`Retry::on_exhausted`].
+pub struct OnExhausted<
+    B: Backoff,
+    T,
+    E,
+    Fut: Future<Output = Result<T, E>>,
+    FutureFn: FnMut() -> Fut,
+    SF: MaybeTrySleeper,
+    RF,
+    NF,
+    AF,
+    GF,
+    XF,
+> {
+    retry: Retry<B, T, E, Fut, FutureFn, SF, RF, NF, AF, GF>,
+    on_exhausted: XF,
+}
+
+impl<B, T, E, E2, Fut, FutureFn, SF, RF, NF, AF, GF, XF> Future
+    for OnExhausted<B, T, E, Fut, FutureFn, SF, RF, NF, AF, GF, XF>
+where
+    B: Backoff,
+    Fut: Future<Output = Result<T, E>>,
+    FutureFn: FnMut() -> Fut,
+    SF: RetrySleeper<E>,
+    RF: Decide<E>,
+    NF: Notify<E>,
+    AF: Adjust<E>,
+    GF: FnMut(&E, usize),
+    E2: From<E>,
+    XF: FnMut(E, usize) -> E2,
+{
+    type Output = Result<T, E2>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // Safety: This is safe because we don't move the `OnExhausted` struct itself,
+        // only its internal state.
+        //
+        // We do the exactly same thing like `pin_project` but without depending on it directly.
+        let this = unsafe { self.get_unchecked_mut() };
+        // Safety: This is safe because we don't move the `OnExhausted` struct and this retry.
+        let retry = unsafe { Pin::new_unchecked(&mut this.retry) };
+
+        match ready!(retry.poll(cx)) {
+            Ok(v) => Poll::Ready(Ok(v)),
+            Err(err)
+                if matches!(
+                    this.retry.progress.give_up,
+                    Some(
+                        GiveUpReason::Exhausted | GiveUpReason::Budget | GiveUpReason::GlobalLimit
+                    )
+                ) =>
+            {
+                Poll::Ready(Err((this.on_exhausted)(err, this.retry.progress.attempts)))
+            }
+            Err(err) => Poll::Ready(Err(err.into())),
+        }
+    }
+}
+
+#[cfg(feature = "futures")]
+impl<B, T, E, E2, Fut, FutureFn, SF, RF, NF, AF, GF, XF> futures_core::FusedFuture
+    for OnExhausted<B, T, E, Fut, FutureFn, SF, RF, NF, AF, GF, XF>
+where
+    B: Backoff,
+    Fut: Future<Output = Result<T, E>>,
+    FutureFn: FnMut() -> Fut,
+    SF: RetrySleeper<E>,
+    RF: Decide<E>,
+    NF: Notify<E>,
+    AF: Adjust<E>,
+    GF: FnMut(&E, usize),
+    E2: From<E>,
+    XF: FnMut(E, usize) -> E2,
+{
+    fn is_terminated(&self) -> bool {
+        self.retry.is_terminated()
+    }
+}
+
+/// Future generated by [`Retry::with_stats`].
+pub struct WithStats<
+    B: Backoff,
+    T,
+    E,
+    Fut: Future<Output = Result<T, E>>,
+    FutureFn: FnMut() -> Fut,
+    SF: MaybeTrySleeper,
+    RF,
+    NF,
+    AF,
+    GF,
+> {
+    retry: Retry<B, T, E, Fut, FutureFn, SF, RF, NF, AF, GF>,
+}
+
+impl<B, T, E, Fut, FutureFn, SF, RF, NF, AF, GF> Future
+    for WithStats<B, T, E, Fut, FutureFn, SF, RF, NF, AF, GF>
+where
+    B: Backoff,
+    Fut: Future<Output = Result<T, E>>,
+    FutureFn: FnMut() -> Fut,
+    SF: RetrySleeper<E>,
+    RF: Decide<E>,
+    NF: Notify<E>,
+    AF: Adjust<E>,
+    GF: FnMut(&E, usize),
+{
+    type Output = (Result<T, E>, RetryStats);
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // Safety: This is safe because we don't move the `WithStats` struct itself,
+        // only its internal state.
+        //
+        // We do the exactly same thing like `pin_project` but without depending on it directly.
+        let this = unsafe { self.get_unchecked_mut() };
+        // Safety: This is safe because we don't move the `WithStats` struct and this retry.
+        let retry = unsafe { Pin::new_unchecked(&mut this.retry) };
+
+        let res = ready!(retry.poll(cx));
+        Poll::Ready((res, this.retry.progress.stats()))
+    }
+}
+
+#[cfg(feature = "futures")]
+impl<B, T, E, Fut, FutureFn, SF, RF, NF, AF, GF> futures_core::FusedFuture
+    for WithStats<B, T, E, Fut, FutureFn, SF, RF, NF, AF, GF>
+where
+    B: Backoff,
+    Fut: Future<Output = Result<T, E>>,
+    FutureFn: FnMut() -> Fut,
+    SF: RetrySleeper<E>,
+    RF: Decide<E>,
+    NF: Notify<E>,
+    AF: Adjust<E>,
+    GF: FnMut(&E, usize),
+{
+    fn is_terminated(&self) -> bool {
+        self.retry.is_terminated()
+    }
+}
+
+/// Future generated by [`Retry::timeout`] and [`Retry::timeout_with`].
+pub struct Timeout<
+    B: Backoff,
+    T,
+    E,
+    Fut: Future<Output = Result<T, E>>,
+    FutureFn: FnMut() -> Fut,
+    SF: MaybeTrySleeper,
+    RF,
+    NF,
+    AF,
+    GF,
+    TS: MaybeSleeper = DefaultSleeper,
+> {
+    retry: Retry<B, T, E, Fut, FutureFn, SF, RF, NF, AF, GF>,
+    timeout: Duration,
+    timer_fn: TS,
+    timer: Option<TS::Sleep>,
+}
+
+impl<B, T, E, Fut, FutureFn, SF, RF, NF, AF, GF, TS> Future
+    for Timeout<B, T, E, Fut, FutureFn, SF, RF, NF, AF, GF, TS>
+where
+    B: Backoff,
+    Fut: Future<Output = Result<T, E>>,
+    FutureFn: FnMut() -> Fut,
+    SF: RetrySleeper<E>,
+    RF: Decide<E>,
+    NF: Notify<E>,
+    AF: Adjust<E>,
+    GF: FnMut(&E, usize),
+    TS: Sleeper,
+    E: From<crate::RetryTimedOut>,
+{
+    type Output = Result<T, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // Safety: This is safe because we don't move the `Timeout` struct itself,
+        // only its internal state.
+        //
+        // We do the exactly same thing like `pin_project` but without depending on it directly.
+        let this = unsafe { self.get_unchecked_mut() };
+        // The timer may have fired already, don't poll it again.
+        if matches!(this.retry.state, State::Done) {
+            return Poll::Pending;
+        }
+        // Safety: This is safe because we don't move the `Timeout` struct and this retry.
+        let retry = unsafe { Pin::new_unchecked(&mut this.retry) };
+
+        if let Poll::Ready(res) = retry.poll(cx) {
+            return Poll::Ready(res);
+        }
+
+        let timer = match &mut this.timer {
+            Some(timer) => timer,
+            None => this.timer.insert(this.timer_fn.sleep(this.timeout)),
+        };
+        // Safety: This is safe because we don't move the `Timeout` struct and this timer.
+        let timer = unsafe { Pin::new_unchecked(timer) };
+        ready!(timer.poll(cx));
+
+        this.retry.progress.give_up = Some(GiveUpReason::Deadline);
+        this.retry.progress.fail();
+        // Drop the attempt in flight.
+        this.retry.state = State::Done;
+        Poll::Ready(Err(crate::RetryTimedOut {
+            timeout: this.timeout,
+        }
+        .into()))
+    }
+}
+
+#[cfg(feature = "futures")]
+impl<B, T, E, Fut, FutureFn, SF, RF, NF, AF, GF, TS> futures_core::FusedFuture
+    for Timeout<B, T, E, Fut, FutureFn, SF, RF, NF, AF, GF, TS>
+where
+    B: Backoff,
+    Fut: Future<Output = Result<T, E>>,
+    FutureFn: FnMut() -> Fut,
+    SF: RetrySleeper<E>,
+    RF: Decide<E>,
+    NF: Notify<E>,
+    AF: Adjust<E>,
+    GF: FnMut(&E, usize),
+    TS: Sleeper,
+    E: From<crate::RetryTimedOut>,
+{
+    fn is_terminated(&self) -> bool {
+        self.retry.is_terminated()
+    }
+}
+
+impl<B, T, E, Fut, FutureFn, SF, RF, NF, AF, GF, TS>
+    Timeout<B, T, E, Fut, FutureFn, SF, RF, NF, AF, GF, TS>
+where
+    B: Backoff,
+    Fut: Future<Output = Result<T, E>>,
+    FutureFn: FnMut() -> Fut,
+    SF: MaybeTrySleeper,
+    TS: MaybeSleeper,
+{
+    /// Return a [`RetryError`] carrying the reason why the retry gave up.
+    ///
+    /// It's the same as [`Retry::with_reason`], except that the retry not finished within the
+    /// timeout is reported as [`GiveUpReason::Deadline`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use core::time::Duration;
+    ///
+    /// use anyhow::Result;
+    /// use backon::ExponentialBuilder;
+    /// use backon::GiveUpReason;
+    /// use backon::Retryable;
+    ///
+    /// async fn fetch() -> Result<String> {
+    ///     Ok(reqwest::get("https://www.rust-lang.org")
+    ///         .await?
+    ///         .text()
+    ///         .await?)
+    /// }
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<()> {
+    ///     let content = fetch
+    ///         .retry(ExponentialBuilder::default())
+    ///         .timeout(Duration::from_secs(30))
+    ///         .with_reason()
+    ///         .await;
+    ///     match content {
+    ///         Ok(content) => println!("fetch succeeded: {}", content),
+    ///         Err(err) if err.reason() == GiveUpReason::Deadline => {
+    ///             println!("fetch didn't finish in time after {} attempts", err.attempts())
+    ///         }
+    ///         Err(err) => return Err(err.into_inner()),
+    ///     }
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn with_reason(self) -> WithReason<Self> {
+        WithReason { inner: self }
+    }
+}
+
+impl<B, T, E, Fut, FutureFn, SF, RF, NF, AF, GF> GiveUp
+    for Retry<B, T, E, Fut, FutureFn, SF, RF, NF, AF, GF>
+where
+    B: Backoff,
+    Fut: Future<Output = Result<T, E>>,
+    FutureFn: FnMut() -> Fut,
+    SF: MaybeTrySleeper,
+{
+    fn give_up(&self) -> (Option<GiveUpReason>, usize) {
+        (self.progress.give_up, self.progress.attempts)
+    }
+}
+
+impl<B, T, E, Fut, FutureFn, SF, RF, NF, AF, GF, TS> GiveUp
+    for Timeout<B, T, E, Fut, FutureFn, SF, RF, NF, AF, GF, TS>
+where
+    B: Backoff,
+    Fut: Future<Output = Result<T, E>>,
+    FutureFn: FnMut() -> Fut,
+    SF: MaybeTrySleeper,
+    TS: MaybeSleeper,
+{
+    fn give_up(&self) -> (Option<GiveUpReason>, usize) {
+        self.retry.give_up()
+    }
+}
+
+/// Future generated by [`Retry::with_reason`] and [`Timeout::with_reason`].
+pub struct WithReason<F> {
+    inner: F,
+}
+
+impl<T, E, F> Future for WithReason<F>
+where
+    F: Future<Output = Result<T, E>> + GiveUp,
+{
+    type Output = Result<T, RetryError<E>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // Safety: This is safe because we don't move the `WithReason` struct itself,
+        // only its internal state.
+        //
+        // We do the exactly same thing like `pin_project` but without depending on it directly.
+        let this = unsafe { self.get_unchecked_mut() };
+        // Safety: This is safe because we don't move the `WithReason` struct and this inner.
+        let inner = unsafe { Pin::new_unchecked(&mut this.inner) };
+
+        match ready!(inner.poll(cx)) {
+            Ok(v) => Poll::Ready(Ok(v)),
+            Err(err) => {
+                let (reason, attempts) = this.inner.give_up();
+                // Every terminal branch records its reason, nothing else stops a retry.
+                let reason = reason.unwrap_or(GiveUpReason::Aborted);
+                Poll::Ready(Err(RetryError::new(err, reason, attempts)))
+            }
+        }
+    }
+}
+
+#[cfg(feature = "futures")]
+impl<T, E, F> futures_core::FusedFuture for WithReason<F>
+where
+    F: futures_core::FusedFuture<Output = Result<T, E>> + GiveUp,
+{
+    fn is_terminated(&self) -> bool {
+        self.inner.is_terminated()
+    }
+}
+
+#[cfg(test)]
+#[cfg(any(feature = "tokio-sleep", feature = "gloo-timers-sleep",))]
+mod default_sleeper_tests {
+    extern crate alloc;
+
+    use alloc::string::String;
+    use alloc::string::ToString;
+    use alloc::vec;
+    use alloc::vec::Vec;
+    use core::time::Duration;
+    use tokio::sync::Mutex;
+
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test as test;
+
+    #[cfg(not(target_arch = "wasm32"))]
+    use tokio::test;
+
+    use super::*;
+    use crate::ExponentialBuilder;
+
+    async fn always_error() -> anyhow::Result<()> {
+        Err(anyhow::anyhow!("test_query meets error"))
+    }
+
+    #[test]
+    async fn test_retry() {
+        let result = always_error
+            .retry(ExponentialBuilder::default().with_min_delay(Duration::from_millis(1)))
+            .await;
+
+        assert!(result.is_err());
+        assert_eq!("test_query meets error", result.unwrap_err().to_string());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    async fn test_retry_timeout() {
+        let result = always_error
+            .retry(
+                crate::ConstantBuilder::default()
+                    .with_delay(Duration::from_millis(10))
+                    .without_max_times(),
+            )
+            .timeout(Duration::from_millis(50))
+            .await;
+
+        let err = result.unwrap_err();
+        let err = err.downcast_ref::<crate::RetryTimedOut>().unwrap();
+        assert_eq!(err.timeout(), Duration::from_millis(50));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    async fn test_retry_timeout_with_no_sleeper() {
+        // The retry sleeps nothing, but the timer still runs on the default sleeper.
+        let result = (|| async {
+            tokio::task::yield_now().await;
+            Err::<(), _>(anyhow::anyhow!("test_query meets error"))
+        })
+        .retry(ExponentialBuilder::default())
+        .sleep(crate::NoSleeper)
+        .timeout(Duration::from_secs(60))
+        .await;
+
+        let err = result.unwrap_err();
+        assert!(!err.is::<crate::RetryTimedOut>());
+        assert_eq!("test_query meets error", err.to_string());
+    }
+
+    #[test]
     async fn test_retry_with_not_retryable_error() {
         let error_times = Mutex::new(0);
 
-        let f = || async {
-            let mut x = error_times.lock().await;
-            *x += 1;
-            Err::<(), anyhow::Error>(anyhow::anyhow!("not retryable"))
+        let f = || async {
+            let mut x = error_times.lock().await;
+            *x += 1;
+            Err::<(), anyhow::Error>(anyhow::anyhow!("not retryable"))
+        };
+
+        let backoff = ExponentialBuilder::default().with_min_delay(Duration::from_millis(1));
+        let result = f
+            .retry(backoff)
+            // Only retry If error message is `retryable`
+            .when(|e| e.to_string() == "retryable")
+            .await;
+
+        assert!(result.is_err());
+        assert_eq!("not retryable", result.unwrap_err().to_string());
+        // `f` always returns error "not retryable", so it should be executed
+        // only once.
+        assert_eq!(*error_times.lock().await, 1);
+    }
+
+    #[test]
+    async fn test_retry_with_retryable_error() {
+        let error_times = Mutex::new(0);
+
+        let f = || async {
+            let mut x = error_times.lock().await;
+            *x += 1;
+            Err::<(), anyhow::Error>(anyhow::anyhow!("retryable"))
+        };
+
+        let backoff = ExponentialBuilder::default().with_min_delay(Duration::from_millis(1));
+        let result = f
+            .retry(backoff)
+            // Only retry If error message is `retryable`
+            .when(|e| e.to_string() == "retryable")
+            .await;
+
+        assert!(result.is_err());
+        assert_eq!("retryable", result.unwrap_err().to_string());
+        // `f` always returns error "retryable", so it should be executed
+        // 4 times (retry 3 times).
+        assert_eq!(*error_times.lock().await, 4);
+    }
+
+    #[test]
+    async fn test_fn_mut_when_and_notify() {
+        let mut calls_retryable: Vec<()> = vec![];
+        let mut calls_notify: Vec<()> = vec![];
+
+        let f = || async { Err::<(), anyhow::Error>(anyhow::anyhow!("retryable")) };
+
+        let backoff = ExponentialBuilder::default().with_min_delay(Duration::from_millis(1));
+        let result = f
+            .retry(backoff)
+            .when(|_| {
+                calls_retryable.push(());
+                true
+            })
+            .notify(|_, _| {
+                calls_notify.push(());
+            })
+            .await;
+
+        assert!(result.is_err());
+        assert_eq!("retryable", result.unwrap_err().to_string());
+        // `f` always returns error "retryable", so it should be executed
+        // 4 times (retry 3 times).
+        assert_eq!(calls_retryable.len(), 4);
+        assert_eq!(calls_notify.len(), 3);
+    }
+
+    #[test]
+    async fn test_on_retry() {
+        let mut attempts: Vec<(usize, Duration)> = vec![];
+
+        let f = || async { Err::<(), anyhow::Error>(anyhow::anyhow!("retryable")) };
+
+        let backoff = ExponentialBuilder::default().with_min_delay(Duration::from_millis(1));
+        let result = f
+            .retry(backoff)
+            .on_retry(|ctx| {
+                assert_eq!("retryable", ctx.error.to_string());
+                attempts.push((ctx.attempt, ctx.next_delay));
+            })
+            .await;
+
+        assert!(result.is_err());
+        assert_eq!(
+            attempts,
+            vec![
+                (1, Duration::from_millis(1)),
+                (2, Duration::from_millis(2)),
+                (3, Duration::from_millis(4)),
+            ]
+        );
+    }
+
+    #[test]
+    async fn test_on_retry_remaining() {
+        let mut remaining: Vec<Option<usize>> = vec![];
+
+        let f = || async { Err::<(), anyhow::Error>(anyhow::anyhow!("retryable")) };
+
+        let backoff = ExponentialBuilder::default().with_min_delay(Duration::from_millis(1));
+        let result = f
+            .retry(backoff)
+            .on_retry(|ctx| remaining.push(ctx.remaining))
+            .await;
+
+        assert!(result.is_err());
+        // The last retry is the final attempt.
+        assert_eq!(remaining, vec![Some(2), Some(1), Some(0)]);
+    }
+
+    #[test]
+    async fn test_on_giveup() {
+        let mut giveups: Vec<usize> = vec![];
+
+        let f = || async { Err::<(), anyhow::Error>(anyhow::anyhow!("retryable")) };
+
+        let backoff = ExponentialBuilder::default().with_min_delay(Duration::from_millis(1));
+        let result = f.retry(backoff).on_giveup(|_, n| giveups.push(n)).await;
+
+        assert!(result.is_err());
+        assert_eq!(giveups, vec![4]);
+    }
+
+    #[test]
+    async fn test_on_giveup_with_not_retryable_error() {
+        let mut giveups: Vec<usize> = vec![];
+
+        let f = || async { Err::<(), anyhow::Error>(anyhow::anyhow!("not retryable")) };
+
+        let backoff = ExponentialBuilder::default().with_min_delay(Duration::from_millis(1));
+        let result = f
+            .retry(backoff)
+            .when(|e| e.to_string() == "retryable")
+            .on_giveup(|_, n| giveups.push(n))
+            .await;
+
+        assert!(result.is_err());
+        assert_eq!(giveups, vec![1]);
+    }
+
+    #[test]
+    async fn test_retry_with_adjust() {
+        let mut delays: Vec<Duration> = vec![];
+
+        let f = || async { Err::<(), anyhow::Error>(anyhow::anyhow!("retryable")) };
+
+        let backoff = ExponentialBuilder::default().with_min_delay(Duration::from_millis(1));
+        let result = f
+            .retry(backoff)
+            // Stop retrying once the delay grows over 2ms.
+            .adjust(|_, dur| dur.filter(|dur| *dur <= Duration::from_millis(2)))
+            .notify(|_, dur| delays.push(dur))
+            .await;
+
+        assert!(result.is_err());
+        assert_eq!(
+            delays,
+            vec![Duration::from_millis(1), Duration::from_millis(2)]
+        );
+    }
+
+    #[test]
+    async fn test_retry_with_adjust_after_exhausted() {
+        let error_times = Mutex::new(0);
+
+        let f = || async {
+            let mut x = error_times.lock().await;
+            *x += 1;
+            Err::<(), anyhow::Error>(anyhow::anyhow!("retryable"))
+        };
+
+        let backoff = ExponentialBuilder::default()
+            .with_min_delay(Duration::from_millis(1))
+            .with_max_times(1);
+        let result = f
+            .retry(backoff)
+            // Keep retrying one more time after the backoff is exhausted.
+            .adjust({
+                let mut extra = 1;
+                move |_, dur| {
+                    dur.or_else(|| {
+                        (extra > 0).then(|| {
+                            extra -= 1;
+                            Duration::from_millis(1)
+                        })
+                    })
+                }
+            })
+            .await;
+
+        assert!(result.is_err());
+        assert_eq!(*error_times.lock().await, 3);
+    }
+
+    #[test]
+    async fn test_retry_with_backoff_for() {
+        let mut delays: Vec<Duration> = vec![];
+        let mut errors = ["slow down", "reset", "slow down", "reset"].into_iter();
+
+        let f = || {
+            let err = errors.next().unwrap();
+            async move { Err::<(), anyhow::Error>(anyhow::anyhow!(err)) }
+        };
+
+        let backoff = ExponentialBuilder::default().with_min_delay(Duration::from_millis(1));
+        let result = f
+            .retry(backoff)
+            .backoff_for(|e| (e.to_string() == "slow down").then(|| Duration::from_millis(10)))
+            .notify(|_, dur| delays.push(dur))
+            .await;
+
+        assert!(result.is_err());
+        // Falls back to the backoff for errors without a delay, and stops once it's exhausted.
+        assert_eq!(
+            delays,
+            vec![
+                Duration::from_millis(10),
+                Duration::from_millis(2),
+                Duration::from_millis(10),
+            ]
+        );
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum FetchError {
+        GaveUp(usize),
+        Other(String),
+    }
+
+    impl From<anyhow::Error> for FetchError {
+        fn from(err: anyhow::Error) -> Self {
+            FetchError::Other(err.to_string())
+        }
+    }
+
+    #[test]
+    async fn test_retry_on_exhausted() {
+        let f = || async { Err::<(), anyhow::Error>(anyhow::anyhow!("retryable")) };
+
+        let backoff = ExponentialBuilder::default().with_min_delay(Duration::from_millis(1));
+        let result = f
+            .retry(backoff)
+            .on_exhausted(|_, attempts| FetchError::GaveUp(attempts))
+            .await;
+
+        assert_eq!(result, Err(FetchError::GaveUp(4)));
+    }
+
+    #[test]
+    async fn test_retry_on_exhausted_with_not_retryable_error() {
+        let f = || async { Err::<(), anyhow::Error>(anyhow::anyhow!("not retryable")) };
+
+        let backoff = ExponentialBuilder::default().with_min_delay(Duration::from_millis(1));
+        let result = f
+            .retry(backoff)
+            .when(|e| e.to_string() == "retryable")
+            .on_exhausted(|_, attempts| FetchError::GaveUp(attempts))
+            .await;
+
+        // Non-retryable errors are not exhausted.
+        assert_eq!(result, Err(FetchError::Other("not retryable".to_string())));
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    async fn test_retry_with_trace() {
+        let error_times = Mutex::new(0);
+
+        let f = || async {
+            let mut x = error_times.lock().await;
+            *x += 1;
+            Err::<(), anyhow::Error>(anyhow::anyhow!("retryable"))
+        };
+
+        let backoff = ExponentialBuilder::default().with_min_delay(Duration::from_millis(1));
+        let result = f.retry(backoff).trace(tracing::Level::WARN).await;
+
+        assert!(result.is_err());
+        assert_eq!("retryable", result.unwrap_err().to_string());
+        // Tracing must not change the retry behavior.
+        assert_eq!(*error_times.lock().await, 4);
+    }
+}
+
+#[cfg(test)]
+mod custom_sleeper_tests {
+    extern crate alloc;
+
+    use alloc::string::ToString;
+    use alloc::sync::Arc;
+    use alloc::vec;
+    use alloc::vec::Vec;
+    use core::{future::ready, time::Duration};
+    use spin::Mutex;
+
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test as test;
+
+    #[cfg(not(target_arch = "wasm32"))]
+    use tokio::test;
+
+    use super::*;
+    use crate::ExponentialBuilder;
+
+    async fn always_error() -> anyhow::Result<()> {
+        Err(anyhow::anyhow!("test_query meets error"))
+    }
+
+    #[test]
+    async fn test_retry_yield_on_zero_delay() {
+        use core::sync::atomic::AtomicUsize;
+        use core::sync::atomic::Ordering;
+
+        use futures::future::poll_fn;
+        use futures::future::select;
+        use futures::future::Either;
+
+        // A task that counts how many times it gets polled.
+        let ticker = |ticks: &'static AtomicUsize| {
+            poll_fn(move |cx| {
+                ticks.fetch_add(1, Ordering::SeqCst);
+                cx.waker().wake_by_ref();
+                Poll::<()>::Pending
+            })
+        };
+        let retry = || {
+            always_error
+                .retry(
+                    crate::ConstantBuilder::default()
+                        .with_delay(Duration::ZERO)
+                        .with_max_times(10),
+                )
+                .sleep(|_| ready(()))
+        };
+
+        static YIELDED: AtomicUsize = AtomicUsize::new(0);
+        let Either::Left((result, _)) =
+            select(core::pin::pin!(retry()), core::pin::pin!(ticker(&YIELDED))).await
+        else {
+            panic!("retry must finish first");
+        };
+        assert!(result.is_err());
+        // The other task makes progress between the attempts.
+        assert_eq!(YIELDED.load(Ordering::SeqCst), 10);
+
+        static NOT_YIELDED: AtomicUsize = AtomicUsize::new(0);
+        let Either::Left((result, _)) = select(
+            core::pin::pin!(retry().yield_on_zero_delay(false)),
+            core::pin::pin!(ticker(&NOT_YIELDED)),
+        )
+        .await
+        else {
+            panic!("retry must finish first");
+        };
+        assert!(result.is_err());
+        assert_eq!(NOT_YIELDED.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    async fn test_retry_cloned() {
+        let sleeps = Arc::new(Mutex::new(Vec::new()));
+
+        let template = always_error
+            .retry(
+                ExponentialBuilder::default()
+                    .with_min_delay(Duration::from_millis(1))
+                    .with_max_times(2),
+            )
+            .sleep({
+                let sleeps = sleeps.clone();
+                move |dur| {
+                    sleeps.lock().push(dur);
+                    ready(())
+                }
+            })
+            .when(|e| e.to_string() == "test_query meets error");
+
+        // Every clone runs the full policy from scratch.
+        assert!(template.clone().await.is_err());
+        assert!(template.await.is_err());
+        assert_eq!(
+            *sleeps.lock(),
+            vec![
+                Duration::from_millis(1),
+                Duration::from_millis(2),
+                Duration::from_millis(1),
+                Duration::from_millis(2),
+            ]
+        );
+    }
+
+    #[test]
+    async fn test_retry_with_stats() {
+        let attempts = Arc::new(Mutex::new(0));
+
+        let (result, stats) = {
+            let attempts = attempts.clone();
+            move || {
+                let mut attempts = attempts.lock();
+                *attempts += 1;
+                ready(if *attempts < 3 {
+                    Err(anyhow::anyhow!("retryable"))
+                } else {
+                    Ok(*attempts)
+                })
+            }
+        }
+        .retry(
+            ExponentialBuilder::default()
+                .with_min_delay(Duration::from_millis(10))
+                .with_factor(2.0),
+        )
+        .sleep(|_| ready(()))
+        .initial_delay(Duration::from_millis(5))
+        .with_stats()
+        .await;
+
+        assert_eq!(result.unwrap(), 3);
+        assert_eq!(stats.attempts, 3);
+        assert_eq!(stats.succeeded_attempt, Some(3));
+        // The initial delay plus two retries.
+        assert_eq!(stats.slept, Duration::from_millis(5 + 10 + 20));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    async fn test_retry_backoff_by_key() {
+        let errors = Arc::new(Mutex::new(
+            vec!["slow", "fast", "slow", "other", "fast", "fast"].into_iter(),
+        ));
+        let delays = Arc::new(Mutex::new(Vec::new()));
+
+        let result = {
+            let errors = errors.clone();
+            move || ready(Err::<(), _>(errors.lock().next().unwrap()))
+        }
+        .retry(
+            crate::ConstantBuilder::default()
+                .with_delay(Duration::from_millis(100))
+                .with_max_times(10),
+        )
+        .sleep({
+            let delays = delays.clone();
+            move |dur| {
+                delays.lock().push(dur);
+                ready(())
+            }
+        })
+        .backoff_by_key(
+            |e: &&str| *e,
+            [
+                (
+                    "slow",
+                    ExponentialBuilder::default().with_min_delay(Duration::from_secs(1)),
+                ),
+                (
+                    "fast",
+                    ExponentialBuilder::default()
+                        .with_min_delay(Duration::from_millis(1))
+                        .with_max_times(2),
+                ),
+            ],
+        )
+        .await;
+
+        // Stop when the backoff of "fast" is exhausted.
+        assert_eq!(result.unwrap_err(), "fast");
+        assert_eq!(
+            *delays.lock(),
+            vec![
+                Duration::from_secs(1),
+                Duration::from_millis(1),
+                Duration::from_secs(2),
+                // The other errors use the backoff of the retry.
+                Duration::from_millis(100),
+                Duration::from_millis(2),
+            ]
+        );
+
+        // The max times of the retry is kept for the keyed errors.
+        let mut attempts = 0;
+        let result = (|| {
+            attempts += 1;
+            ready(Err::<(), _>("slow"))
+        })
+        .retry(crate::ConstantBuilder::default().with_max_times(2))
+        .sleep(|_| ready(()))
+        .backoff_by_key(
+            |e: &&str| *e,
+            [("slow", ExponentialBuilder::default().without_max_times())],
+        )
+        .await;
+
+        assert_eq!(result.unwrap_err(), "slow");
+        assert_eq!(attempts, 3);
+    }
+
+    #[test]
+    async fn test_retry_with_borrowed_backoff() {
+        let mut backoff = ExponentialBuilder::default().with_max_times(5).build();
+
+        let mut attempts = 0;
+        let result = (|| {
+            attempts += 1;
+            ready(if attempts < 3 {
+                Err("retryable")
+            } else {
+                Ok(())
+            })
+        })
+        .retry(&mut backoff)
+        .sleep(|_| ready(()))
+        .await;
+
+        assert!(result.is_ok());
+        // The backoff is available again once the retry has resolved.
+        assert_eq!(backoff.remaining(), Some(3));
+        assert_eq!(backoff.next(), Some(Duration::from_secs(4)));
+    }
+
+    #[test]
+    async fn test_retry_sleep_for_attempt() {
+        #[derive(Clone)]
+        struct RecordingSleeper(Arc<Mutex<Vec<(Duration, usize)>>>);
+
+        impl Sleeper for RecordingSleeper {
+            type Sleep = core::future::Ready<()>;
+
+            fn sleep(&self, _: Duration) -> Self::Sleep {
+                unreachable!("retry must sleep with the attempt")
+            }
+
+            fn sleep_for_attempt(&self, dur: Duration, attempt: usize) -> Self::Sleep {
+                self.0.lock().push((dur, attempt));
+                ready(())
+            }
+        }
+
+        let sleeps = Arc::new(Mutex::new(Vec::new()));
+        let result = always_error
+            .retry(ExponentialBuilder::default().with_max_times(2))
+            .sleep(crate::ScaledSleeper::new(
+                RecordingSleeper(sleeps.clone()),
+                1,
+            ))
+            .initial_delay(Duration::from_millis(500))
+            .await;
+
+        assert!(result.is_err());
+        assert_eq!(
+            *sleeps.lock(),
+            vec![
+                (Duration::from_millis(500), 0),
+                (Duration::from_secs(1), 1),
+                (Duration::from_secs(2), 2),
+            ]
+        );
+    }
+
+    #[test]
+    async fn test_retry_classify() {
+        let errors = Arc::new(Mutex::new(vec![
+            ErrorClass::Transient,
+            ErrorClass::RateLimited,
+            ErrorClass::Permanent,
+            ErrorClass::Transient,
+        ]));
+
+        let mut attempts = 0;
+        let result = (|| {
+            attempts += 1;
+            ready(Err::<(), _>(errors.lock().remove(0)))
+        })
+        .retry(ExponentialBuilder::default().with_max_times(5))
+        .sleep(|_| ready(()))
+        .classify(|class| *class)
+        .await;
+
+        // Stop at the permanent error.
+        assert_eq!(result, Err(ErrorClass::Permanent));
+        assert_eq!(attempts, 3);
+    }
+
+    #[test]
+    async fn test_retry_when_meta() {
+        #[derive(Debug, PartialEq)]
+        struct Status {
+            code: u16,
+        }
+
+        impl ErrorMetadata for Status {
+            type Meta = u16;
+
+            fn metadata(&self) -> &u16 {
+                &self.code
+            }
+        }
+
+        let codes = Arc::new(Mutex::new(vec![503, 429, 400, 503]));
+
+        let mut attempts = 0;
+        let result = (|| {
+            attempts += 1;
+            ready(Err::<(), _>(Status {
+                code: codes.lock().remove(0),
+            }))
+        })
+        .retry(ExponentialBuilder::default().with_max_times(5))
+        .sleep(|_| ready(()))
+        .when_meta(|_, code| matches!(code, 429 | 503))
+        .await;
+
+        // Stop at the client error.
+        assert_eq!(result, Err(Status { code: 400 }));
+        assert_eq!(attempts, 3);
+    }
+
+    #[test]
+    async fn test_retry_shared_borrow() {
+        use core::sync::atomic::AtomicUsize;
+        use core::sync::atomic::Ordering;
+
+        struct Client {
+            calls: AtomicUsize,
+        }
+
+        impl Client {
+            async fn fetch(&self) -> Result<usize, &'static str> {
+                match self.calls.fetch_add(1, Ordering::SeqCst) {
+                    0 | 1 => Err("retryable"),
+                    n => Ok(n),
+                }
+            }
+
+            async fn fetch_with_retry(&self) -> Result<usize, &'static str> {
+                (|| async { self.fetch().await })
+                    .retry(ExponentialBuilder::default())
+                    .sleep(|_| ready(()))
+                    .await
+            }
+        }
+
+        let client = Client {
+            calls: AtomicUsize::new(0),
+        };
+        assert_eq!(client.fetch_with_retry().await, Ok(2));
+
+        // A local is borrowed by every attempt the same way.
+        client.calls.store(0, Ordering::SeqCst);
+        let result = (|| async { client.fetch().await })
+            .retry(ExponentialBuilder::default())
+            .sleep(|_| ready(()))
+            .await;
+        assert_eq!(result, Ok(2));
+        assert_eq!(client.calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    async fn test_retry_macro() {
+        use core::sync::atomic::AtomicUsize;
+        use core::sync::atomic::Ordering;
+
+        let calls = AtomicUsize::new(0);
+        let calls = &calls;
+        let mut notified = 0;
+
+        let result = crate::retry!(
+            ExponentialBuilder::default(),
+            {
+                match calls.fetch_add(1, Ordering::SeqCst) {
+                    0 => Err("retryable"),
+                    1 => Err("not retryable"),
+                    n => Ok(n),
+                }
+            },
+            when = |e| *e == "retryable",
+            notify = |_, _| notified += 1,
+        )
+        .sleep(|_| ready(()))
+        .await;
+
+        assert_eq!(result, Err("not retryable"));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert_eq!(notified, 1);
+
+        // Without the optional arguments.
+        let result = crate::retry!(ExponentialBuilder::default(), {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Ok::<_, ()>(42)
+        })
+        .sleep(|_| ready(()))
+        .await;
+
+        assert_eq!(result, Ok(42));
+    }
+
+    #[test]
+    async fn test_retry_on_non_retryable() {
+        let errors = Arc::new(Mutex::new(vec!["retryable", "permanent"]));
+        let mut non_retryable = Vec::new();
+        let mut given_up = 0;
+
+        let result = (|| ready(Err::<(), _>(errors.lock().remove(0))))
+            .retry(ExponentialBuilder::default())
+            .sleep(|_| ready(()))
+            .when(|e| *e == "retryable")
+            .on_non_retryable(|e| non_retryable.push(*e))
+            .on_giveup(|_, _| given_up += 1)
+            .await;
+
+        assert_eq!(result, Err("permanent"));
+        assert_eq!(non_retryable, vec!["permanent"]);
+        assert_eq!(given_up, 1);
+
+        // Giving up on a retryable error is not reported.
+        let mut non_retryable = 0;
+        let result = (|| ready(Err::<(), _>("retryable")))
+            .retry(ExponentialBuilder::default().with_max_times(1))
+            .sleep(|_| ready(()))
+            .on_non_retryable(|_| non_retryable += 1)
+            .await;
+
+        assert_eq!(result, Err("retryable"));
+        assert_eq!(non_retryable, 0);
+
+        // The hook is kept by the conditions set after it.
+        let mut non_retryable = Vec::new();
+        let result = (|| ready(Err::<(), _>("permanent")))
+            .retry(ExponentialBuilder::default())
+            .sleep(|_| ready(()))
+            .on_non_retryable(|e| non_retryable.push(*e))
+            .when(|e| *e == "retryable")
+            .await;
+
+        assert_eq!(result, Err("permanent"));
+        assert_eq!(non_retryable, vec!["permanent"]);
+    }
+
+    #[test]
+    async fn test_retry_safe_to_retry() {
+        let errors = Arc::new(Mutex::new(vec![
+            "connect",
+            "connect",
+            "response lost",
+            "connect",
+        ]));
+
+        let mut attempts = 0;
+        let result = (|| {
+            attempts += 1;
+            ready(Err::<(), _>(errors.lock().remove(0)))
+        })
+        .retry(ExponentialBuilder::default().with_max_times(5))
+        .sleep(|_| ready(()))
+        .safe_to_retry(|e| *e == "connect")
+        .await;
+
+        // Stop once the request may have taken effect.
+        assert_eq!(result, Err("response lost"));
+        assert_eq!(attempts, 3);
+
+        // The errors that are safe to retry must be retryable as well.
+        let mut attempts = 0;
+        let result = (|| {
+            attempts += 1;
+            ready(Err::<(), _>("invalid"))
+        })
+        .retry(ExponentialBuilder::default().with_max_times(5))
+        .sleep(|_| ready(()))
+        .when(|e| *e != "invalid")
+        .safe_to_retry(|_| true)
+        .await;
+
+        assert_eq!(result, Err("invalid"));
+        assert_eq!(attempts, 1);
+
+        // The guard is kept by the conditions set after it.
+        let mut attempts = 0;
+        let result = (|| {
+            attempts += 1;
+            ready(Err::<(), _>("response lost"))
+        })
+        .retry(ExponentialBuilder::default().with_max_times(5))
+        .sleep(|_| ready(()))
+        .safe_to_retry(|e| *e == "connect")
+        .when(|_| true)
+        .await;
+
+        assert_eq!(result, Err("response lost"));
+        assert_eq!(attempts, 1);
+    }
+
+    #[test]
+    async fn test_retry_timeout_with_custom_sleeper() {
+        #[derive(Debug, PartialEq)]
+        enum Error {
+            Query,
+            TimedOut,
+        }
+
+        impl From<crate::RetryTimedOut> for Error {
+            fn from(_: crate::RetryTimedOut) -> Self {
+                Error::TimedOut
+            }
+        }
+
+        // The timer never fires with this sleeper, the retry gives up as usual.
+        let result = (|| ready(Err::<(), _>(Error::Query)))
+            .retry(ExponentialBuilder::default())
+            .sleep(|_| ready(()))
+            .timeout_with(Duration::from_secs(60), |_| core::future::pending::<()>())
+            .await;
+        assert_eq!(result, Err(Error::Query));
+
+        // The attempt that is stuck is dropped once the timer fires.
+        let result = (|| core::future::pending::<Result<(), Error>>())
+            .retry(ExponentialBuilder::default())
+            .sleep(|_| core::future::pending::<()>())
+            .timeout_with(Duration::from_secs(60), |_| ready(()))
+            .await;
+        assert_eq!(result, Err(Error::TimedOut));
+    }
+
+    #[test]
+    async fn test_retry_polled_after_completion() {
+        let mut retry = core::pin::pin!(always_error
+            .retry(ExponentialBuilder::default())
+            .sleep(|_| ready(())));
+
+        assert!(futures::poll!(retry.as_mut()).is_ready());
+        #[cfg(feature = "futures")]
+        assert!(futures_core::FusedFuture::is_terminated(&*retry));
+        // Polling again neither panics nor starts a new attempt.
+        assert!(futures::poll!(retry.as_mut()).is_pending());
+
+        // The timer that has fired is not polled again.
+        let mut retry = core::pin::pin!((|| core::future::pending::<
+            Result<(), crate::RetryTimedOut>,
+        >())
+        .retry(ExponentialBuilder::default())
+        .sleep(|_| ready(()))
+        .timeout_with(Duration::from_secs(60), |_| ready(())));
+
+        assert!(futures::poll!(retry.as_mut()).is_ready());
+        #[cfg(feature = "futures")]
+        assert!(futures_core::FusedFuture::is_terminated(&*retry));
+        assert!(futures::poll!(retry.as_mut()).is_pending());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    async fn test_retry_pause_and_resume() {
+        let clock = crate::ManualClock::new();
+        let builder = ExponentialBuilder::default().with_max_times(3);
+
+        // Pause the retry while it's sleeping after the first attempt.
+        let retry = always_error
+            .retry(builder)
+            .sleep(|_| core::future::pending())
+            .clock(clock.clone());
+        let mut retry = core::pin::pin!(retry);
+        assert!(futures::poll!(retry.as_mut()).is_pending());
+        clock.advance(Duration::from_secs(5));
+        let state = retry.state();
+        assert_eq!(state, RetryState::new(1, Duration::from_secs(5)));
+
+        let delays = Arc::new(Mutex::new(Vec::new()));
+        let retries = Arc::new(Mutex::new(Vec::new()));
+        let (result, stats) = always_error
+            .retry(builder.with_starting_attempt(state.attempts))
+            .sleep({
+                let delays = delays.clone();
+                move |dur| {
+                    delays.lock().push(dur);
+                    ready(())
+                }
+            })
+            .clock(clock)
+            .on_retry({
+                let retries = retries.clone();
+                move |ctx| retries.lock().push((ctx.attempt, ctx.elapsed))
+            })
+            .resume(state)
+            .with_stats()
+            .await;
+
+        assert!(result.is_err());
+        // The backoff continues after the first delay, and the attempts keep counting.
+        assert_eq!(stats.attempts, 4);
+        assert_eq!(
+            *delays.lock(),
+            vec![Duration::from_secs(2), Duration::from_secs(4)]
+        );
+        assert_eq!(
+            *retries.lock(),
+            vec![(2, Duration::from_secs(5)), (3, Duration::from_secs(5))]
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    async fn test_retry_resume_sleep_left() {
+        let clock = crate::ManualClock::new();
+        let builder = crate::ConstantBuilder::default()
+            .with_delay(Duration::from_secs(10))
+            .with_max_times(1);
+
+        // Drop the retry in the middle of its sleep.
+        let retry = always_error
+            .retry(builder)
+            .sleep(|_| core::future::pending())
+            .clock(clock.clone());
+        let mut retry = core::pin::pin!(retry);
+        assert!(futures::poll!(retry.as_mut()).is_pending());
+        clock.advance(Duration::from_secs(4));
+        let state = retry.state();
+        assert_eq!(state.attempts, 1);
+        assert_eq!(state.sleep_left, Duration::from_secs(6));
+
+        // The recreated retry sleeps the rest before the next attempt.
+        let delays = Arc::new(Mutex::new(Vec::new()));
+        let (result, stats) = always_error
+            .retry(builder.with_max_times(0))
+            .sleep({
+                let delays = delays.clone();
+                move |dur| {
+                    delays.lock().push(dur);
+                    ready(())
+                }
+            })
+            .clock(clock)
+            .resume(state)
+            .with_stats()
+            .await;
+        assert!(result.is_err());
+        assert_eq!(stats.attempts, 2);
+        assert_eq!(*delays.lock(), vec![Duration::from_secs(6)]);
+    }
+
+    #[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+    #[test]
+    async fn test_retry_reads_time_only_if_consumed() {
+        // Nothing reads the elapsed time, so it's not measured.
+        let retry = always_error
+            .retry(ExponentialBuilder::default())
+            .sleep(|_| core::future::pending());
+        let mut retry = core::pin::pin!(retry);
+        assert!(futures::poll!(retry.as_mut()).is_pending());
+        std::thread::sleep(Duration::from_millis(10));
+        assert_eq!(retry.state().elapsed, Duration::ZERO);
+        assert_eq!(retry.state().sleep_left, Duration::from_secs(1));
+
+        let retry = always_error
+            .retry(ExponentialBuilder::default())
+            .sleep(|_| core::future::pending())
+            .on_retry(|_| {});
+        let mut retry = core::pin::pin!(retry);
+        assert!(futures::poll!(retry.as_mut()).is_pending());
+        std::thread::sleep(Duration::from_millis(10));
+        assert!(retry.state().elapsed >= Duration::from_millis(10));
+        assert!(retry.state().sleep_left < Duration::from_secs(1));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    async fn test_retry_metrics() {
+        let metrics = Arc::new(crate::RetryMetrics::new());
+
+        let result = always_error
+            .retry(
+                crate::ConstantBuilder::default()
+                    .with_delay(Duration::from_millis(10))
+                    .with_max_times(2),
+            )
+            .sleep(|_| ready(()))
+            .metrics(metrics.clone())
+            .await;
+        assert!(result.is_err());
+
+        let mut attempts = 0;
+        let result = (|| {
+            attempts += 1;
+            ready(if attempts < 2 {
+                Err("retryable")
+            } else {
+                Ok(())
+            })
+        })
+        .retry(crate::ConstantBuilder::default().with_delay(Duration::from_millis(10)))
+        .sleep(|_| ready(()))
+        .metrics(metrics.clone())
+        .await;
+        assert!(result.is_ok());
+
+        assert_eq!(metrics.attempts(), 3 + 2);
+        assert_eq!(metrics.retries(), 2 + 1);
+        assert_eq!(metrics.slept(), Duration::from_millis(30));
+        assert_eq!(metrics.successes(), 1);
+        assert_eq!(metrics.failures(), 1);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    async fn test_retry_budget() {
+        let budget = Arc::new(crate::RetryBudget::new(2, 1.0));
+
+        let (result, stats) = always_error
+            .retry(ExponentialBuilder::default().with_max_times(5))
+            .sleep(|_| ready(()))
+            .budget(budget.clone())
+            .with_stats()
+            .await;
+        assert!(result.is_err());
+        // Gave up once the two tokens are used up.
+        assert_eq!(stats.attempts, 3);
+        assert_eq!(budget.tokens(), 0.0);
+
+        // A success refills the budget.
+        let result = (|| ready(Ok::<_, anyhow::Error>(())))
+            .retry(ExponentialBuilder::default())
+            .sleep(|_| ready(()))
+            .budget(budget.clone())
+            .await;
+        assert!(result.is_ok());
+        assert_eq!(budget.tokens(), 1.0);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    async fn test_retry_heal() {
+        let backoff = crate::AdaptiveBuilder::new(ExponentialBuilder::default().with_max_times(3));
+
+        let delays = Arc::new(Mutex::new(Vec::new()));
+        let sleep = {
+            let delays = delays.clone();
+            move |dur| {
+                delays.lock().push(dur);
+                ready(())
+            }
+        };
+
+        let result = always_error
+            .retry(&backoff)
+            .sleep(sleep.clone())
+            .heal(&backoff)
+            .await;
+        assert!(result.is_err());
+        assert_eq!(backoff.position(), 3);
+
+        // A retry starts from the delays of the previous failures, and heals on success.
+        let mut attempts = 0;
+        let result = (|| {
+            attempts += 1;
+            ready(if attempts < 2 { Err(()) } else { Ok(()) })
+        })
+        .retry(&backoff)
+        .sleep(sleep)
+        .heal(&backoff)
+        .await;
+        assert!(result.is_ok());
+        assert_eq!(backoff.position(), 2);
+        assert_eq!(
+            *delays.lock(),
+            vec![1, 2, 4, 4]
+                .into_iter()
+                .map(Duration::from_secs)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    async fn test_retry_global_max_retries() {
+        let limit = Arc::new(crate::GlobalRetryLimit::new(3));
+
+        let (_, stats) = always_error
+            .retry(ExponentialBuilder::default().with_max_times(2))
+            .sleep(|_| ready(()))
+            .global_max_retries(limit.clone())
+            .with_stats()
+            .await;
+        assert_eq!(stats.attempts, 3);
+
+        // Only one retry is left for the second operation.
+        let (_, stats) = always_error
+            .retry(ExponentialBuilder::default().with_max_times(2))
+            .sleep(|_| ready(()))
+            .global_max_retries(limit.clone())
+            .with_stats()
+            .await;
+        assert_eq!(stats.attempts, 2);
+
+        // Give up immediately once the limit is reached.
+        let (_, stats) = always_error
+            .retry(ExponentialBuilder::default().with_max_times(2))
+            .sleep(|_| ready(()))
+            .global_max_retries(limit.clone())
+            .with_stats()
+            .await;
+        assert_eq!(stats.attempts, 1);
+        assert_eq!(limit.remaining(), 0);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    async fn test_retry_budget_with_global_max_retries() {
+        let budget = Arc::new(crate::RetryBudget::new(2, 1.0));
+        let limit = Arc::new(crate::GlobalRetryLimit::new(0));
+
+        // The budget is kept if the global limit refuses to retry.
+        let (_, stats) = always_error
+            .retry(ExponentialBuilder::default())
+            .sleep(|_| ready(()))
+            .budget(budget.clone())
+            .global_max_retries(limit.clone())
+            .with_stats()
+            .await;
+        assert_eq!(stats.attempts, 1);
+        assert_eq!(budget.tokens(), 2.0);
+
+        // The global limit is kept if the budget refuses to retry.
+        let budget = Arc::new(crate::RetryBudget::new(0, 1.0));
+        let limit = Arc::new(crate::GlobalRetryLimit::new(2));
+        let (_, stats) = always_error
+            .retry(ExponentialBuilder::default())
+            .sleep(|_| ready(()))
+            .budget(budget.clone())
+            .global_max_retries(limit.clone())
+            .with_stats()
+            .await;
+        assert_eq!(stats.attempts, 1);
+        assert_eq!(limit.remaining(), 2);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    async fn test_retry_kill_switch() {
+        use core::sync::atomic::AtomicBool;
+        use core::sync::atomic::Ordering;
+
+        let switch = Arc::new(AtomicBool::new(true));
+        let (result, stats) = always_error
+            .retry(ExponentialBuilder::default())
+            .sleep(|_| ready(()))
+            .kill_switch(switch.clone())
+            .with_stats()
+            .await;
+        assert!(result.is_err());
+        assert_eq!(stats.attempts, 1);
+
+        // Flipped during the retry.
+        let attempts = Arc::new(Mutex::new(0));
+        let result = {
+            let attempts = attempts.clone();
+            let switch = switch.clone();
+            move || {
+                let mut attempts = attempts.lock();
+                *attempts += 1;
+                switch.store(*attempts >= 2, Ordering::Relaxed);
+                always_error()
+            }
+        }
+        .retry(ExponentialBuilder::default())
+        .sleep(|_| ready(()))
+        .kill_switch(switch.clone())
+        .await;
+        assert!(result.is_err());
+        assert_eq!(*attempts.lock(), 2);
+    }
+
+    #[test]
+    async fn test_retry_notify_timing() {
+        for (timing, expected) in [
+            (
+                NotifyTiming::BeforeSleep,
+                vec!["notify", "sleep", "notify", "sleep"],
+            ),
+            (
+                NotifyTiming::AfterSleep,
+                vec!["sleep", "notify", "sleep", "notify"],
+            ),
+        ] {
+            let events = Arc::new(Mutex::new(Vec::new()));
+
+            let result = always_error
+                .retry(ExponentialBuilder::default().with_max_times(2))
+                .sleep({
+                    let events = events.clone();
+                    move |_| {
+                        events.lock().push("sleep");
+                        ready(())
+                    }
+                })
+                .notify({
+                    let events = events.clone();
+                    move |_, _| events.lock().push("notify")
+                })
+                .notify_timing(timing)
+                .await;
+
+            assert!(result.is_err());
+            assert_eq!(*events.lock(), expected);
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    async fn test_retry_boxed() {
+        let tasks = vec![
+            always_error
+                .retry(ExponentialBuilder::default())
+                .sleep(|_| ready(()))
+                .boxed(),
+            (|| ready(Ok(())))
+                .retry(ExponentialBuilder::default())
+                .sleep(|_| ready(()))
+                .when(|_: &anyhow::Error| false)
+                .boxed(),
+        ];
+
+        let mut results = Vec::new();
+        for task in tasks {
+            results.push(task.await.is_ok());
+        }
+        assert_eq!(results, vec![false, true]);
+    }
+
+    #[test]
+    async fn test_retry_attempts_per_poll() {
+        use futures::future::poll_fn;
+
+        for (n, polls) in [(1, 4), (2, 2), (usize::MAX, 1)] {
+            let retry = always_error
+                .retry(ExponentialBuilder::default().with_max_times(3))
+                .sleep(|_| ready(()))
+                .yield_on_zero_delay(false)
+                .attempts_per_poll(n);
+            let mut retry = core::pin::pin!(retry);
+
+            let mut count = 0;
+            // The sleeps are always ready, so the retry is pending only if it yields.
+            let result = poll_fn(|cx| {
+                count += 1;
+                retry.as_mut().poll(cx)
+            })
+            .await;
+
+            assert!(result.is_err());
+            assert_eq!(count, polls, "attempts per poll: {n}");
+        }
+    }
+
+    #[test]
+    async fn test_retry_immediate_retries() {
+        let delays = Arc::new(Mutex::new(Vec::new()));
+
+        let result = always_error
+            .retry(
+                ExponentialBuilder::default()
+                    .with_min_delay(Duration::from_millis(10))
+                    .with_max_times(2),
+            )
+            .sleep({
+                let delays = delays.clone();
+                move |dur| {
+                    delays.lock().push(dur);
+                    ready(())
+                }
+            })
+            .immediate_retries(2)
+            .yield_on_zero_delay(false)
+            .with_stats()
+            .await;
+
+        assert!(result.0.is_err());
+        // The immediate retries are made on top of the max times of the backoff.
+        assert_eq!(result.1.attempts, 5);
+        // The backoff starts from its first delay after the immediate retries.
+        assert_eq!(
+            *delays.lock(),
+            vec![Duration::from_millis(10), Duration::from_millis(20)]
+        );
+    }
+
+    #[test]
+    async fn test_retry_always_retry_first() {
+        let mut attempts = 0;
+        let result = (|| {
+            attempts += 1;
+            always_error()
+        })
+        .retry(ExponentialBuilder::default().with_max_times(5))
+        .sleep(|_| ready(()))
+        .when(|_| false)
+        .always_retry_first()
+        .await;
+
+        // The first error is retried even though `when` rejects it, the second is not.
+        assert!(result.is_err());
+        assert_eq!(attempts, 2);
+    }
+
+    #[test]
+    async fn test_retry_on_threshold() {
+        let fired = Arc::new(Mutex::new(Vec::new()));
+        let notified = Arc::new(Mutex::new(0));
+
+        let result = always_error
+            .retry(ExponentialBuilder::default().with_max_times(5))
+            .sleep(|_| ready(()))
+            .notify({
+                let notified = notified.clone();
+                move |_, _| *notified.lock() += 1
+            })
+            .on_threshold(2, {
+                let fired = fired.clone();
+                move |err: &anyhow::Error| fired.lock().push(err.to_string())
+            })
+            .await;
+
+        assert!(result.is_err());
+        // Fired only once, while the notify sees every retry.
+        assert_eq!(*fired.lock(), vec!["test_query meets error".to_string()]);
+        assert_eq!(*notified.lock(), 5);
+
+        // Not fired if the retry gives up before the threshold.
+        let fired = Arc::new(Mutex::new(0));
+        let _ = always_error
+            .retry(ExponentialBuilder::default().with_max_times(2))
+            .sleep(|_| ready(()))
+            .on_threshold(3, {
+                let fired = fired.clone();
+                move |_: &anyhow::Error| *fired.lock() += 1
+            })
+            .await;
+        assert_eq!(*fired.lock(), 0);
+
+        // The hook is kept by the notify set after it.
+        let fired = Arc::new(Mutex::new(0));
+        let _ = always_error
+            .retry(ExponentialBuilder::default().with_max_times(5))
+            .sleep(|_| ready(()))
+            .on_threshold(2, {
+                let fired = fired.clone();
+                move |_: &anyhow::Error| *fired.lock() += 1
+            })
+            .notify(|_, _| {})
+            .await;
+        assert_eq!(*fired.lock(), 1);
+    }
+
+    #[test]
+    async fn test_retry_give_up_on_repeat() {
+        let errors = Arc::new(Mutex::new(
+            vec!["a", "b", "b", "a", "a", "a", "a"].into_iter(),
+        ));
+        let attempts = Arc::new(Mutex::new(0));
+
+        let result = {
+            let errors = errors.clone();
+            let attempts = attempts.clone();
+            move || {
+                *attempts.lock() += 1;
+                ready(Err::<(), _>(errors.lock().next().unwrap()))
+            }
+        }
+        .retry(ExponentialBuilder::default().with_max_times(10))
+        .sleep(|_| ready(()))
+        .when(|e| *e != "fatal")
+        .give_up_on_repeat(3, |a, b| a == b)
+        .await;
+
+        // Give up on the third "a" in a row.
+        assert_eq!(result.unwrap_err(), "a");
+        assert_eq!(*attempts.lock(), 6);
+
+        // The inner decide still applies.
+        let result = (|| ready(Err::<(), _>("fatal")))
+            .retry(ExponentialBuilder::default())
+            .sleep(|_| ready(()))
+            .when(|e| *e != "fatal")
+            .give_up_on_repeat(3, |a, b| a == b)
+            .with_stats()
+            .await;
+        assert_eq!(result.0.unwrap_err(), "fatal");
+        assert_eq!(result.1.attempts, 1);
+
+        // The guard is kept by the conditions set after it.
+        let (result, stats) = (|| ready(Err::<(), _>("a")))
+            .retry(ExponentialBuilder::default().with_max_times(10))
+            .sleep(|_| ready(()))
+            .give_up_on_repeat(2, |a, b| a == b)
+            .when(|_| true)
+            .with_stats()
+            .await;
+        assert_eq!(result.unwrap_err(), "a");
+        assert_eq!(stats.attempts, 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid repeat times")]
+    async fn test_retry_give_up_on_repeat_once() {
+        let _ = (|| ready(Err::<(), _>("a")))
+            .retry(ExponentialBuilder::default())
+            .sleep(|_| ready(()))
+            .give_up_on_repeat(1, |a, b| a == b)
+            .await;
+    }
+
+    #[test]
+    async fn test_retry_with_stats_succeeded_attempt() {
+        let (result, stats) = (|| ready(Ok::<_, anyhow::Error>(1)))
+            .retry(crate::ConstantBuilder::default())
+            .sleep(|_| ready(()))
+            .with_stats()
+            .await;
+        assert_eq!(result.unwrap(), 1);
+        assert_eq!(stats.succeeded_attempt, Some(1));
+
+        let (result, stats) = always_error
+            .retry(crate::ConstantBuilder::default().with_max_times(2))
+            .sleep(|_| ready(()))
+            .with_stats()
+            .await;
+        assert!(result.is_err());
+        assert_eq!(stats.attempts, 3);
+        assert_eq!(stats.succeeded_attempt, None);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    async fn test_retry_with_clock() {
+        let clock = crate::ManualClock::new();
+        let elapsed = Arc::new(Mutex::new(Vec::new()));
+
+        let result = always_error
+            .retry(crate::ConstantBuilder::default().with_max_times(2))
+            .clock(clock.clone())
+            .sleep(move |dur| {
+                clock.advance(dur);
+                ready(())
+            })
+            .on_retry({
+                let elapsed = elapsed.clone();
+                move |ctx| elapsed.lock().push(ctx.elapsed)
+            })
+            .await;
+
+        assert!(result.is_err());
+        // Only the time advanced by the sleeper is measured.
+        assert_eq!(
+            *elapsed.lock(),
+            vec![Duration::ZERO, Duration::from_secs(1)]
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    async fn test_retry_min_gap() {
+        let clock = crate::ManualClock::new();
+        let slept = Arc::new(Mutex::new(Vec::new()));
+        let sleep = {
+            let (clock, slept) = (clock.clone(), slept.clone());
+            move |dur| {
+                clock.advance(dur);
+                slept.lock().push(dur);
+                ready(())
+            }
         };
 
-        let backoff = ExponentialBuilder::default().with_min_delay(Duration::from_millis(1));
-        let result = f
-            .retry(backoff)
-            // Only retry If error message is `retryable`
-            .when(|e| e.to_string() == "retryable")
+        // The delays shorter than the gap are extended.
+        let result = always_error
+            .retry(
+                crate::ConstantBuilder::default()
+                    .with_delay(Duration::from_millis(100))
+                    .with_max_times(2),
+            )
+            .clock(clock.clone())
+            .sleep(sleep.clone())
+            .min_gap(Duration::from_millis(250))
+            .await;
+        assert!(result.is_err());
+        assert_eq!(
+            *slept.lock(),
+            [100, 150, 100, 150].map(Duration::from_millis)
+        );
+
+        // The immediate retries keep the gap as well, and the longer delays are kept as is.
+        slept.lock().clear();
+        let result = always_error
+            .retry(
+                crate::ConstantBuilder::default()
+                    .with_delay(Duration::from_millis(300))
+                    .with_max_times(1),
+            )
+            .clock(clock)
+            .sleep(sleep)
+            .immediate_retries(1)
+            .min_gap(Duration::from_millis(250))
             .await;
+        assert!(result.is_err());
+        assert_eq!(*slept.lock(), [250, 300].map(Duration::from_millis));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    async fn test_retry_on_attempt_complete() {
+        let clock = crate::ManualClock::new();
+        let completed = Arc::new(Mutex::new(Vec::new()));
+
+        let mut attempts = 0;
+        let result = {
+            let clock = clock.clone();
+            move || {
+                attempts += 1;
+                // Every attempt takes longer than the previous one.
+                clock.advance(Duration::from_secs(attempts));
+                ready(if attempts < 3 {
+                    Err("retryable")
+                } else {
+                    Ok(attempts)
+                })
+            }
+        }
+        .retry(crate::ConstantBuilder::default().with_delay(Duration::from_secs(10)))
+        .sleep({
+            let clock = clock.clone();
+            move |dur| {
+                clock.advance(dur);
+                ready(())
+            }
+        })
+        .clock(clock)
+        .on_attempt_complete({
+            let completed = completed.clone();
+            move |attempt, took, result: &Result<u64, &str>| {
+                completed.lock().push((attempt, took, result.is_ok()))
+            }
+        })
+        .await;
+
+        assert_eq!(result, Ok(3));
+        // The sleeps are not counted in the duration of the attempts.
+        assert_eq!(
+            *completed.lock(),
+            vec![
+                (1, Duration::from_secs(1), false),
+                (2, Duration::from_secs(2), false),
+                (3, Duration::from_secs(3), true),
+            ]
+        );
+    }
+
+    #[test]
+    async fn test_retry_no_sleep() {
+        let mut attempts = 0;
+        let (result, stats) = (|| {
+            attempts += 1;
+            always_error()
+        })
+        .retry(ExponentialBuilder::default().with_max_times(5))
+        .no_sleep()
+        .with_stats()
+        .await;
 
         assert!(result.is_err());
-        assert_eq!("not retryable", result.unwrap_err().to_string());
-        // `f` always returns error "not retryable", so it should be executed
-        // only once.
-        assert_eq!(*error_times.lock().await, 1);
+        // The backoff is still consumed, only the sleeps are skipped.
+        assert_eq!(attempts, 6);
+        assert_eq!(stats.attempts, 6);
     }
 
     #[test]
-    async fn test_retry_with_retryable_error() {
-        let error_times = Mutex::new(0);
+    async fn test_retry_on_retry_ctl() {
+        let attempts = Arc::new(Mutex::new(0));
+        let gave_up = Arc::new(Mutex::new(None));
 
-        let f = || async {
-            let mut x = error_times.lock().await;
-            *x += 1;
-            Err::<(), anyhow::Error>(anyhow::anyhow!("retryable"))
+        // The hook stops retrying after the second attempt without sleeping.
+        let result = {
+            let attempts = attempts.clone();
+            move || {
+                *attempts.lock() += 1;
+                always_error()
+            }
+        }
+        .retry(ExponentialBuilder::default().with_max_times(5))
+        .sleep(|_| ready(()))
+        .on_retry_ctl({
+            let attempts = attempts.clone();
+            move |_, _| {
+                if *attempts.lock() >= 2 {
+                    ControlFlow::Break(())
+                } else {
+                    ControlFlow::Continue(())
+                }
+            }
+        })
+        .on_giveup({
+            let gave_up = gave_up.clone();
+            move |_, attempts| *gave_up.lock() = Some(attempts)
+        })
+        .await;
+
+        assert!(result.is_err());
+        assert_eq!(*attempts.lock(), 2);
+        assert_eq!(*gave_up.lock(), Some(2));
+
+        // The veto is honored after the sleep as well.
+        let result = always_error
+            .retry(ExponentialBuilder::default().with_max_times(5))
+            .sleep(|_| ready(()))
+            .notify_timing(NotifyTiming::AfterSleep)
+            .on_retry_ctl(|_, _| ControlFlow::Break(()))
+            .with_stats()
+            .await;
+        assert!(result.0.is_err());
+        assert_eq!(result.1.attempts, 1);
+    }
+
+    #[test]
+    async fn test_retry_override_max_times() {
+        let slept = Arc::new(Mutex::new(Vec::new()));
+        let sleep = {
+            let slept = slept.clone();
+            move |dur| {
+                slept.lock().push(dur);
+                ready(())
+            }
         };
+        let backoff = crate::FibonacciBuilder::default()
+            .with_min_delay(Duration::from_secs(1))
+            .with_max_times(3);
 
-        let backoff = ExponentialBuilder::default().with_min_delay(Duration::from_millis(1));
-        let result = f
+        // Fewer retries than the backoff gives.
+        let result = always_error
             .retry(backoff)
-            // Only retry If error message is `retryable`
-            .when(|e| e.to_string() == "retryable")
+            .sleep(sleep.clone())
+            .override_max_times(1)
             .await;
+        assert!(result.is_err());
+        assert_eq!(*slept.lock(), [Duration::from_secs(1)]);
 
+        // More retries than the backoff gives, the last delay is repeated.
+        slept.lock().clear();
+        let result = always_error
+            .retry(backoff)
+            .sleep(sleep)
+            .override_max_times(5)
+            .await;
         assert!(result.is_err());
-        assert_eq!("retryable", result.unwrap_err().to_string());
-        // `f` always returns error "retryable", so it should be executed
-        // 4 times (retry 3 times).
-        assert_eq!(*error_times.lock().await, 4);
+        assert_eq!(*slept.lock(), [1, 1, 2, 2, 2].map(Duration::from_secs));
     }
 
+    #[cfg(feature = "std")]
     #[test]
-    async fn test_fn_mut_when_and_notify() {
-        let mut calls_retryable: Vec<()> = vec![];
-        let mut calls_notify: Vec<()> = vec![];
+    async fn test_retry_with_time_source() {
+        // The time source starts at an arbitrary point.
+        let now = Arc::new(Mutex::new(Duration::from_secs(100)));
+        let elapsed = Arc::new(Mutex::new(Vec::new()));
 
-        let f = || async { Err::<(), anyhow::Error>(anyhow::anyhow!("retryable")) };
+        let result = always_error
+            .retry(crate::ConstantBuilder::default().with_max_times(2))
+            .time_source({
+                let now = now.clone();
+                move || *now.lock()
+            })
+            .sleep(move |dur| {
+                *now.lock() += dur;
+                ready(())
+            })
+            .on_retry({
+                let elapsed = elapsed.clone();
+                move |ctx| elapsed.lock().push(ctx.elapsed)
+            })
+            .await;
 
-        let backoff = ExponentialBuilder::default().with_min_delay(Duration::from_millis(1));
-        let result = f
-            .retry(backoff)
-            .when(|_| {
-                calls_retryable.push(());
-                true
+        assert!(result.is_err());
+        assert_eq!(
+            *elapsed.lock(),
+            vec![Duration::ZERO, Duration::from_secs(1)]
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    async fn test_retry_with_when_elapsed() {
+        let clock = crate::ManualClock::new();
+        let elapsed = Arc::new(Mutex::new(Vec::new()));
+
+        let result = always_error
+            .retry(crate::ConstantBuilder::default().without_max_times())
+            .clock(clock.clone())
+            .sleep(move |dur| {
+                clock.advance(dur);
+                ready(())
             })
-            .notify(|_, _| {
-                calls_notify.push(());
+            .when_elapsed({
+                let elapsed = elapsed.clone();
+                move |_, dur| {
+                    elapsed.lock().push(dur);
+                    dur < Duration::from_secs(2)
+                }
             })
             .await;
 
         assert!(result.is_err());
-        assert_eq!("retryable", result.unwrap_err().to_string());
-        // `f` always returns error "retryable", so it should be executed
-        // 4 times (retry 3 times).
-        assert_eq!(calls_retryable.len(), 4);
-        assert_eq!(calls_notify.len(), 3);
+        // The retry gives up once 2s have elapsed.
+        assert_eq!(
+            *elapsed.lock(),
+            vec![
+                Duration::ZERO,
+                Duration::from_secs(1),
+                Duration::from_secs(2)
+            ]
+        );
     }
-}
 
-#[cfg(test)]
-mod custom_sleeper_tests {
-    extern crate alloc;
+    #[test]
+    async fn test_retry_with_decide() {
+        let attempts = Arc::new(Mutex::new(0));
+        let delays = Arc::new(Mutex::new(Vec::new()));
 
-    use alloc::string::ToString;
-    use core::{future::ready, time::Duration};
+        let result = {
+            let attempts = attempts.clone();
+            move || {
+                let mut attempts = attempts.lock();
+                *attempts += 1;
+                ready(Err::<(), _>(*attempts))
+            }
+        }
+        .retry(crate::ConstantBuilder::default())
+        .sleep(|_| ready(()))
+        .decide(|e: usize| match e {
+            1 => RetryDecision::Retry {
+                error: e,
+                after: Some(Duration::from_millis(5)),
+            },
+            2 => RetryDecision::Retry {
+                error: e * 10,
+                after: None,
+            },
+            _ => RetryDecision::Stop(e),
+        })
+        .notify({
+            let delays = delays.clone();
+            move |e: &usize, dur| delays.lock().push((*e, dur))
+        })
+        .await;
 
-    #[cfg(target_arch = "wasm32")]
-    use wasm_bindgen_test::wasm_bindgen_test as test;
+        assert_eq!(result, Err(3));
+        assert_eq!(*attempts.lock(), 3);
+        assert_eq!(
+            *delays.lock(),
+            vec![(1, Duration::from_millis(5)), (20, Duration::from_secs(1))]
+        );
+    }
 
-    #[cfg(not(target_arch = "wasm32"))]
-    use tokio::test;
+    #[test]
+    async fn test_retry_with_no_sleep_when() {
+        for keeps_backoff in [false, true] {
+            let attempts = Arc::new(Mutex::new(0));
+            let sleeps = Arc::new(Mutex::new(Vec::new()));
 
-    use super::*;
-    use crate::ExponentialBuilder;
+            let retry = {
+                let attempts = attempts.clone();
+                move || {
+                    let mut attempts = attempts.lock();
+                    *attempts += 1;
+                    // Odd attempts fail with stale reads.
+                    ready(Err::<(), _>(*attempts % 2 == 1))
+                }
+            }
+            .retry(crate::ConstantBuilder::default().with_max_times(3))
+            .sleep({
+                let sleeps = sleeps.clone();
+                move |dur| {
+                    sleeps.lock().push(dur);
+                    ready(())
+                }
+            })
+            .no_sleep_when(|stale: &bool| *stale);
+            let result = if keeps_backoff {
+                retry.no_sleep_keeps_backoff().await
+            } else {
+                retry.await
+            };
 
-    async fn always_error() -> anyhow::Result<()> {
-        Err(anyhow::anyhow!("test_query meets error"))
+            assert!(result.is_err());
+            // Stale reads never sleep.
+            assert!(sleeps
+                .lock()
+                .iter()
+                .all(|dur| *dur == Duration::from_secs(1)));
+            if keeps_backoff {
+                // Only the non-stale errors consume the backoff.
+                assert_eq!(*attempts.lock(), 8);
+                assert_eq!(sleeps.lock().len(), 3);
+            } else {
+                assert_eq!(*attempts.lock(), 4);
+                assert_eq!(sleeps.lock().len(), 1);
+            }
+        }
     }
 
     #[test]
@@ -458,4 +5525,340 @@ mod custom_sleeper_tests {
         assert!(result.is_err());
         assert_eq!("test_query meets error", result.unwrap_err().to_string());
     }
+
+    #[test]
+    async fn test_retry_with_initial_delay() {
+        let sleeps = Arc::new(Mutex::new(Vec::new()));
+
+        let result = always_error
+            .retry(ExponentialBuilder::default().with_min_delay(Duration::from_millis(1)))
+            .sleep({
+                let sleeps = sleeps.clone();
+                move |dur| {
+                    sleeps.lock().push(dur);
+                    ready(())
+                }
+            })
+            .initial_delay(Duration::from_secs(5))
+            .await;
+
+        assert!(result.is_err());
+        // The initial delay must not consume the backoff.
+        assert_eq!(
+            *sleeps.lock(),
+            vec![
+                Duration::from_secs(5),
+                Duration::from_millis(1),
+                Duration::from_millis(2),
+                Duration::from_millis(4),
+            ]
+        );
+    }
+
+    #[test]
+    async fn test_retry_with_spread() {
+        let run = |seed: u64| {
+            let sleeps = Arc::new(Mutex::new(Vec::new()));
+            let fut = always_error
+                .retry(crate::ConstantBuilder::default().with_delay(Duration::from_millis(1)))
+                .sleep({
+                    let sleeps = sleeps.clone();
+                    move |dur| {
+                        sleeps.lock().push(dur);
+                        ready(())
+                    }
+                })
+                .initial_delay(Duration::from_secs(5))
+                .spread(Duration::from_secs(1))
+                .spread_seed(seed);
+            async move {
+                assert!(fut.await.is_err());
+                let sleeps = sleeps.lock().clone();
+                sleeps
+            }
+        };
+
+        let sleeps = run(42).await;
+        // The offset is added to the initial delay, and doesn't consume the backoff.
+        assert_eq!(sleeps.len(), 4);
+        assert!(
+            sleeps[0] >= Duration::from_secs(5),
+            "current: {:?}",
+            sleeps[0]
+        );
+        assert!(
+            sleeps[0] < Duration::from_secs(6),
+            "current: {:?}",
+            sleeps[0]
+        );
+        assert_eq!(sleeps[1..], [Duration::from_millis(1); 3]);
+
+        // The same seed gives the same offset.
+        assert_eq!(run(42).await, sleeps);
+        assert_ne!(run(7).await[0], sleeps[0]);
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum TestError {
+        Query,
+        Sleep(SleepError),
+    }
+
+    impl From<SleepError> for TestError {
+        fn from(err: SleepError) -> Self {
+            TestError::Sleep(err)
+        }
+    }
+
+    /// A sleeper that fails after sleeping `limit` times.
+    struct LimitedSleeper {
+        sleeps: Arc<Mutex<usize>>,
+        limit: usize,
+    }
+
+    impl TrySleeper for LimitedSleeper {
+        type Sleep = core::future::Ready<Result<(), SleepError>>;
+
+        fn try_sleep(&self, _: Duration) -> Self::Sleep {
+            let mut sleeps = self.sleeps.lock();
+            if *sleeps >= self.limit {
+                return ready(Err(SleepError::new("closed")));
+            }
+            *sleeps += 1;
+            ready(Ok(()))
+        }
+    }
+
+    #[test]
+    async fn test_retry_with_try_sleep() {
+        let attempts = Arc::new(Mutex::new(0));
+        let sleeps = Arc::new(Mutex::new(0));
+
+        let result = {
+            let attempts = attempts.clone();
+            move || {
+                *attempts.lock() += 1;
+                ready(Err::<(), _>(TestError::Query))
+            }
+        }
+        .retry(ExponentialBuilder::default())
+        .try_sleep(LimitedSleeper {
+            sleeps: sleeps.clone(),
+            limit: 1,
+        })
+        .await;
+
+        // The failed sleep stops the retry.
+        assert_eq!(result, Err(TestError::Sleep(SleepError::new("closed"))));
+        assert_eq!(*attempts.lock(), 2);
+        assert_eq!(*sleeps.lock(), 1);
+    }
+
+    #[test]
+    async fn test_retry_with_try_sleep_of_sleeper() {
+        let attempts = Arc::new(Mutex::new(0));
+
+        let result = {
+            let attempts = attempts.clone();
+            move || {
+                *attempts.lock() += 1;
+                ready(Err::<(), _>(TestError::Query))
+            }
+        }
+        .retry(ExponentialBuilder::default())
+        .try_sleep(|_| ready(()))
+        .await;
+
+        assert_eq!(result, Err(TestError::Query));
+        assert_eq!(*attempts.lock(), 4);
+    }
+
+    #[test]
+    async fn test_retry_nested() {
+        #[derive(Debug, PartialEq)]
+        enum NestedError {
+            Transient,
+            Overloaded,
+        }
+
+        let calls = Arc::new(Mutex::new(0));
+        let slept = Arc::new(Mutex::new(Vec::new()));
+
+        let operation = || {
+            let calls = calls.clone();
+            let slept = slept.clone();
+            (move || {
+                let calls = calls.clone();
+                async move {
+                    let mut calls = calls.lock();
+                    *calls += 1;
+                    // Every third call is rejected by the overloaded service.
+                    if *calls % 3 == 0 {
+                        Err::<(), _>(NestedError::Overloaded)
+                    } else {
+                        Err(NestedError::Transient)
+                    }
+                }
+            })
+            .retry(
+                crate::ConstantBuilder::default()
+                    .with_delay(Duration::from_secs(1))
+                    .with_max_times(2),
+            )
+            .when(|e| *e == NestedError::Transient)
+            .sleep(move |dur| {
+                slept.lock().push(("inner", dur));
+                ready(())
+            })
+        };
+
+        let result = operation
+            .retry(
+                ExponentialBuilder::default()
+                    .with_min_delay(Duration::from_secs(10))
+                    .with_max_times(2),
+            )
+            .when(|e| *e == NestedError::Overloaded)
+            .sleep({
+                let slept = slept.clone();
+                move |dur| {
+                    slept.lock().push(("outer", dur));
+                    ready(())
+                }
+            })
+            .await;
+
+        // Every outer attempt starts a fresh inner retry with its own backoff.
+        assert_eq!(result, Err(NestedError::Overloaded));
+        assert_eq!(*calls.lock(), 9);
+        let inner = ("inner", Duration::from_secs(1));
+        assert_eq!(
+            *slept.lock(),
+            vec![
+                inner,
+                inner,
+                ("outer", Duration::from_secs(10)),
+                inner,
+                inner,
+                ("outer", Duration::from_secs(20)),
+                inner,
+                inner,
+            ]
+        );
+    }
+
+    #[test]
+    async fn test_retry_with_reason() {
+        let err = always_error
+            .retry(ExponentialBuilder::default().with_max_times(2))
+            .sleep(|_| ready(()))
+            .with_reason()
+            .await
+            .unwrap_err();
+        assert_eq!(err.reason(), GiveUpReason::Exhausted);
+        assert_eq!(err.attempts(), 3);
+        assert_eq!(err.error().to_string(), "test_query meets error");
+
+        // Using up the max times is exhausted as well.
+        let err = always_error
+            .retry(ExponentialBuilder::default())
+            .sleep(|_| ready(()))
+            .override_max_times(1)
+            .with_reason()
+            .await
+            .unwrap_err();
+        assert_eq!(err.reason(), GiveUpReason::Exhausted);
+        assert_eq!(err.attempts(), 2);
+
+        let err = always_error
+            .retry(ExponentialBuilder::default())
+            .sleep(|_| ready(()))
+            .when(|_| false)
+            .with_reason()
+            .await
+            .unwrap_err();
+        assert_eq!(err.reason(), GiveUpReason::NonRetryable);
+        assert_eq!(err.attempts(), 1);
+
+        let err = always_error
+            .retry(ExponentialBuilder::default())
+            .sleep(|_| ready(()))
+            .on_retry_ctl(|_, _| ControlFlow::Break(()))
+            .with_reason()
+            .await
+            .unwrap_err();
+        assert_eq!(err.reason(), GiveUpReason::Aborted);
+        assert_eq!(err.attempts(), 1);
+
+        // The timer fires before the attempt that never finishes.
+        #[cfg(feature = "std")]
+        {
+            let err = (|| core::future::pending::<anyhow::Result<()>>())
+                .retry(ExponentialBuilder::default())
+                .sleep(|_| ready(()))
+                .timeout_with(Duration::from_secs(1), |_| ready(()))
+                .with_reason()
+                .await
+                .unwrap_err();
+            assert_eq!(err.reason(), GiveUpReason::Deadline);
+            assert!(err.into_inner().is::<crate::RetryTimedOut>());
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    async fn test_retry_with_reason_kill_switch() {
+        use core::sync::atomic::AtomicBool;
+
+        let err = always_error
+            .retry(ExponentialBuilder::default())
+            .sleep(|_| ready(()))
+            .kill_switch(Arc::new(AtomicBool::new(true)))
+            .with_reason()
+            .await
+            .unwrap_err();
+        assert_eq!(err.reason(), GiveUpReason::KillSwitch);
+        assert_eq!(err.reason().as_str(), "kill_switch");
+        assert_eq!(
+            err.to_string(),
+            "retry gave up (kill_switch) after 1 attempts: test_query meets error"
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    async fn test_retry_with_reason_budget_and_global_limit() {
+        let err = always_error
+            .retry(ExponentialBuilder::default())
+            .sleep(|_| ready(()))
+            .budget(Arc::new(crate::RetryBudget::new(1, 1.0)))
+            .with_reason()
+            .await
+            .unwrap_err();
+        assert_eq!(err.reason(), GiveUpReason::Budget);
+        assert_eq!(err.reason().as_str(), "budget");
+        assert_eq!(err.attempts(), 2);
+
+        let err = always_error
+            .retry(ExponentialBuilder::default())
+            .sleep(|_| ready(()))
+            .global_max_retries(Arc::new(crate::GlobalRetryLimit::new(1)))
+            .with_reason()
+            .await
+            .unwrap_err();
+        assert_eq!(err.reason(), GiveUpReason::GlobalLimit);
+        assert_eq!(err.reason().as_str(), "global_limit");
+        assert_eq!(err.attempts(), 2);
+
+        // The max times is checked before the budget and the global limit.
+        let err = always_error
+            .retry(ExponentialBuilder::default())
+            .sleep(|_| ready(()))
+            .override_max_times(1)
+            .global_max_retries(Arc::new(crate::GlobalRetryLimit::new(1)))
+            .with_reason()
+            .await
+            .unwrap_err();
+        assert_eq!(err.reason(), GiveUpReason::Exhausted);
+    }
 }
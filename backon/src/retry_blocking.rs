@@ -0,0 +1,143 @@
+use core::future::Future;
+use core::pin::Pin;
+use core::task::Context;
+use core::task::Poll;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use crate::backoff::BackoffBuilder;
+use crate::Retry;
+use crate::Retryable;
+
+/// Retry a blocking function from async code, running every attempt on tokio's blocking pool.
+///
+/// Every attempt calls `f` in [`tokio::task::spawn_blocking`], so the blocking call never stalls
+/// the async runtime, and the retry sleeps between the attempts asynchronously by the backoff
+/// built from `builder`. The returned [`Retry`] could be configured as usual, like
+/// [`Retry::when`] and [`Retry::notify`].
+///
+/// `f` and its result are moved to the blocking threads, so they must be `Send + 'static`: move
+/// owned data into the closure instead of borrowing it. The attempts run one after another, `f`
+/// is never called concurrently.
+///
+/// It must be polled within a tokio runtime, and it requires the `tokio-blocking` feature. If
+/// `f` panics, the panic is resumed when the retry is polled.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::fs;
+///
+/// use backon::retry_blocking;
+/// use backon::ExponentialBuilder;
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() -> std::io::Result<()> {
+///     let path = String::from("/var/run/service.pid");
+///     let pid = retry_blocking(ExponentialBuilder::default(), move || {
+///         fs::read_to_string(&path)
+///     })
+///     .notify(|err, dur| println!("retrying {:?} after {:?}", err, dur))
+///     .await?;
+///     println!("service is running as {}", pid.trim());
+///
+///     Ok(())
+/// }
+/// ```
+#[allow(clippy::type_complexity)]
+pub fn retry_blocking<B, T, E, F>(
+    builder: B,
+    f: F,
+) -> Retry<B::Backoff, T, E, BlockingAttempt<T, E>, impl FnMut() -> BlockingAttempt<T, E>>
+where
+    B: BackoffBuilder,
+    T: Send + 'static,
+    E: Send + 'static,
+    F: FnMut() -> Result<T, E> + Send + 'static,
+{
+    let f = Arc::new(Mutex::new(f));
+    let attempt = move || {
+        let f = f.clone();
+        BlockingAttempt(tokio::task::spawn_blocking(move || {
+            let mut f = f.lock().unwrap_or_else(|err| err.into_inner());
+            f()
+        }))
+    };
+    attempt.retry(builder)
+}
+
+/// BlockingAttempt is a single attempt made by [`retry_blocking`] on the blocking pool.
+pub struct BlockingAttempt<T, E>(tokio::task::JoinHandle<Result<T, E>>);
+
+impl<T, E> Future for BlockingAttempt<T, E> {
+    type Output = Result<T, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let handle = Pin::new(&mut self.get_mut().0);
+        match handle.poll(cx) {
+            Poll::Pending => Poll::Pending,
+            Poll::Ready(Ok(result)) => Poll::Ready(result),
+            Poll::Ready(Err(err)) => match err.try_into_panic() {
+                Ok(panic) => std::panic::resume_unwind(panic),
+                // The task is never aborted, so it could only be cancelled by the runtime
+                // shutting down.
+                Err(err) => panic!("blocking attempt was cancelled: {err}"),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+    use std::thread;
+    use std::thread::ThreadId;
+    use std::vec::Vec;
+
+    use tokio::test;
+
+    use super::*;
+    use crate::ConstantBuilder;
+
+    #[test]
+    async fn test_retry_blocking() {
+        let threads = Arc::new(Mutex::new(Vec::<ThreadId>::new()));
+
+        let result = retry_blocking(
+            ConstantBuilder::default()
+                .with_delay(Duration::from_millis(1))
+                .with_max_times(5),
+            {
+                let threads = threads.clone();
+                move || {
+                    let mut threads = threads.lock().unwrap();
+                    threads.push(thread::current().id());
+                    if threads.len() < 3 {
+                        Err("not yet")
+                    } else {
+                        Ok(threads.len())
+                    }
+                }
+            },
+        )
+        .await;
+
+        assert_eq!(result, Ok(3));
+        // The attempts never run on the thread of the async task.
+        let current = thread::current().id();
+        assert!(threads.lock().unwrap().iter().all(|id| *id != current));
+    }
+
+    #[test]
+    async fn test_retry_blocking_exhausted() {
+        let result = retry_blocking(
+            ConstantBuilder::default()
+                .with_delay(Duration::from_millis(1))
+                .with_max_times(2),
+            || Err::<(), _>("fail"),
+        )
+        .await;
+
+        assert_eq!(result, Err("fail"));
+    }
+}
@@ -0,0 +1,235 @@
+use core::future::Future;
+use std::vec::Vec;
+
+use crate::backoff::BackoffBuilder;
+use crate::sleep::MaybeSleeper;
+use crate::DefaultSleeper;
+use crate::Retryable;
+use crate::Sleeper;
+
+/// RetryEach applies the same retry policy to every item of a batch independently.
+///
+/// Every item gets its own [`Retry`](crate::Retry) with a fresh backoff built from a clone of
+/// the builder, so a flaky item doesn't use up the retries of the others. The items are
+/// processed one by one in order.
+///
+/// - [`RetryEach::collect`] retries all items and returns the result of every item. Set
+///   [`RetryEach::with_max_failure_rate`] to abort the batch once too many items have failed.
+/// - [`RetryEach::try_collect`] fails fast: it stops at the first item that still fails after
+///   retrying, and returns its error.
+///
+/// # Examples
+///
+/// ```no_run
+/// use anyhow::Result;
+/// use backon::ExponentialBuilder;
+/// use backon::RetryEach;
+///
+/// async fn fetch(url: &str) -> Result<String> {
+///     Ok(reqwest::get(url).await?.text().await?)
+/// }
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() -> Result<()> {
+///     let urls = ["https://www.rust-lang.org", "https://crates.io"];
+///
+///     let results = RetryEach::new(ExponentialBuilder::default())
+///         .collect(urls, |url| fetch(url))
+///         .await;
+///     for (url, result) in urls.iter().zip(results) {
+///         println!("fetch {} finished: {:?}", url, result.map(|s| s.len()));
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct RetryEach<B, SF: MaybeSleeper = DefaultSleeper> {
+    builder: B,
+    sleep_fn: SF,
+    /// The max failure rate and the number of items to process before checking it.
+    max_failure_rate: Option<(f32, usize)>,
+}
+
+impl<B: BackoffBuilder + Clone> RetryEach<B> {
+    /// Create a new policy that retries every item with a backoff built by `builder`.
+    pub fn new(builder: B) -> Self {
+        RetryEach {
+            builder,
+            sleep_fn: DefaultSleeper::default(),
+            max_failure_rate: None,
+        }
+    }
+}
+
+impl<B: BackoffBuilder + Clone, SF: MaybeSleeper> RetryEach<B, SF> {
+    /// Set the sleeper for retrying the items.
+    ///
+    /// The sleeper should implement the [`Sleeper`] trait. The simplest way is to use a closure that returns a `Future<Output=()>`.
+    ///
+    /// If not specified, we use the [`DefaultSleeper`].
+    pub fn sleep<SN: Sleeper + Clone>(self, sleep_fn: SN) -> RetryEach<B, SN> {
+        RetryEach {
+            builder: self.builder,
+            sleep_fn,
+            max_failure_rate: self.max_failure_rate,
+        }
+    }
+
+    /// Abort [`RetryEach::collect`] once the rate of items that failed after retrying exceeds
+    /// `rate`.
+    ///
+    /// A high failure rate usually means a systemic outage, in which case retrying the rest of
+    /// the batch is pointless. The rate is checked after every item once `min_items` items have
+    /// been processed, so a single early failure doesn't abort the batch. The remaining items
+    /// are not processed, and the results collected so far are returned.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the rate is not within `[0.0, 1.0]`.
+    pub fn with_max_failure_rate(mut self, rate: f32, min_items: usize) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&rate),
+            "invalid failure rate that not within [0.0, 1.0]"
+        );
+
+        self.max_failure_rate = Some((rate, min_items));
+        self
+    }
+}
+
+impl<B: BackoffBuilder + Clone, SF: Sleeper + Clone> RetryEach<B, SF> {
+    /// Retry every item, and return the results of all items in order.
+    ///
+    /// `f` is called with the item for every attempt.
+    ///
+    /// If the batch is aborted by [`RetryEach::with_max_failure_rate`], only the results of the
+    /// items processed so far are returned.
+    pub async fn collect<I, T, E, Fut, F>(&self, items: I, mut f: F) -> Vec<Result<T, E>>
+    where
+        I: IntoIterator,
+        Fut: Future<Output = Result<T, E>>,
+        F: FnMut(&I::Item) -> Fut,
+    {
+        let mut results = Vec::new();
+        let mut failures = 0;
+        for item in items {
+            let result = self.retry(&item, &mut f).await;
+            if result.is_err() {
+                failures += 1;
+            }
+            results.push(result);
+
+            if let Some((rate, min_items)) = self.max_failure_rate {
+                if results.len() >= min_items
+                    && failures as f64 > results.len() as f64 * rate as f64
+                {
+                    break;
+                }
+            }
+        }
+        results
+    }
+
+    /// Retry every item, and stop at the first item that still fails after retrying.
+    ///
+    /// The outputs of all items are returned in order if they all succeed. Otherwise, the error
+    /// of the failed item is returned, and the items after it are not processed.
+    pub async fn try_collect<I, T, E, Fut, F>(&self, items: I, mut f: F) -> Result<Vec<T>, E>
+    where
+        I: IntoIterator,
+        Fut: Future<Output = Result<T, E>>,
+        F: FnMut(&I::Item) -> Fut,
+    {
+        let mut outputs = Vec::new();
+        for item in items {
+            outputs.push(self.retry(&item, &mut f).await?);
+        }
+        Ok(outputs)
+    }
+
+    async fn retry<Item, T, E, Fut, F>(&self, item: &Item, f: &mut F) -> Result<T, E>
+    where
+        Fut: Future<Output = Result<T, E>>,
+        F: FnMut(&Item) -> Fut,
+    {
+        (|| f(item))
+            .retry(self.builder.clone())
+            .sleep(self.sleep_fn.clone())
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::future::ready;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+    use std::vec;
+
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test as test;
+
+    #[cfg(not(target_arch = "wasm32"))]
+    use tokio::test;
+
+    use super::*;
+    use crate::ConstantBuilder;
+
+    /// Item `n` fails `n` times before it succeeds.
+    fn flaky(attempts: &Mutex<HashMap<u32, u32>>, n: u32) -> core::future::Ready<Result<u32, u32>> {
+        let mut attempts = attempts.lock().unwrap();
+        let count = attempts.entry(n).or_default();
+        *count += 1;
+        ready(if *count > n { Ok(n * 10) } else { Err(n) })
+    }
+
+    #[test]
+    async fn test_retry_each_collect() {
+        let attempts = Mutex::new(HashMap::new());
+
+        let results = RetryEach::new(ConstantBuilder::default().with_max_times(2))
+            .sleep(|_| ready(()))
+            .collect([0, 5, 2], |n| flaky(&attempts, *n))
+            .await;
+
+        assert_eq!(results, vec![Ok(0), Err(5), Ok(20)]);
+        // Every item has its own retries.
+        assert_eq!(attempts.lock().unwrap()[&5], 3);
+        assert_eq!(attempts.lock().unwrap()[&2], 3);
+    }
+
+    #[test]
+    async fn test_retry_each_max_failure_rate() {
+        let attempts = Mutex::new(HashMap::new());
+
+        let results = RetryEach::new(ConstantBuilder::default().with_max_times(1))
+            .sleep(|_| ready(()))
+            .with_max_failure_rate(0.5, 2)
+            .collect([5, 0, 5, 5, 0, 0], |n| flaky(&attempts, *n))
+            .await;
+
+        // The first failure alone doesn't abort, the batch is aborted when 2 of 3 items failed.
+        assert_eq!(results, vec![Err(5), Ok(0), Err(5)]);
+        // The remaining items are not processed.
+        assert_eq!(attempts.lock().unwrap().values().sum::<u32>(), 2 + 1 + 2);
+    }
+
+    #[test]
+    async fn test_retry_each_try_collect() {
+        let policy =
+            RetryEach::new(ConstantBuilder::default().with_max_times(2)).sleep(|_| ready(()));
+
+        let attempts = Mutex::new(HashMap::new());
+        let result = policy.try_collect([1, 2], |n| flaky(&attempts, *n)).await;
+        assert_eq!(result, Ok(vec![10, 20]));
+
+        // Stop at the first failed item.
+        let attempts = Mutex::new(HashMap::new());
+        let result = policy
+            .try_collect([1, 5, 2], |n| flaky(&attempts, *n))
+            .await;
+        assert_eq!(result, Err(5));
+        assert!(!attempts.lock().unwrap().contains_key(&2));
+    }
+}
@@ -0,0 +1,333 @@
+use core::future::Future;
+use core::pin::Pin;
+use core::task::ready;
+use core::task::Context;
+use core::task::Poll;
+
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::backoff::BackoffBuilder;
+use crate::sleep::MaybeSleeper;
+use crate::Backoff;
+use crate::DefaultSleeper;
+use crate::Sleeper;
+
+/// RetryLayer wraps a [`Service`] with [`RetryService`] to retry its failed calls.
+///
+/// Every call builds a new backoff from the given [`BackoffBuilder`], and the request is
+/// cloned for every attempt, so the request must implement [`Clone`].
+///
+/// `retryable` decides whether an error of the service should be retried, use `|_| true`
+/// to retry all errors.
+///
+/// # Example
+///
+/// ```no_run
+/// use anyhow::Result;
+/// use backon::ExponentialBuilder;
+/// use backon::RetryLayer;
+/// use tower::ServiceBuilder;
+/// use tower::ServiceExt;
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() -> Result<()> {
+///     let svc = ServiceBuilder::new()
+///         .layer(RetryLayer::new(
+///             ExponentialBuilder::default(),
+///             |e: &anyhow::Error| e.to_string() == "EOF",
+///         ))
+///         .service_fn(|req: String| async move { Ok::<_, anyhow::Error>(req) });
+///
+///     let resp = svc.oneshot("hello".to_string()).await?;
+///     println!("call succeeded: {}", resp);
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct RetryLayer<B, RF, SF: MaybeSleeper = DefaultSleeper> {
+    builder: B,
+    retryable: RF,
+    sleep_fn: SF,
+}
+
+impl<B: BackoffBuilder + Clone, RF> RetryLayer<B, RF> {
+    /// Create a new retry layer.
+    pub fn new(builder: B, retryable: RF) -> Self {
+        RetryLayer {
+            builder,
+            retryable,
+            sleep_fn: DefaultSleeper::default(),
+        }
+    }
+}
+
+impl<B: BackoffBuilder + Clone, RF, SF: MaybeSleeper> RetryLayer<B, RF, SF> {
+    /// Set the sleeper for retrying.
+    ///
+    /// The sleeper should implement the [`Sleeper`] trait. The simplest way is to use a closure that returns a `Future<Output=()>`.
+    ///
+    /// If not specified, we use the [`DefaultSleeper`].
+    pub fn sleep<SN: Sleeper + Clone>(self, sleep_fn: SN) -> RetryLayer<B, RF, SN> {
+        RetryLayer {
+            builder: self.builder,
+            retryable: self.retryable,
+            sleep_fn,
+        }
+    }
+}
+
+impl<S, B, RF, SF> Layer<S> for RetryLayer<B, RF, SF>
+where
+    B: Clone,
+    RF: Clone,
+    SF: MaybeSleeper + Clone,
+{
+    type Service = RetryService<S, B, RF, SF>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RetryService {
+            inner,
+            builder: self.builder.clone(),
+            retryable: self.retryable.clone(),
+            sleep_fn: self.sleep_fn.clone(),
+        }
+    }
+}
+
+/// Service generated by [`RetryLayer`].
+#[derive(Debug, Clone)]
+pub struct RetryService<S, B, RF, SF: MaybeSleeper = DefaultSleeper> {
+    inner: S,
+    builder: B,
+    retryable: RF,
+    sleep_fn: SF,
+}
+
+impl<S, Req, B, RF, SF> Service<Req> for RetryService<S, B, RF, SF>
+where
+    S: Service<Req> + Clone,
+    Req: Clone,
+    B: BackoffBuilder + Clone,
+    RF: FnMut(&S::Error) -> bool + Clone,
+    SF: Sleeper + Clone,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = RetryServiceFuture<S, Req, B::Backoff, RF, SF>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Req) -> Self::Future {
+        // Take the service that has been driven to ready, and leave a clone in place.
+        let clone = self.inner.clone();
+        let mut inner = core::mem::replace(&mut self.inner, clone);
+
+        RetryServiceFuture {
+            state: State::Calling(inner.call(req.clone())),
+            inner,
+            req,
+            backoff: self.builder.clone().build(),
+            retryable: self.retryable.clone(),
+            sleep_fn: self.sleep_fn.clone(),
+        }
+    }
+}
+
+/// Future generated by [`RetryService`].
+pub struct RetryServiceFuture<S: Service<Req>, Req, B: Backoff, RF, SF: Sleeper> {
+    inner: S,
+    req: Req,
+    backoff: B,
+    retryable: RF,
+    sleep_fn: SF,
+
+    state: State<S::Future, SF::Sleep>,
+}
+
+/// State maintains internal state of retry.
+enum State<Fut, SleepFut> {
+    Idle,
+    Calling(Fut),
+    Sleeping(SleepFut),
+}
+
+impl<S, Req, B, RF, SF> Future for RetryServiceFuture<S, Req, B, RF, SF>
+where
+    S: Service<Req>,
+    Req: Clone,
+    B: Backoff,
+    RF: FnMut(&S::Error) -> bool,
+    SF: Sleeper,
+{
+    type Output = Result<S::Response, S::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // Safety: This is safe because we don't move the `RetryServiceFuture` struct itself,
+        // only its internal state.
+        //
+        // We do the exactly same thing like `pin_project` but without depending on it directly.
+        let this = unsafe { self.get_unchecked_mut() };
+
+        loop {
+            match &mut this.state {
+                State::Idle => {
+                    ready!(this.inner.poll_ready(cx))?;
+                    let fut = this.inner.call(this.req.clone());
+                    this.state = State::Calling(fut);
+                    continue;
+                }
+                State::Calling(fut) => {
+                    // Safety: This is safe because we don't move the `RetryServiceFuture` struct and this fut,
+                    // only its internal state.
+                    //
+                    // We do the exactly same thing like `pin_project` but without depending on it directly.
+                    let mut fut = unsafe { Pin::new_unchecked(fut) };
+
+                    match ready!(fut.as_mut().poll(cx)) {
+                        Ok(v) => return Poll::Ready(Ok(v)),
+                        Err(err) => {
+                            // Non-retryable errors are returned directly without consuming the backoff.
+                            let next = if (this.retryable)(&err) {
+                                this.backoff.next()
+                            } else {
+                                None
+                            };
+                            match next {
+                                None => return Poll::Ready(Err(err)),
+                                Some(dur) => {
+                                    this.state = State::Sleeping(this.sleep_fn.sleep(dur));
+                                    continue;
+                                }
+                            }
+                        }
+                    }
+                }
+                State::Sleeping(sl) => {
+                    // Safety: This is safe because we don't move the `RetryServiceFuture` struct and this fut,
+                    // only its internal state.
+                    //
+                    // We do the exactly same thing like `pin_project` but without depending on it directly.
+                    let mut sl = unsafe { Pin::new_unchecked(sl) };
+
+                    ready!(sl.as_mut().poll(cx));
+                    this.state = State::Idle;
+                    continue;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate alloc;
+
+    use alloc::string::String;
+    use alloc::string::ToString;
+    use alloc::sync::Arc;
+    use core::future::poll_fn;
+    use core::future::ready;
+    use core::future::Ready;
+    use core::time::Duration;
+
+    use spin::Mutex;
+
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test as test;
+
+    #[cfg(not(target_arch = "wasm32"))]
+    use tokio::test;
+
+    use super::*;
+    use crate::ConstantBuilder;
+
+    /// A service that fails until it has been called `fail_times` times.
+    #[derive(Clone)]
+    struct Flaky {
+        calls: Arc<Mutex<usize>>,
+        fail_times: usize,
+    }
+
+    impl Service<String> for Flaky {
+        type Response = String;
+        type Error = anyhow::Error;
+        type Future = Ready<anyhow::Result<String>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<anyhow::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, req: String) -> Self::Future {
+            let mut calls = self.calls.lock();
+            *calls += 1;
+            if *calls <= self.fail_times {
+                ready(Err(anyhow::anyhow!("retryable")))
+            } else {
+                ready(Ok(req))
+            }
+        }
+    }
+
+    async fn call<S: Service<String>>(svc: &mut S, req: &str) -> Result<S::Response, S::Error> {
+        poll_fn(|cx| svc.poll_ready(cx)).await?;
+        svc.call(req.to_string()).await
+    }
+
+    #[test]
+    async fn test_retry_layer() {
+        let calls = Arc::new(Mutex::new(0));
+        let mut svc = RetryLayer::new(
+            ConstantBuilder::default().with_delay(Duration::from_millis(1)),
+            |e: &anyhow::Error| e.to_string() == "retryable",
+        )
+        .sleep(|_| ready(()))
+        .layer(Flaky {
+            calls: calls.clone(),
+            fail_times: 2,
+        });
+
+        let resp = call(&mut svc, "hello").await.unwrap();
+
+        assert_eq!(resp, "hello");
+        assert_eq!(*calls.lock(), 3);
+    }
+
+    #[test]
+    async fn test_retry_layer_exhausted() {
+        let calls = Arc::new(Mutex::new(0));
+        let mut svc = RetryLayer::new(
+            ConstantBuilder::default().with_max_times(2),
+            |_: &anyhow::Error| true,
+        )
+        .sleep(|_| ready(()))
+        .layer(Flaky {
+            calls: calls.clone(),
+            fail_times: usize::MAX,
+        });
+
+        let result = call(&mut svc, "hello").await;
+
+        assert_eq!("retryable", result.unwrap_err().to_string());
+        assert_eq!(*calls.lock(), 3);
+    }
+
+    #[test]
+    async fn test_retry_layer_not_retryable() {
+        let calls = Arc::new(Mutex::new(0));
+        let mut svc = RetryLayer::new(ConstantBuilder::default(), |_: &anyhow::Error| false)
+            .sleep(|_| ready(()))
+            .layer(Flaky {
+                calls: calls.clone(),
+                fail_times: usize::MAX,
+            });
+
+        let result = call(&mut svc, "hello").await;
+
+        assert!(result.is_err());
+        assert_eq!(*calls.lock(), 1);
+    }
+}
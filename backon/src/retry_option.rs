@@ -0,0 +1,370 @@
+use core::future::Future;
+use core::pin::Pin;
+use core::task::ready;
+use core::task::Context;
+use core::task::Poll;
+use core::time::Duration;
+
+use crate::backoff::BackoffBuilder;
+use crate::notify::Notify;
+use crate::notify::OnRetry;
+use crate::progress::Progress;
+use crate::sleep::MaybeSleeper;
+use crate::Backoff;
+use crate::DefaultSleeper;
+use crate::RetryContext;
+use crate::Sleeper;
+
+/// RetryableOption adds retry support for functions that produce futures of options.
+///
+/// This means all types that implement `FnMut() -> impl Future<Output = Option<T>>` will be
+/// able to use `retry`.
+///
+/// `None` means "not ready, try later": the function is called again after sleeping as long as
+/// it returns `None`, and the first `Some` is returned. Once the backoff is exhausted, `None`
+/// is returned. There is no error type to wrap the result in, the hooks like
+/// [`RetryOption::when`] and [`RetryOption::notify`] see the unit `()` as the error instead.
+///
+/// # Example
+///
+/// ```no_run
+/// use backon::ExponentialBuilder;
+/// use backon::RetryableOption;
+///
+/// async fn pop_job() -> Option<String> {
+///     // Return `None` if the queue is empty.
+///     None
+/// }
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() {
+///     match pop_job.retry(ExponentialBuilder::default()).await {
+///         Some(job) => println!("got job: {}", job),
+///         None => println!("no job available"),
+///     }
+/// }
+/// ```
+pub trait RetryableOption<
+    B: BackoffBuilder,
+    T,
+    Fut: Future<Output = Option<T>>,
+    FutureFn: FnMut() -> Fut,
+>
+{
+    /// Generate a new retry
+    fn retry(self, builder: B) -> RetryOption<B::Backoff, T, Fut, FutureFn>;
+}
+
+impl<B, T, Fut, FutureFn> RetryableOption<B, T, Fut, FutureFn> for FutureFn
+where
+    B: BackoffBuilder,
+    Fut: Future<Output = Option<T>>,
+    FutureFn: FnMut() -> Fut,
+{
+    fn retry(self, builder: B) -> RetryOption<B::Backoff, T, Fut, FutureFn> {
+        RetryOption::new(self, builder.build())
+    }
+}
+
+/// Future generated by [`RetryableOption`].
+///
+/// Like [`Retry`](crate::Retry), it's fused: once it has returned the result, polling it again
+/// returns [`Poll::Pending`].
+pub struct RetryOption<
+    B: Backoff,
+    T,
+    Fut: Future<Output = Option<T>>,
+    FutureFn: FnMut() -> Fut,
+    SF: MaybeSleeper = DefaultSleeper,
+    RF = fn(&()) -> bool,
+    NF = fn(&(), Duration),
+> {
+    backoff: B,
+    retryable: RF,
+    notify: NF,
+    future_fn: FutureFn,
+    sleep_fn: SF,
+    progress: Progress,
+
+    state: State<Fut, SF::Sleep>,
+}
+
+impl<B, T, Fut, FutureFn> RetryOption<B, T, Fut, FutureFn>
+where
+    B: Backoff,
+    Fut: Future<Output = Option<T>>,
+    FutureFn: FnMut() -> Fut,
+{
+    /// Initiate a new retry.
+    fn new(future_fn: FutureFn, backoff: B) -> Self {
+        RetryOption {
+            backoff,
+            retryable: |_: &()| true,
+            notify: |_: &(), _: Duration| {},
+            future_fn,
+            sleep_fn: DefaultSleeper::default(),
+            progress: Progress::default(),
+            state: State::Idle,
+        }
+    }
+}
+
+impl<B, T, Fut, FutureFn, SF, RF, NF> RetryOption<B, T, Fut, FutureFn, SF, RF, NF>
+where
+    B: Backoff,
+    Fut: Future<Output = Option<T>>,
+    FutureFn: FnMut() -> Fut,
+    SF: MaybeSleeper,
+    RF: FnMut(&()) -> bool,
+    NF: Notify<()>,
+{
+    /// Set the sleeper for retrying.
+    ///
+    /// The sleeper should implement the [`Sleeper`] trait. The simplest way is to use a closure that returns a `Future<Output=()>`.
+    ///
+    /// If not specified, we use the [`DefaultSleeper`].
+    pub fn sleep<SN: Sleeper>(self, sleep_fn: SN) -> RetryOption<B, T, Fut, FutureFn, SN, RF, NF> {
+        RetryOption {
+            backoff: self.backoff,
+            retryable: self.retryable,
+            notify: self.notify,
+            future_fn: self.future_fn,
+            sleep_fn,
+            progress: self.progress,
+            state: State::Idle,
+        }
+    }
+
+    /// Set the conditions for retrying on `None`.
+    ///
+    /// The input function receives the unit `()` in place of an error. It could look at
+    /// anything else, like a shutdown flag, and return `false` to stop retrying and return
+    /// `None` right away.
+    ///
+    /// If not specified, every `None` is retried.
+    pub fn when<RN: FnMut(&()) -> bool>(
+        self,
+        retryable: RN,
+    ) -> RetryOption<B, T, Fut, FutureFn, SF, RN, NF> {
+        RetryOption {
+            backoff: self.backoff,
+            retryable,
+            notify: self.notify,
+            future_fn: self.future_fn,
+            sleep_fn: self.sleep_fn,
+            progress: self.progress,
+            state: self.state,
+        }
+    }
+
+    /// Set to notify for all retry attempts.
+    ///
+    /// When a retry happens, the input function will be invoked with the unit `()` and the sleep
+    /// duration before pausing.
+    ///
+    /// If not specified, this operation does nothing.
+    pub fn notify<NN: FnMut(&(), Duration)>(
+        self,
+        notify: NN,
+    ) -> RetryOption<B, T, Fut, FutureFn, SF, RF, NN> {
+        RetryOption {
+            backoff: self.backoff,
+            retryable: self.retryable,
+            notify,
+            future_fn: self.future_fn,
+            sleep_fn: self.sleep_fn,
+            progress: self.progress,
+            state: self.state,
+        }
+    }
+
+    /// Set to notify for all retry attempts with the whole [`RetryContext`].
+    ///
+    /// `notify` and `on_retry` share the same slot, setting one will replace the other.
+    pub fn on_retry<FN: FnMut(RetryContext<'_, ()>)>(
+        mut self,
+        on_retry: FN,
+    ) -> RetryOption<B, T, Fut, FutureFn, SF, RF, OnRetry<FN>> {
+        self.progress.measure_time();
+        RetryOption {
+            backoff: self.backoff,
+            retryable: self.retryable,
+            notify: OnRetry(on_retry),
+            future_fn: self.future_fn,
+            sleep_fn: self.sleep_fn,
+            progress: self.progress,
+            state: self.state,
+        }
+    }
+}
+
+/// State maintains internal state of retry.
+enum State<Fut, SleepFut> {
+    Idle,
+    Polling(Fut),
+    Sleeping(SleepFut),
+    Done,
+}
+
+impl<B, T, Fut, FutureFn, SF, RF, NF> Future for RetryOption<B, T, Fut, FutureFn, SF, RF, NF>
+where
+    B: Backoff,
+    Fut: Future<Output = Option<T>>,
+    FutureFn: FnMut() -> Fut,
+    SF: Sleeper,
+    RF: FnMut(&()) -> bool,
+    NF: Notify<()>,
+{
+    type Output = Option<T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // Safety: This is safe because we don't move the `RetryOption` struct itself,
+        // only its internal state.
+        //
+        // We do the exactly same thing like `pin_project` but without depending on it directly.
+        let this = unsafe { self.get_unchecked_mut() };
+
+        loop {
+            match &mut this.state {
+                State::Idle => {
+                    this.progress.start_attempt();
+                    let fut = (this.future_fn)();
+                    this.state = State::Polling(fut);
+                    continue;
+                }
+                State::Polling(fut) => {
+                    // Safety: This is safe because we don't move the `RetryOption` struct and this fut,
+                    // only its internal state.
+                    //
+                    // We do the exactly same thing like `pin_project` but without depending on it directly.
+                    let mut fut = unsafe { Pin::new_unchecked(fut) };
+
+                    if let Some(v) = ready!(fut.as_mut().poll(cx)) {
+                        this.state = State::Done;
+                        return Poll::Ready(Some(v));
+                    }
+                    let next = if (this.retryable)(&()) {
+                        this.backoff.next()
+                    } else {
+                        None
+                    };
+                    match next {
+                        None => {
+                            this.state = State::Done;
+                            return Poll::Ready(None);
+                        }
+                        Some(dur) => {
+                            this.notify.notify(RetryContext {
+                                attempt: this.progress.attempts,
+                                error: &(),
+                                next_delay: dur,
+                                elapsed: this.progress.elapsed(),
+                                remaining: this.backoff.remaining(),
+                            });
+                            this.state = State::Sleeping(
+                                this.sleep_fn.sleep_for_attempt(dur, this.progress.attempts),
+                            );
+                            continue;
+                        }
+                    }
+                }
+                State::Sleeping(sl) => {
+                    // Safety: This is safe because we don't move the `RetryOption` struct and this fut,
+                    // only its internal state.
+                    //
+                    // We do the exactly same thing like `pin_project` but without depending on it directly.
+                    let mut sl = unsafe { Pin::new_unchecked(sl) };
+
+                    ready!(sl.as_mut().poll(cx));
+                    this.state = State::Idle;
+                    continue;
+                }
+                State::Done => return Poll::Pending,
+            }
+        }
+    }
+}
+
+#[cfg(feature = "futures")]
+impl<B, T, Fut, FutureFn, SF, RF, NF> futures_core::FusedFuture
+    for RetryOption<B, T, Fut, FutureFn, SF, RF, NF>
+where
+    B: Backoff,
+    Fut: Future<Output = Option<T>>,
+    FutureFn: FnMut() -> Fut,
+    SF: Sleeper,
+    RF: FnMut(&()) -> bool,
+    NF: Notify<()>,
+{
+    fn is_terminated(&self) -> bool {
+        matches!(self.state, State::Done)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::future::ready;
+
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test as test;
+
+    #[cfg(not(target_arch = "wasm32"))]
+    use tokio::test;
+
+    use super::*;
+    use crate::ConstantBuilder;
+    // Having `Retryable` in scope must not make `retry` ambiguous.
+    #[allow(unused_imports)]
+    use crate::Retryable;
+
+    #[test]
+    async fn test_retry_option_eventually_some() {
+        let mut attempts = 0;
+        let mut notified = 0;
+
+        let result = (|| {
+            attempts += 1;
+            ready((attempts >= 3).then_some(attempts))
+        })
+        .retry(ConstantBuilder::default().with_max_times(5))
+        .sleep(|_| ready(()))
+        .notify(|_, _| notified += 1)
+        .await;
+
+        assert_eq!(result, Some(3));
+        assert_eq!(notified, 2);
+    }
+
+    #[test]
+    async fn test_retry_option_exhausted() {
+        let mut attempts = 0;
+
+        let result = (|| {
+            attempts += 1;
+            ready(None::<()>)
+        })
+        .retry(ConstantBuilder::default().with_max_times(2))
+        .sleep(|_| ready(()))
+        .await;
+
+        assert_eq!(result, None);
+        assert_eq!(attempts, 3);
+    }
+
+    #[test]
+    async fn test_retry_option_when() {
+        let mut attempts = 0;
+
+        let result = (|| {
+            attempts += 1;
+            ready(None::<()>)
+        })
+        .retry(ConstantBuilder::default().with_max_times(5))
+        .sleep(|_| ready(()))
+        .when(|_| false)
+        .await;
+
+        assert_eq!(result, None);
+        assert_eq!(attempts, 1);
+    }
+}
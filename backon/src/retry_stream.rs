@@ -0,0 +1,684 @@
+use core::future::Future;
+use core::pin::Pin;
+use core::task::ready;
+use core::task::Context;
+use core::task::Poll;
+use core::time::Duration;
+
+use futures_core::Stream;
+
+use crate::backoff::BackoffBuilder;
+use crate::notify::Notify;
+use crate::notify::OnRetry;
+use crate::progress::Progress;
+use crate::sleep::MaybeSleeper;
+use crate::Backoff;
+use crate::DefaultSleeper;
+use crate::RetryContext;
+use crate::Sleeper;
+
+/// RetryableStream adds retry support for functions that produce streams of results.
+///
+/// This means all types that implement `FnMut() -> impl Stream<Item = Result<T, E>>`
+/// will be able to use `retry`.
+///
+/// Items are passed through as soon as they are yielded. Only an error item triggers a retry:
+/// the failed stream is dropped, and the function is called again to create a new stream
+/// after sleeping. It's up to the function to resume from where the last stream left off.
+///
+/// If the error is not retryable or the backoff is exhausted, the error is yielded and the
+/// stream ends. Items yielded before are never taken back, so a consumer collecting a
+/// paginated read keeps everything it received before the final error, and can resume from
+/// there later.
+///
+/// At most one stream created by the function is alive at any time: the failed stream is
+/// always dropped before sleeping, so it's gone before the function is called again. It's safe
+/// to rely on this to avoid duplicate subscriptions.
+///
+/// Recreating the stream is right for streams that end or break on an error. For streams that
+/// stay usable after yielding an error, like a WebSocket emitting a recoverable frame error,
+/// use [`RetryStream::continue_when`] to sleep and keep polling the same stream on those errors,
+/// and only recreate it on the others.
+///
+/// # Example
+///
+/// ```no_run
+/// use std::cell::Cell;
+/// use std::pin::pin;
+///
+/// use anyhow::Result;
+/// use backon::ExponentialBuilder;
+/// use backon::RetryableStream;
+/// use futures::stream;
+/// use futures::Stream;
+/// use futures::StreamExt;
+///
+/// fn subscribe(offset: usize) -> impl Stream<Item = Result<usize>> {
+///     stream::iter((offset..10).map(Ok))
+/// }
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() -> Result<()> {
+///     // Resume from the next item after reconnecting.
+///     let offset = Cell::new(0);
+///     let mut items = pin!((|| subscribe(offset.get())).retry(ExponentialBuilder::default()));
+///
+///     while let Some(item) = items.next().await {
+///         offset.set(item? + 1);
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+pub trait RetryableStream<
+    B: BackoffBuilder,
+    T,
+    E,
+    S: Stream<Item = Result<T, E>>,
+    StreamFn: FnMut() -> S,
+>
+{
+    /// Generate a new retry
+    fn retry(self, builder: B) -> RetryStream<B::Backoff, T, E, S, StreamFn>;
+}
+
+impl<B, T, E, S, StreamFn> RetryableStream<B, T, E, S, StreamFn> for StreamFn
+where
+    B: BackoffBuilder,
+    S: Stream<Item = Result<T, E>>,
+    StreamFn: FnMut() -> S,
+{
+    fn retry(self, builder: B) -> RetryStream<B::Backoff, T, E, S, StreamFn> {
+        RetryStream::new(self, builder.build())
+    }
+}
+
+/// Stream generated by [`RetryableStream`].
+pub struct RetryStream<
+    B: Backoff,
+    T,
+    E,
+    S: Stream<Item = Result<T, E>>,
+    StreamFn: FnMut() -> S,
+    SF: MaybeSleeper = DefaultSleeper,
+    RF = fn(&E) -> bool,
+    NF = fn(&E, Duration),
+    GF = fn(&E, usize),
+    CF = fn(&E) -> bool,
+> {
+    backoff: B,
+    retryable: RF,
+    notify: NF,
+    on_giveup: GF,
+    continue_when: CF,
+    stream_fn: StreamFn,
+    sleep_fn: SF,
+    progress: Progress,
+
+    state: State<S, SF::Sleep>,
+}
+
+impl<B, T, E, S, StreamFn> RetryStream<B, T, E, S, StreamFn>
+where
+    B: Backoff,
+    S: Stream<Item = Result<T, E>>,
+    StreamFn: FnMut() -> S,
+{
+    /// Initiate a new retry.
+    fn new(stream_fn: StreamFn, backoff: B) -> Self {
+        RetryStream {
+            backoff,
+            retryable: |_: &E| true,
+            notify: |_: &E, _: Duration| {},
+            on_giveup: |_: &E, _: usize| {},
+            continue_when: |_: &E| false,
+            stream_fn,
+            sleep_fn: DefaultSleeper::default(),
+            progress: Progress::default(),
+            state: State::Idle,
+        }
+    }
+}
+
+impl<B, T, E, S, StreamFn, SF, RF, NF, GF, CF> RetryStream<B, T, E, S, StreamFn, SF, RF, NF, GF, CF>
+where
+    B: Backoff,
+    S: Stream<Item = Result<T, E>>,
+    StreamFn: FnMut() -> S,
+    SF: MaybeSleeper,
+    RF: FnMut(&E) -> bool,
+    NF: Notify<E>,
+    GF: FnMut(&E, usize),
+    CF: FnMut(&E) -> bool,
+{
+    /// Set the sleeper for retrying.
+    ///
+    /// The sleeper should implement the [`Sleeper`] trait. The simplest way is to use a closure that returns a `Future<Output=()>`.
+    ///
+    /// If not specified, we use the [`DefaultSleeper`].
+    pub fn sleep<SN: Sleeper>(
+        self,
+        sleep_fn: SN,
+    ) -> RetryStream<B, T, E, S, StreamFn, SN, RF, NF, GF, CF> {
+        RetryStream {
+            backoff: self.backoff,
+            retryable: self.retryable,
+            notify: self.notify,
+            on_giveup: self.on_giveup,
+            continue_when: self.continue_when,
+            stream_fn: self.stream_fn,
+            sleep_fn,
+            progress: self.progress,
+            state: State::Idle,
+        }
+    }
+
+    /// Set the conditions for recovering from a stream error.
+    ///
+    /// If not specified, all errors are considered retryable.
+    pub fn when<RN: FnMut(&E) -> bool>(
+        self,
+        retryable: RN,
+    ) -> RetryStream<B, T, E, S, StreamFn, SF, RN, NF, GF, CF> {
+        RetryStream {
+            backoff: self.backoff,
+            retryable,
+            notify: self.notify,
+            on_giveup: self.on_giveup,
+            continue_when: self.continue_when,
+            stream_fn: self.stream_fn,
+            sleep_fn: self.sleep_fn,
+            progress: self.progress,
+            state: self.state,
+        }
+    }
+
+    /// Set to notify for all retry attempts.
+    ///
+    /// When a retry happens, the input function will be invoked with the error and the sleep duration before pausing.
+    ///
+    /// If not specified, this operation does nothing.
+    pub fn notify<NN: FnMut(&E, Duration)>(
+        self,
+        notify: NN,
+    ) -> RetryStream<B, T, E, S, StreamFn, SF, RF, NN, GF, CF> {
+        RetryStream {
+            backoff: self.backoff,
+            retryable: self.retryable,
+            notify,
+            on_giveup: self.on_giveup,
+            continue_when: self.continue_when,
+            stream_fn: self.stream_fn,
+            sleep_fn: self.sleep_fn,
+            progress: self.progress,
+            state: self.state,
+        }
+    }
+
+    /// Set to notify for all retry attempts with the whole [`RetryContext`].
+    ///
+    /// It's invoked at the same time as [`RetryStream::notify`], but also receives the number of
+    /// the failed stream and the time elapsed since the first stream was created.
+    ///
+    /// `notify` and `on_retry` share the same slot, setting one will replace the other.
+    pub fn on_retry<FN: FnMut(RetryContext<'_, E>)>(
+        mut self,
+        on_retry: FN,
+    ) -> RetryStream<B, T, E, S, StreamFn, SF, RF, OnRetry<FN>, GF, CF> {
+        self.progress.measure_time();
+        RetryStream {
+            backoff: self.backoff,
+            retryable: self.retryable,
+            notify: OnRetry(on_retry),
+            on_giveup: self.on_giveup,
+            continue_when: self.continue_when,
+            stream_fn: self.stream_fn,
+            sleep_fn: self.sleep_fn,
+            progress: self.progress,
+            state: self.state,
+        }
+    }
+
+    /// Set to notify when the retry gives up.
+    ///
+    /// The input function will be invoked exactly once with the last error and the number of
+    /// streams made, when the error is not retryable or no more retry will happen. It's not invoked
+    /// if the stream ends without an error.
+    ///
+    /// If not specified, this operation does nothing.
+    pub fn on_giveup<GN: FnMut(&E, usize)>(
+        self,
+        on_giveup: GN,
+    ) -> RetryStream<B, T, E, S, StreamFn, SF, RF, NF, GN, CF> {
+        RetryStream {
+            backoff: self.backoff,
+            retryable: self.retryable,
+            notify: self.notify,
+            on_giveup,
+            continue_when: self.continue_when,
+            stream_fn: self.stream_fn,
+            sleep_fn: self.sleep_fn,
+            progress: self.progress,
+            state: self.state,
+        }
+    }
+
+    /// Set the conditions for recovering from an item error without recreating the stream.
+    ///
+    /// By default, every retryable error drops the stream and creates a new one, which suits
+    /// streams that end or break on the first error, like a paginated read or a closed
+    /// connection. Some streams stay usable after yielding an error instead, like a WebSocket
+    /// emitting a frame error that can be recovered from. For the errors that match the input
+    /// function, the error is swallowed, and the same stream is polled again after sleeping by
+    /// the backoff, the stream is only recreated for other retryable errors.
+    ///
+    /// The item errors consume the same backoff as the recreations, and are reported by
+    /// [`RetryStream::notify`] and friends. Once the backoff is exhausted, the error is yielded
+    /// and the stream ends.
+    ///
+    /// If not specified, no error is recovered without recreating the stream.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use std::pin::pin;
+    ///
+    /// use anyhow::Result;
+    /// use backon::ExponentialBuilder;
+    /// use backon::RetryableStream;
+    /// use futures::stream;
+    /// use futures::Stream;
+    /// use futures::StreamExt;
+    ///
+    /// #[derive(Debug)]
+    /// enum FrameError {
+    ///     /// A malformed frame, the connection is still fine.
+    ///     Malformed,
+    ///     /// The connection is lost.
+    ///     Disconnected,
+    /// }
+    ///
+    /// fn connect() -> impl Stream<Item = Result<String, FrameError>> {
+    ///     stream::iter([
+    ///         Ok("hello".to_string()),
+    ///         Err(FrameError::Malformed),
+    ///         Err(FrameError::Disconnected),
+    ///     ])
+    /// }
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<()> {
+    ///     let mut frames = pin!(connect
+    ///         .retry(ExponentialBuilder::default())
+    ///         .continue_when(|e| matches!(e, FrameError::Malformed)));
+    ///
+    ///     while let Some(frame) = frames.next().await {
+    ///         println!("received: {:?}", frame);
+    ///     }
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn continue_when<CN: FnMut(&E) -> bool>(
+        self,
+        continue_when: CN,
+    ) -> RetryStream<B, T, E, S, StreamFn, SF, RF, NF, GF, CN> {
+        RetryStream {
+            backoff: self.backoff,
+            retryable: self.retryable,
+            notify: self.notify,
+            on_giveup: self.on_giveup,
+            continue_when,
+            stream_fn: self.stream_fn,
+            sleep_fn: self.sleep_fn,
+            progress: self.progress,
+            state: self.state,
+        }
+    }
+}
+
+/// State maintains internal state of retry.
+enum State<S, SleepFut> {
+    Idle,
+    /// The stream, and the sleep before polling it again after a recovered item error.
+    ///
+    /// The stream stays in place while sleeping, since it's pinned.
+    Streaming(S, Option<SleepFut>),
+    Sleeping(SleepFut),
+    Done,
+}
+
+impl<B, T, E, S, StreamFn, SF, RF, NF, GF, CF> Stream
+    for RetryStream<B, T, E, S, StreamFn, SF, RF, NF, GF, CF>
+where
+    B: Backoff,
+    S: Stream<Item = Result<T, E>>,
+    StreamFn: FnMut() -> S,
+    SF: Sleeper,
+    RF: FnMut(&E) -> bool,
+    NF: Notify<E>,
+    GF: FnMut(&E, usize),
+    CF: FnMut(&E) -> bool,
+{
+    type Item = Result<T, E>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        // Safety: This is safe because we don't move the `RetryStream` struct itself,
+        // only its internal state.
+        //
+        // We do the exactly same thing like `pin_project` but without depending on it directly.
+        let this = unsafe { self.get_unchecked_mut() };
+
+        loop {
+            match &mut this.state {
+                State::Idle => {
+                    this.progress.start_attempt();
+                    let stream = (this.stream_fn)();
+                    this.state = State::Streaming(stream, None);
+                    continue;
+                }
+                State::Streaming(stream, sleep) => {
+                    if let Some(sl) = sleep {
+                        // Safety: This is safe because we don't move the `RetryStream` struct and this fut,
+                        // only its internal state.
+                        //
+                        // We do the exactly same thing like `pin_project` but without depending on it directly.
+                        let mut sl = unsafe { Pin::new_unchecked(sl) };
+
+                        ready!(sl.as_mut().poll(cx));
+                        *sleep = None;
+                    }
+
+                    // Safety: This is safe because we don't move the `RetryStream` struct and this stream,
+                    // only its internal state.
+                    //
+                    // We do the exactly same thing like `pin_project` but without depending on it directly.
+                    let mut stream = unsafe { Pin::new_unchecked(stream) };
+
+                    match ready!(stream.as_mut().poll_next(cx)) {
+                        None => {
+                            this.state = State::Done;
+                            return Poll::Ready(None);
+                        }
+                        Some(Ok(v)) => return Poll::Ready(Some(Ok(v))),
+                        Some(Err(err)) => {
+                            // Non-retryable errors are yielded directly without consuming the backoff.
+                            let next = if (this.retryable)(&err) {
+                                this.backoff.next()
+                            } else {
+                                None
+                            };
+                            match next {
+                                None => {
+                                    (this.on_giveup)(&err, this.progress.attempts);
+                                    this.state = State::Done;
+                                    return Poll::Ready(Some(Err(err)));
+                                }
+                                Some(dur) => {
+                                    let recover = (this.continue_when)(&err);
+                                    // Drop the failed stream before anything else, so that only
+                                    // one stream is ever alive.
+                                    if !recover {
+                                        this.state = State::Idle;
+                                    }
+                                    this.notify.notify(RetryContext {
+                                        attempt: this.progress.attempts,
+                                        error: &err,
+                                        next_delay: dur,
+                                        elapsed: this.progress.elapsed(),
+                                        remaining: this.backoff.remaining(),
+                                    });
+                                    let sl = this
+                                        .sleep_fn
+                                        .sleep_for_attempt(dur, this.progress.attempts);
+                                    match &mut this.state {
+                                        // Keep polling the same stream after sleeping.
+                                        State::Streaming(_, sleep) => *sleep = Some(sl),
+                                        state => *state = State::Sleeping(sl),
+                                    }
+                                    continue;
+                                }
+                            }
+                        }
+                    }
+                }
+                State::Sleeping(sl) => {
+                    // Safety: This is safe because we don't move the `RetryStream` struct and this fut,
+                    // only its internal state.
+                    //
+                    // We do the exactly same thing like `pin_project` but without depending on it directly.
+                    let mut sl = unsafe { Pin::new_unchecked(sl) };
+
+                    ready!(sl.as_mut().poll(cx));
+                    this.state = State::Idle;
+                    continue;
+                }
+                State::Done => return Poll::Ready(None),
+            }
+        }
+    }
+}
+
+impl<B, T, E, S, StreamFn, SF, RF, NF, GF, CF> futures_core::FusedStream
+    for RetryStream<B, T, E, S, StreamFn, SF, RF, NF, GF, CF>
+where
+    B: Backoff,
+    S: Stream<Item = Result<T, E>>,
+    StreamFn: FnMut() -> S,
+    SF: Sleeper,
+    RF: FnMut(&E) -> bool,
+    NF: Notify<E>,
+    GF: FnMut(&E, usize),
+    CF: FnMut(&E) -> bool,
+{
+    fn is_terminated(&self) -> bool {
+        matches!(self.state, State::Done)
+    }
+}
+
+#[cfg(test)]
+#[cfg(any(feature = "tokio-sleep", feature = "gloo-timers-sleep",))]
+mod tests {
+    extern crate alloc;
+
+    use alloc::string::ToString;
+    use alloc::sync::Arc;
+    use alloc::vec;
+    use alloc::vec::Vec;
+    use core::pin::pin;
+    use core::sync::atomic::AtomicUsize;
+    use core::sync::atomic::Ordering;
+    use core::time::Duration;
+    use futures::stream;
+    use futures::stream::FusedStream;
+    use futures::StreamExt;
+
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test as test;
+
+    #[cfg(not(target_arch = "wasm32"))]
+    use tokio::test;
+
+    use super::*;
+    use crate::ExponentialBuilder;
+
+    #[test]
+    async fn test_retry_stream_resubscribe() {
+        let mut subscriptions = 0;
+
+        let items: Vec<_> = {
+            || {
+                subscriptions += 1;
+                // The first two subscriptions fail after yielding one item.
+                let tail = if subscriptions < 3 {
+                    Err(anyhow::anyhow!("disconnected"))
+                } else {
+                    Ok(subscriptions * 10)
+                };
+                stream::iter(vec![Ok(subscriptions), tail])
+            }
+        }
+        .retry(ExponentialBuilder::default().with_min_delay(Duration::from_millis(1)))
+        .map(|v| v.unwrap())
+        .collect()
+        .await;
+
+        assert_eq!(items, vec![1, 2, 3, 30]);
+    }
+
+    #[test]
+    async fn test_retry_stream_with_not_retryable_error() {
+        let mut subscriptions = 0;
+
+        let items: Vec<_> = {
+            || {
+                subscriptions += 1;
+                stream::iter(vec![Ok(1), Err(anyhow::anyhow!("not retryable")), Ok(2)])
+            }
+        }
+        .retry(ExponentialBuilder::default().with_min_delay(Duration::from_millis(1)))
+        .when(|e| e.to_string() == "retryable")
+        .collect()
+        .await;
+
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].as_ref().unwrap(), &1);
+        assert_eq!("not retryable", items[1].as_ref().unwrap_err().to_string());
+        assert_eq!(subscriptions, 1);
+    }
+
+    #[test]
+    async fn test_retry_stream_keeps_items_before_permanent_error() {
+        let mut subscriptions = 0;
+
+        let mut items = pin!({
+            || {
+                subscriptions += 1;
+                // The first page fails with a retryable error, the second one for good.
+                let tail = if subscriptions < 2 {
+                    Err(anyhow::anyhow!("retryable"))
+                } else {
+                    Err(anyhow::anyhow!("permanent"))
+                };
+                stream::iter(vec![Ok(subscriptions), tail, Ok(99)])
+            }
+        }
+        .retry(ExponentialBuilder::default().with_min_delay(Duration::from_millis(1)))
+        .when(|e| e.to_string() == "retryable"));
+
+        let mut collected = Vec::new();
+        let err = loop {
+            match items.next().await {
+                Some(Ok(v)) => collected.push(v),
+                Some(Err(err)) => break err,
+                None => unreachable!("the stream must end with the error"),
+            }
+        };
+
+        assert_eq!(collected, vec![1, 2]);
+        assert_eq!("permanent", err.to_string());
+        assert!(items.next().await.is_none());
+        assert!(items.is_terminated());
+        assert_eq!(subscriptions, 2);
+    }
+
+    #[test]
+    async fn test_retry_stream_continue_when() {
+        let mut subscriptions = 0;
+        let mut notified = 0;
+
+        let items: Vec<_> = {
+            || {
+                subscriptions += 1;
+                // The recoverable errors are skipped, and the stream is recreated on the others.
+                stream::iter(vec![
+                    Ok(subscriptions),
+                    Err(anyhow::anyhow!("recoverable")),
+                    Ok(subscriptions * 10),
+                    Err(anyhow::anyhow!(if subscriptions < 2 {
+                        "disconnected"
+                    } else {
+                        "permanent"
+                    })),
+                ])
+            }
+        }
+        .retry(
+            ExponentialBuilder::default()
+                .with_min_delay(Duration::from_millis(1))
+                .with_max_times(5),
+        )
+        .when(|e| e.to_string() != "permanent")
+        .continue_when(|e| e.to_string() == "recoverable")
+        .notify(|_, _| notified += 1)
+        .collect()
+        .await;
+
+        let (ok, err): (Vec<_>, Vec<_>) = items.into_iter().partition(|v| v.is_ok());
+        assert_eq!(
+            ok.into_iter().map(|v| v.unwrap()).collect::<Vec<_>>(),
+            vec![1, 10, 2, 20]
+        );
+        assert_eq!(err.len(), 1);
+        assert_eq!("permanent", err[0].as_ref().unwrap_err().to_string());
+        assert_eq!(subscriptions, 2);
+        assert_eq!(notified, 3);
+    }
+
+    #[test]
+    async fn test_retry_stream_exhausted() {
+        let mut notified = 0;
+
+        let items: Vec<_> = (|| stream::iter(vec![Err::<(), _>(anyhow::anyhow!("retryable"))]))
+            .retry(ExponentialBuilder::default().with_min_delay(Duration::from_millis(1)))
+            .notify(|_, _| notified += 1)
+            .collect()
+            .await;
+
+        assert_eq!(items.len(), 1);
+        assert!(items[0].is_err());
+        assert_eq!(notified, 3);
+    }
+
+    #[test]
+    async fn test_retry_stream_single_flight() {
+        /// Decrease the counter on drop to track the streams alive.
+        struct Alive(Arc<AtomicUsize>);
+
+        impl Drop for Alive {
+            fn drop(&mut self) {
+                self.0.fetch_sub(1, Ordering::SeqCst);
+            }
+        }
+
+        let alive = Arc::new(AtomicUsize::new(0));
+        let mut subscriptions = 0;
+
+        let items: Vec<_> = {
+            let alive = alive.clone();
+            move || {
+                // The previous stream must have been dropped.
+                assert_eq!(alive.fetch_add(1, Ordering::SeqCst), 0);
+                let guard = Alive(alive.clone());
+
+                subscriptions += 1;
+                let tail = if subscriptions < 3 {
+                    Err(anyhow::anyhow!("disconnected"))
+                } else {
+                    Ok(subscriptions)
+                };
+                stream::iter(vec![tail]).map(move |v| {
+                    let _guard = &guard;
+                    v
+                })
+            }
+        }
+        .retry(ExponentialBuilder::default().with_min_delay(Duration::from_millis(1)))
+        .map(|v| v.unwrap())
+        .collect()
+        .await;
+
+        assert_eq!(items, vec![3]);
+        assert_eq!(alive.load(Ordering::SeqCst), 0);
+    }
+}
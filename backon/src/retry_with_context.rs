@@ -1,4 +1,6 @@
+use core::fmt;
 use core::future::Future;
+use core::future::Ready;
 use core::pin::Pin;
 use core::task::ready;
 use core::task::Context;
@@ -6,9 +8,16 @@ use core::task::Poll;
 use core::time::Duration;
 
 use crate::backoff::BackoffBuilder;
+use crate::notify::Notify;
+use crate::notify::NotifyFnWithContext;
+use crate::notify::NotifyWithContext;
+use crate::notify::OnRetry;
+use crate::progress::Progress;
 use crate::sleep::MaybeSleeper;
 use crate::Backoff;
 use crate::DefaultSleeper;
+use crate::Retry;
+use crate::RetryContext;
 use crate::Sleeper;
 
 /// `RetryableWithContext` adds retry support for functions that produce futures with results
@@ -19,6 +28,11 @@ use crate::Sleeper;
 ///
 /// Users must provide context to the function and can receive it back after the retry is completed.
 ///
+/// The context returned is always the one handed back by the last attempt, whether it succeeds
+/// or the retry gives up, so any partial progress recorded in the context by the final failed
+/// attempt is kept. The hooks never copy the context, and `before_attempt` only mutates it in
+/// place before it's passed to the attempt.
+///
 /// # Example
 ///
 /// Without context, we might encounter errors such as the following:
@@ -75,6 +89,15 @@ use crate::Sleeper;
 ///     Ok(())
 /// }
 /// ```
+///
+/// For the common case of retrying a `&mut self` method, [`retry_method!`](crate::retry_method)
+/// generates the closure above.
+/// Methods taking `&self` don't need a context, retry them with [`Retryable`](crate::Retryable)
+/// directly.
+///
+/// If the compiler complains that `retry` exists but its trait bounds were not satisfied, the
+/// closure most likely doesn't return `(Ctx, Result<T, E>)`. Wrap it with
+/// [`assert_retryable_with_context`] to get an error pointing at the mismatched type instead.
 pub trait RetryableWithContext<
     B: BackoffBuilder,
     T,
@@ -99,7 +122,203 @@ where
     }
 }
 
+/// Check at compile time that the function can be retried with context.
+///
+/// It returns the function as is. The types are pinned by the turbofish, so a closure that
+/// doesn't return `(Ctx, Result<T, E>)` is reported where the closure is written, like
+/// "expected `(Test, Result<usize, Error>)`, found `Result<usize, Error>`", instead of an
+/// unsatisfied trait bound of [`RetryableWithContext`] at the call to `retry`. The argument of
+/// the closure is inferred as `Ctx` as well, so it doesn't need a type annotation.
+///
+/// # Examples
+///
+/// ```no_run
+/// use anyhow::anyhow;
+/// use anyhow::Result;
+/// use backon::assert_retryable_with_context;
+/// use backon::ExponentialBuilder;
+/// use backon::RetryableWithContext;
+///
+/// struct Test;
+///
+/// impl Test {
+///     async fn hello(&mut self) -> Result<usize> {
+///         Err(anyhow!("not retryable"))
+///     }
+/// }
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() -> Result<()> {
+///     let f = assert_retryable_with_context::<Test, usize, anyhow::Error, _, _>(|mut v| async {
+///         let res = v.hello().await;
+///         (v, res)
+///     });
+///
+///     let (_, result) = f.retry(ExponentialBuilder::default()).context(Test).await;
+///
+///     Ok(())
+/// }
+/// ```
+///
+/// Forgetting to return the context is reported on the closure:
+///
+/// ```compile_fail
+/// use anyhow::anyhow;
+/// use anyhow::Result;
+/// use backon::assert_retryable_with_context;
+///
+/// struct Test;
+///
+/// impl Test {
+///     async fn hello(&mut self) -> Result<usize> {
+///         Err(anyhow!("not retryable"))
+///     }
+/// }
+///
+/// let f = assert_retryable_with_context::<Test, usize, anyhow::Error, _, _>(|mut v| async move {
+///     // error: expected `(Test, Result<usize, Error>)`, found `Result<usize, Error>`
+///     v.hello().await
+/// });
+/// ```
+pub fn assert_retryable_with_context<Ctx, T, E, Fut, FutureFn>(future_fn: FutureFn) -> FutureFn
+where
+    Fut: Future<Output = (Ctx, Result<T, E>)>,
+    FutureFn: FnMut(Ctx) -> Fut,
+{
+    future_fn
+}
+
+/// Adapt a closure that borrows the context into a closure usable by [`RetryableWithContext`].
+///
+/// The retry owns the context and lends it to every attempt as `&mut Ctx`, so the closure
+/// doesn't need to move the context in and hand it back along with the result. The adapted
+/// closure moves the context into every attempt, calls `f` with `&mut ctx`, and returns
+/// `(Ctx, Result<T, E>)` as [`RetryableWithContext`] expects.
+///
+/// The future returned by `f` borrows the context until it completes. The type of such a
+/// future depends on the lifetime of the borrow and can't be named in a generic bound, so
+/// `f` must return it boxed, which is the `for<'a> Fn(&'a mut Ctx) -> Pin<Box<dyn Future<..> + Send + 'a>>`
+/// bound. `f` is shared by all attempts, so it must be [`Fn`], and the context, the output and
+/// the error must be `Send + 'static` to be moved into the attempts.
+///
+/// Unlike [`retry_method!`](crate::retry_method), the closure could capture extra arguments.
+///
+/// # Examples
+///
+/// ```no_run
+/// use anyhow::Result;
+/// use backon::borrow_context;
+/// use backon::ExponentialBuilder;
+/// use backon::RetryableWithContext;
+///
+/// struct Client;
+///
+/// impl Client {
+///     async fn fetch(&mut self, url: &str) -> Result<String> {
+///         Ok(reqwest::get(url).await?.text().await?)
+///     }
+/// }
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() -> Result<()> {
+///     let url = "https://www.rust-lang.org";
+///
+///     let (_client, content) = borrow_context(move |client: &mut Client| {
+///         Box::pin(async move { client.fetch(url).await })
+///     })
+///     .retry(ExponentialBuilder::default())
+///     .context(Client)
+///     .await;
+///     println!("fetch finished: {:?}", content);
+///
+///     Ok(())
+/// }
+/// ```
+#[cfg(feature = "std")]
+#[allow(clippy::type_complexity)]
+pub fn borrow_context<Ctx, T, E, F>(
+    f: F,
+) -> impl FnMut(Ctx) -> Pin<std::boxed::Box<dyn Future<Output = (Ctx, Result<T, E>)> + Send>>
+where
+    Ctx: Send + 'static,
+    T: Send + 'static,
+    E: Send + 'static,
+    F: for<'a> Fn(
+            &'a mut Ctx,
+        ) -> Pin<std::boxed::Box<dyn Future<Output = Result<T, E>> + Send + 'a>>
+        + Send
+        + Sync
+        + 'static,
+{
+    let f = std::sync::Arc::new(f);
+    move |mut ctx| {
+        let f = f.clone();
+        std::boxed::Box::pin(async move {
+            let res = f(&mut ctx).await;
+            (ctx, res)
+        })
+    }
+}
+
+/// Retry a `&mut self` method with its receiver as the context.
+///
+/// `retry_method!(ctx, method, builder)` expands to the boilerplate shown in
+/// [`RetryableWithContext`]: the context is moved into every attempt, the method is called with
+/// `&mut ctx`, and the context is handed back along with the result. The expansion is a
+/// [`RetryWithContext`] with the context already set, so it can be configured further before
+/// being awaited.
+///
+/// `method` must be a path to an async function or method taking `&mut Ctx`, like `Test::hello`.
+/// Closures that capture extra arguments are not supported, write the closure by hand with
+/// [`RetryableWithContext`] instead.
+///
+/// # Examples
+///
+/// ```no_run
+/// use anyhow::anyhow;
+/// use anyhow::Result;
+/// use backon::retry_method;
+/// use backon::ExponentialBuilder;
+///
+/// struct Test;
+///
+/// impl Test {
+///     async fn hello(&mut self) -> Result<usize> {
+///         Err(anyhow!("not retryable"))
+///     }
+/// }
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() -> Result<()> {
+///     let test = Test;
+///
+///     // (Test, Result<usize>)
+///     let (_, result) = retry_method!(test, Test::hello, ExponentialBuilder::default())
+///         .when(|e| e.to_string() == "retryable")
+///         .await;
+///
+///     Ok(())
+/// }
+/// ```
+#[macro_export]
+macro_rules! retry_method {
+    ($ctx:expr, $method:path, $builder:expr $(,)?) => {
+        $crate::RetryableWithContext::retry(
+            |mut ctx| async move {
+                let res = $method(&mut ctx).await;
+                (ctx, res)
+            },
+            $builder,
+        )
+        .context($ctx)
+    };
+}
+
 /// Retry struct generated by [`RetryableWithContext`].
+///
+/// The retry is fused: once it has returned the context and the result, polling it again
+/// returns [`Poll::Pending`] instead of panicking or retrying again. With the `futures` feature
+/// enabled, it implements `FusedFuture` to report it.
 pub struct RetryWithContext<
     B: Backoff,
     T,
@@ -110,14 +329,76 @@ pub struct RetryWithContext<
     SF: MaybeSleeper = DefaultSleeper,
     RF = fn(&E) -> bool,
     NF = fn(&E, Duration),
+    GF = fn(&E, usize),
+    BF = fn(&mut Ctx, usize),
+    CF = Ready<Ctx>,
 > {
     backoff: B,
     retryable: RF,
     notify: NF,
+    on_giveup: GF,
+    before_attempt: BF,
+    snapshot: Option<fn(&Ctx) -> Ctx>,
     future_fn: FutureFn,
     sleep_fn: SF,
+    #[cfg(feature = "tracing")]
+    tracer: Option<crate::trace::Tracer<E>>,
+    progress: Progress,
 
-    state: State<T, E, Ctx, Fut, SF::Sleep>,
+    state: State<T, E, Ctx, Fut, SF::Sleep, CF>,
+}
+
+/// Show the backoff and the number of attempts made, the hooks and the context are omitted.
+impl<B, T, E, Ctx, Fut, FutureFn, SF, RF, NF, GF, BF, CF> fmt::Debug
+    for RetryWithContext<B, T, E, Ctx, Fut, FutureFn, SF, RF, NF, GF, BF, CF>
+where
+    B: Backoff + fmt::Debug,
+    Fut: Future<Output = (Ctx, Result<T, E>)>,
+    FutureFn: FnMut(Ctx) -> Fut,
+    SF: MaybeSleeper,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RetryWithContext")
+            .field("backoff", &self.backoff)
+            .field("attempts", &self.progress.attempts)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Cloning a retry clones its configuration into a fresh retry that has not started yet.
+///
+/// It allows defining one policy and spawning many retries from it. The in-flight future, the
+/// number of attempts and the time elapsed are not cloned, and neither is the context, set it on
+/// every clone with [`RetryWithContext::context`]. The backoff is cloned as is, so clone the retry
+/// before polling it to give every retry the full sequence.
+impl<B, T, E, Ctx, Fut, FutureFn, SF, RF, NF, GF, BF, CF> Clone
+    for RetryWithContext<B, T, E, Ctx, Fut, FutureFn, SF, RF, NF, GF, BF, CF>
+where
+    B: Backoff + Clone,
+    Fut: Future<Output = (Ctx, Result<T, E>)>,
+    FutureFn: FnMut(Ctx) -> Fut + Clone,
+    SF: MaybeSleeper + Clone,
+    RF: Clone,
+    NF: Clone,
+    GF: Clone,
+    BF: Clone,
+{
+    fn clone(&self) -> Self {
+        RetryWithContext {
+            backoff: self.backoff.clone(),
+            retryable: self.retryable.clone(),
+            notify: self.notify.clone(),
+            on_giveup: self.on_giveup.clone(),
+            before_attempt: self.before_attempt.clone(),
+            snapshot: self.snapshot,
+            future_fn: self.future_fn.clone(),
+            sleep_fn: self.sleep_fn.clone(),
+            #[cfg(feature = "tracing")]
+            tracer: self.tracer.clone(),
+            progress: self.progress.fresh(),
+            state: State::Idle(None),
+        }
+    }
 }
 
 impl<B, T, E, Ctx, Fut, FutureFn> RetryWithContext<B, T, E, Ctx, Fut, FutureFn>
@@ -132,22 +413,30 @@ where
             backoff,
             retryable: |_: &E| true,
             notify: |_: &E, _: Duration| {},
+            on_giveup: |_: &E, _: usize| {},
+            before_attempt: |_: &mut Ctx, _: usize| {},
+            snapshot: None,
             future_fn,
             sleep_fn: DefaultSleeper::default(),
+            #[cfg(feature = "tracing")]
+            tracer: None,
+            progress: Progress::default(),
             state: State::Idle(None),
         }
     }
 }
 
-impl<B, T, E, Ctx, Fut, FutureFn, SF, RF, NF>
-    RetryWithContext<B, T, E, Ctx, Fut, FutureFn, SF, RF, NF>
+impl<B, T, E, Ctx, Fut, FutureFn, SF, RF, NF, GF, BF, CF>
+    RetryWithContext<B, T, E, Ctx, Fut, FutureFn, SF, RF, NF, GF, BF, CF>
 where
     B: Backoff,
     Fut: Future<Output = (Ctx, Result<T, E>)>,
     FutureFn: FnMut(Ctx) -> Fut,
     SF: Sleeper,
     RF: FnMut(&E) -> bool,
-    NF: FnMut(&E, Duration),
+    NF: NotifyWithContext<Ctx, E>,
+    GF: FnMut(&E, usize),
+    BF: FnMut(&mut Ctx, usize),
 {
     /// Set the sleeper for retrying.
     ///
@@ -157,7 +446,7 @@ where
     pub fn sleep<SN: Sleeper>(
         self,
         sleep_fn: SN,
-    ) -> RetryWithContext<B, T, E, Ctx, Fut, FutureFn, SN, RF, NF> {
+    ) -> RetryWithContext<B, T, E, Ctx, Fut, FutureFn, SN, RF, NF, GF, BF, CF> {
         assert!(
             matches!(self.state, State::Idle(None)),
             "sleep must be set before context"
@@ -167,8 +456,14 @@ where
             backoff: self.backoff,
             retryable: self.retryable,
             notify: self.notify,
+            on_giveup: self.on_giveup,
+            before_attempt: self.before_attempt,
+            snapshot: self.snapshot,
             future_fn: self.future_fn,
             sleep_fn,
+            #[cfg(feature = "tracing")]
+            tracer: self.tracer,
+            progress: self.progress,
             state: State::Idle(None),
         }
     }
@@ -179,17 +474,87 @@ where
     pub fn context(
         self,
         context: Ctx,
-    ) -> RetryWithContext<B, T, E, Ctx, Fut, FutureFn, SF, RF, NF> {
+    ) -> RetryWithContext<B, T, E, Ctx, Fut, FutureFn, SF, RF, NF, GF, BF, CF> {
         RetryWithContext {
             backoff: self.backoff,
             retryable: self.retryable,
             notify: self.notify,
+            on_giveup: self.on_giveup,
+            before_attempt: self.before_attempt,
+            snapshot: self.snapshot,
             future_fn: self.future_fn,
             sleep_fn: self.sleep_fn,
+            #[cfg(feature = "tracing")]
+            tracer: self.tracer,
+            progress: self.progress,
             state: State::Idle(Some(context)),
         }
     }
 
+    /// Set a function to create the context asynchronously for retrying.
+    ///
+    /// The future returned by the function is awaited before the first attempt, and its output
+    /// is used as the context. It's useful when the context requires async construction, like
+    /// establishing a connection. The function is called once, and creating the context is not
+    /// retried.
+    ///
+    /// It replaces the context set by [`RetryWithContext::context`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use anyhow::Result;
+    /// use backon::ExponentialBuilder;
+    /// use backon::RetryableWithContext;
+    ///
+    /// struct Connection;
+    ///
+    /// impl Connection {
+    ///     async fn connect() -> Connection {
+    ///         Connection
+    ///     }
+    ///
+    ///     async fn query(&mut self) -> Result<usize> {
+    ///         Ok(42)
+    ///     }
+    /// }
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<()> {
+    ///     let (_, result) = {
+    ///         |mut conn: Connection| async move {
+    ///             let res = conn.query().await;
+    ///             (conn, res)
+    ///         }
+    ///     }
+    ///     .retry(ExponentialBuilder::default())
+    ///     .context_with(Connection::connect)
+    ///     .await;
+    ///     println!("query succeeded: {}", result?);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn context_with<CN: FnOnce() -> CFut, CFut: Future<Output = Ctx>>(
+        self,
+        context_fn: CN,
+    ) -> RetryWithContext<B, T, E, Ctx, Fut, FutureFn, SF, RF, NF, GF, BF, CFut> {
+        RetryWithContext {
+            backoff: self.backoff,
+            retryable: self.retryable,
+            notify: self.notify,
+            on_giveup: self.on_giveup,
+            before_attempt: self.before_attempt,
+            snapshot: self.snapshot,
+            future_fn: self.future_fn,
+            sleep_fn: self.sleep_fn,
+            #[cfg(feature = "tracing")]
+            tracer: self.tracer,
+            progress: self.progress,
+            state: State::Creating(context_fn()),
+        }
+    }
+
     /// Set the conditions for retrying.
     ///
     /// If not specified, all errors are considered retryable.
@@ -222,13 +587,19 @@ where
     pub fn when<RN: FnMut(&E) -> bool>(
         self,
         retryable: RN,
-    ) -> RetryWithContext<B, T, E, Ctx, Fut, FutureFn, SF, RN, NF> {
+    ) -> RetryWithContext<B, T, E, Ctx, Fut, FutureFn, SF, RN, NF, GF, BF, CF> {
         RetryWithContext {
             backoff: self.backoff,
             retryable,
             notify: self.notify,
+            on_giveup: self.on_giveup,
+            before_attempt: self.before_attempt,
+            snapshot: self.snapshot,
             future_fn: self.future_fn,
             sleep_fn: self.sleep_fn,
+            #[cfg(feature = "tracing")]
+            tracer: self.tracer,
+            progress: self.progress,
             state: self.state,
         }
     }
@@ -271,34 +642,510 @@ where
     pub fn notify<NN: FnMut(&E, Duration)>(
         self,
         notify: NN,
-    ) -> RetryWithContext<B, T, E, Ctx, Fut, FutureFn, SF, RF, NN> {
+    ) -> RetryWithContext<B, T, E, Ctx, Fut, FutureFn, SF, RF, NN, GF, BF, CF> {
         RetryWithContext {
             backoff: self.backoff,
             retryable: self.retryable,
             notify,
+            on_giveup: self.on_giveup,
+            before_attempt: self.before_attempt,
+            snapshot: self.snapshot,
             future_fn: self.future_fn,
             sleep_fn: self.sleep_fn,
+            #[cfg(feature = "tracing")]
+            tracer: self.tracer,
+            progress: self.progress,
             state: self.state,
         }
     }
+
+    /// Set to notify for all retry attempts with the whole [`RetryContext`].
+    ///
+    /// It's invoked at the same time as [`RetryWithContext::notify`], but also receives the number of
+    /// the failed attempt and the time elapsed since the first attempt started.
+    ///
+    /// `notify` and `on_retry` share the same slot, setting one will replace the other.
+    #[allow(clippy::type_complexity)]
+    pub fn on_retry<FN: FnMut(RetryContext<'_, E>)>(
+        mut self,
+        on_retry: FN,
+    ) -> RetryWithContext<B, T, E, Ctx, Fut, FutureFn, SF, RF, OnRetry<FN>, GF, BF, CF> {
+        self.progress.measure_time();
+        RetryWithContext {
+            backoff: self.backoff,
+            retryable: self.retryable,
+            notify: OnRetry(on_retry),
+            on_giveup: self.on_giveup,
+            before_attempt: self.before_attempt,
+            snapshot: self.snapshot,
+            future_fn: self.future_fn,
+            sleep_fn: self.sleep_fn,
+            #[cfg(feature = "tracing")]
+            tracer: self.tracer,
+            progress: self.progress,
+            state: self.state,
+        }
+    }
+
+    /// Set to notify for all retry attempts with a reference to the context.
+    ///
+    /// It's invoked at the same time as [`RetryWithContext::notify`], with the context that will
+    /// be passed to the next attempt. It's useful to log something stored in the context, like
+    /// a request ID.
+    ///
+    /// `notify`, `on_retry` and `notify_with_context` share the same slot, setting one will
+    /// replace the others.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use core::time::Duration;
+    ///
+    /// use anyhow::Result;
+    /// use backon::ExponentialBuilder;
+    /// use backon::RetryableWithContext;
+    ///
+    /// struct Request {
+    ///     id: u64,
+    /// }
+    ///
+    /// impl Request {
+    ///     async fn send(&mut self) -> Result<String> {
+    ///         Ok(reqwest::get("https://www.rust-lang.org")
+    ///             .await?
+    ///             .text()
+    ///             .await?)
+    ///     }
+    /// }
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<()> {
+    ///     let (_, content) = {
+    ///         |mut req: Request| async move {
+    ///             let res = req.send().await;
+    ///             (req, res)
+    ///         }
+    ///     }
+    ///     .retry(ExponentialBuilder::default())
+    ///     .notify_with_context(|req: &Request, err: &anyhow::Error, dur: Duration| {
+    ///         println!("request {} failed: {:?}, retrying in {:?}", req.id, err, dur);
+    ///     })
+    ///     .context(Request { id: 42 })
+    ///     .await;
+    ///     println!("fetch succeeded: {}", content?);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    #[allow(clippy::type_complexity)]
+    pub fn notify_with_context<NN: FnMut(&Ctx, &E, Duration)>(
+        self,
+        notify: NN,
+    ) -> RetryWithContext<B, T, E, Ctx, Fut, FutureFn, SF, RF, NotifyFnWithContext<NN>, GF, BF, CF>
+    {
+        RetryWithContext {
+            backoff: self.backoff,
+            retryable: self.retryable,
+            notify: NotifyFnWithContext(notify),
+            on_giveup: self.on_giveup,
+            before_attempt: self.before_attempt,
+            snapshot: self.snapshot,
+            future_fn: self.future_fn,
+            sleep_fn: self.sleep_fn,
+            #[cfg(feature = "tracing")]
+            tracer: self.tracer,
+            progress: self.progress,
+            state: self.state,
+        }
+    }
+
+    /// Set to notify when the retry gives up.
+    ///
+    /// The input function will be invoked exactly once with the last error and the number of
+    /// attempts made, when the error is not retryable or no more retry will happen. It's not invoked
+    /// if the retry succeeds.
+    ///
+    /// If not specified, this operation does nothing.
+    pub fn on_giveup<GN: FnMut(&E, usize)>(
+        self,
+        on_giveup: GN,
+    ) -> RetryWithContext<B, T, E, Ctx, Fut, FutureFn, SF, RF, NF, GN, BF, CF> {
+        RetryWithContext {
+            backoff: self.backoff,
+            retryable: self.retryable,
+            notify: self.notify,
+            on_giveup,
+            before_attempt: self.before_attempt,
+            snapshot: self.snapshot,
+            future_fn: self.future_fn,
+            sleep_fn: self.sleep_fn,
+            #[cfg(feature = "tracing")]
+            tracer: self.tracer,
+            progress: self.progress,
+            state: self.state,
+        }
+    }
+
+    /// Set a function to update the context before every attempt.
+    ///
+    /// The input function will be invoked with the context and the number of the upcoming
+    /// attempt (starting from `1`) right before the attempt starts, and the updated context is
+    /// passed to the attempt. It's useful to refresh the context between attempts, like
+    /// refreshing an auth token or reconnecting.
+    ///
+    /// If not specified, this operation does nothing.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use anyhow::Result;
+    /// use backon::ExponentialBuilder;
+    /// use backon::RetryableWithContext;
+    ///
+    /// struct Client {
+    ///     token: String,
+    /// }
+    ///
+    /// impl Client {
+    ///     async fn fetch(&self) -> Result<String> {
+    ///         Ok(reqwest::Client::new()
+    ///             .get("https://www.rust-lang.org")
+    ///             .bearer_auth(&self.token)
+    ///             .send()
+    ///             .await?
+    ///             .text()
+    ///             .await?)
+    ///     }
+    /// }
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<()> {
+    ///     let client = Client {
+    ///         token: "initial".to_string(),
+    ///     };
+    ///
+    ///     let (_, content) = (|client: Client| async move {
+    ///         let res = client.fetch().await;
+    ///         (client, res)
+    ///     })
+    ///     .retry(ExponentialBuilder::default())
+    ///     .before_attempt(|client: &mut Client, attempt: usize| {
+    ///         if attempt > 1 {
+    ///             client.token = format!("refreshed-{attempt}");
+    ///         }
+    ///     })
+    ///     .context(client)
+    ///     .await;
+    ///     println!("fetch succeeded: {}", content?);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn before_attempt<BN: FnMut(&mut Ctx, usize)>(
+        self,
+        before_attempt: BN,
+    ) -> RetryWithContext<B, T, E, Ctx, Fut, FutureFn, SF, RF, NF, GF, BN, CF> {
+        RetryWithContext {
+            backoff: self.backoff,
+            retryable: self.retryable,
+            notify: self.notify,
+            on_giveup: self.on_giveup,
+            before_attempt,
+            snapshot: self.snapshot,
+            future_fn: self.future_fn,
+            sleep_fn: self.sleep_fn,
+            #[cfg(feature = "tracing")]
+            tracer: self.tracer,
+            progress: self.progress,
+            state: self.state,
+        }
+    }
+
+    /// Restore the context to its state before every attempt that's going to be retried.
+    ///
+    /// The context is cloned before every attempt, right after [`RetryWithContext::before_attempt`],
+    /// and the clone replaces the context once the attempt fails with an error to retry, so a
+    /// failed attempt can't leave partial changes behind for the next one. The hooks like
+    /// [`RetryWithContext::notify_with_context`] still see the context left by the failed
+    /// attempt. On success or when giving up, the context left by the last attempt is returned.
+    ///
+    /// Every attempt pays for cloning the context, keep it cheap to clone, like a small struct
+    /// or an `Arc`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use anyhow::anyhow;
+    /// use backon::ConstantBuilder;
+    /// use backon::RetryableWithContext;
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() {
+    ///     let (batch, result) = (|mut batch: Vec<u32>| async move {
+    ///         // Push a part of the batch, then fail.
+    ///         batch.push(1);
+    ///         (batch, Err::<(), _>(anyhow!("connection reset")))
+    ///     })
+    ///     .retry(ConstantBuilder::default().with_max_times(2))
+    ///     .sleep(|_| async {})
+    ///     .snapshot_context()
+    ///     .context(Vec::new())
+    ///     .await;
+    ///
+    ///     // Only the last attempt is kept.
+    ///     assert!(result.is_err());
+    ///     assert_eq!(batch, [1]);
+    /// }
+    /// ```
+    pub fn snapshot_context(mut self) -> Self
+    where
+        Ctx: Clone,
+    {
+        self.snapshot = Some(Ctx::clone);
+        self
+    }
+
+    /// Instrument this retry with [`tracing`].
+    ///
+    /// A span is entered every time this retry is polled, and an event carrying the attempt
+    /// number, the error and the upcoming delay is emitted at given `level` for every failed attempt.
+    ///
+    /// The fields of the events are the same as [`Retry::trace`](crate::Retry::trace).
+    #[cfg(feature = "tracing")]
+    pub fn trace(mut self, level: tracing::Level) -> Self
+    where
+        E: fmt::Display,
+    {
+        self.tracer = Some(crate::trace::Tracer::new(
+            level,
+            self.backoff.remaining().map(|n| n.saturating_add(1)),
+        ));
+        self
+    }
+
+    /// Erase the type of this retry by boxing it.
+    ///
+    /// The type of a retry is deeply generic and usually unnameable, which makes it hard to
+    /// store retries of different operations in a collection, like a task queue. The boxed
+    /// retry has a type that could be named: `Pin<Box<dyn Future<Output = (Ctx, Result<T, E>)> + Send + 'a>>`.
+    ///
+    /// The retry must be `Send`, which requires its operation, hooks, sleeper and the futures
+    /// they create to be `Send`. Use [`RetryWithContext::boxed_local`] otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::future::Future;
+    /// use std::pin::Pin;
+    ///
+    /// use anyhow::Result;
+    /// use backon::ExponentialBuilder;
+    /// use backon::RetryableWithContext;
+    ///
+    /// struct Client;
+    ///
+    /// impl Client {
+    ///     async fn fetch(&self) -> Result<String> {
+    ///         Ok(reqwest::get("https://www.rust-lang.org")
+    ///             .await?
+    ///             .text()
+    ///             .await?)
+    ///     }
+    /// }
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<()> {
+    ///     let task: Pin<Box<dyn Future<Output = (Client, Result<String>)> + Send>> = {
+    ///         |client: Client| async {
+    ///             let res = client.fetch().await;
+    ///             (client, res)
+    ///         }
+    ///     }
+    ///     .retry(ExponentialBuilder::default())
+    ///     .context(Client)
+    ///     .boxed();
+    ///
+    ///     let (_, content) = task.await;
+    ///     println!("fetch finished: {:?}", content);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    #[cfg(feature = "std")]
+    #[allow(clippy::type_complexity)]
+    pub fn boxed<'a>(
+        self,
+    ) -> Pin<std::boxed::Box<dyn Future<Output = (Ctx, Result<T, E>)> + Send + 'a>>
+    where
+        Self: Future<Output = (Ctx, Result<T, E>)> + Send + 'a,
+    {
+        std::boxed::Box::pin(self)
+    }
+
+    /// Erase the type of this retry by boxing it, without requiring it to be `Send`.
+    ///
+    /// It's the same as [`RetryWithContext::boxed`] but for retries that are not `Send`, like the ones
+    /// running on wasm32 or a single threaded executor.
+    #[cfg(feature = "std")]
+    #[allow(clippy::type_complexity)]
+    pub fn boxed_local<'a>(
+        self,
+    ) -> Pin<std::boxed::Box<dyn Future<Output = (Ctx, Result<T, E>)> + 'a>>
+    where
+        Self: Future<Output = (Ctx, Result<T, E>)> + 'a,
+    {
+        std::boxed::Box::pin(self)
+    }
+}
+
+impl<B, T, E, Fut, FutureFn, SF, RF, NF, GF>
+    Retry<B, T, E, Fut, FutureFn, SF, RF, NF, AdjustFn<E>, GF>
+where
+    B: Backoff,
+    Fut: Future<Output = Result<T, E>>,
+    FutureFn: FnMut() -> Fut,
+    SF: MaybeSleeper,
+    RF: FnMut(&E) -> bool,
+    NF: Notify<E>,
+    GF: FnMut(&E, usize),
+{
+    /// Upgrade this retry to a [`RetryWithContext`] that carries the given context.
+    ///
+    /// The context is handed back together with the result once the retry is finished, even if
+    /// all attempts failed. The function is still called without the context, so this is useful
+    /// when the context is only needed after the retry, for example to take back the ownership
+    /// of a value instead of rewriting the whole chain with [`RetryableWithContext`].
+    ///
+    /// `when`, `notify`, `on_retry`, `on_giveup` and `sleep` are preserved. `adjust` and
+    /// `try_sleep` are not supported by [`RetryWithContext`], so this method is not available
+    /// after them, and `initial_delay` is not preserved.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use anyhow::Result;
+    /// use backon::ExponentialBuilder;
+    /// use backon::Retryable;
+    ///
+    /// async fn fetch() -> Result<String> {
+    ///     Ok(reqwest::get("https://www.rust-lang.org")
+    ///         .await?
+    ///         .text()
+    ///         .await?)
+    /// }
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<()> {
+    ///     let buf = Vec::<u8>::with_capacity(1024);
+    ///
+    ///     let (buf, content) = fetch
+    ///         .retry(ExponentialBuilder::default())
+    ///         .when(|e| e.to_string() == "retryable")
+    ///         .with_context(buf)
+    ///         .await;
+    ///     println!("fetch result: {:?}, buf capacity: {}", content, buf.capacity());
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    #[allow(clippy::type_complexity)]
+    pub fn with_context<Ctx>(
+        self,
+        context: Ctx,
+    ) -> RetryWithContext<
+        B,
+        T,
+        E,
+        Ctx,
+        ContextFuture<Fut, Ctx>,
+        impl FnMut(Ctx) -> ContextFuture<Fut, Ctx>,
+        SF,
+        RF,
+        NF,
+        GF,
+    > {
+        let mut future_fn = self.future_fn;
+        RetryWithContext {
+            backoff: self.backoff,
+            retryable: self.retryable,
+            notify: self.notify,
+            on_giveup: self.on_giveup,
+            before_attempt: |_: &mut Ctx, _: usize| {},
+            snapshot: None,
+            future_fn: move |ctx: Ctx| ContextFuture {
+                fut: future_fn(),
+                ctx: Some(ctx),
+            },
+            sleep_fn: self.sleep_fn,
+            #[cfg(feature = "tracing")]
+            tracer: self.tracer,
+            progress: Progress::default(),
+            state: State::Idle(Some(context)),
+        }
+    }
+}
+
+/// The default adjust function of [`Retry`].
+type AdjustFn<E> = fn(&E, Option<Duration>) -> Option<Duration>;
+
+/// A future that returns the context along with the output of the inner future, generated by
+/// [`Retry::with_context`].
+#[doc(hidden)]
+pub struct ContextFuture<Fut, Ctx> {
+    fut: Fut,
+    ctx: Option<Ctx>,
+}
+
+impl<Fut: Future, Ctx> Future for ContextFuture<Fut, Ctx> {
+    type Output = (Ctx, Fut::Output);
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // Safety: This is safe because we don't move the `ContextFuture` struct itself,
+        // only its internal state.
+        //
+        // We do the exactly same thing like `pin_project` but without depending on it directly.
+        let this = unsafe { self.get_unchecked_mut() };
+        // Safety: This is safe because we don't move the `ContextFuture` struct and this fut,
+        // only its internal state.
+        //
+        // We do the exactly same thing like `pin_project` but without depending on it directly.
+        let fut = unsafe { Pin::new_unchecked(&mut this.fut) };
+        let res = ready!(fut.poll(cx));
+        let ctx = this.ctx.take().expect("context must be valid");
+        Poll::Ready((ctx, res))
+    }
 }
 
 /// State maintains internal state of retry.
-enum State<T, E, Ctx, Fut: Future<Output = (Ctx, Result<T, E>)>, SleepFut: Future<Output = ()>> {
+enum State<
+    T,
+    E,
+    Ctx,
+    Fut: Future<Output = (Ctx, Result<T, E>)>,
+    SleepFut: Future<Output = ()>,
+    CtxFut,
+> {
+    Creating(CtxFut),
     Idle(Option<Ctx>),
-    Polling(Fut),
+    /// The attempt and the snapshot of the context taken before it.
+    Polling(Fut, Option<Ctx>),
     Sleeping((Option<Ctx>, SleepFut)),
+    /// The context and the result have been returned.
+    Done,
 }
 
-impl<B, T, E, Ctx, Fut, FutureFn, SF, RF, NF> Future
-    for RetryWithContext<B, T, E, Ctx, Fut, FutureFn, SF, RF, NF>
+impl<B, T, E, Ctx, Fut, FutureFn, SF, RF, NF, GF, BF, CF> Future
+    for RetryWithContext<B, T, E, Ctx, Fut, FutureFn, SF, RF, NF, GF, BF, CF>
 where
     B: Backoff,
     Fut: Future<Output = (Ctx, Result<T, E>)>,
     FutureFn: FnMut(Ctx) -> Fut,
     SF: Sleeper,
     RF: FnMut(&E) -> bool,
-    NF: FnMut(&E, Duration),
+    NF: NotifyWithContext<Ctx, E>,
+    GF: FnMut(&E, usize),
+    BF: FnMut(&mut Ctx, usize),
+    CF: Future<Output = Ctx>,
 {
     type Output = (Ctx, Result<T, E>);
 
@@ -309,15 +1156,34 @@ where
         // We do the exactly same thing like `pin_project` but without depending on it directly.
         let this = unsafe { self.get_unchecked_mut() };
 
+        #[cfg(feature = "tracing")]
+        let _span = this.tracer.as_ref().map(|t| t.enter());
+
         loop {
             match &mut this.state {
+                State::Creating(fut) => {
+                    // Safety: This is safe because we don't move the `Retry` struct and this fut,
+                    // only its internal state.
+                    //
+                    // We do the exactly same thing like `pin_project` but without depending on it directly.
+                    let fut = unsafe { Pin::new_unchecked(fut) };
+
+                    let ctx = ready!(fut.poll(cx));
+                    this.state = State::Idle(Some(ctx));
+                    continue;
+                }
                 State::Idle(ctx) => {
-                    let ctx = ctx.take().expect("context must be valid");
+                    let mut ctx = ctx.take().expect(
+                        "context must be set by `RetryWithContext::context` before polling",
+                    );
+                    this.progress.start_attempt();
+                    (this.before_attempt)(&mut ctx, this.progress.attempts);
+                    let saved = this.snapshot.map(|snapshot| snapshot(&ctx));
                     let fut = (this.future_fn)(ctx);
-                    this.state = State::Polling(fut);
+                    this.state = State::Polling(fut, saved);
                     continue;
                 }
-                State::Polling(fut) => {
+                State::Polling(fut, saved) => {
                     // Safety: This is safe because we don't move the `Retry` struct and this fut,
                     // only its internal state.
                     //
@@ -326,18 +1192,45 @@ where
 
                     let (ctx, res) = ready!(fut.as_mut().poll(cx));
                     match res {
-                        Ok(v) => return Poll::Ready((ctx, Ok(v))),
+                        Ok(v) => {
+                            this.state = State::Done;
+                            return Poll::Ready((ctx, Ok(v)));
+                        }
                         Err(err) => {
-                            // If input error is not retryable, return error directly.
-                            if !(this.retryable)(&err) {
-                                return Poll::Ready((ctx, Err(err)));
+                            // Non-retryable errors are returned directly without consuming the backoff.
+                            let next = if (this.retryable)(&err) {
+                                this.backoff.next()
+                            } else {
+                                None
+                            };
+                            #[cfg(feature = "tracing")]
+                            if let Some(tracer) = &this.tracer {
+                                tracer.record(this.progress.attempts, &err, next);
                             }
-                            match this.backoff.next() {
-                                None => return Poll::Ready((ctx, Err(err))),
+                            match next {
+                                None => {
+                                    (this.on_giveup)(&err, this.progress.attempts);
+                                    this.state = State::Done;
+                                    return Poll::Ready((ctx, Err(err)));
+                                }
                                 Some(dur) => {
-                                    (this.notify)(&err, dur);
-                                    this.state =
-                                        State::Sleeping((Some(ctx), this.sleep_fn.sleep(dur)));
+                                    this.notify.notify(
+                                        &ctx,
+                                        RetryContext {
+                                            attempt: this.progress.attempts,
+                                            error: &err,
+                                            next_delay: dur,
+                                            elapsed: this.progress.elapsed(),
+                                            remaining: this.backoff.remaining(),
+                                        },
+                                    );
+                                    // Roll back the changes made by the failed attempt.
+                                    let ctx = saved.take().unwrap_or(ctx);
+                                    this.state = State::Sleeping((
+                                        Some(ctx),
+                                        this.sleep_fn
+                                            .sleep_for_attempt(dur, this.progress.attempts),
+                                    ));
                                     continue;
                                 }
                             }
@@ -356,11 +1249,31 @@ where
                     this.state = State::Idle(Some(ctx));
                     continue;
                 }
+                State::Done => return Poll::Pending,
             }
         }
     }
 }
 
+#[cfg(feature = "futures")]
+impl<B, T, E, Ctx, Fut, FutureFn, SF, RF, NF, GF, BF, CF> futures_core::FusedFuture
+    for RetryWithContext<B, T, E, Ctx, Fut, FutureFn, SF, RF, NF, GF, BF, CF>
+where
+    B: Backoff,
+    Fut: Future<Output = (Ctx, Result<T, E>)>,
+    FutureFn: FnMut(Ctx) -> Fut,
+    SF: Sleeper,
+    RF: FnMut(&E) -> bool,
+    NF: NotifyWithContext<Ctx, E>,
+    GF: FnMut(&E, usize),
+    BF: FnMut(&mut Ctx, usize),
+    CF: Future<Output = Ctx>,
+{
+    fn is_terminated(&self) -> bool {
+        matches!(self.state, State::Done)
+    }
+}
+
 #[cfg(test)]
 #[cfg(any(feature = "tokio-sleep", feature = "gloo-timers-sleep",))]
 mod tests {
@@ -379,6 +1292,7 @@ mod tests {
 
     use super::*;
     use crate::ExponentialBuilder;
+    use crate::Retryable;
 
     struct Test;
 
@@ -388,6 +1302,23 @@ mod tests {
         }
     }
 
+    #[test]
+    async fn test_retry_with_context_polled_after_completion() {
+        let mut retry = core::pin::pin!({ |v: Test| async { (v, Ok::<_, anyhow::Error>(42)) } }
+            .retry(ExponentialBuilder::default())
+            .context(Test));
+
+        match futures::poll!(retry.as_mut()) {
+            Poll::Ready((_, result)) => assert_eq!(result.unwrap(), 42),
+            Poll::Pending => panic!("the retry must be ready"),
+        }
+        #[cfg(feature = "futures")]
+        assert!(futures_core::FusedFuture::is_terminated(&*retry));
+
+        // Polling again neither panics nor starts a new attempt.
+        assert!(futures::poll!(retry.as_mut()).is_pending());
+    }
+
     #[test]
     async fn test_retry_with_not_retryable_error() {
         let error_times = Mutex::new(0);
@@ -417,4 +1348,281 @@ mod tests {
         // only once.
         assert_eq!(*error_times.lock().await, 1);
     }
+
+    #[test]
+    async fn test_retry_with_before_attempt() {
+        let backoff = ExponentialBuilder::default().with_min_delay(Duration::from_millis(1));
+
+        let (attempts, result) = {
+            |attempts: alloc::vec::Vec<usize>| async move {
+                // The context has been updated before this attempt.
+                let res = if attempts.len() < 3 {
+                    Err(anyhow!("retryable"))
+                } else {
+                    Ok(attempts.len())
+                };
+                (attempts, res)
+            }
+        }
+        .retry(backoff)
+        .before_attempt(|attempts: &mut alloc::vec::Vec<usize>, attempt| attempts.push(attempt))
+        .context(alloc::vec::Vec::new())
+        .await;
+
+        assert_eq!(result.unwrap(), 3);
+        assert_eq!(attempts, [1, 2, 3]);
+    }
+
+    #[test]
+    async fn test_retry_with_snapshot_context() {
+        let backoff = ExponentialBuilder::default().with_min_delay(Duration::from_millis(1));
+        let seen = Mutex::new(alloc::vec::Vec::new());
+
+        let seen = &seen;
+        let (ctx, result) = {
+            move |mut ctx: alloc::vec::Vec<&'static str>| async move {
+                // Every attempt starts from the context left by `before_attempt`.
+                seen.lock().await.push(ctx.clone());
+                ctx.push("partial");
+                (ctx, Err::<(), _>(anyhow!("retryable")))
+            }
+        }
+        .retry(backoff)
+        .before_attempt(|ctx: &mut alloc::vec::Vec<&'static str>, _| ctx.push("before"))
+        .notify_with_context(
+            |ctx: &alloc::vec::Vec<&'static str>, _: &anyhow::Error, _| {
+                // The hooks see the context left by the failed attempt.
+                assert_eq!(ctx.last(), Some(&"partial"));
+            },
+        )
+        .snapshot_context()
+        .context(alloc::vec::Vec::new())
+        .await;
+
+        assert!(result.is_err());
+        assert_eq!(
+            *seen.lock().await,
+            [
+                alloc::vec!["before"],
+                alloc::vec!["before", "before"],
+                alloc::vec!["before", "before", "before"],
+                alloc::vec!["before", "before", "before", "before"],
+            ]
+        );
+        // The context of the last attempt is returned when giving up.
+        assert_eq!(ctx, ["before", "before", "before", "before", "partial"]);
+    }
+
+    #[test]
+    async fn test_retry_with_notify_with_context() {
+        struct Request {
+            id: u64,
+            attempts: usize,
+        }
+
+        let backoff = ExponentialBuilder::default()
+            .with_min_delay(Duration::from_millis(1))
+            .with_max_times(2);
+        let notified = spin::Mutex::new(alloc::vec::Vec::new());
+
+        let (req, result) = {
+            |mut req: Request| async move {
+                req.attempts += 1;
+                (req, Err::<(), _>(anyhow!("retryable")))
+            }
+        }
+        .retry(backoff)
+        .notify_with_context(|req: &Request, _: &anyhow::Error, dur| {
+            notified.lock().push((req.id, req.attempts, dur))
+        })
+        .context(Request {
+            id: 42,
+            attempts: 0,
+        })
+        .await;
+
+        assert!(result.is_err());
+        assert_eq!(req.attempts, 3);
+        assert_eq!(
+            *notified.lock(),
+            [
+                (42, 1, Duration::from_millis(1)),
+                (42, 2, Duration::from_millis(2)),
+            ]
+        );
+    }
+
+    #[test]
+    async fn test_retry_gives_up_with_context_of_last_attempt() {
+        let backoff = ExponentialBuilder::default()
+            .with_min_delay(Duration::from_millis(1))
+            .with_max_times(2);
+
+        // Every attempt records its partial progress, then fails.
+        let (progress, result) = {
+            |mut progress: alloc::vec::Vec<usize>| async move {
+                progress.push(progress.len() + 1);
+                (progress, Err::<(), _>(anyhow!("retryable")))
+            }
+        }
+        .retry(backoff)
+        .context(alloc::vec::Vec::new())
+        .await;
+
+        assert!(result.is_err());
+        // The mutations made by the final failed attempt survive the give-up path.
+        assert_eq!(progress, [1, 2, 3]);
+
+        // Non-retryable errors give up with the context of that attempt as well.
+        let (progress, result) = {
+            |mut progress: alloc::vec::Vec<usize>| async move {
+                progress.push(progress.len() + 1);
+                (progress, Err::<(), _>(anyhow!("not retryable")))
+            }
+        }
+        .retry(backoff)
+        .context(alloc::vec![0])
+        .when(|e| e.to_string() == "retryable")
+        .await;
+
+        assert!(result.is_err());
+        assert_eq!(progress, [0, 2]);
+    }
+
+    #[test]
+    async fn test_retry_with_context_created_async() {
+        let backoff = ExponentialBuilder::default()
+            .with_min_delay(Duration::from_millis(1))
+            .with_max_times(3);
+        let notify_times = Mutex::new(0);
+
+        let (attempts, result) = {
+            |attempts: alloc::vec::Vec<usize>| async move {
+                let res = if attempts.len() < 3 {
+                    Err(anyhow!("retryable"))
+                } else {
+                    Ok(attempts.len())
+                };
+                (attempts, res)
+            }
+        }
+        .retry(backoff)
+        .sleep(|_| core::future::ready(()))
+        .context_with(|| async { alloc::vec![0] })
+        .when(|e| e.to_string() == "retryable")
+        .notify(|_, _| *notify_times.try_lock().unwrap() += 1)
+        .before_attempt(|attempts: &mut alloc::vec::Vec<usize>, attempt| attempts.push(attempt))
+        .await;
+
+        // The context is created before the first attempt.
+        assert_eq!(result.unwrap(), 3);
+        assert_eq!(attempts, [0, 1, 2]);
+        assert_eq!(*notify_times.lock().await, 1);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    async fn test_retry_with_borrowed_context() {
+        struct Counter {
+            attempts: usize,
+        }
+
+        let target = 3;
+        let (counter, result) = borrow_context(move |counter: &mut Counter| {
+            alloc::boxed::Box::pin(async move {
+                counter.attempts += 1;
+                if counter.attempts < target {
+                    Err(anyhow!("retryable"))
+                } else {
+                    Ok(counter.attempts)
+                }
+            })
+        })
+        .retry(ExponentialBuilder::default().with_min_delay(Duration::from_millis(1)))
+        .sleep(|_| core::future::ready(()))
+        .context(Counter { attempts: 0 })
+        .await;
+
+        assert_eq!(result.unwrap(), 3);
+        // The changes made by all attempts are kept.
+        assert_eq!(counter.attempts, 3);
+    }
+
+    #[test]
+    async fn test_retry_with_context_cloned() {
+        let backoff = ExponentialBuilder::default()
+            .with_min_delay(Duration::from_millis(1))
+            .with_max_times(2)
+            .build();
+
+        let template = {
+            |mut attempts: usize| async move {
+                attempts += 1;
+                (attempts, Err::<(), _>(anyhow!("retryable")))
+            }
+        }
+        .retry(backoff)
+        .sleep(|_| core::future::ready(()));
+
+        // Every clone starts from scratch with its own context.
+        for start in [0, 10] {
+            let (attempts, result) = template.clone().context(start).await;
+            assert!(result.is_err());
+            assert_eq!(attempts, start + 3);
+        }
+    }
+
+    #[test]
+    async fn test_retry_upgraded_with_context() {
+        let error_times = Mutex::new(0);
+        let notify_times = Mutex::new(0);
+
+        let f = || async {
+            let mut x = error_times.lock().await;
+            *x += 1;
+            Err::<(), _>(anyhow!("retryable"))
+        };
+
+        let backoff = ExponentialBuilder::default()
+            .with_min_delay(Duration::from_millis(1))
+            .with_max_times(2);
+        let (ctx, result) = f
+            .retry(backoff)
+            .when(|e| e.to_string() == "retryable")
+            .notify(|_, _| *notify_times.try_lock().unwrap() += 1)
+            .with_context(Test)
+            .await;
+
+        // The context is handed back even if all attempts failed.
+        let Test = ctx;
+        assert_eq!("retryable", result.unwrap_err().to_string());
+        assert_eq!(*error_times.lock().await, 3);
+        assert_eq!(*notify_times.lock().await, 2);
+    }
+
+    #[test]
+    async fn test_retry_method() {
+        struct Counter {
+            calls: usize,
+        }
+
+        impl Counter {
+            async fn hello(&mut self) -> Result<usize> {
+                self.calls += 1;
+                if self.calls < 3 {
+                    Err(anyhow!("retryable"))
+                } else {
+                    Ok(self.calls)
+                }
+            }
+        }
+
+        let backoff = ExponentialBuilder::default().with_min_delay(Duration::from_millis(1));
+        let (counter, result) = retry_method!(Counter { calls: 0 }, Counter::hello, backoff)
+            .when(|e| e.to_string() == "retryable")
+            .await;
+
+        assert_eq!(result.unwrap(), 3);
+        assert_eq!(counter.calls, 3);
+    }
 }
@@ -0,0 +1,408 @@
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::time::Duration;
+use std::boxed::Box;
+
+use reqwest::header::HeaderMap;
+use reqwest::header::RETRY_AFTER;
+use reqwest::RequestBuilder;
+use reqwest::Response;
+use reqwest::StatusCode;
+
+use crate::backoff::BackoffBuilder;
+use crate::Retry;
+use crate::Retryable;
+
+/// The max delay taken from `Retry-After` by [`RetryableRequest::send_retry`].
+const DEFAULT_MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// RetryableRequest adds retry support for [`reqwest::RequestBuilder`].
+///
+/// The request is retried on:
+///
+/// - connection errors and timeouts.
+/// - responses with status `429 Too Many Requests` or `5xx`.
+///
+/// If the response carries a `Retry-After` header, the delay given by the server is used
+/// instead of the delay given by the backoff through [`Retry::adjust`], while the backoff is
+/// not exhausted. The delay given by the server is capped at 60 seconds, or the max given to [`RetryableRequest::send_retry_with_max`], so a server can't make the
+/// client sleep for a day.
+///
+/// The returned [`SendRetry`] is a [`Retry`], so it could be configured further like any
+/// other retry. Once the backoff is exhausted, the last error is returned, use
+/// [`SendError::into_response`] to get the last response back.
+///
+/// The request must be cloneable via [`RequestBuilder::try_clone`] to be retried, requests
+/// with a streaming body are sent only once.
+///
+/// # Example
+///
+/// ```no_run
+/// use anyhow::Result;
+/// use backon::ExponentialBuilder;
+/// use backon::RetryableRequest;
+/// use backon::SendError;
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() -> Result<()> {
+///     let content = reqwest::Client::new()
+///         .get("https://www.rust-lang.org")
+///         .send_retry(ExponentialBuilder::default())
+///         .notify(|err, dur| println!("retrying {err} after {dur:?}"))
+///         .await
+///         .or_else(SendError::into_response)?
+///         .text()
+///         .await?;
+///     println!("fetch succeeded: {}", content);
+///
+///     Ok(())
+/// }
+/// ```
+pub trait RetryableRequest<B: BackoffBuilder> {
+    /// Send the request with retry.
+    fn send_retry(self, builder: B) -> SendRetry<B::Backoff>;
+
+    /// Send the request with retry, capping the delays given by `Retry-After` at
+    /// `max_retry_after`.
+    fn send_retry_with_max(self, builder: B, max_retry_after: Duration) -> SendRetry<B::Backoff>;
+}
+
+impl<B: BackoffBuilder> RetryableRequest<B> for RequestBuilder {
+    fn send_retry(self, builder: B) -> SendRetry<B::Backoff> {
+        self.send_retry_with_max(builder, DEFAULT_MAX_RETRY_AFTER)
+    }
+
+    fn send_retry_with_max(self, builder: B, max_retry_after: Duration) -> SendRetry<B::Backoff> {
+        // The request to clone for every attempt, taken by the attempt if it can't be cloned.
+        let mut request = Some(self);
+        let send: SendFn = Box::new(move || {
+            let (req, retryable) = match request.as_ref().and_then(RequestBuilder::try_clone) {
+                Some(req) => (req, true),
+                // The request can't be cloned, send it for the last time.
+                None => (request.take().expect("request must be valid"), false),
+            };
+            Box::pin(async move {
+                let mut err = match req.send().await {
+                    Ok(resp) if is_retryable_status(resp.status()) => SendError {
+                        retry_after: retry_after(resp.headers()).map(|d| d.min(max_retry_after)),
+                        kind: Kind::Status(resp),
+                        retryable: true,
+                    },
+                    Ok(resp) => return Ok(resp),
+                    Err(err) => SendError {
+                        retryable: is_retryable_error(&err),
+                        kind: Kind::Request(err),
+                        retry_after: None,
+                    },
+                };
+                err.retryable &= retryable;
+                Err(err)
+            })
+        });
+
+        send.retry(builder)
+            .when(SendError::is_retryable as fn(&SendError) -> bool)
+            .adjust(adjust_retry_after as fn(&SendError, Option<Duration>) -> Option<Duration>)
+    }
+}
+
+/// Future generated by [`RetryableRequest`].
+pub type SendRetry<B> = Retry<
+    B,
+    Response,
+    SendError,
+    Pending,
+    SendFn,
+    crate::DefaultSleeper,
+    fn(&SendError) -> bool,
+    fn(&SendError, Duration),
+    fn(&SendError, Option<Duration>) -> Option<Duration>,
+>;
+
+/// The future returned by [`RequestBuilder::send`], which can't be named.
+#[cfg(not(target_arch = "wasm32"))]
+type Pending = Pin<Box<dyn Future<Output = Result<Response, SendError>> + Send>>;
+/// The future returned by [`RequestBuilder::send`], which can't be named.
+#[cfg(target_arch = "wasm32")]
+type Pending = Pin<Box<dyn Future<Output = Result<Response, SendError>>>>;
+
+/// The function sending the request for every attempt.
+#[cfg(not(target_arch = "wasm32"))]
+type SendFn = Box<dyn FnMut() -> Pending + Send>;
+/// The function sending the request for every attempt.
+#[cfg(target_arch = "wasm32")]
+type SendFn = Box<dyn FnMut() -> Pending>;
+
+/// SendError is the error of a request sent by [`RetryableRequest`].
+///
+/// It's either an error of sending the request, or a response with a retryable status.
+#[derive(Debug)]
+pub struct SendError {
+    kind: Kind,
+    retry_after: Option<Duration>,
+    retryable: bool,
+}
+
+#[derive(Debug)]
+enum Kind {
+    Request(reqwest::Error),
+    Status(Response),
+}
+
+impl SendError {
+    /// Check if the request is worth retrying.
+    pub fn is_retryable(&self) -> bool {
+        self.retryable
+    }
+
+    /// Return the capped delay given by the `Retry-After` header of the response.
+    pub fn retry_after(&self) -> Option<Duration> {
+        self.retry_after
+    }
+
+    /// Return the response with a retryable status, or the error of sending the request.
+    pub fn into_response(self) -> reqwest::Result<Response> {
+        match self.kind {
+            Kind::Request(err) => Err(err),
+            Kind::Status(resp) => Ok(resp),
+        }
+    }
+}
+
+impl fmt::Display for SendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            Kind::Request(err) => write!(f, "{err}"),
+            Kind::Status(resp) => write!(f, "response with status {}", resp.status()),
+        }
+    }
+}
+
+impl std::error::Error for SendError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.kind {
+            Kind::Request(err) => Some(err),
+            Kind::Status(_) => None,
+        }
+    }
+}
+
+/// Use the delay given by the server while the backoff is not exhausted.
+fn adjust_retry_after(err: &SendError, dur: Option<Duration>) -> Option<Duration> {
+    dur.map(|dur| err.retry_after.unwrap_or(dur))
+}
+
+/// Check if the response status is worth retrying.
+fn is_retryable_status(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// Check if the error is worth retrying.
+fn is_retryable_error(err: &reqwest::Error) -> bool {
+    #[cfg(not(target_arch = "wasm32"))]
+    if err.is_connect() {
+        return true;
+    }
+
+    err.is_timeout()
+}
+
+/// Parse the `Retry-After` header of the response.
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?;
+
+    // `SystemTime::now` is not available on wasm32, only delta-seconds is supported there.
+    #[cfg(target_arch = "wasm32")]
+    return crate::http::parse_retry_after(value, std::time::UNIX_EPOCH)
+        .filter(|_| value.trim().bytes().all(|b| b.is_ascii_digit()));
+
+    #[cfg(not(target_arch = "wasm32"))]
+    crate::http::parse_retry_after(value, std::time::SystemTime::now())
+}
+
+#[cfg(test)]
+#[cfg(not(target_arch = "wasm32"))]
+mod tests {
+    extern crate alloc;
+
+    use alloc::format;
+    use alloc::string::String;
+    use alloc::sync::Arc;
+    use alloc::vec;
+    use alloc::vec::Vec;
+    use core::future::ready;
+    use std::io::Read;
+    use std::io::Write;
+    use std::net::TcpListener;
+    use std::thread;
+
+    use spin::Mutex;
+
+    use super::*;
+    use crate::ConstantBuilder;
+
+    /// Serve given responses in order, one per connection.
+    fn serve(responses: Vec<&'static str>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        thread::spawn(move || {
+            for resp in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut buf = [0; 1024];
+                let _ = stream.read(&mut buf).unwrap();
+                stream.write_all(resp.as_bytes()).unwrap();
+            }
+        });
+
+        format!("http://{addr}")
+    }
+
+    const UNAVAILABLE: &str = "HTTP/1.1 503 Service Unavailable\r\nRetry-After: 7\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+    const LONG_UNAVAILABLE: &str = "HTTP/1.1 503 Service Unavailable\r\nRetry-After: 86400\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+    const TOO_MANY_REQUESTS: &str =
+        "HTTP/1.1 429 Too Many Requests\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+    const NOT_FOUND: &str =
+        "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+    const OK: &str = "HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok";
+
+    #[tokio::test]
+    async fn test_send_retry() {
+        let url = serve(vec![UNAVAILABLE, TOO_MANY_REQUESTS, OK]);
+        let sleeps = Arc::new(Mutex::new(Vec::new()));
+
+        let resp = reqwest::Client::new()
+            .get(url)
+            .send_retry(ConstantBuilder::default())
+            .sleep({
+                let sleeps = sleeps.clone();
+                move |dur| {
+                    sleeps.lock().push(dur);
+                    ready(())
+                }
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.text().await.unwrap(), "ok");
+        // The delay given by `Retry-After` is used over the backoff.
+        assert_eq!(
+            *sleeps.lock(),
+            vec![Duration::from_secs(7), Duration::from_secs(1)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_send_retry_exhausted() {
+        let url = serve(vec![TOO_MANY_REQUESTS, TOO_MANY_REQUESTS]);
+
+        let resp = reqwest::Client::new()
+            .get(url)
+            .send_retry(ConstantBuilder::default().with_max_times(1))
+            .sleep(|_| ready(()))
+            .await
+            .unwrap_err()
+            .into_response()
+            .unwrap();
+
+        // The last response is returned after the backoff is exhausted.
+        assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    #[tokio::test]
+    async fn test_send_retry_not_retryable() {
+        let url = serve(vec![NOT_FOUND]);
+
+        let resp = reqwest::Client::new()
+            .get(url)
+            .send_retry(ConstantBuilder::default())
+            .sleep(|_| -> core::future::Ready<()> { panic!("must not retry") })
+            .await
+            .unwrap();
+
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_send_retry_connect_error() {
+        // Bind and drop a listener to get a port that refuses connections.
+        let addr = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+
+        let result = reqwest::Client::new()
+            .get(format!("http://{addr}"))
+            .send_retry(ConstantBuilder::default().with_max_times(2))
+            .sleep(|_| ready(()))
+            .await;
+
+        assert!(result
+            .unwrap_err()
+            .into_response()
+            .unwrap_err()
+            .is_connect());
+    }
+
+    #[tokio::test]
+    async fn test_send_retry_max_retry_after() {
+        let url = serve(vec![LONG_UNAVAILABLE, LONG_UNAVAILABLE, OK]);
+        let sleeps = Arc::new(Mutex::new(Vec::new()));
+        let sleep = {
+            let sleeps = sleeps.clone();
+            move |dur| {
+                sleeps.lock().push(dur);
+                ready(())
+            }
+        };
+
+        let client = reqwest::Client::new();
+        let resp = client
+            .get(&url)
+            .send_retry_with_max(ConstantBuilder::default(), Duration::from_secs(5))
+            .sleep(sleep.clone())
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        // The delay given by the server is capped.
+        let url = serve(vec![LONG_UNAVAILABLE, OK]);
+        let resp = client
+            .get(&url)
+            .send_retry(ConstantBuilder::default())
+            .sleep(sleep)
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            *sleeps.lock(),
+            vec![
+                Duration::from_secs(5),
+                Duration::from_secs(5),
+                Duration::from_secs(60)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_send_retry_when() {
+        let url = serve(vec![UNAVAILABLE]);
+
+        // It's a `Retry`, so the conditions could be replaced.
+        let err = reqwest::Client::new()
+            .get(url)
+            .send_retry(ConstantBuilder::default())
+            .when(|_| false)
+            .sleep(|_| -> core::future::Ready<()> { panic!("must not retry") })
+            .await
+            .unwrap_err();
+        assert!(err.is_retryable());
+        assert_eq!(err.retry_after(), Some(Duration::from_secs(7)));
+        assert_eq!(
+            err.into_response().unwrap().status(),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+    }
+}
@@ -1,5 +1,10 @@
 use core::{
-    future::{Future, Ready},
+    convert::Infallible,
+    fmt,
+    future::{ready, Future, Ready},
+    marker::PhantomData,
+    pin::Pin,
+    task::{ready, Context, Poll},
     time::Duration,
 };
 
@@ -10,6 +15,44 @@ pub trait Sleeper: 'static {
 
     /// Create a future that completes after a set period.
     fn sleep(&self, dur: Duration) -> Self::Sleep;
+
+    /// Create a future that completes after a set period, knowing the attempt it sleeps after.
+    ///
+    /// Retries call this method instead of [`Sleeper::sleep`], `attempt` is the number of
+    /// attempts made before this sleep, starting from `1`. It's `0` for the sleeps made before
+    /// the first attempt, like the initial delay.
+    ///
+    /// Override it to log or adjust the sleeps by the attempt, it calls [`Sleeper::sleep`] by
+    /// default.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::future::ready;
+    /// use std::future::Ready;
+    /// use std::time::Duration;
+    ///
+    /// use backon::Sleeper;
+    ///
+    /// struct LoggingSleeper;
+    ///
+    /// impl Sleeper for LoggingSleeper {
+    ///     type Sleep = Ready<()>;
+    ///
+    ///     fn sleep(&self, _: Duration) -> Self::Sleep {
+    ///         ready(())
+    ///     }
+    ///
+    ///     fn sleep_for_attempt(&self, dur: Duration, attempt: usize) -> Self::Sleep {
+    ///         println!("sleeping {:?} after attempt {}", dur, attempt);
+    ///         self.sleep(dur)
+    ///     }
+    /// }
+    /// ```
+    fn sleep_for_attempt(&self, dur: Duration, attempt: usize) -> Self::Sleep {
+        let _ = attempt;
+        self.sleep(dur)
+    }
 }
 
 /// A stub trait allowing non-[`Sleeper`] types to be used as a generic parameter in [`Retry`][crate::Retry].
@@ -33,10 +76,190 @@ impl<F: Fn(Duration) -> Fut + 'static, Fut: Future<Output = ()>> Sleeper for F {
     }
 }
 
+/// A sleeper that could fail to sleep.
+///
+/// It's useful for sleepers that consult a cancellation token, or rely on a runtime that
+/// could be shut down during the sleep. Use it with [`Retry::try_sleep`][crate::Retry::try_sleep],
+/// a failed sleep stops the retry and is returned as its error.
+///
+/// All [`Sleeper`]s implement `TrySleeper` and never fail.
+///
+/// # Examples
+///
+/// ```
+/// use std::future::ready;
+/// use std::future::Ready;
+/// use std::sync::atomic::AtomicBool;
+/// use std::sync::atomic::Ordering;
+/// use std::sync::Arc;
+/// use std::time::Duration;
+///
+/// use backon::SleepError;
+/// use backon::TrySleeper;
+///
+/// /// A sleeper that refuses to sleep after being cancelled.
+/// struct CancellableSleeper {
+///     cancelled: Arc<AtomicBool>,
+/// }
+///
+/// impl TrySleeper for CancellableSleeper {
+///     type Sleep = Ready<Result<(), SleepError>>;
+///
+///     fn try_sleep(&self, _: Duration) -> Self::Sleep {
+///         if self.cancelled.load(Ordering::Relaxed) {
+///             ready(Err(SleepError::new("cancelled")))
+///         } else {
+///             ready(Ok(()))
+///         }
+///     }
+/// }
+/// ```
+pub trait TrySleeper: 'static {
+    /// The future returned by the `try_sleep` method.
+    type Sleep: Future<Output = Result<(), SleepError>>;
+
+    /// Create a future that completes after a set period, or fails if sleeping is not possible.
+    fn try_sleep(&self, dur: Duration) -> Self::Sleep;
+
+    /// Like [`TrySleeper::try_sleep`], knowing the attempt it sleeps after.
+    ///
+    /// See [`Sleeper::sleep_for_attempt`] for the meaning of `attempt`, it calls
+    /// [`TrySleeper::try_sleep`] by default.
+    fn try_sleep_for_attempt(&self, dur: Duration, attempt: usize) -> Self::Sleep {
+        let _ = attempt;
+        self.try_sleep(dur)
+    }
+}
+
+/// All `Sleeper` will implement `TrySleeper` that never fails.
+impl<T: Sleeper + ?Sized> TrySleeper for T {
+    type Sleep = InfallibleSleep<<T as Sleeper>::Sleep>;
+
+    fn try_sleep(&self, dur: Duration) -> Self::Sleep {
+        InfallibleSleep::new(self.sleep(dur))
+    }
+
+    fn try_sleep_for_attempt(&self, dur: Duration, attempt: usize) -> Self::Sleep {
+        InfallibleSleep::new(self.sleep_for_attempt(dur, attempt))
+    }
+}
+
+/// SleepError is returned by a [`TrySleeper`] that failed to sleep.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SleepError {
+    reason: &'static str,
+}
+
+impl SleepError {
+    /// Create a new sleep error with the reason of the failure.
+    pub fn new(reason: &'static str) -> Self {
+        SleepError { reason }
+    }
+
+    /// The reason why sleeping failed.
+    pub fn reason(&self) -> &'static str {
+        self.reason
+    }
+}
+
+impl fmt::Display for SleepError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "failed to sleep: {}", self.reason)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SleepError {}
+
+/// The future of a [`Sleeper`] used as a [`TrySleeper`], which never fails.
+#[doc(hidden)]
+pub struct InfallibleSleep<F, Err = SleepError> {
+    sleep: F,
+    error: PhantomData<fn() -> Err>,
+}
+
+impl<F, Err> InfallibleSleep<F, Err> {
+    fn new(sleep: F) -> Self {
+        InfallibleSleep {
+            sleep,
+            error: PhantomData,
+        }
+    }
+}
+
+impl<F: Future<Output = ()>, Err> Future for InfallibleSleep<F, Err> {
+    type Output = Result<(), Err>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // Safety: This is safe because we don't move the inner future.
+        //
+        // We do the exactly same thing like `pin_project` but without depending on it directly.
+        let fut = unsafe { self.map_unchecked_mut(|s| &mut s.sleep) };
+        ready!(fut.poll(cx));
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// A wrapper that marks a [`TrySleeper`] set by [`Retry::try_sleep`][crate::Retry::try_sleep].
+#[doc(hidden)]
+#[derive(Clone)]
+pub struct Fallible<S>(pub(crate) S);
+
+/// A stub trait allowing both infallible and fallible sleepers to be used as a generic parameter
+/// in [`Retry`][crate::Retry]. It does not provide actual functionality.
+#[doc(hidden)]
+pub trait MaybeTrySleeper: 'static {
+    type Error;
+    type Sleep: Future<Output = Result<(), Self::Error>>;
+}
+
+/// All `MaybeSleeper` never fail.
+impl<T: MaybeSleeper + ?Sized> MaybeTrySleeper for T {
+    type Error = Infallible;
+    type Sleep = InfallibleSleep<<T as MaybeSleeper>::Sleep, Infallible>;
+}
+
+impl<S: TrySleeper> MaybeTrySleeper for Fallible<S> {
+    type Error = SleepError;
+    type Sleep = S::Sleep;
+}
+
+/// RetrySleeper is the sleeper used by [`Retry`][crate::Retry], which converts the failure of
+/// sleeping into the error of the retry.
+#[doc(hidden)]
+pub trait RetrySleeper<E>: MaybeTrySleeper {
+    fn retry_sleep(&self, dur: Duration, attempt: usize) -> Self::Sleep;
+
+    fn into_error(err: Self::Error) -> E;
+}
+
+impl<T: Sleeper + ?Sized, E> RetrySleeper<E> for T {
+    fn retry_sleep(&self, dur: Duration, attempt: usize) -> Self::Sleep {
+        InfallibleSleep::new(Sleeper::sleep_for_attempt(self, dur, attempt))
+    }
+
+    fn into_error(err: Infallible) -> E {
+        match err {}
+    }
+}
+
+impl<S: TrySleeper, E: From<SleepError>> RetrySleeper<E> for Fallible<S> {
+    fn retry_sleep(&self, dur: Duration, attempt: usize) -> Self::Sleep {
+        self.0.try_sleep_for_attempt(dur, attempt)
+    }
+
+    fn into_error(err: SleepError) -> E {
+        err.into()
+    }
+}
+
 /// The default implementation of `Sleeper` when no features are enabled.
 ///
 /// It will fail to compile if a containing [`Retry`][crate::Retry] is `.await`ed without calling [`Retry::sleep`][crate::Retry::sleep] to provide a valid sleeper.
-#[cfg(all(not(feature = "tokio-sleep"), not(feature = "gloo-timers-sleep"),))]
+#[cfg(any(
+    all(not(target_arch = "wasm32"), not(feature = "tokio-sleep")),
+    all(target_arch = "wasm32", not(feature = "gloo-timers-sleep"))
+))]
 pub type DefaultSleeper = PleaseEnableAFeatureOrProvideACustomSleeper;
 /// The default implementation of `Sleeper` while feature `tokio-sleep` enabled.
 ///
@@ -51,8 +274,11 @@ pub type DefaultSleeper = GlooTimersSleep;
 
 /// A placeholder type that does not implement [`Sleeper`] and will therefore fail to compile if used as one.
 ///
-/// Users should enable a feature of this crate that provides a valid [`Sleeper`] implementation when this type appears in compilation errors. Alternatively, a custom [`Sleeper`] implementation should be provided where necessary, such as in [`crate::Retry::sleeper`].
+/// Users should enable a feature of this crate that provides a valid [`Sleeper`] implementation when this type appears in compilation errors,
+/// that is `tokio-sleep` on non-wasm32 targets and `gloo-timers-sleep` on wasm32. Alternatively, a custom [`Sleeper`] implementation
+/// should be provided where necessary, such as in [`Retry::sleep`][crate::Retry::sleep].
 #[doc(hidden)]
+#[allow(dead_code)]
 #[derive(Clone, Copy, Debug, Default)]
 pub struct PleaseEnableAFeatureOrProvideACustomSleeper;
 
@@ -78,7 +304,83 @@ impl Sleeper for TokioSleeper {
     }
 }
 
+/// A `Sleeper` that spawns a thread for every sleep, which wakes the task when the delay elapses.
+///
+/// It works with any executor, pass it to [`Retry::sleep`](crate::Retry::sleep) if
+/// `tokio-sleep` is disabled and the runtime provides no timer. It's never used by default:
+/// a thread per sleep is not cheap, prefer a sleeper provided by your runtime like
+/// [`TokioSleeper`] if possible.
+///
+/// The spawned thread is not stopped if the future is dropped, it exits after the delay.
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ThreadSleeper;
+
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+impl Sleeper for ThreadSleeper {
+    type Sleep = ThreadSleep;
+
+    fn sleep(&self, dur: Duration) -> Self::Sleep {
+        ThreadSleep {
+            deadline: std::time::Instant::now().checked_add(dur),
+            waker: None,
+        }
+    }
+}
+
+/// The future returned by [`ThreadSleeper`].
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+#[derive(Debug)]
+pub struct ThreadSleep {
+    /// `None` if the deadline is too far to be represented, in which case it never completes.
+    deadline: Option<std::time::Instant>,
+    /// The waker shared with the spawned thread, `None` if the thread is not spawned yet.
+    waker: Option<std::sync::Arc<std::sync::Mutex<Option<core::task::Waker>>>>,
+}
+
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+impl Future for ThreadSleep {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if let Some(deadline) = self.deadline {
+            if std::time::Instant::now() >= deadline {
+                return Poll::Ready(());
+            }
+        }
+
+        match &self.waker {
+            Some(waker) => {
+                // The thread could have woken the previous waker already, but it will be
+                // polled again and find the deadline passed in that case.
+                *waker.lock().unwrap_or_else(|err| err.into_inner()) = Some(cx.waker().clone());
+            }
+            None => {
+                let waker = std::sync::Arc::new(std::sync::Mutex::new(Some(cx.waker().clone())));
+                if let Some(deadline) = self.deadline {
+                    let shared = waker.clone();
+                    std::thread::spawn(move || {
+                        std::thread::sleep(
+                            deadline.saturating_duration_since(std::time::Instant::now()),
+                        );
+                        let waker = shared.lock().unwrap_or_else(|err| err.into_inner()).take();
+                        if let Some(waker) = waker {
+                            waker.wake();
+                        }
+                    });
+                }
+                self.waker = Some(waker);
+            }
+        }
+        Poll::Pending
+    }
+}
+
 /// The default implementation of `Sleeper` utilizes `gloo_timers::future::sleep`.
+///
+/// The returned future is gloo's `TimeoutFuture`, which clears its pending timeout with
+/// `clearTimeout` when dropped. A retry drops its sleep when it's dropped, so a retry dropped
+/// while sleeping won't leave a stray timer callback behind.
 #[cfg(all(target_arch = "wasm32", feature = "gloo-timers-sleep"))]
 #[derive(Clone, Copy, Debug, Default)]
 pub struct GlooTimersSleep;
@@ -91,3 +393,474 @@ impl Sleeper for GlooTimersSleep {
         gloo_timers::future::sleep(dur)
     }
 }
+
+/// A `Sleeper` that speeds up time by dividing every delay by a factor before delegating to
+/// the inner sleeper.
+///
+/// It's useful for tests that should exercise the real sleeping behavior and the relative
+/// order of the delays, but run much faster. It works with any [`Sleeper`], and with any
+/// [`BlockingSleeper`](crate::BlockingSleeper) for blocking retries.
+///
+/// # Examples
+///
+/// ```no_run
+/// use anyhow::Result;
+/// use backon::ExponentialBuilder;
+/// use backon::Retryable;
+/// use backon::ScaledSleeper;
+/// use backon::TokioSleeper;
+///
+/// async fn fetch() -> Result<String> {
+///     Ok(reqwest::get("https://www.rust-lang.org")
+///         .await?
+///         .text()
+///         .await?)
+/// }
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() -> Result<()> {
+///     let content = fetch
+///         .retry(ExponentialBuilder::default())
+///         // Sleep 10ms instead of 1s.
+///         .sleep(ScaledSleeper::new(TokioSleeper, 100))
+///         .await?;
+///     println!("fetch succeeded: {}", content);
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ScaledSleeper<S> {
+    inner: S,
+    factor: u32,
+}
+
+impl<S> ScaledSleeper<S> {
+    /// Create a new sleeper that divides every delay by `factor` before sleeping with `inner`.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the factor is `0`.
+    pub fn new(inner: S, factor: u32) -> Self {
+        assert!(factor > 0, "invalid scale factor that equals to 0");
+
+        ScaledSleeper { inner, factor }
+    }
+}
+
+impl<S: Sleeper> Sleeper for ScaledSleeper<S> {
+    type Sleep = S::Sleep;
+
+    fn sleep(&self, dur: Duration) -> Self::Sleep {
+        self.inner.sleep(dur / self.factor)
+    }
+
+    fn sleep_for_attempt(&self, dur: Duration, attempt: usize) -> Self::Sleep {
+        self.inner.sleep_for_attempt(dur / self.factor, attempt)
+    }
+}
+
+impl<S: crate::BlockingSleeper> crate::BlockingSleeper for ScaledSleeper<S> {
+    fn sleep(&self, dur: Duration) {
+        self.inner.sleep(dur / self.factor)
+    }
+}
+
+/// A `Sleeper` that never sleeps, every sleep completes right away.
+///
+/// The retry still consumes the backoff and counts the attempts as usual, only the delays are
+/// skipped. It's useful in tests, or to retry as fast as possible while keeping the limits and
+/// the classification of the errors. Unlike a backoff with a zero delay, the configured delays
+/// are kept, so the hooks and [`RetryStats::slept`](crate::RetryStats::slept) still see them.
+///
+/// It implements [`BlockingSleeper`](crate::BlockingSleeper) as well.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoSleeper;
+
+impl Sleeper for NoSleeper {
+    type Sleep = Ready<()>;
+
+    fn sleep(&self, _: Duration) -> Self::Sleep {
+        ready(())
+    }
+}
+
+impl crate::BlockingSleeper for NoSleeper {
+    fn sleep(&self, _: Duration) {}
+}
+
+/// An object-safe version of [`Sleeper`], which is implemented by all [`Sleeper`]s whose
+/// futures are `Send`.
+///
+/// [`Sleeper`] can't be made into a trait object because of its associated future type, this
+/// trait returns a boxed future instead. Use it through [`BoxSleeper`].
+#[cfg(feature = "std")]
+pub trait DynSleeper: Send + Sync + 'static {
+    /// Create a boxed future that completes after a set period.
+    fn dyn_sleep(&self, dur: Duration) -> Pin<std::boxed::Box<dyn Future<Output = ()> + Send>>;
+
+    /// Create a boxed future like [`Sleeper::sleep_for_attempt`].
+    fn dyn_sleep_for_attempt(
+        &self,
+        dur: Duration,
+        attempt: usize,
+    ) -> Pin<std::boxed::Box<dyn Future<Output = ()> + Send>> {
+        let _ = attempt;
+        self.dyn_sleep(dur)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: Sleeper + Send + Sync> DynSleeper for T
+where
+    T::Sleep: Send,
+{
+    fn dyn_sleep(&self, dur: Duration) -> Pin<std::boxed::Box<dyn Future<Output = ()> + Send>> {
+        std::boxed::Box::pin(self.sleep(dur))
+    }
+
+    fn dyn_sleep_for_attempt(
+        &self,
+        dur: Duration,
+        attempt: usize,
+    ) -> Pin<std::boxed::Box<dyn Future<Output = ()> + Send>> {
+        std::boxed::Box::pin(self.sleep_for_attempt(dur, attempt))
+    }
+}
+
+/// A boxed [`Sleeper`] that could be selected at runtime.
+///
+/// It's useful to pick a sleeper from the environment or a config, like [`TokioSleeper`] in
+/// production and a sleeper that doesn't sleep in tests. Every sleep allocates a boxed future,
+/// so prefer passing the sleeper itself if it's known at compile time.
+///
+/// The sleeper and its futures must be `Send`, so the retry stays `Send`.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::future::ready;
+///
+/// use anyhow::Result;
+/// use backon::BoxSleeper;
+/// use backon::ExponentialBuilder;
+/// use backon::Retryable;
+/// use backon::TokioSleeper;
+///
+/// async fn fetch() -> Result<String> {
+///     Ok(reqwest::get("https://www.rust-lang.org")
+///         .await?
+///         .text()
+///         .await?)
+/// }
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() -> Result<()> {
+///     let sleeper = if std::env::var("NO_SLEEP").is_ok() {
+///         BoxSleeper::new(|_| ready(()))
+///     } else {
+///         BoxSleeper::new(TokioSleeper)
+///     };
+///
+///     let content = fetch
+///         .retry(ExponentialBuilder::default())
+///         .sleep(sleeper)
+///         .await?;
+///     println!("fetch succeeded: {}", content);
+///
+///     Ok(())
+/// }
+/// ```
+#[cfg(feature = "std")]
+pub struct BoxSleeper(std::boxed::Box<dyn DynSleeper>);
+
+#[cfg(feature = "std")]
+impl BoxSleeper {
+    /// Create a new boxed sleeper from given sleeper.
+    pub fn new(sleeper: impl DynSleeper) -> Self {
+        BoxSleeper(std::boxed::Box::new(sleeper))
+    }
+}
+
+#[cfg(feature = "std")]
+impl fmt::Debug for BoxSleeper {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BoxSleeper").finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "std")]
+impl Sleeper for BoxSleeper {
+    type Sleep = Pin<std::boxed::Box<dyn Future<Output = ()> + Send>>;
+
+    fn sleep(&self, dur: Duration) -> Self::Sleep {
+        self.0.dyn_sleep(dur)
+    }
+
+    fn sleep_for_attempt(&self, dur: Duration, attempt: usize) -> Self::Sleep {
+        self.0.dyn_sleep_for_attempt(dur, attempt)
+    }
+}
+
+#[cfg(test)]
+#[cfg(all(target_arch = "wasm32", feature = "gloo-timers-sleep"))]
+mod tests {
+    extern crate alloc;
+
+    use alloc::boxed::Box;
+    use alloc::sync::Arc;
+    use core::future::Future;
+    use core::pin::Pin;
+    use core::sync::atomic::AtomicBool;
+    use core::sync::atomic::Ordering;
+    use core::task::Context;
+    use core::task::Poll;
+    use core::time::Duration;
+
+    use futures::future::poll_fn;
+    use wasm_bindgen_test::wasm_bindgen_test as test;
+
+    use super::*;
+    use crate::ConstantBuilder;
+    use crate::Retryable;
+
+    /// Wrap [`GlooTimersSleep`] to record whether its sleep fired or has been dropped.
+    #[derive(Clone, Default)]
+    struct RecordSleeper {
+        fired: Arc<AtomicBool>,
+        dropped: Arc<AtomicBool>,
+    }
+
+    struct RecordSleep {
+        sleep: gloo_timers::future::TimeoutFuture,
+        recorder: RecordSleeper,
+    }
+
+    impl Sleeper for RecordSleeper {
+        type Sleep = RecordSleep;
+
+        fn sleep(&self, dur: Duration) -> Self::Sleep {
+            RecordSleep {
+                sleep: GlooTimersSleep.sleep(dur),
+                recorder: self.clone(),
+            }
+        }
+    }
+
+    impl Future for RecordSleep {
+        type Output = ();
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+            let poll = Pin::new(&mut self.sleep).poll(cx);
+            if poll.is_ready() {
+                self.recorder.fired.store(true, Ordering::SeqCst);
+            }
+            poll
+        }
+    }
+
+    impl Drop for RecordSleep {
+        fn drop(&mut self) {
+            self.recorder.dropped.store(true, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    async fn test_drop_retry_while_sleeping() {
+        let recorder = RecordSleeper::default();
+        let mut retry = Some(Box::pin(
+            (|| async { Err::<(), anyhow::Error>(anyhow::anyhow!("retryable")) })
+                .retry(ConstantBuilder::default().with_delay(Duration::from_millis(20)))
+                .sleep(recorder.clone()),
+        ));
+
+        // Poll once to fail the first attempt and start sleeping.
+        poll_fn(|cx| {
+            assert!(retry.as_mut().unwrap().as_mut().poll(cx).is_pending());
+            Poll::Ready(())
+        })
+        .await;
+        assert!(!recorder.dropped.load(Ordering::SeqCst));
+
+        // Dropping the retry drops its sleep, which clears the timeout.
+        drop(retry.take());
+        assert!(recorder.dropped.load(Ordering::SeqCst));
+
+        // Wait longer than the delay, the dropped sleep never fires.
+        gloo_timers::future::sleep(Duration::from_millis(50)).await;
+        assert!(!recorder.fired.load(Ordering::SeqCst));
+    }
+}
+
+#[cfg(test)]
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+mod thread_sleeper_tests {
+    use core::time::Duration;
+    use std::time::Instant;
+
+    use tokio::test;
+
+    use super::*;
+    use crate::ConstantBuilder;
+    use crate::Retryable;
+
+    #[test]
+    async fn test_thread_sleeper() {
+        let start = Instant::now();
+        ThreadSleeper.sleep(Duration::from_millis(20)).await;
+        assert!(start.elapsed() >= Duration::from_millis(20));
+
+        // A zero delay completes immediately.
+        ThreadSleeper.sleep(Duration::ZERO).await;
+    }
+
+    #[test]
+    async fn test_retry_with_thread_sleeper() {
+        let start = Instant::now();
+        let result = (|| async { Err::<(), anyhow::Error>(anyhow::anyhow!("retryable")) })
+            .retry(
+                ConstantBuilder::default()
+                    .with_delay(Duration::from_millis(10))
+                    .with_max_times(2),
+            )
+            .sleep(ThreadSleeper)
+            .await;
+
+        assert!(result.is_err());
+        assert!(start.elapsed() >= Duration::from_millis(20));
+    }
+}
+
+#[cfg(test)]
+mod scaled_sleeper_tests {
+    extern crate alloc;
+
+    use alloc::sync::Arc;
+    use alloc::vec;
+    use alloc::vec::Vec;
+    use core::future::ready;
+    use core::time::Duration;
+
+    use spin::Mutex;
+
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test as test;
+
+    #[cfg(not(target_arch = "wasm32"))]
+    use tokio::test;
+
+    use super::*;
+    use crate::BlockingRetryable;
+    use crate::ExponentialBuilder;
+    use crate::Retryable;
+
+    #[test]
+    async fn test_scaled_sleeper() {
+        let sleeps = Arc::new(Mutex::new(Vec::new()));
+
+        let result = (|| async { Err::<(), _>("retryable") })
+            .retry(ExponentialBuilder::default())
+            .sleep(ScaledSleeper::new(
+                {
+                    let sleeps = sleeps.clone();
+                    move |dur| {
+                        sleeps.lock().push(dur);
+                        ready(())
+                    }
+                },
+                100,
+            ))
+            .await;
+
+        assert!(result.is_err());
+        assert_eq!(
+            *sleeps.lock(),
+            vec![
+                Duration::from_millis(10),
+                Duration::from_millis(20),
+                Duration::from_millis(40),
+            ]
+        );
+    }
+
+    #[test]
+    async fn test_scaled_blocking_sleeper() {
+        let sleeps = Arc::new(Mutex::new(Vec::new()));
+
+        let result = (|| Err::<(), _>("retryable"))
+            .retry(ExponentialBuilder::default().with_max_times(1))
+            .sleep(ScaledSleeper::new(
+                {
+                    let sleeps = sleeps.clone();
+                    move |dur| sleeps.lock().push(dur)
+                },
+                4,
+            ))
+            .call();
+
+        assert!(result.is_err());
+        assert_eq!(*sleeps.lock(), vec![Duration::from_millis(250)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid scale factor")]
+    async fn test_scaled_sleeper_zero_factor() {
+        let _ = ScaledSleeper::new((), 0);
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+mod box_sleeper_tests {
+    use core::future::ready;
+    use core::time::Duration;
+    use std::sync::Arc;
+    use std::sync::Mutex;
+    use std::vec;
+    use std::vec::Vec;
+
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test as test;
+
+    #[cfg(not(target_arch = "wasm32"))]
+    use tokio::test;
+
+    use super::*;
+    use crate::ConstantBuilder;
+    use crate::Retryable;
+
+    fn assert_send<T: Send>(t: T) -> T {
+        t
+    }
+
+    #[test]
+    async fn test_retry_with_box_sleeper() {
+        let sleeps = Arc::new(Mutex::new(Vec::new()));
+
+        let sleepers = [
+            BoxSleeper::new({
+                let sleeps = sleeps.clone();
+                move |dur| {
+                    sleeps.lock().unwrap().push(dur);
+                    ready(())
+                }
+            }),
+            BoxSleeper::new(ScaledSleeper::new(|_| ready(()), 2)),
+        ];
+
+        for sleeper in sleepers {
+            let result = assert_send(
+                (|| async { Err::<(), _>("retryable") })
+                    .retry(ConstantBuilder::default().with_max_times(2))
+                    .sleep(sleeper),
+            )
+            .await;
+            assert!(result.is_err());
+        }
+
+        assert_eq!(
+            *sleeps.lock().unwrap(),
+            vec![Duration::from_secs(1), Duration::from_secs(1)]
+        );
+    }
+}
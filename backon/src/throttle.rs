@@ -0,0 +1,306 @@
+use core::future::Future;
+use core::pin::Pin;
+use core::task::ready;
+use core::task::Context;
+use core::task::Poll;
+use core::time::Duration;
+
+use crate::Sleeper;
+
+/// PermitSource hands out the permits that retries must hold while sleeping.
+///
+/// It's used by [`Retry::throttle`](crate::Retry::throttle) to limit how many retries are
+/// sleeping at the same time. The permit is released by dropping it once the sleep is over.
+///
+/// # Examples
+///
+/// Back it with a tokio semaphore:
+///
+/// ```
+/// use std::future::Future;
+/// use std::pin::Pin;
+/// use std::sync::Arc;
+///
+/// use backon::PermitSource;
+/// use tokio::sync::OwnedSemaphorePermit;
+/// use tokio::sync::Semaphore;
+///
+/// #[derive(Clone)]
+/// struct Permits(Arc<Semaphore>);
+///
+/// impl PermitSource for Permits {
+///     type Permit = OwnedSemaphorePermit;
+///     type Acquire = Pin<Box<dyn Future<Output = OwnedSemaphorePermit> + Send>>;
+///
+///     fn acquire(&self) -> Self::Acquire {
+///         let semaphore = self.0.clone();
+///         Box::pin(async move {
+///             semaphore
+///                 .acquire_owned()
+///                 .await
+///                 .expect("semaphore must not be closed")
+///         })
+///     }
+/// }
+/// ```
+pub trait PermitSource: 'static {
+    /// The permit held while sleeping, it's released when dropped.
+    type Permit;
+    /// The future returned by the `acquire` method.
+    type Acquire: Future<Output = Self::Permit>;
+
+    /// Create a future that completes once a permit is available.
+    fn acquire(&self) -> Self::Acquire;
+}
+
+/// A sleeper that acquires a permit before sleeping, generated by
+/// [`Retry::throttle`](crate::Retry::throttle).
+///
+/// The permit is held during the whole sleep and released after it. The delay starts once the
+/// permit is acquired, so the time spent waiting for the permit is added to the delay.
+#[derive(Clone, Copy, Debug)]
+pub struct Throttled<S, P> {
+    inner: S,
+    permits: P,
+}
+
+impl<S, P> Throttled<S, P> {
+    /// Create a new sleeper that acquires a permit from `permits` before sleeping with `inner`.
+    pub fn new(inner: S, permits: P) -> Self {
+        Throttled { inner, permits }
+    }
+}
+
+impl<S: Sleeper + Clone, P: PermitSource> Sleeper for Throttled<S, P> {
+    type Sleep = ThrottledSleep<S, P>;
+
+    fn sleep(&self, dur: Duration) -> Self::Sleep {
+        ThrottledSleep {
+            state: ThrottleState::Acquiring {
+                acquire: self.permits.acquire(),
+                sleeper: self.inner.clone(),
+                dur,
+            },
+        }
+    }
+}
+
+/// The future returned by [`Throttled`].
+pub struct ThrottledSleep<S: Sleeper, P: PermitSource> {
+    state: ThrottleState<S, P>,
+}
+
+enum ThrottleState<S: Sleeper, P: PermitSource> {
+    Acquiring {
+        acquire: P::Acquire,
+        sleeper: S,
+        dur: Duration,
+    },
+    Sleeping {
+        sleep: S::Sleep,
+        _permit: P::Permit,
+    },
+    Done,
+}
+
+impl<S: Sleeper, P: PermitSource> Future for ThrottledSleep<S, P> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // Safety: This is safe because we don't move the `ThrottledSleep` struct itself,
+        // only its internal state.
+        //
+        // We do the exactly same thing like `pin_project` but without depending on it directly.
+        let this = unsafe { self.get_unchecked_mut() };
+
+        loop {
+            match &mut this.state {
+                ThrottleState::Acquiring {
+                    acquire,
+                    sleeper,
+                    dur,
+                } => {
+                    // Safety: This is safe because we don't move the `ThrottledSleep` struct and this fut,
+                    // only its internal state.
+                    //
+                    // We do the exactly same thing like `pin_project` but without depending on it directly.
+                    let acquire = unsafe { Pin::new_unchecked(acquire) };
+
+                    let permit = ready!(acquire.poll(cx));
+                    let sleep = sleeper.sleep(*dur);
+                    this.state = ThrottleState::Sleeping {
+                        sleep,
+                        _permit: permit,
+                    };
+                }
+                ThrottleState::Sleeping { sleep, .. } => {
+                    // Safety: This is safe because we don't move the `ThrottledSleep` struct and this fut,
+                    // only its internal state.
+                    //
+                    // We do the exactly same thing like `pin_project` but without depending on it directly.
+                    let sleep = unsafe { Pin::new_unchecked(sleep) };
+
+                    ready!(sleep.poll(cx));
+                    // Release the permit.
+                    this.state = ThrottleState::Done;
+                    return Poll::Ready(());
+                }
+                ThrottleState::Done => return Poll::Ready(()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate alloc;
+
+    use alloc::sync::Arc;
+    use core::future::ready;
+    use core::sync::atomic::AtomicBool;
+    use core::sync::atomic::AtomicUsize;
+    use core::sync::atomic::Ordering;
+
+    use futures::future::join_all;
+
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test as test;
+
+    #[cfg(not(target_arch = "wasm32"))]
+    use tokio::test;
+
+    use super::*;
+    use crate::ConstantBuilder;
+    use crate::Retryable;
+
+    /// Hand out a single permit, acquiring waits until it's released.
+    #[derive(Clone, Default)]
+    struct OnePermit(Arc<AtomicBool>);
+
+    struct Permit(Arc<AtomicBool>);
+
+    impl Drop for Permit {
+        fn drop(&mut self) {
+            self.0.store(false, Ordering::SeqCst);
+        }
+    }
+
+    struct Acquire(Arc<AtomicBool>);
+
+    impl Future for Acquire {
+        type Output = Permit;
+
+        fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+            if self.0.swap(true, Ordering::SeqCst) {
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+            Poll::Ready(Permit(self.0.clone()))
+        }
+    }
+
+    impl PermitSource for OnePermit {
+        type Permit = Permit;
+        type Acquire = Acquire;
+
+        fn acquire(&self) -> Self::Acquire {
+            Acquire(self.0.clone())
+        }
+    }
+
+    /// Track the number of sleeps in flight, every sleep yields once.
+    #[derive(Clone, Default)]
+    struct CountingSleeper {
+        current: Arc<AtomicUsize>,
+        max: Arc<AtomicUsize>,
+    }
+
+    struct CountingSleep {
+        sleeper: CountingSleeper,
+        yielded: bool,
+    }
+
+    impl Sleeper for CountingSleeper {
+        type Sleep = CountingSleep;
+
+        fn sleep(&self, _: Duration) -> Self::Sleep {
+            let current = self.current.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max.fetch_max(current, Ordering::SeqCst);
+            CountingSleep {
+                sleeper: self.clone(),
+                yielded: false,
+            }
+        }
+    }
+
+    impl Future for CountingSleep {
+        type Output = ();
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+            if !self.yielded {
+                self.yielded = true;
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+            self.sleeper.current.fetch_sub(1, Ordering::SeqCst);
+            Poll::Ready(())
+        }
+    }
+
+    #[test]
+    async fn test_retry_with_throttle() {
+        let permits = OnePermit::default();
+        let sleeper = CountingSleeper::default();
+
+        let results = join_all((0..3).map(|_| {
+            (|| ready(Err::<(), _>("retryable")))
+                .retry(ConstantBuilder::default().with_max_times(2))
+                .sleep(sleeper.clone())
+                .throttle(permits.clone())
+        }))
+        .await;
+
+        assert!(results.iter().all(|r| r.is_err()));
+        assert_eq!(sleeper.max.load(Ordering::SeqCst), 1);
+        assert_eq!(sleeper.current.load(Ordering::SeqCst), 0);
+        // The permit has been released.
+        assert!(!permits.0.load(Ordering::SeqCst));
+    }
+
+    #[cfg(any(feature = "tokio-sleep", feature = "gloo-timers-sleep"))]
+    #[test]
+    async fn test_retry_timeout_with_throttle() {
+        let permits = OnePermit::default();
+        let sleeper = CountingSleeper::default();
+
+        // The timer doesn't take the only permit, the retry keeps sleeping with it.
+        let result = (|| {
+            ready(Err::<(), _>(crate::RetryTimedOut {
+                timeout: Duration::ZERO,
+            }))
+        })
+        .retry(ConstantBuilder::default().with_max_times(2))
+        .sleep(sleeper.clone())
+        .throttle(permits.clone())
+        .timeout(Duration::from_secs(60))
+        .await;
+
+        assert_eq!(result.unwrap_err().timeout(), Duration::ZERO);
+        assert_eq!(sleeper.max.load(Ordering::SeqCst), 1);
+        assert!(!permits.0.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    async fn test_retry_without_throttle() {
+        let sleeper = CountingSleeper::default();
+
+        join_all((0..3).map(|_| {
+            (|| ready(Err::<(), _>("retryable")))
+                .retry(ConstantBuilder::default().with_max_times(2))
+                .sleep(sleeper.clone())
+        }))
+        .await;
+
+        assert_eq!(sleeper.max.load(Ordering::SeqCst), 3);
+    }
+}
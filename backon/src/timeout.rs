@@ -0,0 +1,53 @@
+use core::fmt;
+use core::time::Duration;
+
+/// AttemptTimedOut is the error of an attempt that didn't finish in time.
+///
+/// It's returned for the attempts that exceed the timeout set by
+/// [`BlockingRetry::attempt_timeout`](crate::BlockingRetry::attempt_timeout) or
+/// [`BlockingRetry::attempt_timeouts`](crate::BlockingRetry::attempt_timeouts).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AttemptTimedOut {
+    pub(crate) timeout: Duration,
+}
+
+impl AttemptTimedOut {
+    /// The timeout that has been exceeded.
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+}
+
+impl fmt::Display for AttemptTimedOut {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "attempt timed out after {:?}", self.timeout)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for AttemptTimedOut {}
+
+/// RetryTimedOut is the error of a retry that didn't finish in time.
+///
+/// It's returned when all attempts and sleeps together exceed the timeout set by
+/// [`Retry::timeout`](crate::Retry::timeout).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryTimedOut {
+    pub(crate) timeout: Duration,
+}
+
+impl RetryTimedOut {
+    /// The timeout that has been exceeded.
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+}
+
+impl fmt::Display for RetryTimedOut {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "retry timed out after {:?}", self.timeout)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for RetryTimedOut {}
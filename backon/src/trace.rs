@@ -0,0 +1,198 @@
+use core::fmt;
+use core::time::Duration;
+
+use tracing::span::EnteredSpan;
+use tracing::Level;
+use tracing::Span;
+
+/// Emit a `tracing` event or span at a level only known at runtime.
+///
+/// `tracing` requires the level of a callsite to be constant, so we have to
+/// dispatch to one callsite per level.
+macro_rules! dispatch_level {
+    ($level:expr, $mac:ident!($($args:tt)*)) => {
+        match $level {
+            Level::ERROR => tracing::$mac!(Level::ERROR, $($args)*),
+            Level::WARN => tracing::$mac!(Level::WARN, $($args)*),
+            Level::INFO => tracing::$mac!(Level::INFO, $($args)*),
+            Level::DEBUG => tracing::$mac!(Level::DEBUG, $($args)*),
+            _ => tracing::$mac!(Level::TRACE, $($args)*),
+        }
+    };
+}
+
+/// Tracer holds the `tracing` instrumentation of a single retry operation.
+///
+/// The fields of the events follow the OpenTelemetry semantic conventions, see
+/// [`Retry::trace`](crate::Retry::trace).
+pub(crate) struct Tracer<E> {
+    level: Level,
+    max_attempts: Option<usize>,
+    fmt: fn(&E, &mut fmt::Formatter<'_>) -> fmt::Result,
+    span: Span,
+}
+
+impl<E: fmt::Display> Tracer<E> {
+    /// Create a new tracer that emits events at given level.
+    ///
+    /// `max_attempts` is `None` if the backoff is unbounded or doesn't tell.
+    pub(crate) fn new(level: Level, max_attempts: Option<usize>) -> Self {
+        Tracer {
+            level,
+            max_attempts,
+            fmt: <E as fmt::Display>::fmt,
+            span: dispatch_level!(level, span!("backon::retry")),
+        }
+    }
+}
+
+impl<E> Clone for Tracer<E> {
+    /// Every retry has its own span, so the clone opens a new one.
+    fn clone(&self) -> Self {
+        Tracer {
+            level: self.level,
+            max_attempts: self.max_attempts,
+            fmt: self.fmt,
+            span: dispatch_level!(self.level, span!("backon::retry")),
+        }
+    }
+}
+
+impl<E> Tracer<E> {
+    /// Enter the span of this retry operation.
+    pub(crate) fn enter(&self) -> EnteredSpan {
+        self.span.clone().entered()
+    }
+
+    /// Record the failure of given attempt.
+    ///
+    /// `delay` is `None` if the retry is going to give up.
+    pub(crate) fn record(&self, attempt: usize, err: &E, delay: Option<Duration>) {
+        let error = DisplayWith(err, self.fmt);
+        let error_type = core::any::type_name::<E>();
+        match delay {
+            Some(delay) => dispatch_level!(
+                self.level,
+                event!(
+                    retry.attempt = attempt,
+                    retry.max_attempts = self.max_attempts,
+                    retry.delay_ms = delay.as_millis() as u64,
+                    error.r#type = error_type,
+                    exception.message = %error,
+                    "retrying"
+                )
+            ),
+            None => dispatch_level!(
+                self.level,
+                event!(
+                    retry.attempt = attempt,
+                    retry.max_attempts = self.max_attempts,
+                    error.r#type = error_type,
+                    exception.message = %error,
+                    "giving up"
+                )
+            ),
+        }
+    }
+}
+
+/// Display an error with the formatter captured by [`Tracer::new`].
+struct DisplayWith<'a, E>(&'a E, fn(&E, &mut fmt::Formatter<'_>) -> fmt::Result);
+
+impl<E> fmt::Display for DisplayWith<'_, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (self.1)(self.0, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use std::string::String;
+    use std::string::ToString;
+    use std::sync::Arc;
+    use std::sync::Mutex;
+    use std::vec;
+    use std::vec::Vec;
+
+    use tracing::field::Field;
+    use tracing::field::Visit;
+    use tracing::span;
+    use tracing::Event;
+    use tracing::Metadata;
+    use tracing::Subscriber;
+
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test as test;
+
+    use super::*;
+
+    /// Collect the names of the fields of every event.
+    #[derive(Default, Clone)]
+    struct FieldNames(Arc<Mutex<Vec<Vec<String>>>>);
+
+    struct Names(Vec<String>);
+
+    impl Visit for Names {
+        fn record_debug(&mut self, field: &Field, _: &dyn fmt::Debug) {
+            self.0.push(field.name().to_string());
+        }
+    }
+
+    impl Subscriber for FieldNames {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, _: &span::Attributes<'_>) -> span::Id {
+            span::Id::from_u64(1)
+        }
+
+        fn record(&self, _: &span::Id, _: &span::Record<'_>) {}
+
+        fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+
+        fn event(&self, event: &Event<'_>) {
+            let mut names = Names(Vec::new());
+            event.record(&mut names);
+            self.0.lock().unwrap().push(names.0);
+        }
+
+        fn enter(&self, _: &span::Id) {}
+
+        fn exit(&self, _: &span::Id) {}
+    }
+
+    #[test]
+    fn test_tracer_opentelemetry_attributes() {
+        let subscriber = FieldNames::default();
+        tracing::subscriber::with_default(subscriber.clone(), || {
+            let tracer = Tracer::<&str>::new(Level::WARN, Some(3));
+            tracer.record(1, &"timeout", Some(Duration::from_millis(100)));
+            tracer.record(2, &"timeout", None);
+        });
+
+        let events = subscriber.0.lock().unwrap();
+        assert_eq!(
+            *events,
+            vec![
+                vec![
+                    "message",
+                    "retry.attempt",
+                    "retry.max_attempts",
+                    "retry.delay_ms",
+                    "error.type",
+                    "exception.message",
+                ],
+                vec![
+                    "message",
+                    "retry.attempt",
+                    "retry.max_attempts",
+                    "error.type",
+                    "exception.message",
+                ],
+            ]
+        );
+    }
+}